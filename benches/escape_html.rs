@@ -0,0 +1,43 @@
+//! Rendering throughput on content-heavy post bodies, which is dominated by
+//! HTML escaping. Run with `cargo bench --bench escape_html`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use serde_json::Value;
+use tailwindsql::render::{render_results, RenderAs, RowData};
+
+const ROWS: usize = 200;
+const ITERATIONS: usize = 200;
+
+fn post(id: usize) -> RowData {
+    let paragraph = "Tailwind & SQL: <b>\"classes\"</b> that don't need a query builder. ";
+    let mut row = RowData::new();
+    row.insert("id".to_string(), Value::from(id));
+    row.insert(
+        "title".to_string(),
+        Value::from(format!("Post #{id} <draft> & 'notes'")),
+    );
+    row.insert("content".to_string(), Value::from(paragraph.repeat(40)));
+    row
+}
+
+fn main() {
+    let rows: Vec<RowData> = (0..ROWS).map(post).collect();
+    let columns = ["id", "title", "content"].map(String::from);
+
+    for render_as in [RenderAs::Table, RenderAs::Json, RenderAs::Div] {
+        let mut bytes = 0;
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            bytes += black_box(render_results(black_box(&rows), &columns, render_as)).len();
+        }
+        let elapsed = start.elapsed();
+        #[allow(clippy::cast_precision_loss)]
+        let mib_per_sec = bytes as f64 / elapsed.as_secs_f64() / (1024.0 * 1024.0);
+        println!(
+            "{render_as:?}: {:?} per render, {mib_per_sec:.0} MiB/s of HTML",
+            elapsed / u32::try_from(ITERATIONS).expect("iteration count fits in u32"),
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Compile-time checked TailwindSQL class names, re-exported as `tailwindsql::tw_sql!`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, LitStr};
+
+// The macro runs the crate's own parser so compile-time and runtime parsing
+// can never disagree about the grammar.
+#[allow(dead_code, unused_imports)]
+#[path = "../../src/parser.rs"]
+mod parser;
+
+use parser::expr::{BinaryOp, Expr};
+use parser::{ColumnExpr, OrderBy, OrderDirection, QueryConfig, WhereOperator, WindowFunction};
+
+/// Parse a class name at compile time into a `tailwindsql::parser::QueryConfig`.
+///
+/// Parsing is strict: misplaced tokens such as `limit-abc` or a where field
+/// without a value fail the build. Column names are not checked, since no
+/// schema is available at compile time.
+///
+/// `QueryConfig` owns its strings, so the macro expands to an expression that
+/// builds the config directly rather than a `const`; no parsing happens at runtime.
+///
+/// ```ignore
+/// let config = tailwindsql::tw_sql!("db-users-name-where-id-1");
+/// ```
+#[proc_macro]
+pub fn tw_sql(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    match parser::parse_class_name_strict(&literal.value(), |_, _| true) {
+        Ok(config) => expand(&config).into(),
+        Err(error) => syn::Error::new(
+            literal.span(),
+            format!("invalid TailwindSQL class: {error}"),
+        )
+        .to_compile_error()
+        .into(),
+    }
+}
+
+fn expand(config: &QueryConfig) -> TokenStream2 {
+    let table = &config.table;
+    let columns = &config.columns;
+
+    let where_clauses = config.where_clauses.iter().map(|clause| {
+        let field = &clause.field;
+        let value = &clause.value;
+        let operator = match clause.operator {
+            WhereOperator::Eq => quote!(Eq),
+            WhereOperator::After => quote!(After),
+            WhereOperator::Before => quote!(Before),
+            WhereOperator::Contains => quote!(Contains),
+            WhereOperator::In => quote!(In),
+        };
+        let subquery = option_tokens(clause.subquery.as_ref().map(|subquery| {
+            let subquery = expand(subquery);
+            quote!(::std::boxed::Box::new(#subquery))
+        }));
+        quote! {
+            ::tailwindsql::parser::WhereClause {
+                field: ::std::string::String::from(#field),
+                operator: ::tailwindsql::parser::WhereOperator::#operator,
+                value: ::std::string::String::from(#value),
+                subquery: #subquery,
+            }
+        }
+    });
+
+    let limit = option_tokens(config.limit.map(|limit| quote!(#limit)));
+    let order_by = option_tokens(config.order_by.as_ref().map(order_by_tokens));
+    let expressions = config
+        .expressions
+        .iter()
+        .map(|expression| match expression {
+            ColumnExpr::Window(window) => {
+                let function = match window.function {
+                    WindowFunction::Rank => quote!(Rank),
+                    WindowFunction::DenseRank => quote!(DenseRank),
+                    WindowFunction::RowNumber => quote!(RowNumber),
+                };
+                let order_by = order_by_tokens(&window.order_by);
+                let partition_by = option_tokens(
+                    window
+                        .partition_by
+                        .as_ref()
+                        .map(|column| quote!(::std::string::String::from(#column))),
+                );
+                quote! {
+                    ::tailwindsql::parser::ColumnExpr::Window(::tailwindsql::parser::WindowExpr {
+                        function: ::tailwindsql::parser::WindowFunction::#function,
+                        order_by: #order_by,
+                        partition_by: #partition_by,
+                    })
+                }
+            }
+            ColumnExpr::Computed(computed) => {
+                let expr = expr_tokens(&computed.expr);
+                let alias = &computed.alias;
+                quote! {
+                    ::tailwindsql::parser::ColumnExpr::Computed(::tailwindsql::parser::ComputedExpr {
+                        expr: #expr,
+                        alias: ::std::string::String::from(#alias),
+                    })
+                }
+            }
+            ColumnExpr::Case(case) => {
+                let column = &case.column;
+                let arms = case.arms.iter().map(|(value, label)| {
+                    quote! {
+                        (
+                            ::std::string::String::from(#value),
+                            ::std::string::String::from(#label),
+                        )
+                    }
+                });
+                quote! {
+                    ::tailwindsql::parser::ColumnExpr::Case(::tailwindsql::parser::CaseExpr {
+                        column: ::std::string::String::from(#column),
+                        arms: ::std::vec![#(#arms),*],
+                    })
+                }
+            }
+            ColumnExpr::Json(json) => {
+                let field = &json.field;
+                quote! {
+                    ::tailwindsql::parser::ColumnExpr::Json(::tailwindsql::parser::JsonExpr {
+                        field: ::std::string::String::from(#field),
+                    })
+                }
+            }
+        });
+    let search = option_tokens(config.search.as_ref().map(|search| {
+        let term = &search.term;
+        quote! {
+            ::tailwindsql::parser::SearchConfig {
+                term: ::std::string::String::from(#term),
+                fts_table: ::std::option::Option::None,
+                columns: ::std::vec::Vec::new(),
+            }
+        }
+    }));
+    let refresh_seconds = option_tokens(config.refresh_seconds.map(|seconds| quote!(#seconds)));
+
+    quote! {
+        ::tailwindsql::parser::QueryConfig {
+            table: ::std::string::String::from(#table),
+            columns: ::std::vec![#(::std::string::String::from(#columns)),*],
+            expressions: ::std::vec![#(#expressions),*],
+            where_clauses: ::std::vec![#(#where_clauses),*],
+            limit: #limit,
+            offset: ::std::option::Option::None,
+            order_by: #order_by,
+            joins: ::std::vec::Vec::new(),
+            search: #search,
+            refresh_seconds: #refresh_seconds,
+        }
+    }
+}
+
+fn order_by_tokens(order_by: &OrderBy) -> TokenStream2 {
+    let field = &order_by.field;
+    let direction = match order_by.direction {
+        OrderDirection::Asc => quote!(Asc),
+        OrderDirection::Desc => quote!(Desc),
+    };
+    quote! {
+        ::tailwindsql::parser::OrderBy {
+            field: ::std::string::String::from(#field),
+            direction: ::tailwindsql::parser::OrderDirection::#direction,
+        }
+    }
+}
+
+fn expr_tokens(expr: &Expr) -> TokenStream2 {
+    match expr {
+        Expr::Column(column) => quote! {
+            ::tailwindsql::parser::expr::Expr::Column(::std::string::String::from(#column))
+        },
+        Expr::Number(number) => quote! {
+            ::tailwindsql::parser::expr::Expr::Number(::std::string::String::from(#number))
+        },
+        Expr::Binary { op, left, right } => {
+            let op = match op {
+                BinaryOp::Add => quote!(Add),
+                BinaryOp::Sub => quote!(Sub),
+                BinaryOp::Mul => quote!(Mul),
+                BinaryOp::Div => quote!(Div),
+                BinaryOp::Rem => quote!(Rem),
+            };
+            let (left, right) = (expr_tokens(left), expr_tokens(right));
+            quote! {
+                ::tailwindsql::parser::expr::Expr::Binary {
+                    op: ::tailwindsql::parser::expr::BinaryOp::#op,
+                    left: ::std::boxed::Box::new(#left),
+                    right: ::std::boxed::Box::new(#right),
+                }
+            }
+        }
+    }
+}
+
+fn option_tokens(value: Option<TokenStream2>) -> TokenStream2 {
+    match value {
+        Some(value) => quote!(::std::option::Option::Some(#value)),
+        None => quote!(::std::option::Option::None),
+    }
+}
@@ -0,0 +1,198 @@
+//! Index suggestions from SQLite query plans.
+//!
+//! [`advise`] reads the `detail` lines of `EXPLAIN QUERY PLAN` for a query
+//! built from a [`QueryConfig`]. Every `SCAN` of a table without an index is
+//! a full scan, as is a `SEARCH` through an automatic index SQLite builds for
+//! the one query. For each table scanned that the query filters, sorts or
+//! joins on, it suggests a `CREATE INDEX` over those columns: equality filters
+//! first, then the sort column, then one range filter, which is the order an
+//! index serves them in. A sort SQLite does in a temporary B-tree gets the
+//! same treatment.
+//!
+//! ```
+//! use tailwindsql::advisor::advise;
+//! use tailwindsql::parser::parse_class_name;
+//!
+//! let config = parse_class_name("db-users-name-where-role-admin").unwrap();
+//! let advice = advise(&config, &["SCAN users".to_string()]);
+//! assert_eq!(advice.full_scans, ["users"]);
+//! assert_eq!(
+//!     advice.suggestions[0].sql,
+//!     "CREATE INDEX \"idx_users_role\" ON \"users\" (\"role\")"
+//! );
+//! ```
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::dialect::{SqlDialect, Sqlite};
+use crate::parser::{QueryConfig, WhereOperator};
+
+/// What the plan of one query shows, and the indexes that would help it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Advice {
+    /// Tables the plan reads row by row, or through an index it builds for
+    /// the one query, in plan order.
+    #[serde(rename = "fullScans")]
+    pub full_scans: Vec<String>,
+    /// Whether the plan sorts rows in a temporary B-tree for `ORDER BY`.
+    #[serde(rename = "tempSort")]
+    pub temp_sort: bool,
+    pub suggestions: Vec<IndexSuggestion>,
+}
+
+/// An index to create, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IndexSuggestion {
+    pub table: String,
+    pub columns: Vec<String>,
+    pub reason: String,
+    pub sql: String,
+}
+
+impl IndexSuggestion {
+    fn new(table: &str, columns: Vec<String>, reason: String) -> Self {
+        let name = format!("idx_{table}_{}", columns.join("_"));
+        let quoted: Vec<String> = columns
+            .iter()
+            .map(|column| Sqlite.quote_identifier(column))
+            .collect();
+        Self {
+            sql: format!(
+                "CREATE INDEX {} ON {} ({})",
+                Sqlite.quote_identifier(&name),
+                Sqlite.quote_identifier(table),
+                quoted.join(", ")
+            ),
+            table: table.to_string(),
+            columns,
+            reason,
+        }
+    }
+}
+
+/// Suggest indexes for `config` from the `detail` lines of its query plan.
+#[must_use]
+pub fn advise(config: &QueryConfig, plan: &[String]) -> Advice {
+    let mut references = HashMap::from([(config.table.as_str(), config.table.as_str())]);
+    for join in &config.joins {
+        references.insert(join.reference(), join.table.as_str());
+        if let Some(through) = &join.through {
+            references.insert(through.table.as_str(), through.table.as_str());
+        }
+    }
+
+    let mut advice = Advice::default();
+    for detail in plan {
+        if detail.starts_with("USE TEMP B-TREE FOR") && detail.contains("ORDER BY") {
+            advice.temp_sort = true;
+        }
+        let Some(scanned) = full_scan(detail) else {
+            continue;
+        };
+        let table = references.get(scanned).copied().unwrap_or(scanned);
+        if !advice.full_scans.iter().any(|seen| seen == table) {
+            advice.full_scans.push(table.to_string());
+        }
+    }
+
+    for table in &advice.full_scans {
+        let suggestion = if *table == config.table {
+            base_index(config).map(|columns| {
+                let reason = format!(
+                    "{table} is scanned in full to filter or sort on {}",
+                    columns.join(", ")
+                );
+                IndexSuggestion::new(table, columns, reason)
+            })
+        } else {
+            join_index(config, table).map(|column| {
+                let reason = format!("{table} is scanned in full for every row it is joined to");
+                IndexSuggestion::new(table, vec![column], reason)
+            })
+        };
+        advice.suggestions.extend(suggestion);
+    }
+    let sort_suggested = advice
+        .suggestions
+        .iter()
+        .any(|suggestion| suggestion.table == config.table);
+    if advice.temp_sort && !sort_suggested && config.order_by.is_some() {
+        if let Some(columns) = base_index(config) {
+            let reason = format!("{} rows are sorted after they are read", config.table);
+            advice
+                .suggestions
+                .push(IndexSuggestion::new(&config.table, columns, reason));
+        }
+    }
+    advice
+}
+
+/// The table or alias a plan line reads without an index, as in `SCAN users`
+/// (`SCAN TABLE users` before SQLite 3.36), or through an automatic index
+/// SQLite builds for the one query. Scans of an index are not full scans, and
+/// neither are subqueries or `CTE`s.
+fn full_scan(detail: &str) -> Option<&str> {
+    let rest = match detail.strip_prefix("SCAN ") {
+        Some(rest) if !rest.contains(" USING ") => rest,
+        Some(_) => return None,
+        None => detail
+            .strip_prefix("SEARCH ")
+            .filter(|rest| rest.contains(" USING AUTOMATIC "))?,
+    };
+    let rest = rest.strip_prefix("TABLE ").unwrap_or(rest);
+    if rest.starts_with("CONSTANT") {
+        return None;
+    }
+    let mut words = rest.split_whitespace();
+    let table = words.next()?;
+    // Aliased tables read `SCAN posts AS authored`.
+    Some(match (words.next(), words.next()) {
+        (Some("AS"), Some(alias)) => alias,
+        _ => table,
+    })
+}
+
+/// The columns of the base table an index should cover, in the order one
+/// serves them: equality filters, the sort column, then one range filter.
+fn base_index(config: &QueryConfig) -> Option<Vec<String>> {
+    let mut columns: Vec<String> = Vec::new();
+    let mut add = |column: &str| {
+        if !columns.iter().any(|seen| seen == column) {
+            columns.push(column.to_string());
+        }
+    };
+    for clause in &config.where_clauses {
+        if matches!(clause.operator, WhereOperator::Eq | WhereOperator::In) {
+            add(&clause.field);
+        }
+    }
+    if let Some(order_by) = &config.order_by {
+        if !order_by.field.contains('.') {
+            add(&order_by.field);
+        }
+    }
+    let range = config.where_clauses.iter().find(|clause| {
+        matches!(
+            clause.operator,
+            WhereOperator::After | WhereOperator::Before
+        )
+    });
+    if let Some(range) = range {
+        add(&range.field);
+    }
+    (!columns.is_empty()).then_some(columns)
+}
+
+/// The column of `table` a join looks rows up by: the joined table's own
+/// key, or the junction's key to the parent.
+fn join_index(config: &QueryConfig, table: &str) -> Option<String> {
+    config.joins.iter().find_map(|join| match &join.through {
+        Some(through) if through.table == table => Some(through.parent_column.clone()),
+        _ if join.table == table && !join.child_column.is_empty() => {
+            Some(join.child_column.clone())
+        }
+        _ => None,
+    })
+}
@@ -1,13 +1,40 @@
 #![allow(clippy::multiple_crate_versions)]
 
-use std::path::PathBuf;
+use std::error::Error;
 
-use tailwindsql::db::seed_database;
+use tailwindsql::db::{lock_for_seeding, seed_database_with, DbConfig, SeedMode, SeedOptions};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let path = PathBuf::from("tailwindsql.db");
-    let mut conn = rusqlite::Connection::open(&path)?;
-    let _ = conn.pragma_update(None, "journal_mode", "WAL");
-    seed_database(&mut conn)?;
+const USAGE: &str = "usage: seed [--users <n>] [--products <n>] [--posts <n>] \
+                     [--tables <table,...>] [--mode random|scenario] [--spec <file>]";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut config = DbConfig::from_env()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    apply_flags(&mut config.seed, &args)?;
+
+    let _seed_lock = lock_for_seeding(&config.path)?;
+    let mut conn = config.open()?;
+    seed_database_with(&mut conn, &config.seed)?;
+    Ok(())
+}
+
+/// Flags override the `SEED_*` variables of the same name.
+fn apply_flags(options: &mut SeedOptions, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or(USAGE)?;
+        let count = || value.parse::<usize>().map_err(|_| USAGE);
+        match flag.as_str() {
+            "--users" => options.users = count()?,
+            "--products" => options.products = count()?,
+            "--posts" => options.posts = count()?,
+            "--tables" => {
+                options.tables = Some(value.split(',').map(|t| t.trim().to_string()).collect());
+            }
+            "--mode" => options.mode = SeedMode::parse(value).ok_or(USAGE)?,
+            "--spec" => options.spec = Some(value.into()),
+            _ => return Err(USAGE.into()),
+        }
+    }
     Ok(())
 }
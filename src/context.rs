@@ -0,0 +1,150 @@
+//! What one query runs under: its deadline, its caller and their limits.
+//!
+//! A [`QueryContext`] is built once per request and handed to every stage,
+//! so parsing, building, the worker pool, execution and rendering all stop
+//! at the same deadline and apply the same caller's limits:
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use serde_json::json;
+//! use tailwindsql::prelude::*;
+//!
+//! let db = MockDatasource::new().with_table("users", ["id"], [vec![json!(1)]]);
+//! let engine = Engine::new(db).unwrap();
+//! let context = engine
+//!     .context()
+//!     .with_principal("reports")
+//!     .with_timeout(Duration::from_secs(5));
+//! let result = engine.query_in(&context, "db-users-id").unwrap();
+//! assert_eq!(result.rows.len(), 1);
+//! ```
+
+use std::time::{Duration, Instant};
+
+use crate::db::DbError;
+use crate::query_builder::QueryPolicy;
+
+/// The moment a query must be done by, and the time it was given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    at: Instant,
+    budget: Duration,
+}
+
+impl Deadline {
+    /// `budget` from now.
+    #[must_use]
+    pub fn after(budget: Duration) -> Self {
+        Self {
+            at: Instant::now() + budget,
+            budget,
+        }
+    }
+
+    #[must_use]
+    pub const fn at(&self) -> Instant {
+        self.at
+    }
+
+    /// The time the query was given in all.
+    #[must_use]
+    pub const fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Time left, zero once passed.
+    #[must_use]
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        Instant::now() >= self.at
+    }
+
+    /// # Errors
+    /// Returns `DbError::Timeout` once the deadline has passed.
+    pub fn check(&self) -> Result<(), DbError> {
+        if self.passed() {
+            return Err(DbError::Timeout(self.budget));
+        }
+        Ok(())
+    }
+}
+
+/// One query's deadline, caller and limits, passed through every stage
+/// that runs it.
+///
+/// The default has no deadline and imposes no limits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryContext {
+    /// When to give up, if ever; stages past it fail with
+    /// `DbError::Timeout`.
+    pub deadline: Option<Deadline>,
+    /// Who the query runs for, such as `admin` or an API key's name.
+    pub principal: Option<String>,
+    /// The request the query belongs to, for logs.
+    pub request_id: Option<String>,
+    /// The row caps, size limits and clamps for this caller.
+    pub limits: QueryPolicy,
+}
+
+impl QueryContext {
+    /// A context applying `limits`, with no deadline.
+    #[must_use]
+    pub fn new(limits: QueryPolicy) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// This context giving up `timeout` from now.
+    #[must_use]
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Deadline::after(timeout))
+    }
+
+    /// This context giving up at `deadline`, or at its own if that is
+    /// sooner.
+    #[must_use]
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = Some(match self.deadline {
+            Some(current) if current.at <= deadline.at => current,
+            _ => deadline,
+        });
+        self
+    }
+
+    #[must_use]
+    pub fn with_principal(mut self, principal: impl Into<String>) -> Self {
+        self.principal = Some(principal.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    #[must_use]
+    pub const fn with_limits(mut self, limits: QueryPolicy) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Time left before the deadline, if there is one.
+    #[must_use]
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.as_ref().map(Deadline::remaining)
+    }
+
+    /// # Errors
+    /// Returns `DbError::Timeout` once the deadline has passed.
+    pub fn check(&self) -> Result<(), DbError> {
+        self.deadline.as_ref().map_or(Ok(()), Deadline::check)
+    }
+}
@@ -1,10 +1,10 @@
-use rand::seq::SliceRandom;
-use rand::Rng;
-use rusqlite::{params, Connection};
-use std::collections::HashSet;
+use rusqlite::{Connection, OpenFlags};
 use std::env;
-use std::fs;
+use std::fs::{File, OpenOptions};
+#[cfg(feature = "seed")]
+use std::fs::{self, TryLockError};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -15,323 +15,507 @@ pub enum DbError {
     Io(#[from] std::io::Error),
     #[error("failed to join blocking task")]
     Join,
+    #[error("database lock poisoned")]
+    Lock,
+    #[error("database queue is full ({0} jobs waiting)")]
+    QueueFull(usize),
+    #[error("query ran longer than {0:?} and was cancelled; add a limit or narrow the query")]
+    Timeout(Duration),
     #[error("seed data missing: {0}")]
     SeedData(&'static str),
+    #[error("unknown SEED_MODE {0:?}; expected random or scenario")]
+    SeedMode(String),
+    #[error("unknown seed table {0:?}; expected users, products, posts, comments, tags, post_tags or sales")]
+    SeedTable(String),
+    #[error("invalid {0}: expected a number of rows")]
+    SeedCount(&'static str),
+    #[error("invalid seed spec: {0}")]
+    SeedSpec(String),
+    #[cfg(feature = "postgres")]
+    #[error("postgres error: {0}")]
+    Postgres(#[from] tokio_postgres::Error),
+    #[cfg(feature = "duckdb")]
+    #[error("duckdb error: {0}")]
+    DuckDb(#[from] ::duckdb::Error),
+    #[error(
+        "result exceeds the memory budget of {budget} bytes; add a limit or select fewer columns"
+    )]
+    MemoryBudget { budget: usize },
+    #[error("mock datasource error: {0}")]
+    Mock(String),
 }
 
-pub struct DbInit {
+mod backend;
+#[cfg(any(feature = "postgres", feature = "duckdb"))]
+mod datetime;
+#[cfg(feature = "duckdb")]
+pub mod duckdb;
+pub mod mock;
+#[cfg(feature = "server")]
+mod pool;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "seed")]
+mod seed;
+pub mod sqlite;
+mod statements;
+mod watchdog;
+#[cfg(feature = "server")]
+mod worker;
+
+pub use backend::{
+    blob_hex, json_size, row_size, value_to_json, Backend, Execution, QueryResult, RowCollector,
+};
+#[cfg(feature = "server")]
+pub use pool::{PoolMetrics, WorkerPool};
+#[cfg(feature = "postgres")]
+pub(crate) use seed::{
+    generate_comments, generate_posts, generate_products, generate_users, print_counts, tag_names,
+    RELATIONSHIPS, SEED_ROWS, SEED_TABLES,
+};
+#[cfg(feature = "seed")]
+pub use seed::{
+    seed_database, seed_database_with, seed_spec, ColumnGenerator, ColumnSpec, SeedMode,
+    SeedOptions, SeedSpec, TableSpec,
+};
+pub use statements::{StatementCache, StatementCacheMetrics};
+#[cfg(feature = "server")]
+pub use worker::Worker;
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const MEMORY: &str = ":memory:";
+
+/// Where the SQLite database lives and how its connections are set up.
+///
+/// ```
+/// use tailwindsql::db::DbConfig;
+///
+/// let config = DbConfig::in_memory();
+/// assert!(config.is_memory());
+/// assert_eq!(config.journal_mode, "WAL");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbConfig {
+    /// The database file, or `:memory:` for one that lasts as long as the
+    /// process, seeded afresh on every start.
     pub path: PathBuf,
-    pub seeded: bool,
-    pub connection: Connection,
+    /// The `journal_mode` pragma; `WAL` lets readers run beside the writer.
+    pub journal_mode: String,
+    /// How long a connection waits on a locked database before failing.
+    pub busy_timeout: Duration,
+    /// Whether SQLite enforces foreign keys on writes (`PRAGMA foreign_keys`).
+    pub foreign_keys: bool,
+    /// Open the file read-only: it is never created, seeded or written.
+    pub read_only: bool,
+    /// The demo rows a fresh database is seeded with.
+    #[cfg(feature = "seed")]
+    pub seed: SeedOptions,
 }
 
-const FIRST_NAMES: &[&str] = &[
-    "Ada", "Alan", "Grace", "Linus", "Margaret", "Dennis", "Bjarne", "Guido",
-    "Brendan", "Ryan", "James", "Ken", "Brian", "Tim", "Vint", "Donald",
-    "Barbara", "Frances", "Jean", "Radia", "Sophie", "Shafi", "Fei-Fei",
-    "John", "Steve", "Bill", "Elon", "Jeff", "Mark", "Larry", "Sergey",
-    "Satya", "Sundar", "Jensen", "Lisa", "Susan", "Marissa", "Sheryl", "Ginni",
-];
-
-const LAST_NAMES: &[&str] = &[
-    "Lovelace", "Turing", "Hopper", "Torvalds", "Hamilton", "Ritchie", "Stroustrup",
-    "van Rossum", "Eich", "Dahl", "Gosling", "Thompson", "Kernighan", "Berners-Lee",
-    "Cerf", "Knuth", "Liskov", "Allen", "Bartik", "Perlman", "Wilson", "Goldwasser",
-    "Li", "McCarthy", "Wozniak", "Gates", "Musk", "Bezos", "Zuckerberg", "Page",
-    "Brin", "Nadella", "Pichai", "Huang", "Su", "Wojcicki", "Mayer", "Sandberg", "Rometty",
-];
-
-const ROLES: &[&str] = &[
-    "admin", "developer", "designer", "manager", "analyst", "engineer", "lead", "intern",
-];
-const STATUSES: &[&str] = &["active", "inactive", "pending", "verified"];
-const AVATARS: &[&str] = &[
-    "coder", "builder", "hacker", "explorer", "penguin", "snake", "coffee", "diamond", "crab", "bolt",
-    "leaf", "rocket", "robot", "chip", "spark",
-];
-
-const PRODUCT_ADJECTIVES: &[&str] = &[
-    "Premium", "Ultra", "Pro", "Elite", "Essential", "Classic", "Modern", "Smart", "Wireless", "Ergonomic",
-];
-const PRODUCT_NOUNS: &[&str] = &[
-    "Keyboard", "Monitor", "Mouse", "Headphones", "Webcam", "Microphone", "Desk", "Chair", "Lamp", "Hub", "Cable", "Stand", "Dock", "Speaker", "Tablet",
-];
-const PRODUCT_DESCRIPTIONS: &[&str] = &[
-    "High-quality build with premium materials",
-    "Perfect for professionals and enthusiasts",
-    "Award-winning design and performance",
-    "Industry-leading technology",
-    "Sleek and modern aesthetic",
-    "Built for comfort and productivity",
-    "Next-generation features",
-    "Eco-friendly and sustainable",
-];
-const CATEGORIES: &[&str] = &[
-    "electronics", "furniture", "accessories", "audio", "lighting", "peripherals", "storage", "networking",
-];
-
-const POST_TITLES: &[&str] = &[
-    "Why {} is the Future of {}",
-    "Getting Started with {}",
-    "10 Tips for Better {}",
-    "The Complete Guide to {}",
-    "How I Built {} with {}",
-    "Understanding {} in {}",
-    "{} vs {}: Which is Better?",
-    "Mastering {} for Beginners",
-    "Advanced {} Techniques",
-    "The State of {} in 2024",
-];
-const TECH_TERMS: &[&str] = &[
-    "React", "TypeScript", "Rust", "Go", "Python", "JavaScript", "SQL", "GraphQL",
-    "Docker", "Kubernetes", "AWS", "Machine Learning", "AI", "Web Development",
-    "Cloud Computing", "DevOps", "Microservices", "REST APIs", "WebAssembly", "Edge Computing",
-];
-
-/// Initialize or create the `SQLite` database, seeding it when missing.
-///
-/// # Errors
-/// Returns `DbError` if the database cannot be opened, copied, or seeded.
-pub fn init_db() -> Result<DbInit, DbError> {
-    let (path, copied) = resolve_db_path()?;
-    let should_seed = !path.exists() && !copied;
-
-    let mut connection = Connection::open(&path)?;
-    let _ = connection.pragma_update(None, "journal_mode", "WAL");
+impl DbConfig {
+    /// The database at `path` with the default options.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            journal_mode: "WAL".to_string(),
+            busy_timeout: BUSY_TIMEOUT,
+            foreign_keys: false,
+            read_only: false,
+            #[cfg(feature = "seed")]
+            seed: SeedOptions::default(),
+        }
+    }
 
-    let seeded = if should_seed {
-        seed_database(&mut connection)?;
-        true
-    } else {
-        false
-    };
+    /// A fresh, seeded database in memory, for tests and throwaway demos.
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self::new(MEMORY)
+    }
 
-    Ok(DbInit {
-        path,
-        seeded,
-        connection,
-    })
-}
+    #[must_use]
+    pub fn is_memory(&self) -> bool {
+        self.path.as_os_str() == MEMORY
+    }
 
-fn resolve_db_path() -> Result<(PathBuf, bool), DbError> {
-    let is_vercel = env::var("VERCEL").ok().as_deref() == Some("1") || env::var("VERCEL_ENV").is_ok();
-    let project_db = env::current_dir()?.join("tailwindsql.db");
+    /// The database named by `DATABASE_PATH` or `DATABASE_URL`, with
+    /// `DB_JOURNAL_MODE`, `DB_BUSY_TIMEOUT` (an interval such as `10s`),
+    /// `DB_FOREIGN_KEYS`, `DB_READ_ONLY` and the `SEED_*` options applied.
+    /// Without either, the database is `tailwindsql.db` in the working
+    /// directory, or on Vercel a copy of it under `/tmp`.
+    ///
+    /// # Errors
+    /// Returns `DbError` if the working directory cannot be read, the Vercel
+    /// copy fails or a `SEED_*` variable is invalid.
+    #[cfg(feature = "seed")]
+    pub fn from_env() -> Result<Self, DbError> {
+        let mut config = Self::new(resolve_db_path()?);
+        if let Some(mode) = env::var("DB_JOURNAL_MODE")
+            .ok()
+            .filter(|mode| !mode.trim().is_empty())
+        {
+            config.journal_mode = mode.trim().to_ascii_uppercase();
+        }
+        if let Some(seconds) = env::var("DB_BUSY_TIMEOUT")
+            .ok()
+            .and_then(|value| crate::parser::tokens::interval_seconds(value.trim()))
+        {
+            config.busy_timeout = Duration::from_secs(seconds);
+        }
+        config.foreign_keys = env_flag("DB_FOREIGN_KEYS");
+        config.read_only = env_flag("DB_READ_ONLY");
+        config.seed = seed_options_from_env()?;
+        Ok(config)
+    }
 
-    if is_vercel {
-        let tmp_db = PathBuf::from("/tmp/tailwindsql.db");
-        let copied = if !tmp_db.exists() && project_db.exists() {
-            copy_db_files(&project_db, &tmp_db)?;
-            true
+    /// Open a connection to the database with these options. A read-only
+    /// database must already exist, and keeps whatever journal mode it has.
+    ///
+    /// # Errors
+    /// Returns `DbError` if the database cannot be opened or a pragma fails.
+    pub fn open(&self) -> Result<Connection, DbError> {
+        let connection = if self.is_memory() {
+            Connection::open_in_memory()?
+        } else if self.read_only {
+            Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)?
         } else {
-            false
+            Connection::open(&self.path)?
         };
-        return Ok((tmp_db, copied));
+        connection.busy_timeout(self.busy_timeout)?;
+        if !self.read_only && !self.is_memory() {
+            let _ = connection.pragma_update(None, "journal_mode", &self.journal_mode);
+        }
+        connection.pragma_update(None, "foreign_keys", self.foreign_keys)?;
+        Ok(connection)
     }
+}
 
-    Ok((project_db, false))
+/// `SEED_MODE`, `SEED_USERS`, `SEED_PRODUCTS`, `SEED_POSTS` and the
+/// comma-separated `SEED_TABLES`, over the defaults.
+#[cfg(feature = "seed")]
+fn seed_options_from_env() -> Result<SeedOptions, DbError> {
+    let set = |name: &str| env::var(name).ok().filter(|value| !value.trim().is_empty());
+    let count = |name: &'static str, default: usize| match set(name) {
+        Some(value) => value.trim().parse().map_err(|_| DbError::SeedCount(name)),
+        None => Ok(default),
+    };
+    let defaults = SeedOptions::default();
+    Ok(SeedOptions {
+        users: count("SEED_USERS", defaults.users)?,
+        products: count("SEED_PRODUCTS", defaults.products)?,
+        posts: count("SEED_POSTS", defaults.posts)?,
+        tables: set("SEED_TABLES").map(|tables| {
+            tables
+                .split(',')
+                .map(|table| table.trim().to_string())
+                .collect()
+        }),
+        mode: match set("SEED_MODE") {
+            Some(mode) => SeedMode::parse(&mode).ok_or(DbError::SeedMode(mode))?,
+            None => defaults.mode,
+        },
+        spec: set("SEED_SPEC").map(PathBuf::from),
+    })
 }
 
-fn copy_db_files(src: &Path, dst: &Path) -> Result<(), DbError> {
-    fs::copy(src, dst)?;
+#[cfg(feature = "seed")]
+fn env_flag(name: &str) -> bool {
+    env::var(name).is_ok_and(|value| matches!(value.trim(), "1" | "true" | "yes"))
+}
 
-    let wal_src = src.with_extension("db-wal");
-    let wal_dst = dst.with_extension("db-wal");
-    if wal_src.exists() {
-        let _ = fs::copy(wal_src, wal_dst);
-    }
+pub struct DbInit {
+    pub path: PathBuf,
+    pub seeded: bool,
+    pub connection: Connection,
+    pub writer_lock: Option<WriterLock>,
+}
 
-    let shm_src = src.with_extension("db-shm");
-    let shm_dst = dst.with_extension("db-shm");
-    if shm_src.exists() {
-        let _ = fs::copy(shm_src, shm_dst);
+impl DbInit {
+    /// Whether this process won the writer election and should run maintenance.
+    #[must_use]
+    pub const fn is_writer(&self) -> bool {
+        self.writer_lock.is_some()
     }
+}
 
-    Ok(())
+/// Advisory lock held for the lifetime of the process elected as writer.
+///
+/// Only one process sharing a database file holds it at a time; the lock is
+/// released by the OS when the process exits. Instances still running only
+/// take over by calling [`try_elect_writer`] again, which the server does on
+/// every maintenance tick.
+pub struct WriterLock {
+    _file: File,
 }
 
-/// Seed the demo database with sample users, products, and posts.
+/// Open the `SQLite` database `config` describes, creating and seeding it
+/// when missing. In-memory databases are always seeded; read-only ones never
+/// are, and their process is never elected writer.
+///
+/// A new file is seeded under a `.seeding` name and renamed into place once
+/// complete, so a process that dies mid-seed leaves no half-seeded database
+/// behind; the next start seeds again.
 ///
 /// # Errors
-/// Returns `DbError` if schema creation or inserts fail.
-pub fn seed_database(conn: &mut Connection) -> Result<(), DbError> {
-    println!("TailwindSQL Database Seeder");
-    println!("================================\n");
+/// Returns `DbError` if the database cannot be opened or seeded.
+#[cfg(feature = "seed")]
+pub fn init_db(config: &DbConfig) -> Result<DbInit, DbError> {
+    let path = config.path.clone();
+    if config.is_memory() {
+        let mut connection = config.open()?;
+        seed_database_with(&mut connection, &config.seed)?;
+        return Ok(DbInit {
+            path,
+            seeded: true,
+            connection,
+            writer_lock: None,
+        });
+    }
+    if config.read_only {
+        return Ok(DbInit {
+            path,
+            seeded: false,
+            connection: config.open()?,
+            writer_lock: None,
+        });
+    }
 
-    create_schema(conn)?;
+    let seed_lock = lock_for_seeding(&path)?;
+    let seeded = !path.exists();
+    if seeded {
+        seed_into_place(config)?;
+    }
+    let connection = config.open()?;
+    drop(seed_lock);
 
-    let mut rng = rand::thread_rng();
-    seed_users(conn, &mut rng)?;
-    seed_products(conn, &mut rng)?;
-    seed_posts(conn, &mut rng)?;
+    let writer_lock = try_elect_writer(&path)?;
 
-    print_summary(conn)?;
+    Ok(DbInit {
+        path,
+        seeded,
+        connection,
+        writer_lock,
+    })
+}
 
+/// Seed a fresh database beside `config.path` and rename it into place.
+/// Call with the seed lock held.
+#[cfg(feature = "seed")]
+fn seed_into_place(config: &DbConfig) -> Result<(), DbError> {
+    let staging = sibling_path(&config.path, "seeding");
+    // Left over from a seed that did not finish.
+    if staging.exists() {
+        fs::remove_file(&staging)?;
+    }
+    // A rollback journal leaves no `-wal` file to carry along in the rename.
+    let staged = DbConfig {
+        path: staging.clone(),
+        journal_mode: "DELETE".to_string(),
+        ..config.clone()
+    };
+    let mut connection = staged.open()?;
+    seed_database_with(&mut connection, &config.seed)?;
+    drop(connection);
+    fs::rename(&staging, &config.path)?;
     Ok(())
 }
 
-fn create_schema(conn: &Connection) -> Result<(), DbError> {
-    conn.execute_batch(
-        "
-        DROP TABLE IF EXISTS posts;
-        DROP TABLE IF EXISTS products;
-        DROP TABLE IF EXISTS users;
-
-        CREATE TABLE users (
-          id INTEGER PRIMARY KEY AUTOINCREMENT,
-          name TEXT NOT NULL,
-          email TEXT UNIQUE NOT NULL,
-          role TEXT NOT NULL,
-          avatar TEXT,
-          status TEXT DEFAULT 'active',
-          created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        );
-
-        CREATE TABLE products (
-          id INTEGER PRIMARY KEY AUTOINCREMENT,
-          title TEXT NOT NULL,
-          description TEXT,
-          price REAL NOT NULL,
-          category TEXT NOT NULL,
-          stock INTEGER DEFAULT 0,
-          rating REAL DEFAULT 0,
-          created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        );
-
-        CREATE TABLE posts (
-          id INTEGER PRIMARY KEY AUTOINCREMENT,
-          title TEXT NOT NULL,
-          content TEXT,
-          author_id INTEGER,
-          likes INTEGER DEFAULT 0,
-          views INTEGER DEFAULT 0,
-          published INTEGER DEFAULT 0,
-          created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-          FOREIGN KEY (author_id) REFERENCES users(id)
-        );
-        ",
-    )?;
-
-    Ok(())
+/// Open an existing database as an additional datasource.
+///
+/// Unlike [`init_db`] the file is never created or seeded; it gets the same
+/// busy timeout and journal mode as the primary database.
+///
+/// # Errors
+/// Returns `DbError` if the file does not exist or cannot be opened.
+pub fn open_database(path: &Path) -> Result<Connection, DbError> {
+    let flags = OpenFlags::default() - OpenFlags::SQLITE_OPEN_CREATE;
+    let connection = Connection::open_with_flags(path, flags)?;
+    connection.busy_timeout(BUSY_TIMEOUT)?;
+    let _ = connection.pragma_update(None, "journal_mode", "WAL");
+    Ok(connection)
 }
 
-fn seed_users(conn: &mut Connection, rng: &mut impl Rng) -> Result<(), DbError> {
-    println!("Seeding 1000 users...");
-
-    let tx = conn.transaction()?;
-    let mut stmt = tx.prepare("INSERT INTO users (name, email, role, avatar, status) VALUES (?, ?, ?, ?, ?)")?;
-    let mut used_emails = HashSet::new();
-
-    for i in 0..1000 {
-        let first = choose_str(FIRST_NAMES, rng, "first_names")?;
-        let last = choose_str(LAST_NAMES, rng, "last_names")?;
-        let name = format!("{first} {last}");
-
-        let mut email = format!("{}.{}{}@example.com", first.to_lowercase(), last.to_lowercase(), i);
-        while used_emails.contains(&email) {
-            let suffix: i32 = rng.gen_range(1..=999);
-            email = format!(
-                "{}.{}{}{}@example.com",
-                first.to_lowercase(),
-                last.to_lowercase(),
-                i,
-                suffix
-            );
-        }
-        used_emails.insert(email.clone());
+/// Open the database at `path` read-only, as one of the connections queries
+/// share alongside its writer.
+///
+/// # Errors
+/// Returns `DbError` if the file does not exist or cannot be opened.
+pub fn open_reader(path: &Path) -> Result<Connection, DbError> {
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY
+        | OpenFlags::SQLITE_OPEN_NO_MUTEX
+        | OpenFlags::SQLITE_OPEN_URI;
+    let connection = Connection::open_with_flags(path, flags)?;
+    connection.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(connection)
+}
 
-        let role = choose_str(ROLES, rng, "roles")?;
-        let avatar = choose_str(AVATARS, rng, "avatars")?;
-        let status = choose_str(STATUSES, rng, "statuses")?;
+/// Block until no other process is seeding the database at `db_path`.
+///
+/// The returned file holds an exclusive advisory lock until dropped.
+///
+/// # Errors
+/// Returns `DbError` if the lock file cannot be created or locked.
+pub fn lock_for_seeding(db_path: &Path) -> Result<File, DbError> {
+    let file = open_lock_file(db_path, "seed.lock")?;
+    file.lock()?;
+    Ok(file)
+}
 
-        stmt.execute(params![name, email, role, avatar, status])?;
+/// Become the writer for the database at `db_path` if no other process is,
+/// returning the lock to hold for as long as this process stays writer.
+///
+/// # Errors
+/// Returns `DbError` if the lock file cannot be created or locked.
+#[cfg(feature = "seed")]
+pub fn try_elect_writer(db_path: &Path) -> Result<Option<WriterLock>, DbError> {
+    let file = open_lock_file(db_path, "writer.lock")?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(WriterLock { _file: file })),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(err)) => Err(err.into()),
     }
+}
 
-    drop(stmt);
-    tx.commit()?;
+fn open_lock_file(db_path: &Path, suffix: &str) -> Result<File, DbError> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(sibling_path(db_path, suffix))?;
+    Ok(file)
+}
+
+/// `db_path` with `.<suffix>` appended, as in `demo.db.writer.lock`.
+fn sibling_path(db_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = db_path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
 
+/// Fold the WAL back into the main database file.
+///
+/// Should only be run by the elected writer so instances don't contend on it.
+///
+/// # Errors
+/// Returns `DbError` if the checkpoint pragma fails.
+pub fn checkpoint_wal(conn: &Connection) -> Result<(), DbError> {
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
     Ok(())
 }
 
-fn seed_products(conn: &mut Connection, rng: &mut impl Rng) -> Result<(), DbError> {
-    println!("Seeding 1000 products...");
-
-    let tx = conn.transaction()?;
-    let mut stmt = tx.prepare(
-        "INSERT INTO products (title, description, price, category, stock, rating) VALUES (?, ?, ?, ?, ?, ?)",
-    )?;
-
-    for i in 0..1000 {
-        let adj = choose_str(PRODUCT_ADJECTIVES, rng, "product_adjectives")?;
-        let noun = choose_str(PRODUCT_NOUNS, rng, "product_nouns")?;
-        let index = i + 1;
-        let title = format!("{adj} {noun} {index}");
-        let description = choose_str(PRODUCT_DESCRIPTIONS, rng, "product_descriptions")?;
-        let price = random_float(rng, 9.99, 999.99, 2);
-        let category = choose_str(CATEGORIES, rng, "categories")?;
-        let stock = rng.gen_range(0..=500);
-        let rating = random_float(rng, 1.0, 5.0, 1);
-
-        stmt.execute(params![title, description, price, category, stock, rating])?;
+/// The database file from the environment, or the project's own; on Vercel,
+/// whose project directory is read-only, a copy of it under `/tmp`, which
+/// then exists and is not seeded again.
+#[cfg(feature = "seed")]
+fn resolve_db_path() -> Result<PathBuf, DbError> {
+    if let Some(path) = database_path_from_env() {
+        return Ok(path);
     }
 
-    drop(stmt);
-    tx.commit()?;
-
-    Ok(())
-}
+    let is_vercel = env::var("VERCEL").ok().as_deref() == Some("1") || env::var("VERCEL_ENV").is_ok();
+    let project_db = env::current_dir()?.join("tailwindsql.db");
 
-fn seed_posts(conn: &mut Connection, rng: &mut impl Rng) -> Result<(), DbError> {
-    println!("Seeding 1000 posts...");
-
-    let tx = conn.transaction()?;
-    let mut stmt = tx.prepare(
-        "INSERT INTO posts (title, content, author_id, likes, views, published) VALUES (?, ?, ?, ?, ?, ?)",
-    )?;
-
-    for _ in 0..1000 {
-        let title_template = choose_str(POST_TITLES, rng, "post_titles")?;
-        let term1 = choose_str(TECH_TERMS, rng, "tech_terms")?;
-        let term2 = choose_str(TECH_TERMS, rng, "tech_terms")?;
-        let title = title_template.replacen("{}", term1, 1).replacen("{}", term2, 1);
-        let content = format!(
-            "This is an in-depth article about {term1} and its applications in modern software development. We'll explore best practices, common pitfalls, and advanced techniques."
-        );
-        let author_id = rng.gen_range(1..=1000);
-        let likes = rng.gen_range(0..=10000);
-        let views = likes + rng.gen_range(100..=50000);
-        let published = i32::from(rng.gen_bool(0.8));
-
-        stmt.execute(params![title, content, author_id, likes, views, published])?;
+    if is_vercel {
+        let tmp_db = PathBuf::from("/tmp/tailwindsql.db");
+        if !tmp_db.exists() && project_db.exists() {
+            copy_db_files(&project_db, &tmp_db)?;
+        }
+        return Ok(tmp_db);
     }
 
-    drop(stmt);
-    tx.commit()?;
+    Ok(project_db)
+}
 
-    Ok(())
+/// Resolve the database file named by `DATABASE_PATH`, a plain path, or
+/// failing that the `DATABASE_URL` environment variable.
+#[must_use]
+pub fn database_path_from_env() -> Option<PathBuf> {
+    env::var_os("DATABASE_PATH")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var("DATABASE_URL")
+                .ok()
+                .and_then(|url| parse_database_url(&url))
+        })
 }
 
-fn print_summary(conn: &Connection) -> Result<(), DbError> {
-    let user_count: i64 = conn.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?;
-    let product_count: i64 = conn.query_row("SELECT COUNT(*) FROM products", [], |row| row.get(0))?;
-    let post_count: i64 = conn.query_row("SELECT COUNT(*) FROM posts", [], |row| row.get(0))?;
+/// Whether `url` names a PostgreSQL server rather than a SQLite file.
+#[must_use]
+pub fn is_postgres_url(url: &str) -> bool {
+    let url = url.trim();
+    url.starts_with("postgres://") || url.starts_with("postgresql://")
+}
 
-    println!("\nDatabase seeded successfully!\n");
-    println!("Summary:");
-    println!("   - Users: {user_count}");
-    println!("   - Products: {product_count}");
-    println!("   - Posts: {post_count}");
-    println!("\nReady to query with TailwindSQL!\n");
+/// The DuckDB database named by a `duckdb://<path>` or `duckdb:<path>` URL;
+/// an empty path means an in-memory database (`:memory:`).
+#[must_use]
+pub fn duckdb_path(url: &str) -> Option<PathBuf> {
+    let url = url.trim();
+    let path = url
+        .strip_prefix("duckdb://")
+        .or_else(|| url.strip_prefix("duckdb:"))?;
+    Some(PathBuf::from(if path.is_empty() {
+        ":memory:"
+    } else {
+        path
+    }))
+}
 
-    Ok(())
+/// The JSON file of canned tables named by a `mock://<path>` or `mock:<path>`
+/// URL; see [`mock::MockDatasource::from_json`].
+#[must_use]
+pub fn mock_path(url: &str) -> Option<PathBuf> {
+    let url = url.trim();
+    let path = url
+        .strip_prefix("mock://")
+        .or_else(|| url.strip_prefix("mock:"))?;
+    Some(PathBuf::from(path))
 }
 
-fn choose_str<'a>(items: &'a [&'a str], rng: &mut impl Rng, label: &'static str) -> Result<&'a str, DbError> {
-    items.choose(rng).copied().ok_or(DbError::SeedData(label))
+/// Parse a connection string such as `sqlite:///data/app.db` into a file path.
+///
+/// Accepts `sqlite://`, `sqlite:`, `file://` and `file:` prefixes as well as bare
+/// paths; `sqlite::memory:` and `:memory:` name an in-memory database. Query
+/// strings (`?mode=rwc`) are ignored. Postgres, DuckDB and mock URLs yield
+/// `None`.
+#[must_use]
+pub fn parse_database_url(url: &str) -> Option<PathBuf> {
+    let url = url.trim();
+    if is_postgres_url(url) || duckdb_path(url).is_some() || mock_path(url).is_some() {
+        return None;
+    }
+    let without_query = url.split('?').next().unwrap_or(url);
+    let path = without_query
+        .strip_prefix("sqlite://")
+        .or_else(|| without_query.strip_prefix("sqlite:"))
+        .or_else(|| without_query.strip_prefix("file://"))
+        .or_else(|| without_query.strip_prefix("file:"))
+        .unwrap_or(without_query);
+
+    if path.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(path))
+    }
 }
 
-fn random_float(rng: &mut impl Rng, min: f64, max: f64, decimals: u32) -> f64 {
-    let value = rng.gen_range(min..=max);
-    let exponent = i32::try_from(decimals).unwrap_or(0);
-    let factor = 10_f64.powi(exponent);
-    (value * factor).round() / factor
+#[cfg(feature = "seed")]
+fn copy_db_files(src: &Path, dst: &Path) -> Result<(), DbError> {
+    fs::copy(src, dst)?;
+
+    let wal_src = src.with_extension("db-wal");
+    let wal_dst = dst.with_extension("db-wal");
+    if wal_src.exists() {
+        let _ = fs::copy(wal_src, wal_dst);
+    }
+
+    let shm_src = src.with_extension("db-shm");
+    let shm_dst = dst.with_extension("db-shm");
+    if shm_src.exists() {
+        let _ = fs::copy(shm_src, shm_dst);
+    }
+
+    Ok(())
 }
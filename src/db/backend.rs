@@ -0,0 +1,292 @@
+use std::fmt::Write;
+use std::io;
+
+use rusqlite::types::Value;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use super::DbError;
+use crate::context::{Deadline, QueryContext};
+use crate::dialect::SqlDialect;
+use crate::query_builder::QueryPolicy;
+use crate::render::RowData;
+use crate::schema::SchemaCatalog;
+
+/// A database that built queries can run against.
+///
+/// Methods block; async callers run them on a blocking thread. Bound values
+/// arrive as `rusqlite` values, whatever the backend, and each implementation
+/// converts them to its own types.
+pub trait Backend: Send + Sync {
+    /// The dialect queries for this backend are built with.
+    fn dialect(&self) -> &dyn SqlDialect;
+
+    /// Introspect the tables and views queries may use.
+    ///
+    /// # Errors
+    /// Returns `DbError` if introspection fails.
+    fn load_schema(&self) -> Result<SchemaCatalog, DbError>;
+
+    /// A counter that changes whenever the schema does, if the backend has
+    /// one. The schema refresher polls it to notice DDL between full reloads.
+    ///
+    /// # Errors
+    /// Returns `DbError` if the counter cannot be read.
+    fn schema_version(&self) -> Result<Option<i64>, DbError> {
+        Ok(None)
+    }
+
+    /// Run `sql`, passing each row to `visit` until it returns `false`.
+    /// Returns the result column names.
+    ///
+    /// # Errors
+    /// Returns `DbError` if the query fails, or the error from `visit`.
+    fn for_each_row(
+        &self,
+        sql: &str,
+        params: &[Value],
+        visit: &mut dyn FnMut(RowData) -> Result<bool, DbError>,
+    ) -> Result<Vec<String>, DbError>;
+
+    /// Run a statement that writes rather than reads, such as a built `INSERT`.
+    ///
+    /// # Errors
+    /// Returns `DbError` if the statement fails.
+    fn execute(&self, sql: &str, params: &[Value]) -> Result<Execution, DbError>;
+
+    /// Run `sql`, collecting rows under the row cap, payload cap and memory
+    /// budget of `limits`.
+    ///
+    /// # Errors
+    /// Returns `DbError::MemoryBudget` once the rows read exceed the budget,
+    /// and otherwise the errors of [`Self::for_each_row`].
+    fn query(
+        &self,
+        sql: &str,
+        params: &[Value],
+        limits: &QueryPolicy,
+    ) -> Result<QueryResult, DbError> {
+        self.query_in(sql, params, &QueryContext::new(*limits))
+    }
+
+    /// [`Self::query`] under the limits of `context`, giving up once its
+    /// deadline passes, before the query or between rows.
+    ///
+    /// # Errors
+    /// Returns `DbError::Timeout` past the deadline, and otherwise the
+    /// errors of [`Self::query`].
+    fn query_in(
+        &self,
+        sql: &str,
+        params: &[Value],
+        context: &QueryContext,
+    ) -> Result<QueryResult, DbError> {
+        context.check()?;
+        let mut rows = RowCollector::in_context(context);
+        let columns = self.for_each_row(sql, params, &mut |row| rows.push(row))?;
+        Ok(rows.finish(columns))
+    }
+
+    /// Run a write ending in `RETURNING`, keeping the rows it reports under
+    /// `limits` like [`Self::query`] but counting every one as affected.
+    ///
+    /// # Errors
+    /// Returns the errors of [`Self::query`].
+    fn execute_returning(
+        &self,
+        sql: &str,
+        params: &[Value],
+        limits: &QueryPolicy,
+    ) -> Result<(Execution, QueryResult), DbError> {
+        let mut rows = RowCollector::new(limits);
+        // Read to the end, so every changed row is counted.
+        let columns = self.for_each_row(sql, params, &mut |row| rows.push(row).map(|_| true))?;
+        let execution = Execution {
+            rows_affected: rows.seen(),
+            last_insert_id: None,
+        };
+        Ok((execution, rows.finish(columns)))
+    }
+}
+
+/// What a write statement did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Execution {
+    pub rows_affected: u64,
+    /// Row id of the inserted row, for backends that report one.
+    pub last_insert_id: Option<i64>,
+}
+
+/// Rows read by a query, in column order `columns`.
+#[derive(Debug, Clone, Default)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<RowData>,
+    /// The rows kept when the row cap or the payload cap cut the result
+    /// short, if either did.
+    pub truncated_at: Option<usize>,
+}
+
+/// Collects result rows, reading at most `max_rows + 1` so a result that was
+/// cut short can be told from one that fit exactly, stopping before the row
+/// that would take the JSON past `max_payload_bytes`, and failing once the
+/// rows read exceed `max_result_bytes` or the deadline, if any, passes.
+#[derive(Debug)]
+pub struct RowCollector {
+    max_rows: Option<usize>,
+    budget: Option<usize>,
+    bytes: usize,
+    max_payload: Option<usize>,
+    payload: usize,
+    /// Whether a row was turned away for the payload cap.
+    payload_full: bool,
+    deadline: Option<Deadline>,
+    rows: Vec<RowData>,
+    seen: u64,
+}
+
+impl RowCollector {
+    #[must_use]
+    pub fn new(limits: &QueryPolicy) -> Self {
+        Self {
+            max_rows: limits.max_rows,
+            budget: limits.max_result_bytes,
+            bytes: 0,
+            max_payload: limits.max_payload_bytes,
+            payload: 0,
+            payload_full: false,
+            deadline: None,
+            rows: Vec::new(),
+            seen: 0,
+        }
+    }
+
+    /// A collector for the limits of `context`, failing between rows once
+    /// its deadline passes.
+    #[must_use]
+    pub fn in_context(context: &QueryContext) -> Self {
+        Self {
+            deadline: context.deadline,
+            ..Self::new(&context.limits)
+        }
+    }
+
+    /// Keep `row`; returns whether more rows are wanted.
+    ///
+    /// Callers that must read every row anyway, such as writes with
+    /// `RETURNING`, may keep pushing: rows past the cap are only counted.
+    ///
+    /// # Errors
+    /// Returns `DbError::MemoryBudget` once the rows kept exceed the budget,
+    /// and `DbError::Timeout` once the deadline has passed.
+    pub fn push(&mut self, row: RowData) -> Result<bool, DbError> {
+        if let Some(deadline) = &self.deadline {
+            deadline.check()?;
+        }
+        self.seen += 1;
+        if self.payload_full || self.max_rows.is_some_and(|max| self.rows.len() > max) {
+            return Ok(false);
+        }
+        if let Some(max) = self.max_payload {
+            // Each row after the first also adds a comma.
+            let size = json_size(&row) + usize::from(!self.rows.is_empty());
+            if self.payload.saturating_add(size) > max {
+                self.payload_full = true;
+                return Ok(false);
+            }
+            self.payload += size;
+        }
+        if let Some(budget) = self.budget {
+            self.bytes = self.bytes.saturating_add(row_size(&row));
+            if self.bytes > budget {
+                return Err(DbError::MemoryBudget { budget });
+            }
+        }
+        self.rows.push(row);
+        Ok(self.max_rows.is_none_or(|max| self.rows.len() <= max))
+    }
+
+    /// Every row pushed, kept or not.
+    #[must_use]
+    pub const fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    /// The rows read, dropping the one read past the cap to detect truncation.
+    #[must_use]
+    pub fn finish(self, columns: Vec<String>) -> QueryResult {
+        let mut rows = self.rows;
+        let truncated_at = self.max_rows.filter(|max| rows.len() > *max);
+        if let Some(max) = truncated_at {
+            rows.truncate(max);
+        }
+        let truncated_at = truncated_at.or_else(|| self.payload_full.then_some(rows.len()));
+        QueryResult {
+            columns,
+            rows,
+            truncated_at,
+        }
+    }
+}
+
+/// Rough heap footprint of a converted row: keys, string contents and a
+/// fixed cost per value.
+#[must_use]
+pub fn row_size(row: &RowData) -> usize {
+    row.iter()
+        .map(|(key, value)| {
+            let value = match value {
+                JsonValue::String(text) => text.len(),
+                _ => 0,
+            };
+            key.len() + value + std::mem::size_of::<JsonValue>()
+        })
+        .sum()
+}
+
+/// Bytes `value` serializes to as compact JSON, counted without building it.
+#[must_use]
+pub fn json_size(value: &impl Serialize) -> usize {
+    struct Count(usize);
+
+    impl io::Write for Count {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut count = Count(0);
+    // Only the value can fail to serialize; it then counts what was written.
+    let _ = serde_json::to_writer(&mut count, value);
+    count.0
+}
+
+/// Convert a SQLite value, or a bound parameter, to JSON.
+#[must_use]
+pub fn value_to_json(value: Value) -> JsonValue {
+    match value {
+        Value::Null => JsonValue::Null,
+        Value::Integer(v) => JsonValue::Number(v.into()),
+        Value::Real(v) => {
+            serde_json::Number::from_f64(v).map_or(JsonValue::Null, JsonValue::Number)
+        }
+        Value::Text(v) => JsonValue::String(v),
+        Value::Blob(bytes) => JsonValue::String(blob_hex(&bytes)),
+    }
+}
+
+/// Blobs render as `0x`-prefixed hex.
+#[must_use]
+pub fn blob_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2 + 2);
+    hex.push_str("0x");
+    for byte in bytes {
+        write!(&mut hex, "{byte:02x}").expect("writing to String should not fail");
+    }
+    hex
+}
@@ -0,0 +1,89 @@
+//! Calendar arithmetic for backends that hand dates over as day or
+//! microsecond counts, formatted the way SQLite stores them.
+
+pub const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+/// Parse `YYYY-MM-DD[ HH:MM[:SS[.ffffff]]]` (space or `T` separated) into
+/// microseconds since 1970-01-01.
+#[cfg(feature = "postgres")]
+#[must_use]
+pub fn parse_datetime(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let (date, time) = match text.split_once([' ', 'T']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+
+    let mut micros = 0_i64;
+    if let Some(time) = time {
+        let mut parts = time.splitn(3, ':');
+        let hours: i64 = parts.next()?.parse().ok()?;
+        let minutes: i64 = parts.next()?.parse().ok()?;
+        let seconds: f64 = parts.next().map_or(Some(0.0), |s| s.parse().ok())?;
+        if hours > 23 || minutes > 59 || !(0.0..60.0).contains(&seconds) {
+            return None;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let second_micros = (seconds * 1_000_000.0).round() as i64;
+        micros = (hours * 3600 + minutes * 60) * 1_000_000 + second_micros;
+    }
+    Some(days * MICROS_PER_DAY + micros)
+}
+
+/// Format microseconds since 1970-01-01 like SQLite's `CURRENT_TIMESTAMP`:
+/// `YYYY-MM-DD HH:MM:SS`.
+#[must_use]
+pub fn format_datetime(micros: i64) -> String {
+    let seconds = micros.rem_euclid(MICROS_PER_DAY) / 1_000_000;
+    format!(
+        "{} {:02}:{:02}:{:02}",
+        format_date(micros.div_euclid(MICROS_PER_DAY)),
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Format days since 1970-01-01 as `YYYY-MM-DD`.
+#[must_use]
+pub fn format_date(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+#[cfg(feature = "postgres")]
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Proleptic Gregorian `(year, month, day)` for days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
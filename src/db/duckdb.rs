@@ -0,0 +1,172 @@
+//! DuckDB backend, enabled with the `duckdb` feature.
+//!
+//! Meant for analytics demos: a DuckDB database file, or an in-memory one,
+//! with Parquet, CSV and JSON files registered as views so class names can
+//! query them like tables. Queries are built with [`Postgres`], whose SQL
+//! DuckDB accepts.
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use ::duckdb::types::{Value as DuckValue, ValueRef};
+use ::duckdb::{params_from_iter, Connection};
+use rusqlite::types::Value;
+use serde_json::Value as JsonValue;
+
+use super::datetime::{format_date, format_datetime};
+use super::{blob_hex, Backend, DbError, Execution};
+use crate::dialect::{Postgres, SqlDialect};
+use crate::render::RowData;
+use crate::schema::{ColumnInfo, SchemaCatalog, TableSchema};
+
+/// A DuckDB connection shared behind a lock, as the SQLite one is.
+pub struct DuckDatabase {
+    conn: Mutex<Connection>,
+}
+
+impl DuckDatabase {
+    /// Open the database file at `path`, creating it if missing, or an
+    /// in-memory database for `:memory:`.
+    ///
+    /// # Errors
+    /// Returns `DbError::DuckDb` if the database cannot be opened.
+    pub fn open(path: &Path) -> Result<Self, DbError> {
+        let conn = if path.as_os_str() == ":memory:" {
+            Connection::open_in_memory()?
+        } else {
+            Connection::open(path)?
+        };
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Expose the data file at `path` as the view `name`, read with
+    /// `read_parquet`, `read_csv_auto` or `read_json_auto` by extension.
+    ///
+    /// # Errors
+    /// Returns `DbError::DuckDb` if the view cannot be created.
+    pub fn register_file(&self, name: &str, path: &Path) -> Result<(), DbError> {
+        let reader = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("parquet") => "read_parquet",
+            Some("json" | "ndjson" | "jsonl") => "read_json_auto",
+            _ => "read_csv_auto",
+        };
+        let name = Postgres.quote_identifier(name);
+        let path = path.to_string_lossy().replace('\'', "''");
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        conn.execute_batch(&format!(
+            "CREATE OR REPLACE VIEW {name} AS SELECT * FROM {reader}('{path}')"
+        ))?;
+        Ok(())
+    }
+}
+
+impl Backend for DuckDatabase {
+    fn dialect(&self) -> &dyn SqlDialect {
+        &Postgres
+    }
+
+    /// Tables and views in the `main` schema, registered files included.
+    fn load_schema(&self) -> Result<SchemaCatalog, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let mut stmt = conn.prepare(
+            "SELECT table_name, column_name, data_type FROM information_schema.columns
+             WHERE table_schema = 'main' ORDER BY table_name, ordinal_position",
+        )?;
+        let mut rows = stmt.query([])?;
+
+        let mut tables: Vec<TableSchema> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let table: String = row.get(0)?;
+            let column = ColumnInfo::new(row.get::<_, String>(1)?, row.get::<_, String>(2)?);
+            match tables.last_mut() {
+                Some(last) if last.name == table => last.columns.push(column),
+                _ => tables.push(TableSchema {
+                    name: table,
+                    columns: vec![column],
+                    foreign_keys: Vec::new(),
+                    primary_key: Vec::new(),
+                }),
+            }
+        }
+        Ok(SchemaCatalog::from_tables(tables))
+    }
+
+    fn for_each_row(
+        &self,
+        sql: &str,
+        params: &[Value],
+        visit: &mut dyn FnMut(RowData) -> Result<bool, DbError>,
+    ) -> Result<Vec<String>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let mut stmt = conn.prepare(sql)?;
+        let mut rows = stmt.query(params_from_iter(params.iter().map(duck_value)))?;
+        // DuckDB only knows the result columns once the statement has run.
+        let names = rows
+            .as_ref()
+            .map(|stmt| stmt.column_names())
+            .unwrap_or_default();
+
+        while let Some(row) = rows.next()? {
+            let mut data = RowData::new();
+            for (i, name) in names.iter().enumerate() {
+                data.insert(name.clone(), json_value(row.get_ref(i)?));
+            }
+            if !visit(data)? {
+                break;
+            }
+        }
+        Ok(names)
+    }
+
+    /// DuckDB has no rowid, so inserts report no id.
+    fn execute(&self, sql: &str, params: &[Value]) -> Result<Execution, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let rows = conn.execute(sql, params_from_iter(params.iter().map(duck_value)))?;
+        Ok(Execution {
+            rows_affected: rows as u64,
+            last_insert_id: None,
+        })
+    }
+}
+
+fn duck_value(value: &Value) -> DuckValue {
+    match value {
+        Value::Null => DuckValue::Null,
+        Value::Integer(v) => DuckValue::BigInt(*v),
+        Value::Real(v) => DuckValue::Double(*v),
+        Value::Text(v) => DuckValue::Text(v.clone()),
+        Value::Blob(v) => DuckValue::Blob(v.clone()),
+    }
+}
+
+/// Convert a DuckDB cell to JSON. Nested and interval values, which have no
+/// SQLite counterpart, come back as `null`.
+fn json_value(value: ValueRef<'_>) -> JsonValue {
+    match value {
+        ValueRef::Boolean(v) => JsonValue::Bool(v),
+        ValueRef::TinyInt(v) => v.into(),
+        ValueRef::SmallInt(v) => v.into(),
+        ValueRef::Int(v) => v.into(),
+        ValueRef::BigInt(v) => v.into(),
+        ValueRef::UTinyInt(v) => v.into(),
+        ValueRef::USmallInt(v) => v.into(),
+        ValueRef::UInt(v) => v.into(),
+        ValueRef::UBigInt(v) => v.into(),
+        ValueRef::HugeInt(v) => i64::try_from(v).map_or_else(|_| v.to_string().into(), Into::into),
+        ValueRef::UHugeInt(v) => u64::try_from(v).map_or_else(|_| v.to_string().into(), Into::into),
+        ValueRef::Float(v) => number(f64::from(v)),
+        ValueRef::Double(v) => number(v),
+        ValueRef::Decimal(v) => v.to_string().parse().map_or(JsonValue::Null, number),
+        ValueRef::Timestamp(unit, v) => JsonValue::String(format_datetime(unit.to_micros(v))),
+        ValueRef::Date32(days) => JsonValue::String(format_date(i64::from(days))),
+        ValueRef::Text(text) => JsonValue::String(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(bytes) => JsonValue::String(blob_hex(bytes)),
+        _ => JsonValue::Null,
+    }
+}
+
+fn number(value: f64) -> JsonValue {
+    serde_json::Number::from_f64(value).map_or(JsonValue::Null, JsonValue::Number)
+}
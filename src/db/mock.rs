@@ -0,0 +1,481 @@
+//! Canned in-memory tables for offline development, docs and component tests.
+//!
+//! [`MockDatasource`] answers the single-table `SELECT`s the query builder
+//! emits (simple `WHERE` comparisons and `LIKE`, `ORDER BY`, `LIMIT`/`OFFSET`)
+//! from rows defined in Rust or JSON, with no SQLite involved. Anything else,
+//! such as joins, search or relative dates, fails with `DbError::Mock`.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use rusqlite::types::Value;
+use serde_json::Value as JsonValue;
+
+use super::{value_to_json, Backend, DbError, Execution};
+use crate::dialect::{SqlDialect, Sqlite};
+use crate::render::RowData;
+use crate::schema::{ColumnInfo, SchemaCatalog, TableSchema};
+
+/// Read-only tables of fixed rows that queries run against deterministically.
+///
+/// ```
+/// use serde_json::json;
+/// use tailwindsql::db::mock::MockDatasource;
+/// use tailwindsql::db::Backend;
+/// use tailwindsql::parser::parse_class_names;
+/// use tailwindsql::query_builder::{build_query_for, QueryPolicy};
+///
+/// let db = MockDatasource::new().with_table(
+///     "users",
+///     ["id", "name"],
+///     [vec![json!(1), json!("Ada")], vec![json!(2), json!("Grace")]],
+/// );
+/// let config = parse_class_names("db-users-name-where-id-2").unwrap();
+/// let built = build_query_for(db.dialect(), &config, &QueryPolicy::default()).unwrap();
+/// let result = db.query(&built.sql, &built.params, &QueryPolicy::default()).unwrap();
+/// assert_eq!(result.rows[0]["name"], "Grace");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockDatasource {
+    tables: BTreeMap<String, MockTable>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct MockTable {
+    columns: Vec<String>,
+    rows: Vec<RowData>,
+}
+
+impl MockDatasource {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add (or replace) table `name` with positional `rows`, one value per
+    /// column in `columns` order; missing trailing values are `NULL`.
+    #[must_use]
+    pub fn with_table<C, R>(mut self, name: impl Into<String>, columns: C, rows: R) -> Self
+    where
+        C: IntoIterator,
+        C::Item: Into<String>,
+        R: IntoIterator<Item = Vec<JsonValue>>,
+    {
+        let columns: Vec<String> = columns.into_iter().map(Into::into).collect();
+        let rows = rows
+            .into_iter()
+            .map(|values| {
+                let mut values = values.into_iter();
+                columns
+                    .iter()
+                    .map(|column| (column.clone(), values.next().unwrap_or(JsonValue::Null)))
+                    .collect()
+            })
+            .collect();
+        self.tables.insert(name.into(), MockTable { columns, rows });
+        self
+    }
+
+    /// Tables from JSON shaped `{"users": [{"id": 1, "name": "Ada"}, ...]}`.
+    ///
+    /// Columns are every key seen in a table's rows, in sorted order; rows
+    /// without a key read it as `NULL`.
+    ///
+    /// # Errors
+    /// Returns `DbError::Mock` if `json` is not an object of arrays of objects.
+    pub fn from_json(json: &str) -> Result<Self, DbError> {
+        let invalid = |reason: &str| DbError::Mock(format!("invalid mock data: {reason}"));
+        let tables: BTreeMap<String, Vec<JsonValue>> =
+            serde_json::from_str(json).map_err(|err| invalid(&err.to_string()))?;
+
+        let mut mock = Self::new();
+        for (name, rows) in tables {
+            let mut columns = Vec::<String>::new();
+            let mut objects = Vec::with_capacity(rows.len());
+            for row in rows {
+                let JsonValue::Object(object) = row else {
+                    return Err(invalid(&format!("rows of {name} must be objects")));
+                };
+                for key in object.keys() {
+                    if !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+                objects.push(object);
+            }
+            columns.sort();
+            let rows = objects.into_iter().map(|mut object| {
+                columns
+                    .iter()
+                    .map(|column| object.remove(column).unwrap_or(JsonValue::Null))
+                    .collect()
+            });
+            mock = mock.with_table(name, columns.clone(), rows);
+        }
+        Ok(mock)
+    }
+
+    fn table(&self, name: &str) -> Result<&MockTable, DbError> {
+        self.tables
+            .get(name)
+            .ok_or_else(|| DbError::Mock(format!("no such table: {name}")))
+    }
+
+    fn select(&self, sql: &str, params: &[Value]) -> Result<(Vec<String>, Vec<RowData>), DbError> {
+        let select = Select::parse(sql, params)?;
+        let table = self.table(&select.table)?;
+
+        let mut rows: Vec<&RowData> = Vec::new();
+        for row in &table.rows {
+            if select
+                .conditions
+                .iter()
+                .all(|condition| condition.matches(row))
+            {
+                rows.push(row);
+            }
+        }
+
+        if let Projection::Count(alias) = &select.projection {
+            let mut row = RowData::new();
+            row.insert(alias.clone(), JsonValue::from(rows.len()));
+            return Ok((vec![alias.clone()], vec![row]));
+        }
+
+        if let Some((column, descending)) = &select.order_by {
+            rows.sort_by(|a, b| {
+                let ordering = sort_order(&a[column], &b[column]);
+                if *descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+
+        let columns = match select.projection {
+            Projection::Columns(columns) => {
+                if let Some(unknown) = columns.iter().find(|c| !table.columns.contains(c)) {
+                    return Err(DbError::Mock(format!("no such column: {unknown}")));
+                }
+                columns
+            }
+            _ => table.columns.clone(),
+        };
+        let rows = rows
+            .into_iter()
+            .skip(select.offset)
+            .take(select.limit.unwrap_or(usize::MAX))
+            .map(|row| {
+                columns
+                    .iter()
+                    .map(|column| (column.clone(), row[column].clone()))
+                    .collect()
+            })
+            .collect();
+        Ok((columns, rows))
+    }
+}
+
+impl Backend for MockDatasource {
+    fn dialect(&self) -> &dyn SqlDialect {
+        &Sqlite
+    }
+
+    fn load_schema(&self) -> Result<SchemaCatalog, DbError> {
+        Ok(SchemaCatalog::from_tables(self.tables.iter().map(
+            |(name, table)| {
+                TableSchema {
+                    name: name.clone(),
+                    columns: table
+                        .columns
+                        .iter()
+                        .map(|column| ColumnInfo::new(column, column_type(&table.rows, column)))
+                        .collect(),
+                    foreign_keys: Vec::new(),
+                    primary_key: Vec::new(),
+                }
+            },
+        )))
+    }
+
+    fn for_each_row(
+        &self,
+        sql: &str,
+        params: &[Value],
+        visit: &mut dyn FnMut(RowData) -> Result<bool, DbError>,
+    ) -> Result<Vec<String>, DbError> {
+        let (columns, rows) = self.select(sql, params)?;
+        for row in rows {
+            if !visit(row)? {
+                break;
+            }
+        }
+        Ok(columns)
+    }
+
+    fn execute(&self, _sql: &str, _params: &[Value]) -> Result<Execution, DbError> {
+        Err(DbError::Mock("mock datasources are read-only".to_string()))
+    }
+}
+
+/// The declared type of `column`, from its first non-null value.
+fn column_type(rows: &[RowData], column: &str) -> &'static str {
+    match rows
+        .iter()
+        .map(|row| &row[column])
+        .find(|value| !value.is_null())
+    {
+        Some(JsonValue::Bool(_)) => "BOOLEAN",
+        Some(JsonValue::Number(number)) if number.is_f64() => "REAL",
+        Some(JsonValue::Number(_)) => "INTEGER",
+        _ => "TEXT",
+    }
+}
+
+#[derive(Debug)]
+enum Projection {
+    All,
+    /// `COUNT(*) AS <alias>`.
+    Count(String),
+    Columns(Vec<String>),
+}
+
+#[derive(Debug)]
+enum Condition {
+    Compare {
+        column: String,
+        operator: String,
+        value: JsonValue,
+    },
+    Like {
+        column: String,
+        pattern: String,
+    },
+}
+
+impl Condition {
+    fn matches(&self, row: &RowData) -> bool {
+        match self {
+            Self::Compare {
+                column,
+                operator,
+                value,
+            } => {
+                let Some(ordering) = compare(row.get(column).unwrap_or(&JsonValue::Null), value)
+                else {
+                    return false;
+                };
+                match operator.as_str() {
+                    "=" => ordering == Ordering::Equal,
+                    "!=" | "<>" => ordering != Ordering::Equal,
+                    ">" => ordering == Ordering::Greater,
+                    ">=" => ordering != Ordering::Less,
+                    "<" => ordering == Ordering::Less,
+                    "<=" => ordering != Ordering::Greater,
+                    _ => false,
+                }
+            }
+            Self::Like { column, pattern } => match row.get(column) {
+                Some(JsonValue::String(text)) => like(text, pattern),
+                Some(JsonValue::Null) | None => false,
+                Some(other) => like(&other.to_string(), pattern),
+            },
+        }
+    }
+}
+
+/// A parsed single-table `SELECT`, with placeholders bound from `params`.
+#[derive(Debug)]
+struct Select {
+    projection: Projection,
+    table: String,
+    conditions: Vec<Condition>,
+    order_by: Option<(String, bool)>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+impl Select {
+    fn parse(sql: &str, params: &[Value]) -> Result<Self, DbError> {
+        let unsupported = || DbError::Mock(format!("mock datasources cannot run: {sql}"));
+        let rest = sql.trim().strip_prefix("SELECT ").ok_or_else(unsupported)?;
+        let (select_list, rest) = rest.split_once(" FROM ").ok_or_else(unsupported)?;
+        let projection = match select_list.trim() {
+            "*" => Projection::All,
+            list => match list.strip_prefix("COUNT(*) AS ") {
+                Some(alias) => Projection::Count(unquote(alias)),
+                None => Projection::Columns(list.split(", ").map(unquote).collect()),
+            },
+        };
+
+        let mut params = params.iter();
+        let mut bound = |token: &str| -> Result<JsonValue, DbError> {
+            if token == "?" || token.starts_with(':') || token.starts_with('$') {
+                params
+                    .next()
+                    .cloned()
+                    .map(value_to_json)
+                    .ok_or_else(unsupported)
+            } else {
+                token
+                    .parse::<i64>()
+                    .map(JsonValue::from)
+                    .map_err(|_| unsupported())
+            }
+        };
+
+        let mut tokens = rest.split_whitespace().peekable();
+        let table = unquote(tokens.next().ok_or_else(unsupported)?);
+        let mut select = Self {
+            projection,
+            table,
+            conditions: Vec::new(),
+            order_by: None,
+            limit: None,
+            offset: 0,
+        };
+
+        if tokens.next_if_eq(&"WHERE").is_some() {
+            loop {
+                let column = tokens.next().ok_or_else(unsupported)?;
+                if column.starts_with('(') || column.contains('.') {
+                    return Err(unsupported());
+                }
+                let operator = tokens.next().ok_or_else(unsupported)?;
+                let value = tokens.next().ok_or_else(unsupported)?;
+                let condition = if operator == "LIKE" {
+                    if tokens.next_if_eq(&"ESCAPE").is_some() {
+                        tokens.next();
+                    }
+                    match bound(value)? {
+                        JsonValue::String(pattern) => Condition::Like {
+                            column: unquote(column),
+                            pattern,
+                        },
+                        _ => return Err(unsupported()),
+                    }
+                } else {
+                    if value.contains('(') {
+                        return Err(unsupported());
+                    }
+                    Condition::Compare {
+                        column: unquote(column),
+                        operator: operator.to_string(),
+                        value: bound(value)?,
+                    }
+                };
+                select.conditions.push(condition);
+                if tokens.next_if_eq(&"AND").is_none() {
+                    break;
+                }
+            }
+        }
+
+        if tokens.next_if_eq(&"ORDER").is_some() {
+            if tokens.next() != Some("BY") {
+                return Err(unsupported());
+            }
+            let column = unquote(tokens.next().ok_or_else(unsupported)?);
+            let descending = match tokens.next_if(|token| matches!(*token, "ASC" | "DESC")) {
+                Some(direction) => direction == "DESC",
+                None => false,
+            };
+            select.order_by = Some((column, descending));
+        }
+
+        if tokens.next_if_eq(&"LIMIT").is_some() {
+            let limit = bound(tokens.next().ok_or_else(unsupported)?)?;
+            // SQLite reads a negative limit as none.
+            select.limit = limit.as_i64().and_then(|limit| usize::try_from(limit).ok());
+        }
+        if tokens.next_if_eq(&"OFFSET").is_some() {
+            let offset = bound(tokens.next().ok_or_else(unsupported)?)?;
+            select.offset = offset
+                .as_i64()
+                .and_then(|offset| usize::try_from(offset).ok())
+                .unwrap_or(0);
+        }
+
+        if tokens.next().is_some() {
+            return Err(unsupported());
+        }
+        Ok(select)
+    }
+}
+
+fn unquote(identifier: &str) -> String {
+    let identifier = identifier.trim();
+    identifier
+        .strip_prefix('"')
+        .and_then(|quoted| quoted.strip_suffix('"'))
+        .map_or_else(
+            || identifier.to_string(),
+            |quoted| quoted.replace("\"\"", "\""),
+        )
+}
+
+/// Compare like SQLite with column affinity: numbers against numeric text
+/// compare as numbers, and anything against `NULL` is unknown.
+fn compare(left: &JsonValue, right: &JsonValue) -> Option<Ordering> {
+    match (as_number(left), as_number(right)) {
+        (Some(left), Some(right)) => left.partial_cmp(&right),
+        _ => match (left, right) {
+            (JsonValue::Null, _) | (_, JsonValue::Null) => None,
+            (JsonValue::String(left), JsonValue::String(right)) => Some(left.cmp(right)),
+            _ => Some(sort_order(left, right)),
+        },
+    }
+}
+
+fn as_number(value: &JsonValue) -> Option<f64> {
+    match value {
+        JsonValue::Number(number) => number.as_f64(),
+        JsonValue::Bool(flag) => Some(f64::from(u8::from(*flag))),
+        JsonValue::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// `ORDER BY` order: `NULL`s, then numbers, then text, as SQLite sorts.
+fn sort_order(left: &JsonValue, right: &JsonValue) -> Ordering {
+    let rank = |value: &JsonValue| match value {
+        JsonValue::Null => 0,
+        JsonValue::Bool(_) | JsonValue::Number(_) => 1,
+        JsonValue::String(_) => 2,
+        _ => 3,
+    };
+    rank(left)
+        .cmp(&rank(right))
+        .then_with(|| match (left, right) {
+            (JsonValue::String(left), JsonValue::String(right)) => left.cmp(right),
+            _ => as_number(left)
+                .zip(as_number(right))
+                .and_then(|(left, right)| left.partial_cmp(&right))
+                .unwrap_or(Ordering::Equal),
+        })
+}
+
+/// SQLite `LIKE`: ASCII case-insensitive, `%` and `_` wildcards, `\` escapes.
+fn like(text: &str, pattern: &str) -> bool {
+    fn matches(text: &[char], pattern: &[char]) -> bool {
+        match pattern.split_first() {
+            None => text.is_empty(),
+            Some(('%', rest)) => (0..=text.len()).any(|skip| matches(&text[skip..], rest)),
+            Some(('_', rest)) => !text.is_empty() && matches(&text[1..], rest),
+            Some(('\\', [escaped, rest @ ..])) => {
+                text.first()
+                    .is_some_and(|c| c.eq_ignore_ascii_case(escaped))
+                    && matches(&text[1..], rest)
+            }
+            Some((expected, rest)) => {
+                text.first()
+                    .is_some_and(|c| c.eq_ignore_ascii_case(expected))
+                    && matches(&text[1..], rest)
+            }
+        }
+    }
+    let text: Vec<char> = text.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    matches(&text, &pattern)
+}
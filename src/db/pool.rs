@@ -0,0 +1,171 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use serde::Serialize;
+use tokio::sync::oneshot;
+use tracing::Span;
+
+use super::DbError;
+use crate::context::Deadline;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Dedicated threads for blocking database work.
+///
+/// Queries run here rather than on tokio's shared blocking pool, so a burst
+/// of slow queries queues behind `size` workers instead of starving file
+/// serving and other blocking tasks.
+pub struct WorkerPool {
+    sender: Sender<Job>,
+    size: usize,
+    stats: Arc<PoolStats>,
+}
+
+#[derive(Debug, Default)]
+struct PoolStats {
+    queued: AtomicUsize,
+    active: AtomicUsize,
+    started: AtomicU64,
+    completed: AtomicU64,
+    wait_micros: AtomicU64,
+    max_wait_micros: AtomicU64,
+}
+
+/// A snapshot of [`WorkerPool`] load.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PoolMetrics {
+    pub workers: usize,
+    /// Jobs waiting for a free worker.
+    #[serde(rename = "queueDepth")]
+    pub queue_depth: usize,
+    pub active: usize,
+    pub completed: u64,
+    /// Mean time jobs spent queued before a worker picked them up.
+    #[serde(rename = "meanWaitMicros")]
+    pub mean_wait_micros: u64,
+    #[serde(rename = "maxWaitMicros")]
+    pub max_wait_micros: u64,
+}
+
+impl WorkerPool {
+    /// Start `size` worker threads (at least one).
+    ///
+    /// # Errors
+    /// Returns `DbError::Io` if a thread cannot be spawned.
+    pub fn new(size: usize) -> Result<Self, DbError> {
+        let size = size.max(1);
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..size {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("db-worker-{index}"))
+                .spawn(move || work(&receiver))?;
+        }
+        Ok(Self {
+            sender,
+            size,
+            stats: Arc::new(PoolStats::default()),
+        })
+    }
+
+    /// Run `job` on a worker and wait for its result. The job runs inside the
+    /// caller's tracing span, so its logs keep the caller's request context.
+    ///
+    /// # Errors
+    /// Returns `DbError::Join` if the job panics or the pool has shut down.
+    pub async fn run<T, F>(&self, job: F) -> Result<T, DbError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        self.run_until(None, job).await
+    }
+
+    /// [`Self::run`], giving up at `deadline`: a job still queued then is
+    /// dropped without running, and the caller stops waiting for one still
+    /// running, which should watch the deadline itself.
+    ///
+    /// # Errors
+    /// Returns `DbError::Timeout` once `deadline` passes, and otherwise the
+    /// errors of [`Self::run`].
+    pub async fn run_until<T, F>(&self, deadline: Option<Deadline>, job: F) -> Result<T, DbError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (reply, result) = oneshot::channel();
+        let stats = self.stats.clone();
+        let queued_at = Instant::now();
+        let span = Span::current();
+        stats.queued.fetch_add(1, Ordering::Relaxed);
+        let job: Job = Box::new(move || {
+            stats.queued.fetch_sub(1, Ordering::Relaxed);
+            if let Err(error) = deadline.as_ref().map_or(Ok(()), Deadline::check) {
+                let _ = reply.send(Err(error));
+                return;
+            }
+            let waited = u64::try_from(queued_at.elapsed().as_micros()).unwrap_or(u64::MAX);
+            stats.active.fetch_add(1, Ordering::Relaxed);
+            stats.started.fetch_add(1, Ordering::Relaxed);
+            stats.wait_micros.fetch_add(waited, Ordering::Relaxed);
+            stats.max_wait_micros.fetch_max(waited, Ordering::Relaxed);
+
+            // A panicking job drops `reply`, which the caller sees as a join error.
+            let result = span.in_scope(|| panic::catch_unwind(AssertUnwindSafe(job)));
+            if let Ok(value) = result {
+                let _ = reply.send(Ok(value));
+            }
+
+            stats.active.fetch_sub(1, Ordering::Relaxed);
+            stats.completed.fetch_add(1, Ordering::Relaxed);
+        });
+        if self.sender.send(job).is_err() {
+            self.stats.queued.fetch_sub(1, Ordering::Relaxed);
+            return Err(DbError::Join);
+        }
+        let result = match deadline {
+            Some(deadline) => tokio::time::timeout(deadline.remaining(), result)
+                .await
+                .map_err(|_| DbError::Timeout(deadline.budget()))?,
+            None => result.await,
+        };
+        result.map_err(|_| DbError::Join)?
+    }
+
+    #[must_use]
+    pub fn metrics(&self) -> PoolMetrics {
+        let started = self.stats.started.load(Ordering::Relaxed);
+        PoolMetrics {
+            workers: self.size,
+            queue_depth: self.stats.queued.load(Ordering::Relaxed),
+            active: self.stats.active.load(Ordering::Relaxed),
+            completed: self.stats.completed.load(Ordering::Relaxed),
+            mean_wait_micros: self
+                .stats
+                .wait_micros
+                .load(Ordering::Relaxed)
+                .checked_div(started)
+                .unwrap_or_default(),
+            max_wait_micros: self.stats.max_wait_micros.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        // Hold the lock only while taking a job, not while running it.
+        let job = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+        match job {
+            Ok(job) => job(),
+            Err(_) => return,
+        }
+    }
+}
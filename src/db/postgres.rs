@@ -0,0 +1,535 @@
+//! PostgreSQL backend, enabled with the `postgres` feature.
+//!
+//! Mirrors the SQLite path: the same demo schema and seed data, schema
+//! introspection into a [`SchemaCatalog`], and streaming query execution.
+//! Queries are built with [`crate::dialect::Postgres`] and bound from the same
+//! `rusqlite` values, converted to whatever type Postgres infers for each
+//! placeholder.
+
+use std::error::Error;
+use std::fmt::Write;
+use std::pin::pin;
+
+use bytes::BytesMut;
+use futures_util::StreamExt;
+use rusqlite::types::Value;
+use serde_json::Value as JsonValue;
+use tokio_postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+use tokio_postgres::{Client, NoTls};
+use tracing::error;
+
+use super::datetime::{format_date, format_datetime, parse_datetime, MICROS_PER_DAY};
+use super::{
+    generate_comments, generate_posts, generate_products, generate_users, print_counts, tag_names,
+    DbError, Execution, RELATIONSHIPS, SEED_ROWS, SEED_TABLES,
+};
+use crate::dialect::{Postgres, SqlDialect};
+use crate::render::RowData;
+use crate::schema::{ColumnInfo, ForeignKey, SchemaCatalog, TableSchema};
+
+type BoxError = Box<dyn Error + Sync + Send>;
+
+/// Days from 1970-01-01 to 2000-01-01, the Postgres date epoch.
+const PG_EPOCH_DAYS: i64 = 10_957;
+const PG_EPOCH_MICROS: i64 = PG_EPOCH_DAYS * MICROS_PER_DAY;
+
+/// A connection to a PostgreSQL server.
+pub struct PgDatabase {
+    client: Client,
+}
+
+impl PgDatabase {
+    /// Connect to `url` without TLS, driving the connection on a background task.
+    ///
+    /// # Errors
+    /// Returns `DbError::Postgres` if the server cannot be reached.
+    pub async fn connect(url: &str) -> Result<Self, DbError> {
+        let (client, connection) = tokio_postgres::connect(url, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                error!("postgres connection error: {err}");
+            }
+        });
+        Ok(Self { client })
+    }
+
+    /// Create and seed the demo tables unless a `users` table already exists.
+    /// Returns whether seeding ran.
+    ///
+    /// # Errors
+    /// Returns `DbError` if the check, schema creation or inserts fail.
+    pub async fn seed_if_empty(&mut self) -> Result<bool, DbError> {
+        let exists: bool = self
+            .client
+            .query_one("SELECT to_regclass('public.users') IS NOT NULL", &[])
+            .await?
+            .get(0);
+        if exists {
+            return Ok(false);
+        }
+        self.seed().await?;
+        Ok(true)
+    }
+
+    /// Recreate the demo tables and fill them with sample users, products,
+    /// posts, comments and tags.
+    ///
+    /// # Errors
+    /// Returns `DbError` if schema creation or inserts fail.
+    pub async fn seed(&mut self) -> Result<(), DbError> {
+        println!("TailwindSQL Database Seeder (PostgreSQL)");
+        println!("================================\n");
+
+        // Generate up front: the thread-local RNG cannot be held across awaits.
+        let (users, products, posts, comments) = {
+            let mut rng = rand::thread_rng();
+            (
+                generate_users(&mut rng, SEED_ROWS)?,
+                generate_products(&mut rng, SEED_ROWS)?,
+                generate_posts(&mut rng, SEED_ROWS, SEED_ROWS)?,
+                generate_comments(&mut rng, SEED_ROWS, SEED_ROWS)?,
+            )
+        };
+
+        let tx = self.client.transaction().await?;
+        tx.batch_execute(
+            "
+            DROP TABLE IF EXISTS post_tags;
+            DROP TABLE IF EXISTS tags;
+            DROP TABLE IF EXISTS comments;
+            DROP TABLE IF EXISTS posts;
+            DROP TABLE IF EXISTS products;
+            DROP TABLE IF EXISTS users;
+
+            CREATE TABLE users (
+              id BIGSERIAL PRIMARY KEY,
+              name TEXT NOT NULL,
+              email TEXT UNIQUE NOT NULL,
+              role TEXT NOT NULL,
+              avatar TEXT,
+              status TEXT DEFAULT 'active',
+              created_at TIMESTAMP DEFAULT now()
+            );
+
+            CREATE TABLE products (
+              id BIGSERIAL PRIMARY KEY,
+              title TEXT NOT NULL,
+              description TEXT,
+              price DOUBLE PRECISION NOT NULL,
+              category TEXT NOT NULL,
+              stock BIGINT DEFAULT 0,
+              rating DOUBLE PRECISION DEFAULT 0,
+              created_at TIMESTAMP DEFAULT now()
+            );
+
+            CREATE TABLE posts (
+              id BIGSERIAL PRIMARY KEY,
+              title TEXT NOT NULL,
+              content TEXT,
+              author_id BIGINT REFERENCES users(id),
+              likes BIGINT DEFAULT 0,
+              views BIGINT DEFAULT 0,
+              published BIGINT DEFAULT 0,
+              created_at TIMESTAMP DEFAULT now()
+            );
+
+            CREATE TABLE comments (
+              id BIGSERIAL PRIMARY KEY,
+              post_id BIGINT NOT NULL REFERENCES posts(id),
+              author_id BIGINT NOT NULL REFERENCES users(id),
+              body TEXT NOT NULL,
+              likes BIGINT DEFAULT 0,
+              created_at TIMESTAMP DEFAULT now()
+            );
+
+            CREATE TABLE tags (
+              id BIGSERIAL PRIMARY KEY,
+              name TEXT UNIQUE NOT NULL
+            );
+
+            CREATE TABLE post_tags (
+              post_id BIGINT NOT NULL REFERENCES posts(id),
+              tag_id BIGINT NOT NULL REFERENCES tags(id),
+              PRIMARY KEY (post_id, tag_id)
+            );
+            ",
+        )
+        .await?;
+
+        println!("Seeding {SEED_ROWS} users...");
+        let stmt = tx
+            .prepare(
+                "INSERT INTO users (name, email, role, avatar, status) VALUES ($1, $2, $3, $4, $5)",
+            )
+            .await?;
+        for user in &users {
+            tx.execute(
+                &stmt,
+                &[
+                    &user.name,
+                    &user.email,
+                    &user.role,
+                    &user.avatar,
+                    &user.status,
+                ],
+            )
+            .await?;
+        }
+
+        println!("Seeding {SEED_ROWS} products...");
+        let stmt = tx
+            .prepare(
+                "INSERT INTO products (title, description, price, category, stock, rating) VALUES ($1, $2, $3, $4, $5, $6)",
+            )
+            .await?;
+        for p in &products {
+            tx.execute(
+                &stmt,
+                &[
+                    &p.title,
+                    &p.description,
+                    &p.price,
+                    &p.category,
+                    &p.stock,
+                    &p.rating,
+                ],
+            )
+            .await?;
+        }
+
+        println!("Seeding {SEED_ROWS} posts and their tags...");
+        let stmt = tx.prepare("INSERT INTO tags (name) VALUES ($1)").await?;
+        for name in tag_names() {
+            tx.execute(&stmt, &[&name]).await?;
+        }
+        let stmt = tx
+            .prepare(
+                "INSERT INTO posts (title, content, author_id, likes, views, published) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+            )
+            .await?;
+        let tag_stmt = tx
+            .prepare("INSERT INTO post_tags (post_id, tag_id) VALUES ($1, $2)")
+            .await?;
+        for p in &posts {
+            let post_id: i64 = tx
+                .query_one(
+                    &stmt,
+                    &[
+                        &p.title,
+                        &p.content,
+                        &p.author_id,
+                        &p.likes,
+                        &p.views,
+                        &p.published,
+                    ],
+                )
+                .await?
+                .get(0);
+            for tag_id in &p.tag_ids {
+                tx.execute(&tag_stmt, &[&post_id, tag_id]).await?;
+            }
+        }
+
+        println!("Seeding {} comments...", comments.len());
+        let stmt = tx
+            .prepare(
+                "INSERT INTO comments (post_id, author_id, body, likes) VALUES ($1, $2, $3, $4)",
+            )
+            .await?;
+        for c in &comments {
+            tx.execute(&stmt, &[&c.post_id, &c.author_id, &c.body, &c.likes])
+                .await?;
+        }
+        for relationship in RELATIONSHIPS {
+            tx.batch_execute(&relationship.index_sql()).await?;
+        }
+        tx.commit().await?;
+
+        let mut counts = Vec::with_capacity(SEED_TABLES.len());
+        for table in SEED_TABLES {
+            counts.push((*table, self.row_count(table).await?));
+        }
+        print_counts(&counts);
+        Ok(())
+    }
+
+    /// Introspect the tables and foreign keys in the `public` schema via
+    /// `information_schema`.
+    ///
+    /// # Errors
+    /// Returns `DbError::Postgres` if introspection fails.
+    pub async fn load_schema(&self) -> Result<SchemaCatalog, DbError> {
+        let rows = self
+            .client
+            .query(
+                "SELECT c.table_name::text, c.column_name::text, c.data_type::text
+                 FROM information_schema.columns c
+                 JOIN information_schema.tables t
+                   ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+                 WHERE c.table_schema = 'public' AND t.table_type = 'BASE TABLE'
+                 ORDER BY c.table_name, c.ordinal_position",
+                &[],
+            )
+            .await?;
+
+        let mut tables: Vec<TableSchema> = Vec::new();
+        for row in rows {
+            let table: String = row.get(0);
+            let column: String = row.get(1);
+            let data_type: String = row.get(2);
+            let column = ColumnInfo::new(column, data_type.to_ascii_uppercase());
+            match tables.last_mut() {
+                Some(last) if last.name == table => last.columns.push(column),
+                _ => tables.push(TableSchema {
+                    name: table,
+                    columns: vec![column],
+                    foreign_keys: Vec::new(),
+                    primary_key: Vec::new(),
+                }),
+            }
+        }
+
+        let keys = self
+            .client
+            .query(
+                "SELECT kcu.table_name::text, kcu.column_name::text,
+                        ccu.table_name::text, ccu.column_name::text
+                 FROM information_schema.table_constraints tc
+                 JOIN information_schema.key_column_usage kcu
+                   ON kcu.constraint_schema = tc.constraint_schema
+                  AND kcu.constraint_name = tc.constraint_name
+                 JOIN information_schema.constraint_column_usage ccu
+                   ON ccu.constraint_schema = tc.constraint_schema
+                  AND ccu.constraint_name = tc.constraint_name
+                 WHERE tc.table_schema = 'public' AND tc.constraint_type = 'FOREIGN KEY'
+                 ORDER BY kcu.table_name, kcu.ordinal_position",
+                &[],
+            )
+            .await?;
+        for row in keys {
+            let table: String = row.get(0);
+            if let Some(schema) = tables.iter_mut().find(|schema| schema.name == table) {
+                schema.foreign_keys.push(ForeignKey {
+                    column: row.get(1),
+                    foreign_table: row.get(2),
+                    foreign_column: row.get(3),
+                });
+            }
+        }
+        Ok(SchemaCatalog::from_tables(tables))
+    }
+
+    /// Number of rows in `table`, which is quoted as an identifier.
+    ///
+    /// # Errors
+    /// Returns `DbError::Postgres` if the count fails.
+    pub async fn row_count(&self, table: &str) -> Result<i64, DbError> {
+        let table = Postgres.quote_identifier(table);
+        let row = self
+            .client
+            .query_one(&format!("SELECT COUNT(*) FROM {table}"), &[])
+            .await?;
+        Ok(row.get(0))
+    }
+
+    /// Run `sql`, streaming each row to `visit` until it returns `false`.
+    /// Returns the result column names.
+    ///
+    /// # Errors
+    /// Returns `DbError::Postgres` if the query fails, or the error from `visit`.
+    pub async fn for_each_row<E>(
+        &self,
+        sql: &str,
+        params: &[Value],
+        mut visit: impl FnMut(RowData) -> Result<bool, E>,
+    ) -> Result<Vec<String>, E>
+    where
+        E: From<DbError>,
+    {
+        let pg_err = |err: tokio_postgres::Error| E::from(DbError::Postgres(err));
+        let stmt = self.client.prepare(sql).await.map_err(pg_err)?;
+        let names: Vec<String> = stmt
+            .columns()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect();
+
+        let rows = self
+            .client
+            .query_raw(&stmt, params.iter().map(PgParam))
+            .await
+            .map_err(pg_err)?;
+        let mut rows = pin!(rows);
+        while let Some(row) = rows.next().await {
+            let row = row.map_err(pg_err)?;
+            let mut data = RowData::new();
+            for (i, name) in names.iter().enumerate() {
+                let PgValue(value) = row.try_get(i).map_err(pg_err)?;
+                data.insert(name.clone(), value);
+            }
+            if !visit(data)? {
+                break;
+            }
+        }
+        Ok(names)
+    }
+
+    /// Run a statement that writes rather than reads. Postgres has no
+    /// last-insert id, so none is reported.
+    ///
+    /// # Errors
+    /// Returns `DbError::Postgres` if the statement fails.
+    pub async fn execute(&self, sql: &str, params: &[Value]) -> Result<Execution, DbError> {
+        let stmt = self.client.prepare(sql).await?;
+        let rows = self
+            .client
+            .execute_raw(&stmt, params.iter().map(PgParam))
+            .await?;
+        Ok(Execution {
+            rows_affected: rows,
+            last_insert_id: None,
+        })
+    }
+}
+
+/// A bound value converted to the type Postgres inferred for its placeholder.
+/// The builder binds most values as text, so text is parsed as needed.
+#[derive(Debug)]
+pub struct PgParam<'a>(pub &'a Value);
+
+impl PgParam<'_> {
+    fn integer(&self) -> Result<i64, BoxError> {
+        match self.0 {
+            Value::Integer(value) => Ok(*value),
+            Value::Text(text) => Ok(text.trim().parse()?),
+            other => Err(format!("expected an integer, got {other:?}").into()),
+        }
+    }
+
+    fn float(&self) -> Result<f64, BoxError> {
+        match self.0 {
+            #[allow(clippy::cast_precision_loss)]
+            Value::Integer(value) => Ok(*value as f64),
+            Value::Real(value) => Ok(*value),
+            Value::Text(text) => Ok(text.trim().parse()?),
+            other => Err(format!("expected a number, got {other:?}").into()),
+        }
+    }
+
+    fn boolean(&self) -> Result<bool, BoxError> {
+        match self.0 {
+            Value::Integer(value) => Ok(*value != 0),
+            Value::Text(text) => match text.trim() {
+                "true" | "t" | "1" => Ok(true),
+                "false" | "f" | "0" => Ok(false),
+                other => Err(format!("expected a boolean, got {other:?}").into()),
+            },
+            other => Err(format!("expected a boolean, got {other:?}").into()),
+        }
+    }
+
+    fn text(&self) -> String {
+        match self.0 {
+            Value::Integer(value) => value.to_string(),
+            Value::Real(value) => value.to_string(),
+            Value::Text(text) => text.clone(),
+            Value::Null | Value::Blob(_) => String::new(),
+        }
+    }
+}
+
+impl ToSql for PgParam<'_> {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, BoxError> {
+        if matches!(self.0, Value::Null) {
+            return Ok(IsNull::Yes);
+        }
+        match *ty {
+            Type::BOOL => self.boolean()?.to_sql(ty, out),
+            Type::INT2 => i16::try_from(self.integer()?)?.to_sql(ty, out),
+            Type::INT4 => i32::try_from(self.integer()?)?.to_sql(ty, out),
+            Type::INT8 => self.integer()?.to_sql(ty, out),
+            #[allow(clippy::cast_possible_truncation)]
+            Type::FLOAT4 => (self.float()? as f32).to_sql(ty, out),
+            Type::FLOAT8 => self.float()?.to_sql(ty, out),
+            Type::BYTEA => match self.0 {
+                Value::Blob(bytes) => bytes.to_sql(ty, out),
+                _ => self.text().as_bytes().to_sql(ty, out),
+            },
+            Type::TIMESTAMP | Type::TIMESTAMPTZ => {
+                let text = self.text();
+                let micros = parse_datetime(&text)
+                    .ok_or_else(|| format!("expected a timestamp, got {text:?}"))?
+                    - PG_EPOCH_MICROS;
+                out.extend_from_slice(&micros.to_be_bytes());
+                Ok(IsNull::No)
+            }
+            Type::DATE => {
+                let text = self.text();
+                let days = parse_datetime(&text)
+                    .and_then(|micros| {
+                        i32::try_from(micros.div_euclid(MICROS_PER_DAY) - PG_EPOCH_DAYS).ok()
+                    })
+                    .ok_or_else(|| format!("expected a date, got {text:?}"))?;
+                out.extend_from_slice(&days.to_be_bytes());
+                Ok(IsNull::No)
+            }
+            _ => self.text().to_sql(&Type::TEXT, out),
+        }
+    }
+
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+/// A result cell of any type, decoded to JSON. Types without a natural JSON
+/// form come back as `null`.
+#[derive(Debug)]
+pub struct PgValue(pub JsonValue);
+
+impl<'a> FromSql<'a> for PgValue {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, BoxError> {
+        let value = match *ty {
+            Type::BOOL => JsonValue::Bool(bool::from_sql(ty, raw)?),
+            Type::INT2 => i16::from_sql(ty, raw)?.into(),
+            Type::INT4 => i32::from_sql(ty, raw)?.into(),
+            Type::INT8 => i64::from_sql(ty, raw)?.into(),
+            Type::OID => u32::from_sql(ty, raw)?.into(),
+            Type::FLOAT4 => f64::from(f32::from_sql(ty, raw)?).into(),
+            Type::FLOAT8 => f64::from_sql(ty, raw)?.into(),
+            Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN => {
+                JsonValue::String(String::from_sql(ty, raw)?)
+            }
+            Type::JSON | Type::JSONB => JsonValue::from_sql(ty, raw)?,
+            Type::TIMESTAMP | Type::TIMESTAMPTZ => JsonValue::String(format_datetime(
+                i64::from_be_bytes(raw.try_into()?) + PG_EPOCH_MICROS,
+            )),
+            Type::DATE => {
+                let days = i32::from_be_bytes(raw.try_into()?);
+                JsonValue::String(format_date(i64::from(days) + PG_EPOCH_DAYS))
+            }
+            Type::UUID => {
+                let mut text = String::with_capacity(36);
+                for (i, byte) in raw.iter().enumerate() {
+                    if matches!(i, 4 | 6 | 8 | 10) {
+                        text.push('-');
+                    }
+                    write!(&mut text, "{byte:02x}").expect("writing to String should not fail");
+                }
+                JsonValue::String(text)
+            }
+            _ => JsonValue::Null,
+        };
+        Ok(Self(value))
+    }
+
+    fn from_sql_null(_ty: &Type) -> Result<Self, BoxError> {
+        Ok(Self(JsonValue::Null))
+    }
+
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+}
@@ -0,0 +1,1072 @@
+//! Demo data: the users, products and posts every fresh database starts with.
+//!
+//! [`SeedMode::Scenario`] spreads the same rows over two years instead of
+//! stamping them all with the seeding time: signups grow month on month,
+//! posts and comments follow their authors, and a `sales` table peaks every
+//! November and December. Queries over dates then have a story to tell.
+//!
+//! Behind the `seed` feature so a minimal build does not pull in `rand`.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rusqlite::{params, Connection};
+
+use super::DbError;
+
+mod spec;
+
+pub use spec::{seed_spec, ColumnGenerator, ColumnSpec, SeedSpec, TableSpec};
+
+const FIRST_NAMES: &[&str] = &[
+    "Ada", "Alan", "Grace", "Linus", "Margaret", "Dennis", "Bjarne", "Guido", "Brendan", "Ryan",
+    "James", "Ken", "Brian", "Tim", "Vint", "Donald", "Barbara", "Frances", "Jean", "Radia",
+    "Sophie", "Shafi", "Fei-Fei", "John", "Steve", "Bill", "Elon", "Jeff", "Mark", "Larry",
+    "Sergey", "Satya", "Sundar", "Jensen", "Lisa", "Susan", "Marissa", "Sheryl", "Ginni",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Lovelace",
+    "Turing",
+    "Hopper",
+    "Torvalds",
+    "Hamilton",
+    "Ritchie",
+    "Stroustrup",
+    "van Rossum",
+    "Eich",
+    "Dahl",
+    "Gosling",
+    "Thompson",
+    "Kernighan",
+    "Berners-Lee",
+    "Cerf",
+    "Knuth",
+    "Liskov",
+    "Allen",
+    "Bartik",
+    "Perlman",
+    "Wilson",
+    "Goldwasser",
+    "Li",
+    "McCarthy",
+    "Wozniak",
+    "Gates",
+    "Musk",
+    "Bezos",
+    "Zuckerberg",
+    "Page",
+    "Brin",
+    "Nadella",
+    "Pichai",
+    "Huang",
+    "Su",
+    "Wojcicki",
+    "Mayer",
+    "Sandberg",
+    "Rometty",
+];
+
+const ROLES: &[&str] = &[
+    "admin",
+    "developer",
+    "designer",
+    "manager",
+    "analyst",
+    "engineer",
+    "lead",
+    "intern",
+];
+const STATUSES: &[&str] = &["active", "inactive", "pending", "verified"];
+const AVATARS: &[&str] = &[
+    "coder", "builder", "hacker", "explorer", "penguin", "snake", "coffee", "diamond", "crab",
+    "bolt", "leaf", "rocket", "robot", "chip", "spark",
+];
+
+const PRODUCT_ADJECTIVES: &[&str] = &[
+    "Premium",
+    "Ultra",
+    "Pro",
+    "Elite",
+    "Essential",
+    "Classic",
+    "Modern",
+    "Smart",
+    "Wireless",
+    "Ergonomic",
+];
+const PRODUCT_NOUNS: &[&str] = &[
+    "Keyboard",
+    "Monitor",
+    "Mouse",
+    "Headphones",
+    "Webcam",
+    "Microphone",
+    "Desk",
+    "Chair",
+    "Lamp",
+    "Hub",
+    "Cable",
+    "Stand",
+    "Dock",
+    "Speaker",
+    "Tablet",
+];
+const PRODUCT_DESCRIPTIONS: &[&str] = &[
+    "High-quality build with premium materials",
+    "Perfect for professionals and enthusiasts",
+    "Award-winning design and performance",
+    "Industry-leading technology",
+    "Sleek and modern aesthetic",
+    "Built for comfort and productivity",
+    "Next-generation features",
+    "Eco-friendly and sustainable",
+];
+const CATEGORIES: &[&str] = &[
+    "electronics",
+    "furniture",
+    "accessories",
+    "audio",
+    "lighting",
+    "peripherals",
+    "storage",
+    "networking",
+];
+
+const POST_TITLES: &[&str] = &[
+    "Why {} is the Future of {}",
+    "Getting Started with {}",
+    "10 Tips for Better {}",
+    "The Complete Guide to {}",
+    "How I Built {} with {}",
+    "Understanding {} in {}",
+    "{} vs {}: Which is Better?",
+    "Mastering {} for Beginners",
+    "Advanced {} Techniques",
+    "The State of {} in 2024",
+];
+const TECH_TERMS: &[&str] = &[
+    "React",
+    "TypeScript",
+    "Rust",
+    "Go",
+    "Python",
+    "JavaScript",
+    "SQL",
+    "GraphQL",
+    "Docker",
+    "Kubernetes",
+    "AWS",
+    "Machine Learning",
+    "AI",
+    "Web Development",
+    "Cloud Computing",
+    "DevOps",
+    "Microservices",
+    "REST APIs",
+    "WebAssembly",
+    "Edge Computing",
+];
+const COMMENT_TEMPLATES: &[&str] = &[
+    "Great write-up on {}, thanks!",
+    "We moved to {} last year and never looked back.",
+    "Does this still hold for large {} projects?",
+    "I'd love a follow-up on testing {}.",
+    "Bookmarked. The {} section cleared a lot up for me.",
+    "Not convinced {} is worth the complexity here.",
+    "Any benchmarks comparing this to plain {}?",
+    "Shared this with my team, we're evaluating {} now.",
+];
+
+/// A foreign key between demo tables: `table.column` holds an `id` of
+/// `parent`. Each one is indexed, so joins along it stay cheap, and checked
+/// once seeded, since SQLite does not enforce foreign keys by default.
+pub(crate) struct Relationship {
+    pub table: &'static str,
+    pub column: &'static str,
+    pub parent: &'static str,
+}
+
+pub(crate) const RELATIONSHIPS: &[Relationship] = &[
+    Relationship {
+        table: "posts",
+        column: "author_id",
+        parent: "users",
+    },
+    Relationship {
+        table: "comments",
+        column: "post_id",
+        parent: "posts",
+    },
+    Relationship {
+        table: "comments",
+        column: "author_id",
+        parent: "users",
+    },
+    Relationship {
+        table: "post_tags",
+        column: "post_id",
+        parent: "posts",
+    },
+    Relationship {
+        table: "post_tags",
+        column: "tag_id",
+        parent: "tags",
+    },
+];
+
+/// The sales a scenario database has, as foreign keys.
+const SALE_RELATIONSHIPS: &[Relationship] = &[
+    Relationship {
+        table: "sales",
+        column: "product_id",
+        parent: "products",
+    },
+    Relationship {
+        table: "sales",
+        column: "user_id",
+        parent: "users",
+    },
+];
+
+/// What the demo rows look like over time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SeedMode {
+    /// Every row is created at the time of seeding.
+    #[default]
+    Random,
+    /// Rows spread over the last two years, with growing signups and
+    /// seasonal `sales`.
+    Scenario,
+}
+
+impl SeedMode {
+    /// `random` or `scenario`, in any case.
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "random" => Some(Self::Random),
+            "scenario" => Some(Self::Scenario),
+            _ => None,
+        }
+    }
+}
+
+/// The tables each demo table's rows refer to, which are filled with it.
+/// Posts are written together with their `post_tags`.
+const DEPENDENCIES: &[(&str, &[&str])] = &[
+    ("posts", &["users", "tags", "post_tags"]),
+    ("post_tags", &["posts"]),
+    ("comments", &["posts", "users"]),
+    ("sales", &["users", "products"]),
+];
+
+/// How many demo rows to create, and in which tables.
+///
+/// ```
+/// use tailwindsql::db::SeedOptions;
+///
+/// let options = SeedOptions {
+///     users: 50,
+///     posts: 20,
+///     tables: Some(vec!["comments".to_string()]),
+///     ..SeedOptions::default()
+/// };
+/// // Comments need posts, and posts need their authors and tags.
+/// let filled: Vec<_> = options.filled_tables().unwrap().into_iter().collect();
+/// assert_eq!(filled, ["comments", "post_tags", "posts", "tags", "users"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedOptions {
+    pub users: usize,
+    pub products: usize,
+    /// Posts to write; each gets three comments on average.
+    pub posts: usize,
+    /// Tables to fill, along with the tables their rows refer to; `None`
+    /// fills all of them. The rest are created empty.
+    pub tables: Option<Vec<String>>,
+    pub mode: SeedMode,
+    /// A [`SeedSpec`] file to seed instead of the demo tables, which the
+    /// other options then do not apply to.
+    pub spec: Option<PathBuf>,
+}
+
+impl Default for SeedOptions {
+    fn default() -> Self {
+        Self {
+            users: SEED_ROWS,
+            products: SEED_ROWS,
+            posts: SEED_ROWS,
+            tables: None,
+            mode: SeedMode::Random,
+            spec: None,
+        }
+    }
+}
+
+impl SeedOptions {
+    /// The tables these options fill, by name.
+    ///
+    /// # Errors
+    /// Returns `DbError::SeedTable` for a table the demo data does not have.
+    pub fn filled_tables(&self) -> Result<BTreeSet<&'static str>, DbError> {
+        let known = SEED_TABLES.iter().chain(&["sales"]).copied();
+        let Some(tables) = &self.tables else {
+            return Ok(known.collect());
+        };
+        let mut pending = Vec::with_capacity(tables.len());
+        for table in tables {
+            let name = table.trim();
+            let found = known
+                .clone()
+                .find(|known| known.eq_ignore_ascii_case(name))
+                .ok_or_else(|| DbError::SeedTable(table.clone()))?;
+            pending.push(found);
+        }
+        let mut filled = BTreeSet::new();
+        while let Some(table) = pending.pop() {
+            if filled.insert(table) {
+                let needs = DEPENDENCIES
+                    .iter()
+                    .filter(|(dependent, _)| *dependent == table)
+                    .flat_map(|(_, needs)| needs.iter().copied());
+                pending.extend(needs);
+            }
+        }
+        Ok(filled)
+    }
+}
+
+/// Seed the demo database with sample users, products, posts, their comments
+/// and tags.
+///
+/// # Errors
+/// Returns `DbError` if schema creation or inserts fail.
+pub fn seed_database(conn: &mut Connection) -> Result<(), DbError> {
+    seed_database_with(conn, &SeedOptions::default())
+}
+
+/// [`seed_database`] with as many rows, in as many tables, as `options` ask
+/// for, laid out over time as its `mode` says, or the tables of its `spec`.
+///
+/// # Errors
+/// Returns `DbError` if a table is unknown, the spec is unusable, or schema
+/// creation or inserts fail.
+pub fn seed_database_with(conn: &mut Connection, options: &SeedOptions) -> Result<(), DbError> {
+    let spec = options.spec.as_deref().map(SeedSpec::read).transpose()?;
+    let filled = options.filled_tables()?;
+    println!("TailwindSQL Database Seeder");
+    println!("================================\n");
+    if let Some(spec) = spec {
+        return seed_spec(conn, &spec);
+    }
+
+    create_schema(conn)?;
+
+    let mut rng = rand::thread_rng();
+    if filled.contains("users") {
+        seed_users(conn, &mut rng, options.users)?;
+    }
+    if filled.contains("products") {
+        seed_products(conn, &mut rng, options.products)?;
+    }
+    if filled.contains("tags") {
+        seed_tags(conn)?;
+    }
+    if filled.contains("posts") {
+        seed_posts(conn, &mut rng, options.posts, options.users)?;
+    }
+    if filled.contains("comments") {
+        seed_comments(conn, &mut rng, options.posts, options.users)?;
+    }
+    index_relationships(conn)?;
+    create_search_index(conn, "posts", &["title", "content"])?;
+    create_search_index(conn, "products", &["title", "description"])?;
+    if options.mode == SeedMode::Scenario {
+        seed_scenario(conn, &mut rng, filled.contains("sales"))?;
+    }
+
+    print_summary(conn, options.mode)?;
+
+    Ok(())
+}
+
+fn create_schema(conn: &Connection) -> Result<(), DbError> {
+    conn.execute_batch(
+        "
+        DROP TABLE IF EXISTS posts_fts;
+        DROP TABLE IF EXISTS products_fts;
+        DROP TABLE IF EXISTS sales;
+        DROP TABLE IF EXISTS post_tags;
+        DROP TABLE IF EXISTS tags;
+        DROP TABLE IF EXISTS comments;
+        DROP TABLE IF EXISTS posts;
+        DROP TABLE IF EXISTS products;
+        DROP TABLE IF EXISTS users;
+
+        CREATE TABLE users (
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          name TEXT NOT NULL,
+          email TEXT UNIQUE NOT NULL,
+          role TEXT NOT NULL,
+          avatar TEXT,
+          status TEXT DEFAULT 'active',
+          created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE products (
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          title TEXT NOT NULL,
+          description TEXT,
+          price REAL NOT NULL,
+          category TEXT NOT NULL,
+          stock INTEGER DEFAULT 0,
+          rating REAL DEFAULT 0,
+          created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE posts (
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          title TEXT NOT NULL,
+          content TEXT,
+          author_id INTEGER,
+          likes INTEGER DEFAULT 0,
+          views INTEGER DEFAULT 0,
+          published INTEGER DEFAULT 0,
+          created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+          FOREIGN KEY (author_id) REFERENCES users(id)
+        );
+
+        CREATE TABLE comments (
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          post_id INTEGER NOT NULL,
+          author_id INTEGER NOT NULL,
+          body TEXT NOT NULL,
+          likes INTEGER DEFAULT 0,
+          created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+          FOREIGN KEY (post_id) REFERENCES posts(id),
+          FOREIGN KEY (author_id) REFERENCES users(id)
+        );
+
+        CREATE TABLE tags (
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          name TEXT UNIQUE NOT NULL
+        );
+
+        CREATE TABLE post_tags (
+          post_id INTEGER NOT NULL,
+          tag_id INTEGER NOT NULL,
+          PRIMARY KEY (post_id, tag_id),
+          FOREIGN KEY (post_id) REFERENCES posts(id),
+          FOREIGN KEY (tag_id) REFERENCES tags(id)
+        );
+        ",
+    )?;
+
+    Ok(())
+}
+
+/// A generated demo user, shared by the SQLite and Postgres seeders.
+pub(crate) struct SeedUser {
+    pub name: String,
+    pub email: String,
+    pub role: &'static str,
+    pub avatar: &'static str,
+    pub status: &'static str,
+}
+
+pub(crate) struct SeedProduct {
+    pub title: String,
+    pub description: &'static str,
+    pub price: f64,
+    pub category: &'static str,
+    pub stock: i64,
+    pub rating: f64,
+}
+
+pub(crate) struct SeedPost {
+    pub title: String,
+    pub content: String,
+    pub author_id: i64,
+    pub likes: i64,
+    pub views: i64,
+    pub published: i64,
+    /// Ids of the [`tag_names`] for the terms in the title.
+    pub tag_ids: Vec<i64>,
+}
+
+pub(crate) struct SeedComment {
+    pub post_id: i64,
+    pub author_id: i64,
+    pub body: String,
+    pub likes: i64,
+}
+
+pub(crate) const SEED_ROWS: usize = 1000;
+/// Comments per post, on average.
+const COMMENTS_PER_POST: usize = 3;
+
+/// One tag per tech term, slugged (`machine-learning`); tag ids follow
+/// this order from 1.
+pub(crate) fn tag_names() -> Vec<String> {
+    TECH_TERMS
+        .iter()
+        .map(|term| term.to_lowercase().replace(' ', "-"))
+        .collect()
+}
+
+/// The id [`tag_names`] gives `term`.
+fn tag_id(term: &str) -> Result<i64, DbError> {
+    TECH_TERMS
+        .iter()
+        .position(|known| *known == term)
+        .and_then(|index| i64::try_from(index + 1).ok())
+        .ok_or(DbError::SeedData("tech_terms"))
+}
+
+pub(crate) fn generate_users(rng: &mut impl Rng, count: usize) -> Result<Vec<SeedUser>, DbError> {
+    let mut users = Vec::with_capacity(count);
+    let mut used_emails = HashSet::new();
+
+    for i in 0..count {
+        let first = choose_str(FIRST_NAMES, rng, "first_names")?;
+        let last = choose_str(LAST_NAMES, rng, "last_names")?;
+        let name = format!("{first} {last}");
+
+        let mut email = format!(
+            "{}.{}{}@example.com",
+            first.to_lowercase(),
+            last.to_lowercase(),
+            i
+        );
+        while used_emails.contains(&email) {
+            let suffix: i32 = rng.gen_range(1..=999);
+            email = format!(
+                "{}.{}{}{}@example.com",
+                first.to_lowercase(),
+                last.to_lowercase(),
+                i,
+                suffix
+            );
+        }
+        used_emails.insert(email.clone());
+
+        users.push(SeedUser {
+            name,
+            email,
+            role: choose_str(ROLES, rng, "roles")?,
+            avatar: choose_str(AVATARS, rng, "avatars")?,
+            status: choose_str(STATUSES, rng, "statuses")?,
+        });
+    }
+
+    Ok(users)
+}
+
+pub(crate) fn generate_products(
+    rng: &mut impl Rng,
+    count: usize,
+) -> Result<Vec<SeedProduct>, DbError> {
+    let mut products = Vec::with_capacity(count);
+
+    for i in 0..count {
+        let adj = choose_str(PRODUCT_ADJECTIVES, rng, "product_adjectives")?;
+        let noun = choose_str(PRODUCT_NOUNS, rng, "product_nouns")?;
+        let index = i + 1;
+        products.push(SeedProduct {
+            title: format!("{adj} {noun} {index}"),
+            description: choose_str(PRODUCT_DESCRIPTIONS, rng, "product_descriptions")?,
+            price: random_float(rng, 9.99, 999.99, 2),
+            category: choose_str(CATEGORIES, rng, "categories")?,
+            stock: rng.gen_range(0..=500),
+            rating: random_float(rng, 1.0, 5.0, 1),
+        });
+    }
+
+    Ok(products)
+}
+
+/// `count` posts by the first `users` users.
+pub(crate) fn generate_posts(
+    rng: &mut impl Rng,
+    count: usize,
+    users: usize,
+) -> Result<Vec<SeedPost>, DbError> {
+    let mut posts = Vec::with_capacity(count);
+    let users = id_range(users, "users")?;
+
+    for _ in 0..count {
+        let title_template = choose_str(POST_TITLES, rng, "post_titles")?;
+        let term1 = choose_str(TECH_TERMS, rng, "tech_terms")?;
+        let term2 = choose_str(TECH_TERMS, rng, "tech_terms")?;
+        let title = title_template
+            .replacen("{}", term1, 1)
+            .replacen("{}", term2, 1);
+        let content = format!(
+            "This is an in-depth article about {term1} and its applications in modern software development. We'll explore best practices, common pitfalls, and advanced techniques."
+        );
+        let likes = rng.gen_range(0..=10000);
+        let mut tag_ids = vec![tag_id(term1)?];
+        if title_template.matches("{}").count() > 1 && term2 != term1 {
+            tag_ids.push(tag_id(term2)?);
+        }
+        posts.push(SeedPost {
+            title,
+            content,
+            author_id: rng.gen_range(users.clone()),
+            likes,
+            views: likes + rng.gen_range(100..=50000),
+            published: i64::from(rng.gen_bool(0.8)),
+            tag_ids,
+        });
+    }
+
+    Ok(posts)
+}
+
+/// Comments on the first `posts` posts by the first `users` users.
+pub(crate) fn generate_comments(
+    rng: &mut impl Rng,
+    posts: usize,
+    users: usize,
+) -> Result<Vec<SeedComment>, DbError> {
+    let count = posts * COMMENTS_PER_POST;
+    let mut comments = Vec::with_capacity(count);
+    let (posts, users) = (id_range(posts, "posts")?, id_range(users, "users")?);
+
+    for _ in 0..count {
+        let template = choose_str(COMMENT_TEMPLATES, rng, "comment_templates")?;
+        let term = choose_str(TECH_TERMS, rng, "tech_terms")?;
+        comments.push(SeedComment {
+            post_id: rng.gen_range(posts.clone()),
+            author_id: rng.gen_range(users.clone()),
+            body: template.replacen("{}", term, 1),
+            likes: rng.gen_range(0..=200),
+        });
+    }
+
+    Ok(comments)
+}
+
+/// Ids `1..=count`, for rows referring to the `count` rows of `table`.
+fn id_range(count: usize, table: &'static str) -> Result<std::ops::RangeInclusive<i64>, DbError> {
+    match i64::try_from(count) {
+        Ok(last) if last > 0 => Ok(1..=last),
+        _ => Err(DbError::SeedData(table)),
+    }
+}
+
+fn seed_users(conn: &mut Connection, rng: &mut impl Rng, count: usize) -> Result<(), DbError> {
+    println!("Seeding {count} users...");
+
+    let tx = conn.transaction()?;
+    let mut stmt =
+        tx.prepare("INSERT INTO users (name, email, role, avatar, status) VALUES (?, ?, ?, ?, ?)")?;
+    for user in generate_users(rng, count)? {
+        stmt.execute(params![
+            user.name,
+            user.email,
+            user.role,
+            user.avatar,
+            user.status
+        ])?;
+    }
+
+    drop(stmt);
+    tx.commit()?;
+
+    Ok(())
+}
+
+fn seed_products(conn: &mut Connection, rng: &mut impl Rng, count: usize) -> Result<(), DbError> {
+    println!("Seeding {count} products...");
+
+    let tx = conn.transaction()?;
+    let mut stmt = tx.prepare(
+        "INSERT INTO products (title, description, price, category, stock, rating) VALUES (?, ?, ?, ?, ?, ?)",
+    )?;
+    for p in generate_products(rng, count)? {
+        stmt.execute(params![
+            p.title,
+            p.description,
+            p.price,
+            p.category,
+            p.stock,
+            p.rating
+        ])?;
+    }
+
+    drop(stmt);
+    tx.commit()?;
+
+    Ok(())
+}
+
+fn seed_tags(conn: &mut Connection) -> Result<(), DbError> {
+    let tx = conn.transaction()?;
+    let mut stmt = tx.prepare("INSERT INTO tags (name) VALUES (?)")?;
+    for name in tag_names() {
+        stmt.execute([name])?;
+    }
+    drop(stmt);
+    tx.commit()?;
+
+    Ok(())
+}
+
+fn seed_posts(
+    conn: &mut Connection,
+    rng: &mut impl Rng,
+    count: usize,
+    users: usize,
+) -> Result<(), DbError> {
+    println!("Seeding {count} posts and their tags...");
+
+    let tx = conn.transaction()?;
+    let mut stmt = tx.prepare(
+        "INSERT INTO posts (title, content, author_id, likes, views, published) VALUES (?, ?, ?, ?, ?, ?)",
+    )?;
+    let mut tag_stmt = tx.prepare("INSERT INTO post_tags (post_id, tag_id) VALUES (?, ?)")?;
+    for p in generate_posts(rng, count, users)? {
+        stmt.execute(params![
+            p.title,
+            p.content,
+            p.author_id,
+            p.likes,
+            p.views,
+            p.published
+        ])?;
+        let post_id = tx.last_insert_rowid();
+        for tag_id in p.tag_ids {
+            tag_stmt.execute(params![post_id, tag_id])?;
+        }
+    }
+
+    drop(tag_stmt);
+    drop(stmt);
+    tx.commit()?;
+
+    Ok(())
+}
+
+fn seed_comments(
+    conn: &mut Connection,
+    rng: &mut impl Rng,
+    posts: usize,
+    users: usize,
+) -> Result<(), DbError> {
+    let comments = generate_comments(rng, posts, users)?;
+    println!("Seeding {} comments...", comments.len());
+
+    let tx = conn.transaction()?;
+    let mut stmt =
+        tx.prepare("INSERT INTO comments (post_id, author_id, body, likes) VALUES (?, ?, ?, ?)")?;
+    for c in comments {
+        stmt.execute(params![c.post_id, c.author_id, c.body, c.likes])?;
+    }
+
+    drop(stmt);
+    tx.commit()?;
+
+    Ok(())
+}
+
+fn index_relationships(conn: &Connection) -> Result<(), DbError> {
+    check_relationships(conn, RELATIONSHIPS)
+}
+
+fn check_relationships(conn: &Connection, relationships: &[Relationship]) -> Result<(), DbError> {
+    for relationship in relationships {
+        conn.execute_batch(&relationship.index_sql())?;
+        let orphans: i64 = conn.query_row(&relationship.orphans_sql(), [], |row| row.get(0))?;
+        if orphans > 0 {
+            return Err(DbError::SeedData("relationships"));
+        }
+    }
+    Ok(())
+}
+
+impl Relationship {
+    /// `CREATE INDEX` for the referencing column, valid in SQLite and Postgres.
+    pub(crate) fn index_sql(&self) -> String {
+        let Self { table, column, .. } = self;
+        format!("CREATE INDEX IF NOT EXISTS idx_{table}_{column} ON {table} ({column});")
+    }
+
+    /// Counts rows whose `column` names no `parent` row.
+    fn orphans_sql(&self) -> String {
+        let Self {
+            table,
+            column,
+            parent,
+        } = self;
+        format!("SELECT COUNT(*) FROM {table} WHERE {column} NOT IN (SELECT id FROM {parent})")
+    }
+}
+
+/// Build an external-content FTS5 index `<table>_fts` over `columns`, kept in
+/// sync with the base table by triggers.
+fn create_search_index(conn: &Connection, table: &str, columns: &[&str]) -> Result<(), DbError> {
+    println!("Indexing {table} for full-text search...");
+
+    let fts = format!("{table}_fts");
+    let cols = columns.join(", ");
+    let new_cols = columns
+        .iter()
+        .map(|c| format!("new.{c}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let old_cols = columns
+        .iter()
+        .map(|c| format!("old.{c}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    conn.execute_batch(&format!(
+        "
+        CREATE VIRTUAL TABLE {fts} USING fts5({cols}, content='{table}', content_rowid='id');
+        INSERT INTO {fts}({fts}) VALUES ('rebuild');
+
+        CREATE TRIGGER {fts}_ai AFTER INSERT ON {table} BEGIN
+          INSERT INTO {fts}(rowid, {cols}) VALUES (new.id, {new_cols});
+        END;
+        CREATE TRIGGER {fts}_ad AFTER DELETE ON {table} BEGIN
+          INSERT INTO {fts}({fts}, rowid, {cols}) VALUES ('delete', old.id, {old_cols});
+        END;
+        CREATE TRIGGER {fts}_au AFTER UPDATE ON {table} BEGIN
+          INSERT INTO {fts}({fts}, rowid, {cols}) VALUES ('delete', old.id, {old_cols});
+          INSERT INTO {fts}(rowid, {cols}) VALUES (new.id, {new_cols});
+        END;
+        "
+    ))?;
+
+    Ok(())
+}
+
+/// The demo tables, in the order the summary lists them.
+pub(crate) const SEED_TABLES: &[&str] = &[
+    "users",
+    "products",
+    "posts",
+    "comments",
+    "tags",
+    "post_tags",
+];
+
+fn print_summary(conn: &Connection, mode: SeedMode) -> Result<(), DbError> {
+    let mut tables = SEED_TABLES.to_vec();
+    if mode == SeedMode::Scenario {
+        tables.push("sales");
+    }
+    let mut counts = Vec::with_capacity(tables.len());
+    for table in tables {
+        let count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+            row.get(0)
+        })?;
+        counts.push((table, count));
+    }
+    print_counts(&counts);
+    Ok(())
+}
+
+pub(crate) fn print_counts(counts: &[(&str, i64)]) {
+    println!("\nDatabase seeded successfully!\n");
+    println!("Summary:");
+    for (table, count) in counts {
+        println!("   - {table}: {count}");
+    }
+    println!("\nReady to query with TailwindSQL!\n");
+}
+
+/// How far back a scenario reaches.
+const SCENARIO_DAYS: i64 = 730;
+const DAY_SECONDS: i64 = 86_400;
+/// Signups in the last month over those in the first, roughly.
+const SIGNUP_GROWTH: f64 = 10.0;
+/// Sales a day at the start of the scenario, before seasonality; the rate
+/// grows linearly to twice this by today.
+const DAILY_SALES: f64 = 4.0;
+/// How busy each month is, January first: quiet after the holidays, a
+/// summer lull, and the run-up to the end of the year.
+const SEASONALITY: [f64; 12] = [0.7, 0.8, 0.9, 1.0, 1.0, 0.8, 0.7, 0.8, 1.0, 1.1, 1.6, 2.0];
+
+/// Backdate the seeded rows and add a year-on-year `sales` history.
+///
+/// Every age is in seconds before now, stored the way `CURRENT_TIMESTAMP`
+/// writes them, so `after`/`before` filters treat them like any other row.
+fn seed_scenario(conn: &mut Connection, rng: &mut impl Rng, sales: bool) -> Result<(), DbError> {
+    println!("Spreading rows over {SCENARIO_DAYS} days and seeding sales...");
+
+    let tx = conn.transaction()?;
+    let span = SCENARIO_DAYS * DAY_SECONDS;
+
+    // Newer signups are likelier: ages follow an exponential density whose
+    // rate makes the last month about `SIGNUP_GROWTH` times the first.
+    let rate = SIGNUP_GROWTH.ln() / (SCENARIO_DAYS as f64);
+    let floor = (-rate * SCENARIO_DAYS as f64).exp();
+    let user_ids: Vec<i64> = ids(&tx, "SELECT id FROM users ORDER BY id")?;
+    let mut signups = HashMap::with_capacity(user_ids.len());
+    for id in user_ids {
+        let unit: f64 = rng.gen();
+        let days = -(1.0 - unit * (1.0 - floor)).ln() / rate;
+        let age = ((days * DAY_SECONDS as f64) as i64).clamp(0, span);
+        signups.insert(id, age);
+    }
+    backdate(&tx, "users", signups.iter().map(|(id, age)| (*id, *age)))?;
+
+    // The catalogue was in place before the first sale.
+    let products: Vec<(i64, f64)> = tx
+        .prepare("SELECT id, price FROM products ORDER BY id")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    backdate(
+        &tx,
+        "products",
+        products
+            .iter()
+            .map(|(id, _)| (*id, span + rng.gen_range(0..90 * DAY_SECONDS))),
+    )?;
+
+    // A post comes after its author signed up, a comment after both the
+    // post and its author.
+    let mut posted = HashMap::new();
+    for (id, author) in pairs(&tx, "SELECT id, author_id FROM posts")? {
+        let joined = signups.get(&author).copied().unwrap_or(span);
+        posted.insert(id, rng.gen_range(0..=joined));
+    }
+    backdate(&tx, "posts", posted.iter().map(|(id, age)| (*id, *age)))?;
+    let mut commented = Vec::new();
+    for (id, post, author) in triples(&tx, "SELECT id, post_id, author_id FROM comments")? {
+        let post_age = posted.get(&post).copied().unwrap_or(0);
+        let joined = signups.get(&author).copied().unwrap_or(span);
+        commented.push((id, rng.gen_range(0..=post_age.min(joined))));
+    }
+    backdate(&tx, "comments", commented.into_iter())?;
+
+    seed_sales(&tx, rng, &signups, if sales { &products } else { &[] })?;
+    tx.commit()?;
+
+    check_relationships(conn, SALE_RELATIONSHIPS)?;
+    Ok(())
+}
+
+/// A day-by-day sales history: the daily rate grows over the scenario and
+/// follows [`SEASONALITY`], and buyers only buy once they have signed up.
+fn seed_sales(
+    tx: &Connection,
+    rng: &mut impl Rng,
+    signups: &HashMap<i64, i64>,
+    products: &[(i64, f64)],
+) -> Result<(), DbError> {
+    tx.execute_batch(
+        "
+        CREATE TABLE sales (
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          product_id INTEGER NOT NULL,
+          user_id INTEGER NOT NULL,
+          quantity INTEGER NOT NULL,
+          amount REAL NOT NULL,
+          sold_at DATETIME NOT NULL,
+          FOREIGN KEY (product_id) REFERENCES products(id),
+          FOREIGN KEY (user_id) REFERENCES users(id)
+        );
+        ",
+    )?;
+    // Without products or buyers, e.g. when `sales` was not asked for, the
+    // table stays empty.
+    if products.is_empty() || signups.is_empty() {
+        return Ok(());
+    }
+
+    // Longest-standing users first, so those signed up by a given age are
+    // a prefix.
+    let mut buyers: Vec<(i64, i64)> = signups.iter().map(|(id, age)| (*age, *id)).collect();
+    buyers.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut month = tx.prepare("SELECT CAST(strftime('%m', 'now', ?) AS INTEGER)")?;
+    let mut insert = tx.prepare(
+        "INSERT INTO sales (product_id, user_id, quantity, amount, sold_at) \
+         VALUES (?, ?, ?, ?, datetime('now', ?))",
+    )?;
+    for day in (0..SCENARIO_DAYS).rev() {
+        let number: usize = month.query_row([format!("-{day} days")], |row| row.get(0))?;
+        let season = SEASONALITY
+            .get(number.wrapping_sub(1))
+            .ok_or(DbError::SeedData("seasonality"))?;
+        let growth = 2.0 - day as f64 / SCENARIO_DAYS as f64;
+        let expected = DAILY_SALES * growth * season;
+        let sales = (expected + rng.gen::<f64>()) as usize;
+        for _ in 0..sales {
+            let age = day * DAY_SECONDS + rng.gen_range(0..DAY_SECONDS);
+            let signed_up = buyers.partition_point(|(joined, _)| *joined >= age);
+            // Nobody to sell to before the first signup.
+            let Some(&(_, user)) = buyers[..signed_up].choose(rng) else {
+                continue;
+            };
+            let &(product, price) = products.choose(rng).ok_or(DbError::SeedData("products"))?;
+            let quantity: i64 = rng.gen_range(1..=3);
+            let amount = (price * quantity as f64 * 100.0).round() / 100.0;
+            insert.execute(params![
+                product,
+                user,
+                quantity,
+                amount,
+                format!("-{age} seconds")
+            ])?;
+        }
+    }
+    Ok(())
+}
+
+/// Set `created_at` of each `(id, age)` in `table` to `age` seconds ago.
+fn backdate(
+    tx: &Connection,
+    table: &str,
+    ages: impl Iterator<Item = (i64, i64)>,
+) -> Result<(), DbError> {
+    let mut stmt = tx.prepare(&format!(
+        "UPDATE {table} SET created_at = datetime('now', ?) WHERE id = ?"
+    ))?;
+    for (id, age) in ages {
+        stmt.execute(params![format!("-{age} seconds"), id])?;
+    }
+    Ok(())
+}
+
+fn ids(conn: &Connection, sql: &str) -> Result<Vec<i64>, DbError> {
+    Ok(conn
+        .prepare(sql)?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?)
+}
+
+fn pairs(conn: &Connection, sql: &str) -> Result<Vec<(i64, i64)>, DbError> {
+    Ok(conn
+        .prepare(sql)?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?)
+}
+
+fn triples(conn: &Connection, sql: &str) -> Result<Vec<(i64, i64, i64)>, DbError> {
+    Ok(conn
+        .prepare(sql)?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<_, _>>()?)
+}
+
+fn choose_str<'a>(
+    items: &'a [&'a str],
+    rng: &mut impl Rng,
+    label: &'static str,
+) -> Result<&'a str, DbError> {
+    items.choose(rng).copied().ok_or(DbError::SeedData(label))
+}
+
+fn random_float(rng: &mut impl Rng, min: f64, max: f64, decimals: u32) -> f64 {
+    let value = rng.gen_range(min..=max);
+    let exponent = i32::try_from(decimals).unwrap_or(0);
+    let factor = 10_f64.powi(exponent);
+    (value * factor).round() / factor
+}
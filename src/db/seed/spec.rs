@@ -0,0 +1,381 @@
+//! Seed tables described in a spec file instead of the built-in demo.
+//!
+//! A spec lists tables in the order they are filled, how many rows each
+//! gets, and where each column's values come from:
+//!
+//! ```toml
+//! [[tables]]
+//! name = "customers"
+//! rows = 200
+//! columns = [
+//!   { name = "name", generator = "name" },
+//!   { name = "email", generator = "email" },
+//!   { name = "age", generator = "int-range", min = 18, max = 90 },
+//!   { name = "plan", generator = "choice", choices = ["free", "pro", "team"] },
+//! ]
+//!
+//! [[tables]]
+//! name = "orders"
+//! rows = 1000
+//! columns = [
+//!   { name = "customer_id", generator = "reference", table = "customers" },
+//!   { name = "total", generator = "float-range", min = 5, max = 500 },
+//!   { name = "paid", generator = "bool" },
+//!   { name = "placed_at", generator = "timestamp", days = 90 },
+//! ]
+//! ```
+//!
+//! The same spec can be written as JSON, with `tables` as its only key.
+//! Every table gets an `id INTEGER PRIMARY KEY`, which `reference` columns
+//! point at; a column's `type` overrides the SQL type its generator implies.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use serde::Deserialize;
+
+use super::{
+    choose_str, print_counts, random_float, DbError, DAY_SECONDS, FIRST_NAMES, LAST_NAMES,
+};
+use crate::parser::expr::is_identifier;
+
+/// Tables to create and fill, in order.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SeedSpec {
+    pub tables: Vec<TableSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TableSpec {
+    pub name: String,
+    pub rows: usize,
+    /// The columns besides `id`.
+    pub columns: Vec<ColumnSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ColumnSpec {
+    pub name: String,
+    /// The declared SQL type, such as `TEXT`; the generator's when unset.
+    #[serde(rename = "type", default)]
+    pub sql_type: Option<String>,
+    #[serde(flatten)]
+    pub generator: ColumnGenerator,
+}
+
+/// Where a column's values come from, named by its `generator` key.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "generator", rename_all = "kebab-case")]
+pub enum ColumnGenerator {
+    /// A full name, such as `Ada Lovelace`.
+    Name,
+    /// An address no other row of the table has.
+    Email,
+    /// A whole number from `min` to `max`.
+    IntRange { min: i64, max: i64 },
+    /// A number from `min` to `max`, rounded to `decimals` places.
+    FloatRange {
+        min: f64,
+        max: f64,
+        #[serde(default = "default_decimals")]
+        decimals: u32,
+    },
+    /// One of `choices`.
+    Choice { choices: Vec<String> },
+    /// 1 or 0.
+    Bool,
+    /// A time within the last `days` days.
+    Timestamp {
+        #[serde(default = "default_days")]
+        days: u32,
+    },
+    /// The id of a row of `table`, which must come earlier in the spec.
+    Reference { table: String },
+}
+
+const fn default_decimals() -> u32 {
+    2
+}
+
+const fn default_days() -> u32 {
+    365
+}
+
+impl SeedSpec {
+    /// A spec written as TOML.
+    ///
+    /// # Errors
+    /// Returns `DbError::SeedSpec` if `text` is not a valid spec.
+    pub fn from_toml(text: &str) -> Result<Self, DbError> {
+        let spec: Self =
+            basic_toml::from_str(text).map_err(|error| DbError::SeedSpec(error.to_string()))?;
+        spec.check()?;
+        Ok(spec)
+    }
+
+    /// A spec written as JSON.
+    ///
+    /// # Errors
+    /// Returns `DbError::SeedSpec` if `text` is not a valid spec.
+    pub fn from_json(text: &str) -> Result<Self, DbError> {
+        let spec: Self =
+            serde_json::from_str(text).map_err(|error| DbError::SeedSpec(error.to_string()))?;
+        spec.check()?;
+        Ok(spec)
+    }
+
+    /// The spec at `path`: JSON if it ends in `.json`, TOML otherwise.
+    ///
+    /// # Errors
+    /// Returns `DbError::Io` if the file cannot be read, and
+    /// `DbError::SeedSpec` if it is not a valid spec.
+    pub fn read(path: &Path) -> Result<Self, DbError> {
+        let text = fs::read_to_string(path)?;
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+        {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        }
+    }
+
+    /// Check that every name is an identifier used once, every range is
+    /// ordered and every reference points at an earlier table with rows.
+    ///
+    /// # Errors
+    /// Returns `DbError::SeedSpec` naming the first problem.
+    pub fn check(&self) -> Result<(), DbError> {
+        let invalid = |message: String| Err(DbError::SeedSpec(message));
+        if self.tables.is_empty() {
+            return invalid("no tables".to_string());
+        }
+        let mut rows: HashMap<String, usize> = HashMap::new();
+        for table in &self.tables {
+            let name = &table.name;
+            if !is_identifier(name) {
+                return invalid(format!("table name {name:?} is not an identifier"));
+            }
+            if rows.contains_key(&name.to_ascii_lowercase()) {
+                return invalid(format!("table {name:?} is listed twice"));
+            }
+            let mut columns = HashSet::from(["id".to_string()]);
+            for column in &table.columns {
+                let at = format!("{name}.{}", column.name);
+                if !is_identifier(&column.name) {
+                    return invalid(format!("column name {at:?} is not an identifier"));
+                }
+                if !columns.insert(column.name.to_ascii_lowercase()) {
+                    return invalid(format!("column {at:?} is listed twice, or is `id`"));
+                }
+                if let Some(sql_type) = &column.sql_type {
+                    if !sql_type.split_whitespace().all(is_identifier) || sql_type.trim().is_empty()
+                    {
+                        return invalid(format!("type {sql_type:?} of {at} is not a SQL type"));
+                    }
+                }
+                match &column.generator {
+                    ColumnGenerator::IntRange { min, max } if min > max => {
+                        return invalid(format!("{at} has min {min} above max {max}"));
+                    }
+                    ColumnGenerator::FloatRange { min, max, .. }
+                        if !(min.is_finite() && max.is_finite() && min <= max) =>
+                    {
+                        return invalid(format!("{at} has no numbers from {min} to {max}"));
+                    }
+                    ColumnGenerator::Choice { choices } if choices.is_empty() => {
+                        return invalid(format!("{at} has no choices"));
+                    }
+                    ColumnGenerator::Reference { table: parent } => {
+                        match rows.get(&parent.to_ascii_lowercase()) {
+                            None => {
+                                return invalid(format!(
+                                    "{at} refers to {parent:?}, which is not an earlier table"
+                                ));
+                            }
+                            Some(0) if table.rows > 0 => {
+                                return invalid(format!(
+                                    "{at} refers to {parent:?}, which is empty"
+                                ));
+                            }
+                            Some(_) => {}
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            rows.insert(name.to_ascii_lowercase(), table.rows);
+        }
+        Ok(())
+    }
+}
+
+impl TableSpec {
+    fn create_sql(&self) -> String {
+        let mut columns = vec!["id INTEGER PRIMARY KEY AUTOINCREMENT".to_string()];
+        columns.extend(self.columns.iter().map(ColumnSpec::definition));
+        format!(
+            "CREATE TABLE \"{}\" (\n  {}\n);",
+            self.name,
+            columns.join(",\n  ")
+        )
+    }
+
+    fn insert_sql(&self) -> String {
+        if self.columns.is_empty() {
+            return format!("INSERT INTO \"{}\" DEFAULT VALUES", self.name);
+        }
+        let names: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| format!("\"{}\"", column.name))
+            .collect();
+        let values: Vec<&str> = self
+            .columns
+            .iter()
+            .map(|column| match column.generator {
+                ColumnGenerator::Timestamp { .. } => "datetime('now', ?)",
+                _ => "?",
+            })
+            .collect();
+        format!(
+            "INSERT INTO \"{}\" ({}) VALUES ({})",
+            self.name,
+            names.join(", "),
+            values.join(", ")
+        )
+    }
+
+    /// `CREATE INDEX` for each `reference` column.
+    fn index_sql(&self) -> String {
+        let table = &self.name;
+        self.columns
+            .iter()
+            .filter(|column| matches!(column.generator, ColumnGenerator::Reference { .. }))
+            .map(|column| {
+                let column = &column.name;
+                format!("CREATE INDEX \"idx_{table}_{column}\" ON \"{table}\" (\"{column}\");")
+            })
+            .collect()
+    }
+}
+
+impl ColumnSpec {
+    fn definition(&self) -> String {
+        let sql_type = self.sql_type.as_deref().unwrap_or(match self.generator {
+            ColumnGenerator::Name | ColumnGenerator::Email | ColumnGenerator::Choice { .. } => {
+                "TEXT"
+            }
+            ColumnGenerator::IntRange { .. } | ColumnGenerator::Reference { .. } => "INTEGER",
+            ColumnGenerator::FloatRange { .. } => "REAL",
+            ColumnGenerator::Bool => "BOOLEAN",
+            ColumnGenerator::Timestamp { .. } => "DATETIME",
+        });
+        match &self.generator {
+            ColumnGenerator::Reference { table } => {
+                format!(
+                    "\"{}\" {sql_type} NOT NULL REFERENCES \"{table}\"(id)",
+                    self.name
+                )
+            }
+            _ => format!("\"{}\" {sql_type} NOT NULL", self.name),
+        }
+    }
+}
+
+impl ColumnGenerator {
+    /// The value for the row at `index`, given how many rows each earlier
+    /// table has.
+    fn value(
+        &self,
+        rng: &mut impl Rng,
+        index: usize,
+        rows: &HashMap<String, usize>,
+    ) -> Result<Value, DbError> {
+        Ok(match self {
+            Self::Name => {
+                let first = choose_str(FIRST_NAMES, rng, "first_names")?;
+                let last = choose_str(LAST_NAMES, rng, "last_names")?;
+                Value::Text(format!("{first} {last}"))
+            }
+            Self::Email => {
+                let first = choose_str(FIRST_NAMES, rng, "first_names")?;
+                let last = choose_str(LAST_NAMES, rng, "last_names")?;
+                Value::Text(format!(
+                    "{}.{}{index}@example.com",
+                    first.to_lowercase(),
+                    last.to_lowercase()
+                ))
+            }
+            Self::IntRange { min, max } => Value::Integer(rng.gen_range(*min..=*max)),
+            Self::FloatRange { min, max, decimals } => {
+                Value::Real(random_float(rng, *min, *max, *decimals))
+            }
+            Self::Choice { choices } => choices
+                .choose(rng)
+                .map(|choice| Value::Text(choice.clone()))
+                .ok_or(DbError::SeedData("choices"))?,
+            Self::Bool => Value::Integer(i64::from(rng.gen_bool(0.5))),
+            Self::Timestamp { days } => {
+                let age = rng.gen_range(0..=i64::from(*days) * DAY_SECONDS);
+                Value::Text(format!("-{age} seconds"))
+            }
+            Self::Reference { table } => {
+                let count = rows.get(&table.to_ascii_lowercase()).copied().unwrap_or(0);
+                let last = i64::try_from(count).map_err(|_| DbError::SeedData("references"))?;
+                if last < 1 {
+                    return Err(DbError::SeedData("references"));
+                }
+                Value::Integer(rng.gen_range(1..=last))
+            }
+        })
+    }
+}
+
+/// Replace the tables `spec` lists with freshly generated rows. Other
+/// tables in the database are left alone.
+///
+/// # Errors
+/// Returns `DbError::SeedSpec` if the spec is invalid, and `DbError` if a
+/// table cannot be created or filled.
+pub fn seed_spec(conn: &mut Connection, spec: &SeedSpec) -> Result<(), DbError> {
+    spec.check()?;
+    let mut rng = rand::thread_rng();
+    let tx = conn.transaction()?;
+    for table in spec.tables.iter().rev() {
+        tx.execute_batch(&format!("DROP TABLE IF EXISTS \"{}\";", table.name))?;
+    }
+
+    let mut rows = HashMap::new();
+    let mut counts = Vec::with_capacity(spec.tables.len());
+    for table in &spec.tables {
+        println!("Seeding {} {}...", table.rows, table.name);
+        tx.execute_batch(&table.create_sql())?;
+        let mut stmt = tx.prepare(&table.insert_sql())?;
+        for index in 0..table.rows {
+            let values = table
+                .columns
+                .iter()
+                .map(|column| column.generator.value(&mut rng, index, &rows))
+                .collect::<Result<Vec<_>, _>>()?;
+            stmt.execute(params_from_iter(values))?;
+        }
+        drop(stmt);
+        tx.execute_batch(&table.index_sql())?;
+        rows.insert(table.name.to_ascii_lowercase(), table.rows);
+        counts.push((
+            table.name.as_str(),
+            i64::try_from(table.rows).unwrap_or(i64::MAX),
+        ));
+    }
+    tx.commit()?;
+
+    print_counts(&counts);
+    Ok(())
+}
@@ -0,0 +1,359 @@
+//! SQLite backend, the default.
+
+use std::ops::Deref;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{CachedStatement, Connection, ErrorCode, Statement};
+use serde_json::Value as JsonValue;
+
+use super::watchdog::{Watch, Watchdog};
+use super::{
+    value_to_json, Backend, DbError, Execution, QueryResult, RowCollector, StatementCache,
+};
+use crate::context::{Deadline, QueryContext};
+use crate::dialect::{SqlDialect, Sqlite};
+use crate::render::RowData;
+use crate::schema::SchemaCatalog;
+
+/// [`StatementCache`] slot of the writer; readers follow it.
+const WRITER: usize = 0;
+
+/// A SQLite database: one connection for writes, behind a lock, and a pool
+/// of read-only connections that queries share, so readers neither wait for
+/// each other nor for a write. Without readers, as for an in-memory
+/// database, reads go through the writer too.
+pub struct SqliteDatabase {
+    writer: Mutex<Connection>,
+    /// Readers not lent out, each with its [`StatementCache`] slot.
+    idle: Mutex<Vec<(usize, Connection)>>,
+    /// Signalled whenever a reader is returned to `idle`.
+    returned: Condvar,
+    readers: usize,
+    statements: StatementCache,
+    /// Interrupts reads held past the query timeout, if there is one.
+    watchdog: Option<Watchdog>,
+}
+
+impl SqliteDatabase {
+    #[must_use]
+    pub fn new(conn: Connection) -> Self {
+        Self::with_statement_cache(conn, StatementCache::DEFAULT_CAPACITY)
+    }
+
+    /// Like [`Self::new`], caching up to `capacity` prepared statements.
+    #[must_use]
+    pub fn with_statement_cache(conn: Connection, capacity: usize) -> Self {
+        Self::build(conn, Vec::new(), capacity)
+    }
+
+    /// `writer`, with `readers` (opened read-only on the same file, e.g. by
+    /// [`super::open_reader`]) to run queries on.
+    #[must_use]
+    pub fn pooled(writer: Connection, readers: Vec<Connection>) -> Self {
+        Self::build(writer, readers, StatementCache::DEFAULT_CAPACITY)
+    }
+
+    fn build(writer: Connection, readers: Vec<Connection>, capacity: usize) -> Self {
+        writer.set_prepared_statement_cache_capacity(capacity);
+        for reader in &readers {
+            reader.set_prepared_statement_cache_capacity(capacity);
+        }
+        Self {
+            writer: Mutex::new(writer),
+            readers: readers.len(),
+            statements: StatementCache::new(capacity, readers.len() + 1),
+            idle: Mutex::new((WRITER + 1..).zip(readers).collect()),
+            returned: Condvar::new(),
+            watchdog: None,
+        }
+    }
+
+    /// Cancel any read still running `timeout` after it took its connection,
+    /// through the connection's [`rusqlite::InterruptHandle`], so one bad
+    /// join cannot hold a connection indefinitely. The query fails with
+    /// `DbError::Timeout`.
+    ///
+    /// # Errors
+    /// Returns `DbError::Io` if the thread timing reads cannot be spawned.
+    pub fn with_query_timeout(mut self, timeout: Duration) -> Result<Self, DbError> {
+        self.watchdog = Some(Watchdog::spawn(timeout)?);
+        Ok(self)
+    }
+
+    /// How long a read may run before it is cancelled, if it may not run
+    /// indefinitely.
+    #[must_use]
+    pub fn query_timeout(&self) -> Option<Duration> {
+        self.watchdog.as_ref().map(Watchdog::timeout)
+    }
+
+    /// `error` from a read, as `DbError::Timeout` if it was cancelled for
+    /// running past the query timeout.
+    #[must_use]
+    pub fn timed_out(&self, error: rusqlite::Error) -> DbError {
+        self.timed_out_by(error, None)
+    }
+
+    /// [`Self::timed_out`] for a read taken with [`Self::read_until`]
+    /// `deadline`, which reports that deadline's budget once it has passed.
+    #[must_use]
+    pub fn timed_out_by(&self, error: rusqlite::Error, deadline: Option<Deadline>) -> DbError {
+        if error.sqlite_error_code() != Some(ErrorCode::OperationInterrupted) {
+            return DbError::Sqlite(error);
+        }
+        match (deadline.filter(Deadline::passed), self.query_timeout()) {
+            (Some(deadline), _) => DbError::Timeout(deadline.budget()),
+            (None, Some(timeout)) => DbError::Timeout(timeout),
+            (None, None) => DbError::Sqlite(error),
+        }
+    }
+
+    /// An empty in-memory database, for tests and as a placeholder.
+    ///
+    /// # Errors
+    /// Returns `DbError::Sqlite` if the database cannot be opened.
+    pub fn open_in_memory() -> Result<Self, DbError> {
+        Ok(Self::new(Connection::open_in_memory()?))
+    }
+
+    /// Lock the writer, for writes and SQLite-only work such as WAL
+    /// checkpoints.
+    ///
+    /// # Errors
+    /// Returns `DbError::Lock` if the lock is poisoned.
+    pub fn lock(&self) -> Result<MutexGuard<'_, Connection>, DbError> {
+        self.writer.lock().map_err(|_| DbError::Lock)
+    }
+
+    /// A reader, waiting for one to be returned if all are in use, or the
+    /// writer when there are none. Under a query timeout, the read is
+    /// cancelled if the connection is still held once it passes.
+    ///
+    /// # Errors
+    /// Returns `DbError::Lock` if a lock is poisoned.
+    pub fn read(&self) -> Result<PooledConnection<'_>, DbError> {
+        self.read_until(None)
+    }
+
+    /// [`Self::read`], cancelled at `deadline` too if that comes before the
+    /// query timeout. Without a query timeout nothing watches the read, and
+    /// the deadline is only checked between rows.
+    ///
+    /// # Errors
+    /// Returns `DbError::Timeout` if `deadline` has passed, and
+    /// `DbError::Lock` if a lock is poisoned.
+    pub fn read_until(&self, deadline: Option<Deadline>) -> Result<PooledConnection<'_>, DbError> {
+        if let Some(deadline) = &deadline {
+            deadline.check()?;
+        }
+        let (slot, held) = if self.readers == 0 {
+            (WRITER, Held::Writer(self.lock()?))
+        } else {
+            let mut idle = self.idle.lock().map_err(|_| DbError::Lock)?;
+            loop {
+                if let Some((slot, conn)) = idle.pop() {
+                    break (slot, Held::Reader(Some(conn)));
+                }
+                idle = self.returned.wait(idle).map_err(|_| DbError::Lock)?;
+            }
+        };
+        let mut pooled = PooledConnection {
+            db: self,
+            slot,
+            held,
+            watch: None,
+        };
+        pooled.watch = self
+            .watchdog
+            .as_ref()
+            .map(|watchdog| watchdog.watch(&pooled, deadline.as_ref().map(Deadline::at)));
+        Ok(pooled)
+    }
+
+    /// How many read-only connections queries share.
+    #[must_use]
+    pub const fn readers(&self) -> usize {
+        self.readers
+    }
+
+    /// Hits and misses of every connection's prepared statement cache.
+    #[must_use]
+    pub const fn statements(&self) -> &StatementCache {
+        &self.statements
+    }
+
+    /// [`Backend::for_each_row`] on a read taken until `deadline`.
+    fn for_each_row_until(
+        &self,
+        sql: &str,
+        params: &[Value],
+        deadline: Option<Deadline>,
+        visit: &mut dyn FnMut(RowData) -> Result<bool, DbError>,
+    ) -> Result<Vec<String>, DbError> {
+        let conn = self.read_until(deadline)?;
+        let mut stmt = conn.prepare_cached(sql)?;
+        if stmt.readonly() || self.readers == 0 {
+            return read_rows(&mut stmt, params, visit).map_err(|error| match error {
+                DbError::Sqlite(error) => self.timed_out_by(error, deadline),
+                error => error,
+            });
+        }
+        // A write ending in `RETURNING`, which readers cannot run.
+        drop(stmt);
+        drop(conn);
+        let conn = self.lock()?;
+        let mut stmt = self.statements.prepare(WRITER, &conn, sql)?;
+        read_rows(&mut stmt, params, visit)
+    }
+}
+
+/// A connection lent by [`SqliteDatabase::read`], returned when dropped.
+pub struct PooledConnection<'a> {
+    db: &'a SqliteDatabase,
+    slot: usize,
+    held: Held<'a>,
+    watch: Option<Watch>,
+}
+
+enum Held<'a> {
+    /// `None` only while being returned.
+    Reader(Option<Connection>),
+    Writer(MutexGuard<'a, Connection>),
+}
+
+impl PooledConnection<'_> {
+    /// [`Connection::prepare_cached`], counted in the database's
+    /// [`StatementCache`] metrics.
+    ///
+    /// # Errors
+    /// Returns the error from preparing `sql`.
+    pub fn prepare_cached(&self, sql: &str) -> rusqlite::Result<CachedStatement<'_>> {
+        self.db.statements.prepare(self.slot, self, sql)
+    }
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        match &self.held {
+            Held::Reader(conn) => conn.as_ref().expect("reader is held until dropped"),
+            Held::Writer(conn) => conn,
+        }
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        // The watch ends before anyone else can take the connection.
+        self.watch = None;
+        if let Held::Reader(conn) = &mut self.held {
+            if let Some(conn) = conn.take() {
+                // Pushing cannot panic halfway, so a poisoned pool is still usable.
+                let mut idle = self.db.idle.lock().unwrap_or_else(PoisonError::into_inner);
+                idle.push((self.slot, conn));
+                self.db.returned.notify_one();
+            }
+        }
+    }
+}
+
+impl Backend for SqliteDatabase {
+    fn dialect(&self) -> &dyn SqlDialect {
+        &Sqlite
+    }
+
+    fn load_schema(&self) -> Result<SchemaCatalog, DbError> {
+        Ok(SchemaCatalog::load(&*self.read()?)?)
+    }
+
+    fn schema_version(&self) -> Result<Option<i64>, DbError> {
+        let conn = self.read()?;
+        Ok(Some(conn.query_row(
+            "PRAGMA schema_version",
+            [],
+            |row| row.get(0),
+        )?))
+    }
+
+    fn for_each_row(
+        &self,
+        sql: &str,
+        params: &[Value],
+        visit: &mut dyn FnMut(RowData) -> Result<bool, DbError>,
+    ) -> Result<Vec<String>, DbError> {
+        self.for_each_row_until(sql, params, None, visit)
+    }
+
+    fn query_in(
+        &self,
+        sql: &str,
+        params: &[Value],
+        context: &QueryContext,
+    ) -> Result<QueryResult, DbError> {
+        let mut rows = RowCollector::in_context(context);
+        let columns =
+            self.for_each_row_until(sql, params, context.deadline, &mut |row| rows.push(row))?;
+        Ok(rows.finish(columns))
+    }
+
+    fn execute(&self, sql: &str, params: &[Value]) -> Result<Execution, DbError> {
+        let conn = self.lock()?;
+        let rows = self
+            .statements
+            .prepare(WRITER, &conn, sql)?
+            .execute(rusqlite::params_from_iter(params.iter()))?;
+        Ok(Execution {
+            rows_affected: rows as u64,
+            last_insert_id: Some(conn.last_insert_rowid()).filter(|_| rows > 0),
+        })
+    }
+}
+
+/// [`Backend::for_each_row`] on a connection the caller already holds.
+///
+/// # Errors
+/// Returns `DbError` if the query fails, or the error from `visit`.
+pub fn for_each_row(
+    conn: &Connection,
+    sql: &str,
+    params: &[Value],
+    visit: &mut dyn FnMut(RowData) -> Result<bool, DbError>,
+) -> Result<Vec<String>, DbError> {
+    read_rows(&mut conn.prepare(sql)?, params, visit)
+}
+
+fn read_rows(
+    stmt: &mut Statement<'_>,
+    params: &[Value],
+    visit: &mut dyn FnMut(RowData) -> Result<bool, DbError>,
+) -> Result<Vec<String>, DbError> {
+    let names: Vec<String> = stmt
+        .column_names()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+
+    while let Some(row) = rows.next()? {
+        let mut data = RowData::new();
+        for (i, name) in names.iter().enumerate() {
+            data.insert(name.clone(), cell_to_json(row.get_ref(i)?));
+        }
+        if !visit(data)? {
+            break;
+        }
+    }
+    Ok(names)
+}
+
+/// [`value_to_json`] for a cell read in place; text that is not UTF-8 is
+/// decoded lossily rather than failing the row.
+fn cell_to_json(value: ValueRef<'_>) -> JsonValue {
+    match value {
+        ValueRef::Text(text) => JsonValue::String(String::from_utf8_lossy(text).into_owned()),
+        value => value_to_json(value.into()),
+    }
+}
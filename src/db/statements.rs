@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use rusqlite::{CachedStatement, Connection};
+use serde::Serialize;
+
+/// Prepared statements kept per SQL text, so a class name seen before skips
+/// parsing and compiling its query.
+///
+/// The statements live in each connection's own LRU cache
+/// ([`Connection::prepare_cached`]), sized to `capacity` by
+/// [`super::sqlite::SqliteDatabase`]. This keeps the same LRU order of SQL
+/// texts alongside each, to count hits and misses.
+#[derive(Debug)]
+pub struct StatementCache {
+    capacity: usize,
+    /// Cached SQL per connection, most recently used first.
+    recent: Vec<Mutex<VecDeque<Box<str>>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// A snapshot of [`StatementCache`] use.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct StatementCacheMetrics {
+    pub capacity: usize,
+    /// Statements now cached, over every connection.
+    pub cached: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl StatementCache {
+    pub const DEFAULT_CAPACITY: usize = 64;
+
+    /// Counters for `connections` connections, each caching up to
+    /// `capacity` statements.
+    #[must_use]
+    pub(super) fn new(capacity: usize, connections: usize) -> Self {
+        Self {
+            capacity,
+            recent: (0..connections)
+                .map(|_| Mutex::new(VecDeque::with_capacity(capacity)))
+                .collect(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The statement for `sql` from `conn`'s cache, prepared on a miss.
+    /// `conn` is the database's `slot`th connection.
+    pub(super) fn prepare<'conn>(
+        &self,
+        slot: usize,
+        conn: &'conn Connection,
+        sql: &str,
+    ) -> rusqlite::Result<CachedStatement<'conn>> {
+        let statement = conn.prepare_cached(sql)?;
+        // rusqlite keys its cache on the trimmed text, and only caches
+        // statements that prepared.
+        let key = sql.trim();
+        let mut recent = self.recent[slot]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(index) = recent.iter().position(|cached| &**cached == key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            if let Some(cached) = recent.remove(index) {
+                recent.push_front(cached);
+            }
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            if self.capacity > 0 {
+                recent.truncate(self.capacity - 1);
+                recent.push_front(key.into());
+            }
+        }
+        Ok(statement)
+    }
+
+    #[must_use]
+    pub fn metrics(&self) -> StatementCacheMetrics {
+        StatementCacheMetrics {
+            capacity: self.capacity,
+            cached: self
+                .recent
+                .iter()
+                .map(|recent| recent.lock().unwrap_or_else(PoisonError::into_inner).len())
+                .sum(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rusqlite::{Connection, InterruptHandle};
+
+use super::DbError;
+
+/// The handle of a connection being watched, taken by the [`Watch`] when it
+/// ends, so a late interrupt can never reach the connection's next user.
+type Slot = Arc<Mutex<Option<InterruptHandle>>>;
+
+/// A thread that interrupts connections held longer than `timeout`.
+pub(super) struct Watchdog {
+    timeout: Duration,
+    sender: Sender<(Instant, Slot)>,
+}
+
+/// A connection's time under a [`Watchdog`], which ends when dropped.
+pub(super) struct Watch(Slot);
+
+impl Watchdog {
+    /// # Errors
+    /// Returns `DbError::Io` if the thread cannot be spawned.
+    pub(super) fn spawn(timeout: Duration) -> Result<Self, DbError> {
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("db-watchdog".to_string())
+            .spawn(move || watch(&receiver))?;
+        Ok(Self { timeout, sender })
+    }
+
+    pub(super) const fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Interrupt whatever `conn` is running once `timeout` has passed, or
+    /// at `until` if that is sooner, unless the returned [`Watch`] is
+    /// dropped first.
+    pub(super) fn watch(&self, conn: &Connection, until: Option<Instant>) -> Watch {
+        let slot = Arc::new(Mutex::new(Some(conn.get_interrupt_handle())));
+        let deadline = Instant::now() + self.timeout;
+        let deadline = until.map_or(deadline, |until| until.min(deadline));
+        // A stopped watchdog only means the connection runs unbounded.
+        let _ = self.sender.send((deadline, slot.clone()));
+        Watch(slot)
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).take();
+    }
+}
+
+fn watch(receiver: &Receiver<(Instant, Slot)>) {
+    let mut watched: Vec<(Instant, Slot)> = Vec::new();
+    loop {
+        let now = Instant::now();
+        watched.retain(|(deadline, slot)| {
+            if *deadline > now {
+                // Keep only connections still held.
+                return Arc::strong_count(slot) > 1;
+            }
+            // Interrupt under the lock, so the connection cannot be returned
+            // and lent out again in between.
+            let handle = slot.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(handle) = handle.as_ref() {
+                handle.interrupt();
+            }
+            false
+        });
+        let next = match watched.iter().map(|(deadline, _)| *deadline).min() {
+            Some(deadline) => receiver.recv_timeout(deadline.saturating_duration_since(now)),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match next {
+            Ok(entry) => watched.push(entry),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+use rusqlite::Connection;
+use tokio::sync::oneshot;
+use tracing::Span;
+
+use super::sqlite::SqliteDatabase;
+use super::{DbError, PoolMetrics};
+
+type Job = Box<dyn FnOnce(&SqliteDatabase) + Send>;
+
+/// A SQLite connection owned by one thread, which runs the jobs sent to it
+/// in order.
+///
+/// Callers hand over a closure and await its result rather than taking a
+/// lock on a shared connection: jobs wait in a queue of at most `queue`
+/// entries, and a full queue fails fast with `DbError::QueueFull` instead of
+/// piling up more work behind a slow query.
+///
+/// ```
+/// # async fn demo() -> Result<(), tailwindsql::db::DbError> {
+/// use rusqlite::Connection;
+/// use tailwindsql::db::{Backend, Worker};
+/// use tailwindsql::query_builder::QueryPolicy;
+///
+/// let worker = Worker::spawn(Connection::open_in_memory()?, 64)?;
+/// let result = worker
+///     .run(|db| db.query("SELECT 1 AS one", &[], &QueryPolicy::default()))
+///     .await??;
+/// assert_eq!(result.columns, ["one"]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Worker {
+    sender: SyncSender<Job>,
+    stats: Arc<WorkerStats>,
+}
+
+#[derive(Debug, Default)]
+struct WorkerStats {
+    queued: AtomicUsize,
+    active: AtomicUsize,
+    started: AtomicU64,
+    completed: AtomicU64,
+    wait_micros: AtomicU64,
+    max_wait_micros: AtomicU64,
+}
+
+impl Worker {
+    /// Move `conn` onto a new thread that takes jobs from a queue of up to
+    /// `queue` (at least one). The thread exits once the worker is dropped
+    /// and its queue has drained.
+    ///
+    /// # Errors
+    /// Returns `DbError::Io` if the thread cannot be spawned.
+    pub fn spawn(conn: Connection, queue: usize) -> Result<Self, DbError> {
+        let (sender, receiver) = mpsc::sync_channel::<Job>(queue.max(1));
+        thread::Builder::new()
+            .name("db-connection".to_string())
+            .spawn(move || work(&SqliteDatabase::new(conn), &receiver))?;
+        Ok(Self {
+            sender,
+            stats: Arc::new(WorkerStats::default()),
+        })
+    }
+
+    /// Run `job` with the connection and wait for its result. The job runs
+    /// inside the caller's tracing span, so its logs keep the caller's
+    /// request context.
+    ///
+    /// # Errors
+    /// Returns `DbError::QueueFull` if the queue is full, and `DbError::Join`
+    /// if the job panics or the thread has exited.
+    pub async fn run<T, F>(&self, job: F) -> Result<T, DbError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&SqliteDatabase) -> T + Send + 'static,
+    {
+        let (reply, result) = oneshot::channel();
+        let stats = self.stats.clone();
+        let queued_at = Instant::now();
+        let span = Span::current();
+        let job: Job = Box::new(move |db| {
+            let waited = u64::try_from(queued_at.elapsed().as_micros()).unwrap_or(u64::MAX);
+            stats.queued.fetch_sub(1, Ordering::Relaxed);
+            stats.active.fetch_add(1, Ordering::Relaxed);
+            stats.started.fetch_add(1, Ordering::Relaxed);
+            stats.wait_micros.fetch_add(waited, Ordering::Relaxed);
+            stats.max_wait_micros.fetch_max(waited, Ordering::Relaxed);
+
+            // A panicking job drops `reply`, which the caller sees as a join error.
+            let result = span.in_scope(|| panic::catch_unwind(AssertUnwindSafe(|| job(db))));
+            if let Ok(value) = result {
+                let _ = reply.send(value);
+            }
+
+            stats.active.fetch_sub(1, Ordering::Relaxed);
+            stats.completed.fetch_add(1, Ordering::Relaxed);
+        });
+        let queued = self.stats.queued.fetch_add(1, Ordering::Relaxed);
+        if let Err(error) = self.sender.try_send(job) {
+            self.stats.queued.fetch_sub(1, Ordering::Relaxed);
+            return Err(match error {
+                TrySendError::Full(_) => DbError::QueueFull(queued),
+                TrySendError::Disconnected(_) => DbError::Join,
+            });
+        }
+        result.await.map_err(|_| DbError::Join)
+    }
+
+    /// Load in the shape of [`super::WorkerPool`]'s, for one thread.
+    #[must_use]
+    pub fn metrics(&self) -> PoolMetrics {
+        let started = self.stats.started.load(Ordering::Relaxed);
+        PoolMetrics {
+            workers: 1,
+            queue_depth: self.stats.queued.load(Ordering::Relaxed),
+            active: self.stats.active.load(Ordering::Relaxed),
+            completed: self.stats.completed.load(Ordering::Relaxed),
+            mean_wait_micros: self
+                .stats
+                .wait_micros
+                .load(Ordering::Relaxed)
+                .checked_div(started)
+                .unwrap_or_default(),
+            max_wait_micros: self.stats.max_wait_micros.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn work(db: &SqliteDatabase, receiver: &Receiver<Job>) {
+    while let Ok(job) = receiver.recv() {
+        job(db);
+    }
+}
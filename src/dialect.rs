@@ -0,0 +1,161 @@
+//! SQL dialects the query builder can target.
+//!
+//! The builder decides what a query means; a [`SqlDialect`] decides how the
+//! parts that differ between databases are spelled: placeholders, quoted
+//! identifiers, `LIMIT`/`OFFSET`, relative timestamps and full-text matches.
+
+use std::fmt::Write;
+
+use rusqlite::types::Value;
+
+use crate::query_builder::ParamStyle;
+
+pub trait SqlDialect {
+    /// Placeholder for the `index`th bound value (1-based), named `name`.
+    fn placeholder(&self, index: usize, name: &str, style: ParamStyle) -> String;
+
+    /// Quote an already validated identifier.
+    fn quote_identifier(&self, identifier: &str) -> String {
+        format!("\"{}\"", identifier.replace('"', "\"\""))
+    }
+
+    /// Trailing `LIMIT`/`OFFSET` clause, with a leading space, from placeholders.
+    fn limit_offset(&self, limit: Option<&str>, offset: Option<&str>) -> String {
+        let mut clause = String::new();
+        if let Some(limit) = limit {
+            write!(&mut clause, " LIMIT {limit}").expect("writing to SQL buffer should not fail");
+        }
+        if let Some(offset) = offset {
+            write!(&mut clause, " OFFSET {offset}").expect("writing to SQL buffer should not fail");
+        }
+        clause
+    }
+
+    /// The current time shifted by SQLite-style `modifiers` such as
+    /// `start of day` or `-7 days`. `bind` binds a value and returns its placeholder.
+    fn relative_datetime(
+        &self,
+        modifiers: &[String],
+        bind: &mut dyn FnMut(Value) -> String,
+    ) -> String;
+
+    /// Condition matching `table` rows through the full-text index
+    /// `fts_table`, both already quoted, or `None` if the dialect has no
+    /// such index.
+    fn full_text_match(&self, table: &str, fts_table: &str, term: &str) -> Option<String>;
+
+    /// `sql` wrapped so running it returns the query plan instead of rows.
+    fn explain(&self, sql: &str) -> String {
+        format!("EXPLAIN {sql}")
+    }
+
+    /// The value at `path` inside the JSON text of the column reference
+    /// `column`. Keys are already
+    /// validated identifiers or array indexes, so they are written inline.
+    fn json_extract(&self, column: &str, path: &[&str]) -> String {
+        let mut json_path = String::from("$");
+        for key in path {
+            if key.bytes().all(|b| b.is_ascii_digit()) {
+                write!(&mut json_path, "[{key}]")
+            } else {
+                write!(&mut json_path, ".{key}")
+            }
+            .expect("writing to SQL buffer should not fail");
+        }
+        format!("json_extract({column}, '{json_path}')")
+    }
+}
+
+/// SQLite, the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sqlite;
+
+impl SqlDialect for Sqlite {
+    fn placeholder(&self, _index: usize, name: &str, style: ParamStyle) -> String {
+        match style {
+            ParamStyle::Positional => "?".to_string(),
+            ParamStyle::Named => format!(":{name}"),
+        }
+    }
+
+    fn limit_offset(&self, limit: Option<&str>, offset: Option<&str>) -> String {
+        match (limit, offset) {
+            // SQLite only accepts OFFSET after a LIMIT; -1 means no limit.
+            (None, Some(offset)) => format!(" LIMIT -1 OFFSET {offset}"),
+            (Some(limit), Some(offset)) => format!(" LIMIT {limit} OFFSET {offset}"),
+            (Some(limit), None) => format!(" LIMIT {limit}"),
+            (None, None) => String::new(),
+        }
+    }
+
+    fn relative_datetime(
+        &self,
+        modifiers: &[String],
+        bind: &mut dyn FnMut(Value) -> String,
+    ) -> String {
+        let mut expr = String::from("datetime('now'");
+        for modifier in modifiers {
+            let placeholder = bind(Value::Text(modifier.clone()));
+            write!(&mut expr, ", {placeholder}").expect("writing to SQL buffer should not fail");
+        }
+        expr.push(')');
+        expr
+    }
+
+    fn full_text_match(&self, table: &str, fts_table: &str, term: &str) -> Option<String> {
+        Some(format!(
+            "{table}.rowid IN (SELECT rowid FROM {fts_table} WHERE {fts_table} MATCH {term})"
+        ))
+    }
+
+    fn explain(&self, sql: &str) -> String {
+        format!("EXPLAIN QUERY PLAN {sql}")
+    }
+}
+
+/// PostgreSQL. Placeholders are always `$1`, `$2`... since Postgres has no
+/// named parameters. DuckDB accepts the same SQL.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Postgres;
+
+impl SqlDialect for Postgres {
+    fn placeholder(&self, index: usize, _name: &str, _style: ParamStyle) -> String {
+        format!("${index}")
+    }
+
+    fn relative_datetime(
+        &self,
+        modifiers: &[String],
+        bind: &mut dyn FnMut(Value) -> String,
+    ) -> String {
+        let mut expr = String::from("now()");
+        for modifier in modifiers {
+            expr = match modifier.strip_prefix("start of ") {
+                Some(unit) => format!("date_trunc('{}', {expr})", truncation_unit(unit)),
+                None => {
+                    let placeholder = bind(Value::Text(modifier.clone()));
+                    format!("({expr} + {placeholder}::text::interval)")
+                }
+            };
+        }
+        expr
+    }
+
+    fn full_text_match(&self, _table: &str, _fts_table: &str, _term: &str) -> Option<String> {
+        None
+    }
+
+    fn json_extract(&self, column: &str, path: &[&str]) -> String {
+        let keys: Vec<String> = path.iter().map(|key| format!(", '{key}'")).collect();
+        format!("jsonb_extract_path_text({column}::jsonb{})", keys.concat())
+    }
+}
+
+/// `date_trunc` field for a SQLite `start of <unit>` modifier.
+fn truncation_unit(unit: &str) -> &'static str {
+    match unit {
+        "month" => "month",
+        "year" => "year",
+        _ => "day",
+    }
+}
@@ -0,0 +1,203 @@
+//! Class names to rows or HTML in one call, for embedding the library.
+//!
+//! [`Engine`] runs the same pipeline as the server: parse, check the access
+//! policy, validate against the schema, build, execute and coerce booleans.
+//! The `_in` methods run it under a [`QueryContext`], which every stage
+//! checks for its deadline and which supplies the caller's limits.
+
+use std::sync::Arc;
+
+use crate::context::QueryContext;
+use crate::db::{Backend, QueryResult};
+use crate::error::Error;
+use crate::parser::tokens::GrammarConfig;
+use crate::parser::{parse_class_names_with, ParseError, QueryConfig};
+use crate::policy::AccessPolicy;
+use crate::query_builder::{build_query_for, QueryPolicy};
+use crate::render::{render_results_with, RenderAs, RenderOptions};
+use crate::schema::{validate, SchemaCatalog};
+
+/// A datasource with its schema, access policy and limits.
+///
+/// ```
+/// use serde_json::json;
+/// use tailwindsql::prelude::*;
+///
+/// let db = MockDatasource::new().with_table(
+///     "users",
+///     ["id", "name"],
+///     [vec![json!(1), json!("Ada")], vec![json!(2), json!("Grace")]],
+/// );
+/// let engine = Engine::new(db).unwrap();
+/// let html = engine.render("db-users-name-where-id-2", RenderAs::Span).unwrap();
+/// assert!(html.contains("Grace"));
+/// ```
+#[derive(Clone)]
+pub struct Engine {
+    backend: Arc<dyn Backend>,
+    schema: Arc<SchemaCatalog>,
+    policy: AccessPolicy,
+    limits: QueryPolicy,
+    grammar: GrammarConfig,
+}
+
+impl Engine {
+    /// Serve `backend` under the default policy, limits and grammar.
+    ///
+    /// # Errors
+    /// Returns `Error::Db` if the schema cannot be loaded.
+    pub fn new(backend: impl Backend + 'static) -> Result<Self, Error> {
+        Self::from_shared(Arc::new(backend))
+    }
+
+    /// [`Self::new`] for a backend that is already shared.
+    ///
+    /// # Errors
+    /// Returns `Error::Db` if the schema cannot be loaded.
+    pub fn from_shared(backend: Arc<dyn Backend>) -> Result<Self, Error> {
+        let schema = Arc::new(backend.load_schema()?);
+        Ok(Self {
+            backend,
+            schema,
+            policy: AccessPolicy::default(),
+            limits: QueryPolicy::default(),
+            grammar: GrammarConfig::default(),
+        })
+    }
+
+    #[must_use]
+    pub fn with_policy(mut self, policy: AccessPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    #[must_use]
+    pub fn with_limits(mut self, limits: QueryPolicy) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    #[must_use]
+    pub fn with_grammar(mut self, grammar: GrammarConfig) -> Self {
+        self.grammar = grammar;
+        self
+    }
+
+    #[must_use]
+    pub fn schema(&self) -> &SchemaCatalog {
+        &self.schema
+    }
+
+    /// The context the methods without `_in` run under: this engine's
+    /// limits, with no deadline or principal.
+    #[must_use]
+    pub fn context(&self) -> QueryContext {
+        QueryContext::new(self.limits)
+    }
+
+    /// Parse `class_names` and check them against the policy and schema,
+    /// returning the config that [`Self::query`] would run.
+    ///
+    /// # Errors
+    /// Returns `Error::Parse`, `Policy` or `Invalid`.
+    pub fn config(&self, class_names: &str) -> Result<QueryConfig, Error> {
+        self.config_in(&self.context(), class_names)
+    }
+
+    /// [`Self::config`] under `context`.
+    ///
+    /// # Errors
+    /// Returns the errors of [`Self::config`], and `Error::Db` if the
+    /// deadline of `context` has passed.
+    pub fn config_in(
+        &self,
+        context: &QueryContext,
+        class_names: &str,
+    ) -> Result<QueryConfig, Error> {
+        context.check()?;
+        let config = parse_class_names_with(class_names, &self.grammar)
+            .ok_or_else(|| ParseError::NotAClassName(class_names.trim().to_string()))?;
+        self.policy.check(&config)?;
+        let errors = validate(&config, &self.schema);
+        if !errors.is_empty() {
+            return Err(Error::Invalid(errors));
+        }
+        let config = self.schema.resolve_joins(&config);
+        let visible = |table: &str, column: &str| self.policy.is_column_allowed(table, column);
+        let config = self
+            .schema
+            .resolve_search_visible(&self.schema.expand_wildcards(&config), &visible);
+        // Join keys taken from foreign keys and `through-` tables are only known now.
+        self.policy.check(&config)?;
+        Ok(self.schema.type_where_values(&config))
+    }
+
+    /// Run `class_names`, with boolean columns as JSON booleans.
+    ///
+    /// # Errors
+    /// Returns the errors of [`Self::config`], and `Error::Query` or
+    /// `Error::Db` if the query cannot be built or run.
+    pub fn query(&self, class_names: &str) -> Result<QueryResult, Error> {
+        self.query_in(&self.context(), class_names)
+    }
+
+    /// [`Self::query`] under `context`: built and run with its limits, and
+    /// stopped with `DbError::Timeout` once its deadline passes.
+    ///
+    /// # Errors
+    /// Returns the errors of [`Self::query`].
+    pub fn query_in(
+        &self,
+        context: &QueryContext,
+        class_names: &str,
+    ) -> Result<QueryResult, Error> {
+        let config = self.config_in(context, class_names)?;
+        let built = build_query_for(self.backend.dialect(), &config, &context.limits)?;
+        let mut result = self.backend.query_in(&built.sql, &built.params, context)?;
+        self.schema.coerce_booleans(&config, &mut result.rows);
+        Ok(result)
+    }
+
+    /// Run `class_names` and render the rows `render_as`.
+    ///
+    /// # Errors
+    /// Returns the errors of [`Self::query`].
+    pub fn render(&self, class_names: &str, render_as: RenderAs) -> Result<String, Error> {
+        self.render_with(class_names, render_as, &RenderOptions::default())
+    }
+
+    /// [`Self::render`] with custom cell renderers and table options.
+    ///
+    /// # Errors
+    /// Returns the errors of [`Self::query`].
+    pub fn render_with(
+        &self,
+        class_names: &str,
+        render_as: RenderAs,
+        options: &RenderOptions,
+    ) -> Result<String, Error> {
+        self.render_in(&self.context(), class_names, render_as, options)
+    }
+
+    /// [`Self::render_with`] under `context`, which is checked once more
+    /// before rendering.
+    ///
+    /// # Errors
+    /// Returns the errors of [`Self::query`].
+    pub fn render_in(
+        &self,
+        context: &QueryContext,
+        class_names: &str,
+        render_as: RenderAs,
+        options: &RenderOptions,
+    ) -> Result<String, Error> {
+        let result = self.query_in(context, class_names)?;
+        context.check()?;
+        Ok(render_results_with(
+            &result.rows,
+            &result.columns,
+            render_as,
+            options,
+        ))
+    }
+}
@@ -0,0 +1,142 @@
+//! The crate-wide [`Error`], classified once for every caller.
+//!
+//! Library entry points such as [`crate::engine::Engine`] return it, and the
+//! server wraps it, so whether a failure is the caller's fault, whether it is
+//! worth retrying and which HTTP status it maps to are decided here rather
+//! than per handler.
+
+use std::io;
+
+use http::StatusCode;
+use rusqlite::ErrorCode;
+use thiserror::Error;
+
+use crate::db::DbError;
+#[cfg(feature = "server")]
+use crate::history::HistoryError;
+use crate::parser::ParseError;
+use crate::policy::PolicyError;
+use crate::query_builder::QueryBuilderError;
+use crate::schema::ValidationError;
+
+/// Whose fault an [`Error`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// The request was wrong: bad syntax, a denied table, an unknown column.
+    /// Repeating it unchanged fails the same way.
+    User,
+    /// The database or the host failed; the request itself may be fine.
+    System,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A malformed request, such as a missing parameter.
+    #[error("{0}")]
+    Request(String),
+    #[error("{0}")]
+    Parse(#[from] ParseError),
+    #[error("{0}")]
+    Policy(#[from] PolicyError),
+    #[error("{}", join_errors(.0))]
+    Invalid(Vec<ValidationError>),
+    #[error("query error: {0}")]
+    Query(#[from] QueryBuilderError),
+    #[error("db error: {0}")]
+    Db(#[from] DbError),
+    #[cfg(feature = "server")]
+    #[error("history error: {0}")]
+    History(#[from] HistoryError),
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+fn join_errors(errors: &[ValidationError]) -> String {
+    let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    messages.join("; ")
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::Db(DbError::Sqlite(error))
+    }
+}
+
+impl Error {
+    /// Client errors are the user's; everything else is the system's.
+    #[must_use]
+    pub fn category(&self) -> Category {
+        if self.status().is_client_error() {
+            Category::User
+        } else {
+            Category::System
+        }
+    }
+
+    /// Whether the same request may succeed later: a locked SQLite
+    /// database or a full queue for it, a Postgres serialization failure or
+    /// deadlock, a dropped connection or a timed-out read.
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Db(DbError::Sqlite(error)) => matches!(
+                error.sqlite_error_code(),
+                Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+            ),
+            #[cfg(feature = "postgres")]
+            Self::Db(DbError::Postgres(error)) => {
+                use tokio_postgres::error::SqlState;
+                error.is_closed()
+                    || error.code().is_some_and(|code| {
+                        *code == SqlState::T_R_SERIALIZATION_FAILURE
+                            || *code == SqlState::T_R_DEADLOCK_DETECTED
+                    })
+            }
+            Self::Db(DbError::Io(error)) | Self::Io(error) => is_transient(error),
+            Self::Db(DbError::QueueFull(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// The HTTP status the server answers with.
+    #[must_use]
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::Request(_) | Self::Parse(_) | Self::Invalid(_) | Self::Query(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            Self::Policy(_) => StatusCode::FORBIDDEN,
+            Self::Db(DbError::MemoryBudget { .. }) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Db(DbError::Timeout(_)) => StatusCode::REQUEST_TIMEOUT,
+            Self::Db(DbError::Sqlite(error))
+                if error.sqlite_error_code() == Some(ErrorCode::ConstraintViolation) =>
+            {
+                StatusCode::CONFLICT
+            }
+            // Class 23: integrity constraint violations.
+            #[cfg(feature = "postgres")]
+            Self::Db(DbError::Postgres(error))
+                if error
+                    .code()
+                    .is_some_and(|code| code.code().starts_with("23")) =>
+            {
+                StatusCode::CONFLICT
+            }
+            _ if self.is_retryable() => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::TimedOut
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+    )
+}
@@ -0,0 +1,292 @@
+//! Query history: a JSON-lines log of executed queries, and a replayer that
+//! re-runs a log against another database to compare latency and results.
+//!
+//! The server appends one [`HistoryEntry`] per `/api/query` when
+//! `QUERY_HISTORY` names a file; `tailwindsql replay <file>` feeds it back
+//! through [`replay`] to check a backend or index change before shipping it.
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+use tokio::task::JoinSet;
+
+use crate::db::{Backend, DbError, WorkerPool};
+use crate::query_builder::QueryPolicy;
+
+/// A replayed query counts as a regression when it takes this many times its
+/// recorded latency...
+pub const REGRESSION_FACTOR: f64 = 2.0;
+/// ...and at least this many milliseconds more, so sub-millisecond noise
+/// does not count.
+pub const REGRESSION_MIN_MS: f64 = 1.0;
+
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("line {line}: {source}")]
+    Line {
+        line: usize,
+        source: serde_json::Error,
+    },
+}
+
+/// One executed query as logged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub class_name: String,
+    pub sql: String,
+    pub params: Vec<JsonValue>,
+    pub rows: usize,
+    pub elapsed_ms: f64,
+}
+
+/// An append-only history file, shared by every request.
+pub struct HistoryLog {
+    file: Mutex<File>,
+}
+
+impl HistoryLog {
+    /// Open `path` for appending, creating it if needed.
+    ///
+    /// # Errors
+    /// Returns `HistoryError::Io` if the file cannot be opened.
+    pub fn open(path: &Path) -> Result<Self, HistoryError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Append `entry` as one line.
+    ///
+    /// # Errors
+    /// Returns `HistoryError` if the entry cannot be written.
+    pub fn record(&self, entry: &HistoryEntry) -> Result<(), HistoryError> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        // One write per line, so concurrent entries never interleave.
+        self.file
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .write_all(&line)?;
+        Ok(())
+    }
+}
+
+/// Every entry in the history file at `path`, skipping blank lines.
+///
+/// # Errors
+/// Returns `HistoryError::Line` for the first line that is not an entry.
+pub fn read_history(path: &Path) -> Result<Vec<HistoryEntry>, HistoryError> {
+    let mut entries = Vec::new();
+    for (index, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line).map_err(|source| HistoryError::Line {
+            line: index + 1,
+            source,
+        })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// How one entry fared when replayed.
+#[derive(Debug, Clone)]
+pub struct ReplayOutcome {
+    pub entry: HistoryEntry,
+    pub elapsed_ms: f64,
+    /// The row count, or the error the query failed with.
+    pub result: Result<usize, String>,
+}
+
+impl ReplayOutcome {
+    #[must_use]
+    pub fn is_regression(&self) -> bool {
+        self.elapsed_ms > self.entry.elapsed_ms * REGRESSION_FACTOR
+            && self.elapsed_ms - self.entry.elapsed_ms >= REGRESSION_MIN_MS
+    }
+
+    /// Whether the query succeeded but returned a different number of rows.
+    #[must_use]
+    pub fn rows_changed(&self) -> bool {
+        self.result
+            .as_ref()
+            .is_ok_and(|rows| *rows != self.entry.rows)
+    }
+}
+
+/// Every outcome of a [`replay`], in history order.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+    pub outcomes: Vec<ReplayOutcome>,
+}
+
+impl ReplayReport {
+    pub fn failures(&self) -> impl Iterator<Item = &ReplayOutcome> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.result.is_err())
+    }
+
+    pub fn regressions(&self) -> impl Iterator<Item = &ReplayOutcome> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.result.is_ok() && outcome.is_regression())
+    }
+
+    pub fn changed_rows(&self) -> impl Iterator<Item = &ReplayOutcome> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.rows_changed())
+    }
+
+    /// No failures, latency regressions or changed row counts.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.failures().next().is_none()
+            && self.regressions().next().is_none()
+            && self.changed_rows().next().is_none()
+    }
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let recorded: Vec<f64> = self.outcomes.iter().map(|o| o.entry.elapsed_ms).collect();
+        let replayed: Vec<f64> = self.outcomes.iter().map(|o| o.elapsed_ms).collect();
+        writeln!(f, "replayed {} queries", self.outcomes.len())?;
+        for (label, quantile) in [("p50", 0.5), ("p95", 0.95), ("max", 1.0)] {
+            writeln!(
+                f,
+                "  {label}: {:.2}ms recorded, {:.2}ms replayed",
+                percentile(&recorded, quantile),
+                percentile(&replayed, quantile),
+            )?;
+        }
+
+        let mut section = |title: &str, lines: Vec<String>| -> fmt::Result {
+            if lines.is_empty() {
+                return Ok(());
+            }
+            writeln!(f, "{title} ({}):", lines.len())?;
+            lines.iter().try_for_each(|line| writeln!(f, "  {line}"))
+        };
+        section(
+            "slower",
+            self.regressions()
+                .map(|o| {
+                    let (before, after) = (o.entry.elapsed_ms, o.elapsed_ms);
+                    format!("{before:.2}ms -> {after:.2}ms  {}", o.entry.class_name)
+                })
+                .collect(),
+        )?;
+        section(
+            "row count changed",
+            self.changed_rows()
+                .map(|o| {
+                    let rows = o.result.as_ref().map_or(0, |rows| *rows);
+                    format!("{} -> {rows}  {}", o.entry.rows, o.entry.class_name)
+                })
+                .collect(),
+        )?;
+        section(
+            "failed",
+            self.failures()
+                .map(|o| {
+                    let error = o.result.as_ref().err().map_or("", String::as_str);
+                    format!("{}: {error}", o.entry.class_name)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// The `quantile` (0 to 1) of `values` by nearest rank; 0 when empty.
+fn percentile(values: &[f64], quantile: f64) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    let rank = ((sorted.len() as f64 * quantile).ceil() as usize).max(1);
+    sorted.get(rank - 1).copied().unwrap_or(0.0)
+}
+
+/// Re-run every entry's SQL against `backend`, `concurrency` at a time,
+/// under the row caps in `limits`.
+///
+/// # Errors
+/// Returns `DbError::Io` if the worker threads cannot be started; failing
+/// queries are reported in their [`ReplayOutcome`] instead.
+pub async fn replay(
+    backend: Arc<dyn Backend>,
+    entries: Vec<HistoryEntry>,
+    concurrency: usize,
+    limits: QueryPolicy,
+) -> Result<ReplayReport, DbError> {
+    let workers = Arc::new(WorkerPool::new(concurrency)?);
+    let mut tasks = JoinSet::new();
+    for (index, entry) in entries.into_iter().enumerate() {
+        let (workers, backend) = (workers.clone(), backend.clone());
+        tasks.spawn(async move {
+            let params: Vec<Value> = entry.params.iter().map(json_to_param).collect();
+            let sql = entry.sql.clone();
+            let timed = workers
+                .run(move || {
+                    let started = Instant::now();
+                    let result = backend.query(&sql, &params, &limits);
+                    (started.elapsed().as_secs_f64() * 1000.0, result)
+                })
+                .await;
+            let (elapsed_ms, result) = match timed {
+                Ok((elapsed_ms, result)) => (elapsed_ms, result.map(|result| result.rows.len())),
+                Err(error) => (0.0, Err(error)),
+            };
+            let outcome = ReplayOutcome {
+                entry,
+                elapsed_ms,
+                result: result.map_err(|error| error.to_string()),
+            };
+            (index, outcome)
+        });
+    }
+
+    let mut outcomes = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        outcomes.push(joined.map_err(|_| DbError::Join)?);
+    }
+    outcomes.sort_by_key(|(index, _)| *index);
+    Ok(ReplayReport {
+        outcomes: outcomes.into_iter().map(|(_, outcome)| outcome).collect(),
+    })
+}
+
+/// A logged parameter bound back as the SQL value it was recorded from.
+fn json_to_param(value: &JsonValue) -> Value {
+    match value {
+        JsonValue::Null => Value::Null,
+        JsonValue::Bool(flag) => Value::Integer(i64::from(*flag)),
+        JsonValue::Number(number) => number.as_i64().map_or_else(
+            || Value::Real(number.as_f64().unwrap_or(0.0)),
+            Value::Integer,
+        ),
+        JsonValue::String(text) => Value::Text(text.clone()),
+        other => Value::Text(other.to_string()),
+    }
+}
@@ -1,6 +1,32 @@
+//! Tailwind-style class names compiled to parameterized SQL and rendered as HTML.
+//!
+//! Start from [`prelude`]: its items are the supported API and follow semver.
+//! The modules stay public for the server binary, tests and advanced use, but
+//! anything not in the prelude may change in any release.
+
 #![allow(clippy::multiple_crate_versions)]
 
+pub mod advisor;
+pub mod context;
 pub mod db;
+pub mod dialect;
+pub mod engine;
+pub mod error;
+#[cfg(feature = "server")]
+pub mod history;
 pub mod parser;
+pub mod policy;
+pub mod prelude;
 pub mod query_builder;
 pub mod render;
+pub mod schema;
+#[cfg(feature = "server")]
+#[doc(hidden)]
+pub mod server;
+pub mod sql_diff;
+pub mod subject;
+#[cfg(feature = "server")]
+pub mod test_support;
+
+pub use error::Error;
+pub use tailwindsql_macros::tw_sql;
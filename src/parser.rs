@@ -1,4 +1,33 @@
 use std::collections::HashMap;
+use std::fmt;
+
+use thiserror::Error;
+
+use self::expr::Expr;
+use self::tokens::{ClassAst, Clause, GrammarConfig, GrammarVersion};
+
+// An explicit path keeps this resolvable when `tailwindsql-macros` compiles
+// this file from outside the crate.
+#[path = "parser/expr.rs"]
+pub mod expr;
+#[path = "parser/tokens.rs"]
+pub mod tokens;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ParseError {
+    #[error("not a TailwindSQL class: {0}")]
+    NotAClassName(String),
+    #[error("unexpected token '{token}' at {position}")]
+    InvalidToken { token: String, position: usize },
+    #[error("unknown column '{column}' on table '{table}' at {position}")]
+    UnknownColumn {
+        table: String,
+        column: String,
+        position: usize,
+    },
+    #[error("unknown variable '${name}' at {position}")]
+    UnknownVariable { name: String, position: usize },
+}
 
 #[derive(Debug, Clone)]
 pub enum JoinType {
@@ -16,15 +45,95 @@ impl JoinType {
             Self::Right => "RIGHT",
         }
     }
+
+    /// Spelling used in `join=` params.
+    #[must_use]
+    pub const fn as_keyword(&self) -> &'static str {
+        match self {
+            Self::Inner => "inner",
+            Self::Left => "left",
+            Self::Right => "right",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct JoinConfig {
     pub table: String,
+    pub alias: Option<String>,
+    pub parent_table: Option<String>,
+    /// Empty, along with `child_column`, for a join that names no columns;
+    /// `SchemaCatalog::resolve_joins` then fills both in from a foreign key
+    /// between the two tables.
     pub parent_column: String,
     pub child_column: String,
     pub columns: Vec<String>,
     pub join_type: JoinType,
+    /// The junction table of a `through-<table>` join, which then joins the
+    /// parent to the junction and the junction to `table`.
+    pub through: Option<Through>,
+}
+
+/// The junction table a many-to-many join passes through, as in
+/// `tags:through-post_tags`.
+///
+/// The parser leaves the key columns, and the join's own `parent_column` and
+/// `child_column`, empty; `SchemaCatalog::resolve_joins` fills them in
+/// from the junction's foreign keys.
+#[derive(Debug, Clone)]
+pub struct Through {
+    pub table: String,
+    /// Junction column referencing the parent table.
+    pub parent_column: String,
+    /// Junction column referencing the joined table.
+    pub child_column: String,
+}
+
+impl JoinConfig {
+    /// Name the joined table is referred to by in SQL: its alias when set.
+    #[must_use]
+    pub fn reference(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.table)
+    }
+
+    /// Whether the join columns are left to be found from foreign keys.
+    #[must_use]
+    pub fn is_automatic(&self) -> bool {
+        self.through.is_none() && self.parent_column.is_empty() && self.child_column.is_empty()
+    }
+
+    /// Serialize back into the `join=` param syntax accepted by [`parse_join_param`].
+    #[must_use]
+    pub fn to_join_param(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Formats as `table[ as alias]:[parent.]column-child:col,col:type`, with
+/// `through-junction` in place of the column pair, or nothing for an
+/// automatic join.
+impl fmt::Display for JoinConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.table)?;
+        if let Some(alias) = &self.alias {
+            write!(f, " as {alias}")?;
+        }
+        f.write_str(":")?;
+        if let Some(parent) = &self.parent_table {
+            write!(f, "{parent}.")?;
+        }
+        match &self.through {
+            Some(through) => write!(f, "through-{}", through.table)?,
+            None if self.is_automatic() => {}
+            None => write!(f, "{}-{}", self.parent_column, self.child_column)?,
+        }
+        write!(
+            f,
+            ":{}:{}",
+            self.columns.join(","),
+            self.join_type.as_keyword()
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -40,6 +149,16 @@ pub enum OrderDirection {
 }
 
 impl OrderDirection {
+    /// `asc` or `desc`, in any case.
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "asc" => Some(Self::Asc),
+            "desc" => Some(Self::Desc),
+            _ => None,
+        }
+    }
+
     #[must_use]
     pub const fn as_sql(&self) -> &'static str {
         match self {
@@ -47,175 +166,658 @@ impl OrderDirection {
             Self::Desc => "DESC",
         }
     }
+
+    #[must_use]
+    pub const fn as_keyword(&self) -> &'static str {
+        match self {
+            Self::Asc => "asc",
+            Self::Desc => "desc",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhereOperator {
+    Eq,
+    After,
+    Before,
+    /// Case-insensitive substring match (`LIKE '%value%'`).
+    Contains,
+    /// Membership in a subquery's single column, as in
+    /// `where-author_id-in-(db-users-id-where-role-admin)`.
+    In,
+}
+
+impl WhereOperator {
+    #[must_use]
+    pub const fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Eq => "=",
+            Self::After => ">",
+            Self::Before => "<",
+            Self::Contains => "LIKE",
+            Self::In => "IN",
+        }
+    }
+
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "after" => Some(Self::After),
+            "before" => Some(Self::Before),
+            "contains" => Some(Self::Contains),
+            "in" => Some(Self::In),
+            _ => None,
+        }
+    }
+
+    const fn as_keyword(self) -> Option<&'static str> {
+        match self {
+            Self::Eq => None,
+            Self::After => Some("after"),
+            Self::Before => Some("before"),
+            Self::Contains => Some("contains"),
+            Self::In => Some("in"),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct QueryConfig {
-    pub table: String,
+pub struct WhereClause {
+    pub field: String,
+    pub operator: WhereOperator,
+    pub value: String,
+    /// The nested class name of an `in-(...)` value; `value` keeps its text.
+    pub subquery: Option<Box<QueryConfig>>,
+}
+
+/// Free-text search requested with a `search-<term>` token.
+///
+/// `fts_table` and `columns` are filled in from the schema before building:
+/// an FTS5 index is used when present, otherwise the columns are `LIKE`-matched.
+#[derive(Debug, Clone)]
+pub struct SearchConfig {
+    pub term: String,
+    pub fts_table: Option<String>,
     pub columns: Vec<String>,
-    pub where_clauses: Vec<(String, String)>,
-    pub limit: Option<i64>,
-    pub order_by: Option<OrderBy>,
-    pub joins: Vec<JoinConfig>,
 }
 
-#[derive(Debug, Clone, Copy)]
-enum ParserState {
-    Column,
-    WhereField,
-    WhereValue,
-    Limit,
-    OrderByField,
-    OrderByDir,
+/// A computed entry in the select list. Expressions follow the plain
+/// columns, each named by [`ColumnExpr::alias`].
+#[derive(Debug, Clone)]
+pub enum ColumnExpr {
+    /// `rank-over-likes-desc`: `RANK() OVER (ORDER BY likes DESC)`.
+    Window(WindowExpr),
+    /// `expr-[price*stock]-as-inventory_value`.
+    Computed(ComputedExpr),
+    /// `case-published-1-Yes-0-No`: `published` shown as `Yes` or `No`.
+    Case(CaseExpr),
+    /// `json-metadata.color`: the `color` field of the JSON text in `metadata`.
+    Json(JsonExpr),
 }
 
-#[must_use]
-pub fn parse_class_name(class_name: &str) -> Option<QueryConfig> {
-    if !class_name.starts_with("db-") {
-        return None;
+impl ColumnExpr {
+    /// The name of the result column.
+    #[must_use]
+    pub fn alias(&self) -> &str {
+        match self {
+            Self::Window(window) => window.function.as_keyword(),
+            Self::Computed(computed) => &computed.alias,
+            Self::Case(case) => &case.column,
+            Self::Json(json) => &json.field,
+        }
     }
 
-    let parts: Vec<&str> = class_name.trim().strip_prefix("db-")?.split('-').collect();
-    if parts.is_empty() || parts[0].is_empty() {
-        return None;
+    /// Every column of the base table the expression reads.
+    #[must_use]
+    pub fn columns(&self) -> Vec<&str> {
+        match self {
+            Self::Window(window) => std::iter::once(window.order_by.field.as_str())
+                .chain(window.partition_by.as_deref())
+                .collect(),
+            Self::Computed(computed) => computed.expr.columns(),
+            Self::Case(case) => vec![case.column.as_str()],
+            Self::Json(json) => vec![json.column()],
+        }
     }
+}
 
-    let mut config = QueryConfig {
-        table: parts[0].to_string(),
-        columns: Vec::new(),
-        where_clauses: Vec::new(),
-        limit: None,
-        order_by: None,
-        joins: Vec::new(),
-    };
+/// Formats as the class name segments that parse back to it.
+impl fmt::Display for ColumnExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Window(window) => {
+                write!(
+                    f,
+                    "{}-over-{}-{}",
+                    window.function.as_keyword(),
+                    window.order_by.field,
+                    window.order_by.direction.as_keyword()
+                )?;
+                if let Some(partition) = &window.partition_by {
+                    write!(f, "-partition-{partition}")?;
+                }
+                Ok(())
+            }
+            Self::Computed(computed) => write!(f, "expr-[{}]-as-{}", computed.expr, computed.alias),
+            Self::Case(case) => {
+                write!(f, "case-{}", case.column)?;
+                for (value, label) in &case.arms {
+                    write!(f, "-{value}-{label}")?;
+                }
+                // Closed explicitly so segments after it are not read as arms.
+                f.write_str("-end")
+            }
+            Self::Json(json) => write!(f, "json-{}", json.field),
+        }
+    }
+}
 
-    let mut state = ParserState::Column;
-    let mut current_where_field = String::new();
-    let mut i = 1;
+/// A ranking function over the rows in `order_by` order, restarting for
+/// each distinct `partition_by` value when set.
+#[derive(Debug, Clone)]
+pub struct WindowExpr {
+    pub function: WindowFunction,
+    pub order_by: OrderBy,
+    pub partition_by: Option<String>,
+}
 
-    while i < parts.len() {
-        let part = parts[i];
+/// A column with listed values replaced by labels, selected under the
+/// column's own name. Values not listed are shown as text, unchanged.
+#[derive(Debug, Clone)]
+pub struct CaseExpr {
+    pub column: String,
+    /// `(value, label)` pairs; values bind like where values, labels as text.
+    pub arms: Vec<(String, String)>,
+}
 
-        if part == "where" {
-            state = ParserState::WhereField;
-            i += 1;
-            continue;
+/// A field inside a column of JSON text, written `<column>.<key>...` and
+/// selected under that name. Numeric keys index into arrays: `tags.0`.
+#[derive(Debug, Clone)]
+pub struct JsonExpr {
+    pub field: String,
+}
+
+impl JsonExpr {
+    /// `None` unless `field` is a column and at least one key, each key an
+    /// identifier or an array index.
+    #[must_use]
+    pub fn parse(field: &str) -> Option<Self> {
+        let mut parts = field.split('.');
+        let valid = parts.next().is_some_and(expr::is_identifier)
+            && field.contains('.')
+            && parts.all(|key| {
+                expr::is_identifier(key)
+                    || (!key.is_empty() && key.bytes().all(|b| b.is_ascii_digit()))
+            });
+        valid.then(|| Self {
+            field: field.to_string(),
+        })
+    }
+
+    /// The column holding the JSON.
+    #[must_use]
+    pub fn column(&self) -> &str {
+        self.field.split('.').next().unwrap_or_default()
+    }
+
+    /// The keys to follow inside the column, outermost first.
+    pub fn path(&self) -> impl Iterator<Item = &str> {
+        self.field.split('.').skip(1)
+    }
+}
+
+/// Arithmetic over the base table's columns, selected as `alias`.
+#[derive(Debug, Clone)]
+pub struct ComputedExpr {
+    pub expr: Expr,
+    pub alias: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowFunction {
+    /// Ties share a rank and leave a gap after them: 1, 1, 3.
+    Rank,
+    /// Ties share a rank without a gap: 1, 1, 2.
+    DenseRank,
+    /// Every row gets the next number, ties in no particular order.
+    RowNumber,
+}
+
+impl WindowFunction {
+    #[must_use]
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "rank" => Some(Self::Rank),
+            "dense_rank" => Some(Self::DenseRank),
+            "row_number" => Some(Self::RowNumber),
+            _ => None,
         }
+    }
 
-        if part == "limit" {
-            state = ParserState::Limit;
-            i += 1;
-            continue;
+    #[must_use]
+    pub const fn as_keyword(self) -> &'static str {
+        match self {
+            Self::Rank => "rank",
+            Self::DenseRank => "dense_rank",
+            Self::RowNumber => "row_number",
         }
+    }
 
-        if part == "orderby" {
-            state = ParserState::OrderByField;
-            i += 1;
-            continue;
+    #[must_use]
+    pub const fn as_sql(self) -> &'static str {
+        match self {
+            Self::Rank => "RANK",
+            Self::DenseRank => "DENSE_RANK",
+            Self::RowNumber => "ROW_NUMBER",
         }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryConfig {
+    pub table: String,
+    pub columns: Vec<String>,
+    /// Computed columns such as window functions, selected after `columns`.
+    pub expressions: Vec<ColumnExpr>,
+    pub where_clauses: Vec<WhereClause>,
+    pub limit: Option<i64>,
+    /// Rows to skip, from an `offset-<n>` token or a `page=` request parameter.
+    pub offset: Option<i64>,
+    pub order_by: Option<OrderBy>,
+    pub joins: Vec<JoinConfig>,
+    pub search: Option<SearchConfig>,
+    /// Auto-refresh interval from a `refresh-30s` token, for fragment output.
+    pub refresh_seconds: Option<u64>,
+    /// Key columns selected together as each row's `__id`, `ref.column` for
+    /// joined tables; filled in from the schema, not the class syntax.
+    pub identity: Vec<String>,
+}
+
+impl QueryConfig {
+    /// Serialize back into a `db-` class name that parses to the same query.
+    ///
+    /// Joins are written as `join-[<join param>]` tokens, and values or search
+    /// terms the grammar would split or read as a keyword, such as
+    /// `2024-01-15`, are bracketed. Schema-resolved search columns are dropped,
+    /// leaving only the `search-<term>` token.
+    #[must_use]
+    pub fn to_class_name(&self) -> String {
+        self.to_string()
+    }
 
-        match state {
-            ParserState::Column => {
-                if !matches!(part, "where" | "limit" | "orderby") {
-                    config.columns.push(part.to_string());
+    /// Base table columns that a `case-` mapping selects in the column's place.
+    pub fn case_columns(&self) -> impl Iterator<Item = &str> {
+        self.expressions
+            .iter()
+            .filter_map(|expression| match expression {
+                ColumnExpr::Case(case) => Some(case.column.as_str()),
+                _ => None,
+            })
+    }
+}
+
+impl fmt::Display for QueryConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", tokens::PREFIX, self.table)?;
+        for column in &self.columns {
+            write!(f, "-{column}")?;
+        }
+        for expression in &self.expressions {
+            write!(f, "-{expression}")?;
+        }
+        for join in &self.joins {
+            write!(f, "-join-[{join}]")?;
+        }
+        if !self.where_clauses.is_empty() {
+            f.write_str("-where")?;
+            for clause in &self.where_clauses {
+                write!(f, "-{}", clause.field)?;
+                if let Some(keyword) = clause.operator.as_keyword() {
+                    write!(f, "-{keyword}")?;
+                }
+                match &clause.subquery {
+                    Some(subquery) => write!(f, "-({subquery})")?,
+                    None => write!(f, "-{}", Bracketed(&clause.value))?,
                 }
             }
-            ParserState::WhereField => {
-                current_where_field = part.to_string();
-                state = ParserState::WhereValue;
+        }
+        if let Some(search) = &self.search {
+            write!(f, "-search-{}", Bracketed(&search.term))?;
+        }
+        if let Some(order_by) = &self.order_by {
+            write!(
+                f,
+                "-orderby-{}-{}",
+                order_by.field,
+                order_by.direction.as_keyword()
+            )?;
+        }
+        if let Some(limit) = self.limit {
+            write!(f, "-limit-{limit}")?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, "-offset-{offset}")?;
+        }
+        if let Some(seconds) = self.refresh_seconds {
+            match seconds {
+                s if s % 3600 == 0 => write!(f, "-refresh-{}h", s / 3600)?,
+                s if s % 60 == 0 => write!(f, "-refresh-{}m", s / 60)?,
+                s => write!(f, "-refresh-{s}s")?,
             }
-            ParserState::WhereValue => {
-                config
-                    .where_clauses
-                    .push((current_where_field.clone(), part.to_string()));
-                state = ParserState::WhereField;
+        }
+        Ok(())
+    }
+}
+
+/// A where value or search term, bracketed when written bare it would span
+/// segments, open a bracket or subquery, or read as a keyword.
+struct Bracketed<'a>(&'a str);
+
+impl fmt::Display for Bracketed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.0;
+        if value.is_empty()
+            || value.contains('-')
+            || value.starts_with(['[', '('])
+            || tokens::ALIAS_TARGETS.contains(&value)
+        {
+            write!(f, "[{value}]")
+        } else {
+            f.write_str(value)
+        }
+    }
+}
+
+/// Parse a single `db-` class name into a query config.
+///
+/// See [`tokens`] for the underlying lexer and syntax tree.
+#[must_use]
+pub fn parse_class_name(class_name: &str) -> Option<QueryConfig> {
+    parse_class_name_with(class_name, &GrammarConfig::default())
+}
+
+/// Parse a class name under a specific grammar.
+#[must_use]
+pub fn parse_class_name_with(class_name: &str, grammar: &GrammarConfig) -> Option<QueryConfig> {
+    tokens::parse_with(&grammar.expand_snippets(class_name), grammar)
+        .map(|ast| lower(&ast, grammar))
+}
+
+/// Lower a syntax tree, substituting registered `$NAME` where values.
+///
+/// An unregistered variable stays as the literal text `$NAME`, which matches
+/// no rows rather than dropping the filter; strict parsing rejects it instead.
+fn lower(ast: &ClassAst<'_>, grammar: &GrammarConfig) -> QueryConfig {
+    let mut config = ast.to_config();
+    substitute_variables(&mut config, grammar);
+    config
+}
+
+fn substitute_variables(config: &mut QueryConfig, grammar: &GrammarConfig) {
+    for clause in &mut config.where_clauses {
+        if let Some(subquery) = clause.subquery.as_mut() {
+            substitute_variables(subquery, grammar);
+        } else if let Ok(Some(value)) = grammar.resolve_variable(&clause.value) {
+            clause.value = value.to_string();
+        }
+    }
+}
+
+/// Parse a class name, rejecting anything the lenient parser would guess at.
+///
+/// Tokens the grammar cannot place (`limit-abc`, a where field without a
+/// value) are errors, and every column, where field and order field must
+/// satisfy `is_column(table, column)`, so a typo such as `ordeby` is reported
+/// instead of being selected as a column.
+///
+/// # Errors
+/// Returns the first `ParseError` in source order.
+pub fn parse_class_name_strict(
+    class_name: &str,
+    is_column: impl Fn(&str, &str) -> bool,
+) -> Result<QueryConfig, ParseError> {
+    parse_class_name_strict_with(class_name, &GrammarConfig::default(), is_column)
+}
+
+/// [`parse_class_name_strict`] under a specific grammar. Error positions
+/// refer to the class name after snippet expansion.
+///
+/// # Errors
+/// Returns the first `ParseError` in source order.
+pub fn parse_class_name_strict_with(
+    class_name: &str,
+    grammar: &GrammarConfig,
+    is_column: impl Fn(&str, &str) -> bool,
+) -> Result<QueryConfig, ParseError> {
+    let expanded = grammar.expand_snippets(class_name);
+    let ast = tokens::parse_with(&expanded, grammar)
+        .ok_or_else(|| ParseError::NotAClassName(class_name.to_string()))?;
+    check_strict(&ast, grammar, &is_column)?;
+    Ok(lower(&ast, grammar))
+}
+
+fn check_strict(
+    ast: &ClassAst<'_>,
+    grammar: &GrammarConfig,
+    is_column: &dyn Fn(&str, &str) -> bool,
+) -> Result<(), ParseError> {
+    if let Some(token) = ast.invalid.first() {
+        return Err(ParseError::InvalidToken {
+            token: token.text.to_string(),
+            position: token.start,
+        });
+    }
+
+    let table = ast.table.text;
+    for clause in &ast.clauses {
+        let expr;
+        let columns: Vec<(&str, usize)> = match clause {
+            Clause::Column(column) => vec![(column.text, column.start)],
+            Clause::Where { field, .. }
+            | Clause::OrderBy { field, .. }
+            | Clause::Case { field, .. } => vec![(field.text, field.start)],
+            Clause::Json(field) => {
+                let column = field.text.split('.').next().unwrap_or_default();
+                vec![(column, field.start)]
             }
-            ParserState::Limit => {
-                if let Ok(limit) = part.parse::<i64>() {
-                    config.limit = Some(limit);
-                }
-                state = ParserState::Column;
+            Clause::Window {
+                field, partition, ..
+            } => std::iter::once(field)
+                .chain(partition)
+                .map(|token| (token.text, token.start))
+                .collect(),
+            // Expression columns have no tokens of their own.
+            Clause::Expression { expression, .. } => {
+                expr = tokens::parse_expression(expression.text);
+                expr.iter()
+                    .flat_map(Expr::columns)
+                    .map(|column| (column, expression.start))
+                    .collect()
             }
-            ParserState::OrderByField => {
-                config.order_by = Some(OrderBy {
-                    field: part.to_string(),
-                    direction: OrderDirection::Asc,
+            Clause::Limit(_)
+            | Clause::Offset(_)
+            | Clause::Search(_)
+            | Clause::Refresh(_)
+            | Clause::Join(_) => continue,
+        };
+        for (column, position) in columns {
+            let (qualifier, name) = split_qualified(column);
+            if !is_column(qualifier.unwrap_or(table), name) {
+                return Err(ParseError::UnknownColumn {
+                    table: table.to_string(),
+                    column: column.to_string(),
+                    position,
                 });
-                state = ParserState::OrderByDir;
             }
-            ParserState::OrderByDir => {
-                if let Some(order_by) = config.order_by.as_mut() {
-                    if part == "asc" {
-                        order_by.direction = OrderDirection::Asc;
-                    } else if part == "desc" {
-                        order_by.direction = OrderDirection::Desc;
-                    }
-                }
-                state = ParserState::Column;
+        }
+        if let Clause::Where {
+            subquery: Some(subquery),
+            ..
+        } = clause
+        {
+            check_strict(subquery, grammar, is_column)?;
+        } else if let Clause::Where { value, .. } = clause {
+            if let Err(name) = grammar.resolve_variable(value.text) {
+                return Err(ParseError::UnknownVariable {
+                    name,
+                    position: value.start,
+                });
             }
         }
+    }
+    Ok(())
+}
 
-        i += 1;
+/// Deprecation and compatibility notes for parsing `class_name` under `grammar`.
+///
+/// Parsing under an older grammar version is itself deprecated. When the
+/// class name means something different under another version, the note
+/// shows both readings so the embed can be migrated (or pinned) deliberately.
+#[must_use]
+pub fn grammar_warnings(class_name: &str, grammar: &GrammarConfig) -> Vec<String> {
+    let version = grammar.version;
+    let mut warnings = Vec::new();
+    if version < GrammarVersion::CURRENT {
+        warnings.push(format!(
+            "grammar v{} is deprecated; migrate class names to v{}",
+            version.number(),
+            GrammarVersion::CURRENT.number()
+        ));
     }
 
-    Some(config)
+    let requested = parse_class_name_with(class_name, grammar).map(|c| c.to_string());
+    for other in [GrammarVersion::V1, GrammarVersion::CURRENT] {
+        if other == version {
+            continue;
+        }
+        let other_grammar = grammar.clone().with_version(other);
+        let reading = parse_class_name_with(class_name, &other_grammar).map(|c| c.to_string());
+        if let (Some(requested), Some(reading)) = (&requested, reading) {
+            if *requested != reading {
+                warnings.push(format!(
+                    "{class_name} reads as {requested} under grammar v{} but as {reading} under v{}",
+                    version.number(),
+                    other.number()
+                ));
+            }
+        }
+    }
+    warnings
 }
 
 #[must_use]
 pub fn parse_class_names(class_names: &str) -> Option<QueryConfig> {
-    for class_name in class_names.split_whitespace() {
-        let trimmed = class_name.trim();
-        if trimmed.is_empty() {
+    parse_class_names_with(class_names, &GrammarConfig::default())
+}
+
+/// Parse every class in a whitespace-separated list under `grammar` and merge
+/// them, so `db-users-name db-users-where-role-admin db-users-limit-5` stacks
+/// like utility classes. The first class picks the table; classes for other
+/// tables are ignored. See [`merge_configs`].
+#[must_use]
+pub fn parse_class_names_with(class_names: &str, grammar: &GrammarConfig) -> Option<QueryConfig> {
+    let mut configs = class_names
+        .split_whitespace()
+        .filter_map(|class_name| parse_class_name_with(class_name, grammar));
+    let first = configs.next()?;
+    let table = first.table.clone();
+    Some(
+        configs
+            .filter(|config| config.table == table)
+            .fold(first, merge_configs),
+    )
+}
+
+/// [`parse_class_names_with`], checking each class like [`parse_class_name_strict`].
+///
+/// # Errors
+/// Returns `ParseError::NotAClassName` when no class matches the grammar,
+/// otherwise the first error in the first class that has one.
+pub fn parse_class_names_strict_with(
+    class_names: &str,
+    grammar: &GrammarConfig,
+    is_column: impl Fn(&str, &str) -> bool,
+) -> Result<QueryConfig, ParseError> {
+    let expanded: Vec<_> = class_names
+        .split_whitespace()
+        .map(|class_name| grammar.expand_snippets(class_name))
+        .collect();
+    let mut merged: Option<QueryConfig> = None;
+    for ast in expanded
+        .iter()
+        .filter_map(|class_name| tokens::parse_with(class_name, grammar))
+    {
+        if merged
+            .as_ref()
+            .is_some_and(|config| config.table != ast.table.text)
+        {
             continue;
         }
-        if let Some(config) = parse_class_name(trimmed) {
-            return Some(config);
-        }
+        check_strict(&ast, grammar, &is_column)?;
+        let config = lower(&ast, grammar);
+        merged = Some(match merged {
+            Some(merged) => merge_configs(merged, config),
+            None => config,
+        });
     }
-    None
+    merged.ok_or_else(|| ParseError::NotAClassName(class_names.to_string()))
 }
 
 #[must_use]
 pub fn parse_join_param(param: &str) -> Option<JoinConfig> {
     let parts: Vec<&str> = param.split(':').collect();
-    if parts.len() < 2 {
+    if parts[0].trim().is_empty() {
         return None;
     }
 
-    let table = parts[0].to_string();
-    let on_clause = parts[1];
-    let select_cols = parts.get(2).copied().unwrap_or("");
-    let join_type = parts.get(3).copied().unwrap_or("left");
+    Some(join_config_from_parts(
+        parts[0],
+        parts.get(1).copied().unwrap_or_default(),
+        parts.get(2).copied(),
+        parts.get(3).copied(),
+    ))
+}
 
-    let mut on_parts = on_clause.split('-');
-    let parent_column = on_parts.next().unwrap_or("id").to_string();
-    let fallback_child = format!("{table}_id");
-    let child_column = on_parts.next().unwrap_or(fallback_child.as_str()).to_string();
+/// Parse a comma-separated list of join params, e.g.
+/// `posts:id-author_id:title,likes:inner,comments:posts.id-post_id`.
+///
+/// A join without columns to join on, such as `posts` or `comments:posts.`,
+/// leaves them empty for `SchemaCatalog::resolve_joins` to find.
+///
+/// Commas also separate the select list of a single join, so a segment only
+/// starts a new join when it carries its own `table:on` pair.
+#[must_use]
+pub fn parse_join_params(param: &str) -> Vec<JoinConfig> {
+    let mut groups: Vec<String> = Vec::new();
 
-    let columns = if select_cols.is_empty() {
-        Vec::new()
-    } else {
-        select_cols
-            .split(',')
-            .map(str::trim)
-            .filter(|c| !c.is_empty())
-            .map(ToString::to_string)
-            .collect()
-    };
+    for segment in param.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let continues_select = groups
+            .last()
+            .is_some_and(|group| group.split(':').count() == 3 && is_select_continuation(segment));
 
-    let join_type = match join_type {
-        "inner" => JoinType::Inner,
-        "right" => JoinType::Right,
-        _ => JoinType::Left,
-    };
+        match groups.last_mut() {
+            Some(group) if continues_select => {
+                group.push(',');
+                group.push_str(segment);
+            }
+            _ => groups.push(segment.to_string()),
+        }
+    }
 
-    Some(JoinConfig {
-        table,
-        parent_column,
-        child_column,
-        columns,
-        join_type,
-    })
+    groups
+        .iter()
+        .filter_map(|group| parse_join_param(group))
+        .collect()
+}
+
+fn is_select_continuation(segment: &str) -> bool {
+    let mut parts = segment.split(':');
+    let _column = parts.next();
+    match (parts.next(), parts.next()) {
+        (None, _) => true,
+        (Some(join_type), None) => matches!(join_type, "inner" | "left" | "right"),
+        _ => false,
+    }
 }
 
 #[must_use]
@@ -225,10 +827,30 @@ pub fn join_config_from_parts(
     select: Option<&str>,
     join_type: Option<&str>,
 ) -> JoinConfig {
+    let (table, alias) = split_table_alias(table);
     let mut on_parts = on.split('-');
-    let parent_column = on_parts.next().unwrap_or("id").to_string();
-    let fallback_child = format!("{table}_id");
-    let child_column = on_parts.next().unwrap_or(fallback_child.as_str()).to_string();
+    let parent = on_parts.next().unwrap_or("id");
+    let (parent_table, parent) = match parent.split_once('.') {
+        Some((parent_table, column)) => (Some(parent_table.to_string()), column),
+        None => (None, parent),
+    };
+    let through = (parent == "through").then(|| Through {
+        table: on_parts.next().unwrap_or_default().to_string(),
+        parent_column: String::new(),
+        child_column: String::new(),
+    });
+    let (parent_column, child_column) = if through.is_some() || parent.is_empty() {
+        (String::new(), String::new())
+    } else {
+        let fallback_child = format!("{table}_id");
+        (
+            parent.to_string(),
+            on_parts
+                .next()
+                .unwrap_or(fallback_child.as_str())
+                .to_string(),
+        )
+    };
 
     let columns = select
         .unwrap_or("")
@@ -246,10 +868,34 @@ pub fn join_config_from_parts(
 
     JoinConfig {
         table: table.to_string(),
+        alias: alias.map(ToString::to_string),
+        parent_table,
         parent_column,
         child_column,
         columns,
         join_type,
+        through,
+    }
+}
+
+/// Split `posts as authored` into the table name and its alias.
+fn split_table_alias(table: &str) -> (&str, Option<&str>) {
+    let mut words = table.split_whitespace();
+    match (words.next(), words.next(), words.next(), words.next()) {
+        (Some(name), Some(keyword), Some(alias), None) if keyword.eq_ignore_ascii_case("as") => {
+            (name, Some(alias))
+        }
+        _ => (table.trim(), None),
+    }
+}
+
+/// Split a column reference such as `posts.title` into its table qualifier
+/// (a table name or join alias) and column name.
+#[must_use]
+pub fn split_qualified(column: &str) -> (Option<&str>, &str) {
+    match column.split_once('.') {
+        Some((table, column)) => (Some(table), column),
+        None => (None, column),
     }
 }
 
@@ -259,11 +905,199 @@ pub fn config_with_join(mut config: QueryConfig, join: JoinConfig) -> QueryConfi
     config
 }
 
+/// Fold a later class's config into an earlier one for the same table.
+///
+/// Columns, expressions and where clauses accumulate (a repeated column is
+/// kept once); `limit`, `offset`, `orderby`, `search` and `refresh` from
+/// `other` replace earlier ones.
 #[must_use]
+pub fn merge_configs(mut config: QueryConfig, other: QueryConfig) -> QueryConfig {
+    for column in other.columns {
+        if !config.columns.contains(&column) {
+            config.columns.push(column);
+        }
+    }
+    config.expressions.extend(other.expressions);
+    config.where_clauses.extend(other.where_clauses);
+    config.joins.extend(other.joins);
+    config.limit = other.limit.or(config.limit);
+    config.offset = other.offset.or(config.offset);
+    config.order_by = other.order_by.or(config.order_by);
+    config.search = other.search.or(config.search);
+    config.refresh_seconds = other.refresh_seconds.or(config.refresh_seconds);
+    config
+}
+
+#[must_use]
+#[deprecated(note = "read `config.where_clauses`; a map loses operators and repeated fields")]
 pub fn where_as_map(config: &QueryConfig) -> HashMap<String, String> {
     let mut map = HashMap::new();
-    for (field, value) in &config.where_clauses {
-        map.insert(field.clone(), value.clone());
+    for clause in &config.where_clauses {
+        map.insert(clause.field.clone(), clause.value.clone());
     }
     map
 }
+
+/// Prefix of insert class names, as in `ins-users-name-[Ada]`.
+pub const INSERT_PREFIX: &str = "ins";
+pub const UPDATE_PREFIX: &str = "upd";
+pub const DELETE_PREFIX: &str = "del";
+
+/// What a [`MutationConfig`] does to its table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutationKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl MutationKind {
+    /// The class name prefix for this kind of mutation.
+    #[must_use]
+    pub const fn prefix(self) -> &'static str {
+        match self {
+            Self::Insert => INSERT_PREFIX,
+            Self::Update => UPDATE_PREFIX,
+            Self::Delete => DELETE_PREFIX,
+        }
+    }
+}
+
+/// A value written to one column by a mutation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnValue {
+    pub column: String,
+    /// Bound like a where value, so `int:42` and `text:007` type hints apply.
+    pub value: String,
+}
+
+/// A write parsed from an `ins-`, `upd-` or `del-` class name or built from a
+/// JSON body.
+#[derive(Debug, Clone)]
+pub struct MutationConfig {
+    pub kind: MutationKind,
+    pub table: String,
+    /// Columns an insert or update sets; always empty for deletes.
+    pub values: Vec<ColumnValue>,
+    /// Rows an update or delete applies to; always empty for inserts.
+    pub where_clauses: Vec<WhereClause>,
+    /// Read back the rows the statement changed with `RETURNING *`.
+    pub returning: bool,
+}
+
+/// Parse a mutation class name: `ins-<table>` or `upd-<table>` followed by
+/// `<column>-<value>` pairs, e.g. `ins-users-name-[Ada]-email-[a@x.com]`.
+///
+/// Updates and deletes name the rows they change after `where`, as equality
+/// pairs: `upd-users-role-admin-where-id-42`, `del-posts-where-id-42`. Values
+/// in square brackets may contain `-`; bare values run to the next `-`.
+///
+/// # Errors
+/// Returns `ParseError::NotAClassName` without an `ins-`/`upd-`/`del-` prefix
+/// or a table, and `ParseError::InvalidToken` for a column without a value, an
+/// unterminated `[` value, `where` in an insert, or a value set by a delete.
+pub fn parse_mutation(class_name: &str) -> Result<MutationConfig, ParseError> {
+    let input = class_name.trim();
+    let not_a_class_name = || ParseError::NotAClassName(input.to_string());
+    let (kind, body) = [
+        MutationKind::Insert,
+        MutationKind::Update,
+        MutationKind::Delete,
+    ]
+    .into_iter()
+    .find_map(|kind| {
+        let body = input.strip_prefix(kind.prefix())?.strip_prefix('-')?;
+        Some((kind, body))
+    })
+    .ok_or_else(not_a_class_name)?;
+    let (table, rest) = body.split_once('-').unwrap_or((body, ""));
+    if table.is_empty() {
+        return Err(not_a_class_name());
+    }
+
+    let mut values = Vec::new();
+    let mut where_clauses = Vec::new();
+    let mut filtering = false;
+    let mut position = kind.prefix().len() + 1 + table.len() + 1;
+    let mut rest = rest;
+    while !rest.is_empty() {
+        if let Some(filters) = rest.strip_prefix("where-").filter(|_| !filtering) {
+            if kind == MutationKind::Insert {
+                return Err(ParseError::InvalidToken {
+                    token: "where".to_string(),
+                    position,
+                });
+            }
+            filtering = true;
+            position += "where-".len();
+            rest = filters;
+            continue;
+        }
+        if kind == MutationKind::Delete && !filtering {
+            return Err(ParseError::InvalidToken {
+                token: rest.split('-').next().unwrap_or(rest).to_string(),
+                position,
+            });
+        }
+        let pair = next_column_value(&mut rest, &mut position)?;
+        if filtering {
+            where_clauses.push(WhereClause {
+                field: pair.column,
+                operator: WhereOperator::Eq,
+                value: pair.value,
+                subquery: None,
+            });
+        } else {
+            values.push(pair);
+        }
+    }
+
+    Ok(MutationConfig {
+        kind,
+        table: table.to_string(),
+        values,
+        where_clauses,
+        returning: false,
+    })
+}
+
+/// Take one `<column>-<value>` pair off the front of `rest`, advancing
+/// `position` past it and its trailing `-`.
+fn next_column_value(rest: &mut &str, position: &mut usize) -> Result<ColumnValue, ParseError> {
+    let Some((column, tail)) = rest.split_once('-').filter(|(_, tail)| !tail.is_empty()) else {
+        return Err(ParseError::InvalidToken {
+            token: (*rest).to_string(),
+            position: *position,
+        });
+    };
+    let value_position = *position + column.len() + 1;
+    let (value, consumed) = match tail.strip_prefix('[') {
+        Some(bracketed) => {
+            let end = bracketed
+                .find(']')
+                .ok_or_else(|| ParseError::InvalidToken {
+                    token: tail.to_string(),
+                    position: value_position,
+                })?;
+            (&bracketed[..end], end + 2)
+        }
+        None => {
+            let end = tail.find('-').unwrap_or(tail.len());
+            (&tail[..end], end)
+        }
+    };
+    let after = &tail[consumed..];
+    if !(after.is_empty() || after.starts_with('-')) {
+        return Err(ParseError::InvalidToken {
+            token: after.to_string(),
+            position: value_position + consumed,
+        });
+    }
+
+    *position += column.len() + 1 + consumed + usize::from(!after.is_empty());
+    *rest = after.strip_prefix('-').unwrap_or(after);
+    Ok(ColumnValue {
+        column: column.to_string(),
+        value: value.to_string(),
+    })
+}
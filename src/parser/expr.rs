@@ -0,0 +1,245 @@
+//! Arithmetic for `expr-[<expression>]-as-<alias>` computed columns.
+//!
+//! The grammar is deliberately small: column names, unsigned numeric
+//! literals, `+ - * / %` and parentheses. Anything else fails to parse, so an
+//! expression cannot carry SQL of its own; the builder still sanitizes every
+//! column it emits.
+
+use std::fmt;
+
+/// Longest expression accepted, which also bounds the depth of the tree.
+pub const MAX_LEN: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Column(String),
+    /// Digits with at most one `.`, kept as written.
+    Number(String),
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    #[must_use]
+    pub const fn as_sql(self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Rem => "%",
+        }
+    }
+
+    const fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            b'+' => Some(Self::Add),
+            b'-' => Some(Self::Sub),
+            b'*' => Some(Self::Mul),
+            b'/' => Some(Self::Div),
+            b'%' => Some(Self::Rem),
+            _ => None,
+        }
+    }
+
+    const fn precedence(self) -> u8 {
+        match self {
+            Self::Add | Self::Sub => 1,
+            Self::Mul | Self::Div | Self::Rem => 2,
+        }
+    }
+}
+
+impl Expr {
+    /// Parse an expression such as `price*stock` or `(price-cost)/price`.
+    #[must_use]
+    pub fn parse(text: &str) -> Option<Self> {
+        if text.len() > MAX_LEN {
+            return None;
+        }
+        let mut parser = Parser {
+            bytes: text.as_bytes(),
+            pos: 0,
+        };
+        let expr = parser.sum()?;
+        (parser.pos == parser.bytes.len()).then_some(expr)
+    }
+
+    /// Every column the expression reads, in source order.
+    #[must_use]
+    pub fn columns(&self) -> Vec<&str> {
+        let mut columns = Vec::new();
+        self.collect_columns(&mut columns);
+        columns
+    }
+
+    fn collect_columns<'a>(&'a self, columns: &mut Vec<&'a str>) {
+        match self {
+            Self::Column(column) => columns.push(column),
+            Self::Number(_) => {}
+            Self::Binary { left, right, .. } => {
+                left.collect_columns(columns);
+                right.collect_columns(columns);
+            }
+        }
+    }
+
+    /// The expression as SQL, with every column written by `column` and
+    /// nested operations parenthesized.
+    ///
+    /// # Errors
+    /// Returns the first error `column` returns.
+    pub fn to_sql<E>(
+        &self,
+        column: &mut impl FnMut(&str) -> Result<String, E>,
+    ) -> Result<String, E> {
+        match self {
+            Self::Column(name) => column(name),
+            Self::Number(number) => Ok(number.clone()),
+            Self::Binary { op, left, right } => {
+                let operand = |expr: &Self, column: &mut _| -> Result<String, E> {
+                    let sql = expr.to_sql(column)?;
+                    Ok(match expr {
+                        Self::Binary { .. } => format!("({sql})"),
+                        _ => sql,
+                    })
+                };
+                let left = operand(left, column)?;
+                let right = operand(right, column)?;
+                Ok(format!("{left} {} {right}", op.as_sql()))
+            }
+        }
+    }
+}
+
+/// Formats without spaces and with only the parentheses precedence needs,
+/// as the text inside `expr-[...]`.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Column(text) | Self::Number(text) => f.write_str(text),
+            Self::Binary { op, left, right } => {
+                let binds_looser = |expr: &Self, right_side: bool| match expr {
+                    Self::Binary { op: inner, .. } => {
+                        inner.precedence() < op.precedence()
+                            || (right_side && inner.precedence() == op.precedence())
+                    }
+                    _ => false,
+                };
+                if binds_looser(left, false) {
+                    write!(f, "({left})")?;
+                } else {
+                    write!(f, "{left}")?;
+                }
+                f.write_str(op.as_sql())?;
+                if binds_looser(right, true) {
+                    write!(f, "({right})")
+                } else {
+                    write!(f, "{right}")
+                }
+            }
+        }
+    }
+}
+
+/// Whether `text` is a plain identifier: a letter or `_`, then letters,
+/// digits and `_`.
+#[must_use]
+pub fn is_identifier(text: &str) -> bool {
+    let mut bytes = text.bytes();
+    bytes
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == b'_')
+        && bytes.all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+}
+
+/// Recursive descent over `sum := product (('+'|'-') product)*`,
+/// `product := atom (('*'|'/'|'%') atom)*` and
+/// `atom := number | column | '(' sum ')'`.
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn sum(&mut self) -> Option<Expr> {
+        self.binary(1, Self::product)
+    }
+
+    fn product(&mut self) -> Option<Expr> {
+        self.binary(2, Self::atom)
+    }
+
+    /// A left-associative chain of `operand`s joined by operators of
+    /// `precedence`.
+    fn binary(&mut self, precedence: u8, operand: fn(&mut Self) -> Option<Expr>) -> Option<Expr> {
+        let mut expr = operand(self)?;
+        while let Some(op) = self
+            .bytes
+            .get(self.pos)
+            .and_then(|byte| BinaryOp::from_byte(*byte))
+            .filter(|op| op.precedence() == precedence)
+        {
+            self.pos += 1;
+            expr = Expr::Binary {
+                op,
+                left: Box::new(expr),
+                right: Box::new(operand(self)?),
+            };
+        }
+        Some(expr)
+    }
+
+    fn atom(&mut self) -> Option<Expr> {
+        let start = self.pos;
+        let first = *self.bytes.get(start)?;
+        if first == b'(' {
+            self.pos += 1;
+            let expr = self.sum()?;
+            (self.bytes.get(self.pos) == Some(&b')')).then(|| {
+                self.pos += 1;
+                expr
+            })
+        } else if first.is_ascii_digit() {
+            self.take_while(|byte| byte.is_ascii_digit());
+            if self.bytes.get(self.pos) == Some(&b'.') {
+                self.pos += 1;
+                if self.take_while(|byte| byte.is_ascii_digit()) == 0 {
+                    return None;
+                }
+            }
+            Some(Expr::Number(self.text(start)))
+        } else if first.is_ascii_alphabetic() || first == b'_' {
+            self.take_while(|byte| byte.is_ascii_alphanumeric() || byte == b'_');
+            Some(Expr::Column(self.text(start)))
+        } else {
+            None
+        }
+    }
+
+    /// Advance past bytes matching `accept`, returning how many.
+    fn take_while(&mut self, accept: impl Fn(u8) -> bool) -> usize {
+        let start = self.pos;
+        while self.bytes.get(self.pos).is_some_and(|byte| accept(*byte)) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    /// The input from `start` to the current position; only ever ASCII.
+    fn text(&self, start: usize) -> String {
+        String::from_utf8_lossy(&self.bytes[start..self.pos]).into_owned()
+    }
+}
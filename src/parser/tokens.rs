@@ -0,0 +1,1272 @@
+//! Lexer and syntax tree for `db-` class names.
+//!
+//! [`tokenize`] classifies every segment of a class name with its byte span so
+//! editors and linters can highlight it, and [`parse`] groups the tokens into a
+//! [`ClassAst`]. Neither step touches the database; [`ClassAst::to_config`]
+//! lowers the tree into the [`QueryConfig`] used by the query builder.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use super::expr::{is_identifier, Expr};
+use super::{
+    parse_join_param, CaseExpr, ColumnExpr, ComputedExpr, JsonExpr, OrderBy, OrderDirection,
+    QueryConfig, SearchConfig, WhereClause, WhereOperator, WindowExpr, WindowFunction,
+};
+
+/// Default class name prefix, as in `db-users-name`.
+pub const PREFIX: &str = "db";
+
+/// Built-in words an alias may stand for: keywords, where operators, and
+/// `eq` for the implicit equality of `where-<field>-<value>`.
+pub const ALIAS_TARGETS: &[&str] = &[
+    "where", "limit", "offset", "orderby", "search", "refresh", "join", "after", "before",
+    "contains", "in", "eq",
+];
+
+/// Revision of the class name grammar.
+///
+/// Each version may claim new keywords, so a segment that was a column or a
+/// value under an older grammar can change meaning. Parsing under an older
+/// version keeps existing class names working while they are migrated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum GrammarVersion {
+    /// `where`, `limit` and `orderby` only; every other segment is a column or value.
+    V1,
+    /// Adds `search-`, `refresh-`, `offset-`, `join-[<join param>]`, the
+    /// `after`/`before`/`contains` where operators, `last-<n>-<unit>` date
+    /// values, `[...]` values spanning dashes, `in-(<class name>)`
+    /// subqueries, `rank-over-<field>` window functions,
+    /// `expr-[<expression>]-as-<alias>` computed columns,
+    /// `case-<field>-<value>-<label>` value mappings and
+    /// `json-<column>.<key>` JSON fields.
+    V2,
+}
+
+impl GrammarVersion {
+    pub const CURRENT: Self = Self::V2;
+
+    /// Accepts `1`, `v1`, `2` or `v2`.
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().trim_start_matches(['v', 'V']) {
+            "1" => Some(Self::V1),
+            "2" => Some(Self::V2),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn number(self) -> u8 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+        }
+    }
+
+    const fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Self::V1 => &["where", "limit", "orderby"],
+            Self::V2 => &[
+                "where", "limit", "offset", "orderby", "search", "refresh", "join",
+            ],
+        }
+    }
+}
+
+impl Default for GrammarVersion {
+    fn default() -> Self {
+        Self::CURRENT
+    }
+}
+
+/// Units a `last-<n>-<unit>` date value may count in.
+pub const DATE_UNITS: &[&str] = &[
+    "minute", "minutes", "hour", "hours", "day", "days", "week", "weeks", "month", "months",
+    "year", "years",
+];
+
+/// Everything the lexer needs to know about the class name syntax in use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarConfig {
+    /// Accepted prefixes, without the trailing `-`. Longer prefixes win when
+    /// several match.
+    pub prefixes: Vec<String>,
+    pub version: GrammarVersion,
+    /// Extra words mapped to one of [`ALIAS_TARGETS`], e.g. `sort` → `orderby`,
+    /// for class names carried over from other query-string DSLs.
+    pub aliases: BTreeMap<String, String>,
+    /// Named class fragments: a `-@<name>` segment is replaced by its body
+    /// before tokenizing, e.g. `@active-users` → `where-status-active-limit-50`.
+    pub snippets: BTreeMap<String, String>,
+    /// Constants for `$NAME` where values, such as a tenant id that differs
+    /// between environments. Only names registered here are substituted.
+    pub variables: BTreeMap<String, String>,
+}
+
+impl Default for GrammarConfig {
+    fn default() -> Self {
+        Self {
+            prefixes: vec![PREFIX.to_string()],
+            version: GrammarVersion::CURRENT,
+            aliases: BTreeMap::new(),
+            snippets: BTreeMap::new(),
+            variables: BTreeMap::new(),
+        }
+    }
+}
+
+impl GrammarConfig {
+    /// Replace the accepted prefixes, e.g. `["q", "sql"]`.
+    #[must_use]
+    pub fn with_prefixes<I, S>(mut self, prefixes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.prefixes = prefixes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    #[must_use]
+    pub const fn with_version(mut self, version: GrammarVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Accept `alias` wherever `target` is accepted. Targets outside
+    /// [`ALIAS_TARGETS`] never match anything.
+    #[must_use]
+    pub fn with_alias(mut self, alias: impl Into<String>, target: impl Into<String>) -> Self {
+        self.aliases.insert(alias.into(), target.into());
+        self
+    }
+
+    /// Register a snippet; `name` may be given with or without its `@`.
+    #[must_use]
+    pub fn with_snippet(mut self, name: &str, body: &str) -> Self {
+        self.snippets.insert(
+            name.trim_start_matches('@').to_string(),
+            body.trim_matches('-').to_string(),
+        );
+        self
+    }
+
+    /// Register a value for `$name`; `name` may be given with or without its `$`.
+    #[must_use]
+    pub fn with_variable(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.variables
+            .insert(name.trim_start_matches('$').to_string(), value.into());
+        self
+    }
+
+    /// The value a `$NAME` where value stands for, or `None` when `value` is
+    /// not a variable reference.
+    ///
+    /// # Errors
+    /// Returns the variable name when it is not registered.
+    pub fn resolve_variable<'s>(&'s self, value: &str) -> Result<Option<&'s str>, String> {
+        let Some(name) = value.strip_prefix('$') else {
+            return Ok(None);
+        };
+        self.variables
+            .get(name)
+            .map(|value| Some(value.as_str()))
+            .ok_or_else(|| name.to_string())
+    }
+
+    /// Replace every `-@<name>` segment with the snippet body.
+    ///
+    /// Expansion is a single pass, so snippets cannot reference each other,
+    /// and unknown names are left in place for the parser to reject.
+    #[must_use]
+    pub fn expand_snippets<'a>(&self, class_name: &'a str) -> Cow<'a, str> {
+        if self.snippets.is_empty() || !class_name.contains("-@") {
+            return Cow::Borrowed(class_name);
+        }
+
+        let mut expanded = String::with_capacity(class_name.len());
+        let mut rest = class_name;
+        while let Some(at) = rest.find("-@") {
+            expanded.push_str(&rest[..=at]);
+            let after = &rest[at + 2..];
+            let snippet = self
+                .snippets
+                .iter()
+                .filter(|(name, _)| {
+                    after
+                        .strip_prefix(name.as_str())
+                        .is_some_and(|tail| tail.is_empty() || tail.starts_with('-'))
+                })
+                .max_by_key(|(name, _)| name.len());
+            match snippet {
+                Some((name, body)) => {
+                    expanded.push_str(body);
+                    rest = &after[name.len()..];
+                }
+                None => {
+                    expanded.push('@');
+                    rest = after;
+                }
+            }
+        }
+        expanded.push_str(rest);
+        Cow::Owned(expanded)
+    }
+
+    /// The built-in word `segment` is registered as an alias for.
+    fn resolve_alias(&self, segment: &str) -> Option<&'static str> {
+        let target = self.aliases.get(segment)?;
+        ALIAS_TARGETS.iter().copied().find(|known| known == target)
+    }
+
+    /// The prefix `class_name` starts with, if any.
+    #[must_use]
+    pub fn match_prefix(&self, class_name: &str) -> Option<&str> {
+        self.prefixes
+            .iter()
+            .filter(|prefix| {
+                class_name
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.starts_with('-'))
+            })
+            .max_by_key(|prefix| prefix.len())
+            .map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    Prefix,
+    Table,
+    Column,
+    Keyword,
+    WhereField,
+    Operator,
+    Value,
+    Limit,
+    Offset,
+    OrderField,
+    Direction,
+    SearchTerm,
+    /// The bracketed join param of `join-[users:author_id-id:name:inner]`,
+    /// possibly spanning several segments.
+    Join,
+    /// `rank`, `dense_rank` or `row_number`, followed by an `over` keyword.
+    Function,
+    WindowField,
+    PartitionField,
+    /// The bracketed arithmetic of `expr-[price*stock]`, possibly spanning
+    /// several segments when it subtracts.
+    Expression,
+    /// The result column name after `as`.
+    Alias,
+    /// The column of `case-<field>`, followed by value/label pairs.
+    CaseField,
+    CaseValue,
+    CaseLabel,
+    /// `<column>.<key>...` after `json`.
+    JsonField,
+    /// Refresh interval such as `30s`, `5m` or `1h`.
+    Interval,
+    /// A segment the grammar cannot place, e.g. `limit-abc` or a dangling where field.
+    Invalid,
+}
+
+/// One classified segment of a class name. `start..end` is a byte range into
+/// the original input; `text` spans several segments for `last-7-days` and
+/// `[2024-01-15]` values and `(db-users-id)` subqueries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub start: usize,
+    pub end: usize,
+    /// The built-in word an aliased keyword or operator stands for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias_of: Option<&'static str>,
+}
+
+impl<'a> Token<'a> {
+    /// The token's text, with an alias resolved to the word it stands for.
+    #[must_use]
+    pub fn keyword(&self) -> &'a str {
+        self.alias_of.unwrap_or(self.text)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Column,
+    WhereField,
+    WhereValue,
+    Limit,
+    Offset,
+    OrderByField,
+    OrderByDir,
+    Search,
+    Refresh,
+    Join,
+    WindowField,
+    WindowDir,
+    WindowPartition,
+    Partition,
+    CaseValue,
+    CaseLabel,
+}
+
+/// Split a class name into tokens under the default grammar.
+///
+/// Returns `None` when the input is not a `db-<table>` class name.
+#[must_use]
+pub fn tokenize(class_name: &str) -> Option<Vec<Token<'_>>> {
+    tokenize_with(class_name, &GrammarConfig::default())
+}
+
+/// Split a class name into tokens under a specific grammar.
+#[must_use]
+pub fn tokenize_with<'a>(class_name: &'a str, grammar: &GrammarConfig) -> Option<Vec<Token<'a>>> {
+    let version = grammar.version;
+    let input = class_name.trim_end();
+    let prefix_len = grammar.match_prefix(input)?.len();
+    let body = &input[prefix_len + 1..];
+
+    let mut segments = Vec::new();
+    let mut offset = prefix_len + 1;
+    for text in body.split('-') {
+        segments.push((offset, text));
+        offset += text.len() + 1;
+    }
+    if segments.first().is_none_or(|(_, table)| table.is_empty()) {
+        return None;
+    }
+
+    let token = |kind, start: usize, end: usize| Token {
+        kind,
+        text: &input[start..end],
+        start,
+        end,
+        alias_of: None,
+    };
+
+    let mut tokens = vec![token(TokenKind::Prefix, 0, prefix_len)];
+    let (table_start, table) = segments[0];
+    tokens.push(token(
+        TokenKind::Table,
+        table_start,
+        table_start + table.len(),
+    ));
+
+    let mut state = State::Column;
+    let mut operator_seen = false;
+    let mut i = 1;
+    while i < segments.len() {
+        let (start, text) = segments[i];
+        let end = start + text.len();
+        let alias_of = grammar.resolve_alias(text);
+        let word = alias_of.unwrap_or(text);
+
+        if version.keywords().contains(&word) {
+            close_dangling(&mut tokens, state);
+            tokens.push(Token {
+                alias_of,
+                ..token(TokenKind::Keyword, start, end)
+            });
+            state = match word {
+                "where" => State::WhereField,
+                "limit" => State::Limit,
+                "offset" => State::Offset,
+                "orderby" => State::OrderByField,
+                "search" => State::Search,
+                "join" => State::Join,
+                _ => State::Refresh,
+            };
+            i += 1;
+            continue;
+        }
+
+        match state {
+            State::Column => {
+                let is_window = version >= GrammarVersion::V2
+                    && WindowFunction::from_keyword(text).is_some()
+                    && segments.get(i + 1).is_some_and(|(_, next)| *next == "over");
+                let is_expression = version >= GrammarVersion::V2
+                    && text == "expr"
+                    && segments
+                        .get(i + 1)
+                        .is_some_and(|(_, next)| next.starts_with('['));
+                if is_expression {
+                    tokens.push(token(TokenKind::Keyword, start, end));
+                    let (open_start, _) = segments[i + 1];
+                    let (close, balanced) = closing_segment(&segments, i + 1, b'[', b']');
+                    let (close_start, close_text) = segments[close];
+                    let expression = token(
+                        TokenKind::Expression,
+                        open_start,
+                        close_start + close_text.len(),
+                    );
+                    tokens.push(expression);
+                    i = close + 1;
+                    let alias = segments
+                        .get(i + 1)
+                        .filter(|_| segments[i].1 == "as")
+                        .filter(|(_, alias)| is_identifier(alias));
+                    if let Some(&(alias_start, alias)) = alias {
+                        let (as_start, as_text) = segments[i];
+                        tokens.push(token(
+                            TokenKind::Keyword,
+                            as_start,
+                            as_start + as_text.len(),
+                        ));
+                        tokens.push(token(
+                            TokenKind::Alias,
+                            alias_start,
+                            alias_start + alias.len(),
+                        ));
+                        i += 2;
+                    }
+                    if alias.is_none() || !balanced || parse_expression(expression.text).is_none() {
+                        mark_invalid(&mut tokens, TokenKind::Expression);
+                    }
+                    continue;
+                }
+                if version >= GrammarVersion::V2 && text == "case" && i + 1 < segments.len() {
+                    tokens.push(token(TokenKind::Keyword, start, end));
+                    let (field_start, field) = segments[i + 1];
+                    tokens.push(token(
+                        TokenKind::CaseField,
+                        field_start,
+                        field_start + field.len(),
+                    ));
+                    state = State::CaseValue;
+                    i += 2;
+                    continue;
+                }
+                if version >= GrammarVersion::V2 && text == "json" && i + 1 < segments.len() {
+                    tokens.push(token(TokenKind::Keyword, start, end));
+                    let (field_start, field) = segments[i + 1];
+                    tokens.push(token(
+                        TokenKind::JsonField,
+                        field_start,
+                        field_start + field.len(),
+                    ));
+                    if JsonExpr::parse(field).is_none() {
+                        mark_invalid(&mut tokens, TokenKind::JsonField);
+                    }
+                    i += 2;
+                    continue;
+                }
+                if is_window {
+                    tokens.push(token(TokenKind::Function, start, end));
+                    let (over_start, over) = segments[i + 1];
+                    tokens.push(token(
+                        TokenKind::Keyword,
+                        over_start,
+                        over_start + over.len(),
+                    ));
+                    state = State::WindowField;
+                    i += 2;
+                    continue;
+                }
+                tokens.push(token(TokenKind::Column, start, end));
+            }
+            State::WhereField => {
+                tokens.push(token(TokenKind::WhereField, start, end));
+                operator_seen = false;
+                state = State::WhereValue;
+            }
+            State::WhereValue => {
+                let is_operator = match WhereOperator::from_keyword(word) {
+                    // Only before a subquery, so `where-status-in` still means the value `in`.
+                    Some(WhereOperator::In) => segments
+                        .get(i + 1)
+                        .is_some_and(|(_, next)| next.starts_with('(')),
+                    Some(_) => true,
+                    None => alias_of == Some("eq"),
+                };
+                if version >= GrammarVersion::V2 && !operator_seen && is_operator {
+                    tokens.push(Token {
+                        alias_of,
+                        ..token(TokenKind::Operator, start, end)
+                    });
+                    operator_seen = true;
+                    i += 1;
+                    continue;
+                }
+
+                let in_subquery = tokens
+                    .last()
+                    .is_some_and(|t| t.kind == TokenKind::Operator && t.keyword() == "in");
+                if in_subquery {
+                    let (close, balanced) = closing_segment(&segments, i, b'(', b')');
+                    i = close;
+                    let (close_start, close_text) = segments[i];
+                    tokens.push(token(
+                        TokenKind::Value,
+                        start,
+                        close_start + close_text.len(),
+                    ));
+                    if !balanced {
+                        mark_invalid(&mut tokens, TokenKind::WhereField);
+                    }
+                    state = State::WhereField;
+                    i += 1;
+                    continue;
+                }
+                if version >= GrammarVersion::V2 && text.starts_with('[') {
+                    let (close, balanced) = closing_segment(&segments, i, b'[', b']');
+                    i = close;
+                    let (close_start, close_text) = segments[i];
+                    let kind = if balanced {
+                        TokenKind::Value
+                    } else {
+                        TokenKind::Invalid
+                    };
+                    tokens.push(token(kind, start, close_start + close_text.len()));
+                    state = State::WhereField;
+                    i += 1;
+                    continue;
+                }
+
+                // `last` only takes the next two segments as `<n>-<unit>`, so
+                // `where-name-last-limit-5` still compares against `last`.
+                let relative = version >= GrammarVersion::V2
+                    && text == "last"
+                    && matches!(segments.get(i + 1..i + 3), Some([(_, amount), (_, unit)])
+                        if amount.parse::<u32>().is_ok() && DATE_UNITS.contains(unit));
+                let end = if relative {
+                    i += 2;
+                    let (last_start, last) = segments[i];
+                    last_start + last.len()
+                } else {
+                    end
+                };
+                tokens.push(token(TokenKind::Value, start, end));
+                state = State::WhereField;
+            }
+            State::Limit | State::Offset => {
+                let kind = match state {
+                    _ if text.parse::<i64>().is_err() => TokenKind::Invalid,
+                    State::Limit => TokenKind::Limit,
+                    _ => TokenKind::Offset,
+                };
+                tokens.push(token(kind, start, end));
+                state = State::Column;
+            }
+            State::OrderByField => {
+                tokens.push(token(TokenKind::OrderField, start, end));
+                state = State::OrderByDir;
+            }
+            State::OrderByDir => {
+                let kind = if OrderDirection::parse(text).is_some() {
+                    TokenKind::Direction
+                } else {
+                    TokenKind::Invalid
+                };
+                tokens.push(token(kind, start, end));
+                state = State::Column;
+            }
+            State::Search | State::Join if text.starts_with('[') => {
+                let (close, balanced) = closing_segment(&segments, i, b'[', b']');
+                i = close;
+                let (close_start, close_text) = segments[i];
+                let end = close_start + close_text.len();
+                let kind = match state {
+                    _ if !balanced => TokenKind::Invalid,
+                    State::Search => TokenKind::SearchTerm,
+                    _ if parse_join_param(unbracket(&input[start..end])).is_none() => {
+                        TokenKind::Invalid
+                    }
+                    _ => TokenKind::Join,
+                };
+                tokens.push(token(kind, start, end));
+                state = State::Column;
+            }
+            State::Search => {
+                tokens.push(token(TokenKind::SearchTerm, start, end));
+                state = State::Column;
+            }
+            State::Join => {
+                tokens.push(token(TokenKind::Invalid, start, end));
+                state = State::Column;
+            }
+            State::Refresh => {
+                let kind = if interval_seconds(text).is_some() {
+                    TokenKind::Interval
+                } else {
+                    TokenKind::Invalid
+                };
+                tokens.push(token(kind, start, end));
+                state = State::Column;
+            }
+            State::WindowField => {
+                tokens.push(token(TokenKind::WindowField, start, end));
+                state = State::WindowDir;
+            }
+            State::WindowDir | State::WindowPartition => {
+                if matches!(state, State::WindowDir) && OrderDirection::parse(text).is_some() {
+                    tokens.push(token(TokenKind::Direction, start, end));
+                    state = State::WindowPartition;
+                } else if text == "partition" && i + 1 < segments.len() {
+                    tokens.push(token(TokenKind::Keyword, start, end));
+                    state = State::Partition;
+                } else {
+                    // The window is complete; read this segment again as a column.
+                    state = State::Column;
+                    continue;
+                }
+            }
+            State::Partition => {
+                tokens.push(token(TokenKind::PartitionField, start, end));
+                state = State::Column;
+            }
+            State::CaseValue if text == "end" => {
+                close_dangling(&mut tokens, state);
+                tokens.push(token(TokenKind::Keyword, start, end));
+                state = State::Column;
+            }
+            State::CaseValue => {
+                tokens.push(token(TokenKind::CaseValue, start, end));
+                state = State::CaseLabel;
+            }
+            State::CaseLabel => {
+                tokens.push(token(TokenKind::CaseLabel, start, end));
+                state = State::CaseValue;
+            }
+        }
+
+        i += 1;
+    }
+
+    close_dangling(&mut tokens, state);
+
+    Some(tokens)
+}
+
+/// Demote a clause the input ended (or a keyword cut off) while in `state`:
+/// a where field without a value, a window function without its field, or a
+/// `case-` without a complete value/label pair.
+fn close_dangling(tokens: &mut [Token<'_>], state: State) {
+    match state {
+        State::WhereValue => mark_invalid(tokens, TokenKind::WhereField),
+        State::WindowField | State::Partition => mark_invalid(tokens, TokenKind::Function),
+        State::CaseLabel => mark_invalid(tokens, TokenKind::CaseValue),
+        State::CaseValue
+            if tokens
+                .last()
+                .is_some_and(|t| t.kind == TokenKind::CaseField) =>
+        {
+            mark_invalid(tokens, TokenKind::CaseField);
+        }
+        _ => {}
+    }
+}
+
+/// Index of the segment closing the `left` bracket opened at
+/// `segments[open]`, and whether it closes exactly there; otherwise the last
+/// segment.
+fn closing_segment(segments: &[(usize, &str)], open: usize, left: u8, right: u8) -> (usize, bool) {
+    let mut depth = 0usize;
+    for (index, (_, text)) in segments.iter().enumerate().skip(open) {
+        for byte in text.bytes() {
+            if byte == left {
+                depth += 1;
+            } else if byte == right {
+                match depth.checked_sub(1) {
+                    Some(remaining) => depth = remaining,
+                    None => return (index, false),
+                }
+            }
+        }
+        if depth == 0 {
+            return (index, text.as_bytes().last() == Some(&right));
+        }
+    }
+    (segments.len() - 1, false)
+}
+
+/// A value or search term with the brackets that let it span dashes taken off.
+#[must_use]
+pub fn unbracket(text: &str) -> &str {
+    text.strip_prefix('[')
+        .and_then(|inner| inner.strip_suffix(']'))
+        .unwrap_or(text)
+}
+
+/// The arithmetic inside an `[...]` expression token.
+pub(super) fn parse_expression(text: &str) -> Option<Expr> {
+    Expr::parse(text.strip_prefix('[')?.strip_suffix(']')?)
+}
+
+/// Seconds in an interval like `30s`, `5m` or `1h`; a bare number is seconds.
+#[must_use]
+pub fn interval_seconds(text: &str) -> Option<u64> {
+    let (amount, scale) = match text.as_bytes().last()? {
+        b's' => (&text[..text.len() - 1], 1),
+        b'm' => (&text[..text.len() - 1], 60),
+        b'h' => (&text[..text.len() - 1], 3600),
+        _ => (text, 1),
+    };
+    amount
+        .parse::<u64>()
+        .ok()
+        .filter(|amount| *amount > 0)?
+        .checked_mul(scale)
+}
+
+/// Demote the most recent token of `kind` (and anything after it) to `Invalid`.
+fn mark_invalid(tokens: &mut [Token<'_>], kind: TokenKind) {
+    if let Some(index) = tokens.iter().rposition(|token| token.kind == kind) {
+        for token in &mut tokens[index..] {
+            token.kind = TokenKind::Invalid;
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Clause<'a> {
+    Column(Token<'a>),
+    Where {
+        field: Token<'a>,
+        operator: Option<Token<'a>>,
+        value: Token<'a>,
+        /// The class name inside an `in-(...)` value, with spans into the
+        /// outer input.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        subquery: Option<Box<ClassAst<'a>>>,
+    },
+    Limit(Token<'a>),
+    Offset(Token<'a>),
+    OrderBy {
+        field: Token<'a>,
+        direction: Option<Token<'a>>,
+    },
+    Search(Token<'a>),
+    Refresh(Token<'a>),
+    Join(Token<'a>),
+    Window {
+        function: Token<'a>,
+        field: Token<'a>,
+        direction: Option<Token<'a>>,
+        partition: Option<Token<'a>>,
+    },
+    Expression {
+        expression: Token<'a>,
+        alias: Token<'a>,
+    },
+    Case {
+        field: Token<'a>,
+        /// `(value, label)` pairs.
+        arms: Vec<(Token<'a>, Token<'a>)>,
+    },
+    Json(Token<'a>),
+}
+
+/// Syntax tree of a class name: its table and clauses in source order.
+/// Invalid tokens are kept in `invalid` rather than dropped, so tools can
+/// report them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClassAst<'a> {
+    pub prefix: Token<'a>,
+    pub table: Token<'a>,
+    pub clauses: Vec<Clause<'a>>,
+    pub invalid: Vec<Token<'a>>,
+}
+
+/// Tokenize and group a class name into a [`ClassAst`] under the default grammar.
+#[must_use]
+pub fn parse(class_name: &str) -> Option<ClassAst<'_>> {
+    parse_with(class_name, &GrammarConfig::default())
+}
+
+/// Tokenize and group a class name under a specific grammar.
+#[must_use]
+pub fn parse_with<'a>(class_name: &'a str, grammar: &GrammarConfig) -> Option<ClassAst<'a>> {
+    let mut tokens = tokenize_with(class_name, grammar)?.into_iter().peekable();
+    let prefix = tokens.next()?;
+    let table = tokens.next()?;
+
+    let mut ast = ClassAst {
+        prefix,
+        table,
+        clauses: Vec::new(),
+        invalid: Vec::new(),
+    };
+
+    while let Some(token) = tokens.next() {
+        match token.kind {
+            TokenKind::Column => ast.clauses.push(Clause::Column(token)),
+            TokenKind::WhereField => {
+                let operator = tokens.next_if(|t| t.kind == TokenKind::Operator);
+                if let Some(value) = tokens.next_if(|t| t.kind == TokenKind::Value) {
+                    let subquery = match operator {
+                        Some(op) if op.keyword() == "in" => {
+                            let inner = &value.text[1..value.text.len() - 1];
+                            match parse_with(inner, grammar) {
+                                Some(mut subquery) => {
+                                    subquery.shift(value.start + 1);
+                                    Some(Box::new(subquery))
+                                }
+                                None => {
+                                    ast.invalid.extend([token, op, value]);
+                                    continue;
+                                }
+                            }
+                        }
+                        _ => None,
+                    };
+                    ast.clauses.push(Clause::Where {
+                        field: token,
+                        operator,
+                        value,
+                        subquery,
+                    });
+                }
+            }
+            TokenKind::Limit => ast.clauses.push(Clause::Limit(token)),
+            TokenKind::Offset => ast.clauses.push(Clause::Offset(token)),
+            TokenKind::OrderField => {
+                let direction = tokens.next_if(|t| t.kind == TokenKind::Direction);
+                ast.clauses.push(Clause::OrderBy {
+                    field: token,
+                    direction,
+                });
+            }
+            TokenKind::SearchTerm => ast.clauses.push(Clause::Search(token)),
+            TokenKind::Interval => ast.clauses.push(Clause::Refresh(token)),
+            TokenKind::Join => ast.clauses.push(Clause::Join(token)),
+            TokenKind::Function => {
+                tokens.next_if(|t| t.kind == TokenKind::Keyword);
+                let Some(field) = tokens.next_if(|t| t.kind == TokenKind::WindowField) else {
+                    ast.invalid.push(token);
+                    continue;
+                };
+                let direction = tokens.next_if(|t| t.kind == TokenKind::Direction);
+                let partition = tokens
+                    .next_if(|t| t.kind == TokenKind::Keyword && t.text == "partition")
+                    .and_then(|_| tokens.next_if(|t| t.kind == TokenKind::PartitionField));
+                ast.clauses.push(Clause::Window {
+                    function: token,
+                    field,
+                    direction,
+                    partition,
+                });
+            }
+            TokenKind::Expression => {
+                tokens.next_if(|t| t.kind == TokenKind::Keyword);
+                if let Some(alias) = tokens.next_if(|t| t.kind == TokenKind::Alias) {
+                    ast.clauses.push(Clause::Expression {
+                        expression: token,
+                        alias,
+                    });
+                }
+            }
+            TokenKind::CaseField => {
+                let mut arms = Vec::new();
+                while let Some(value) = tokens.next_if(|t| t.kind == TokenKind::CaseValue) {
+                    if let Some(label) = tokens.next_if(|t| t.kind == TokenKind::CaseLabel) {
+                        arms.push((value, label));
+                    }
+                }
+                ast.clauses.push(Clause::Case { field: token, arms });
+            }
+            TokenKind::JsonField => ast.clauses.push(Clause::Json(token)),
+            TokenKind::Invalid => ast.invalid.push(token),
+            TokenKind::Prefix
+            | TokenKind::Table
+            | TokenKind::Keyword
+            | TokenKind::Operator
+            | TokenKind::Value
+            | TokenKind::Direction
+            | TokenKind::WindowField
+            | TokenKind::PartitionField
+            | TokenKind::Alias
+            | TokenKind::CaseValue
+            | TokenKind::CaseLabel => {}
+        }
+    }
+
+    Some(ast)
+}
+
+impl ClassAst<'_> {
+    /// Move every span `by` bytes right, for a tree parsed from a slice of
+    /// a longer input.
+    fn shift(&mut self, by: usize) {
+        let shift = |token: &mut Token<'_>| {
+            token.start += by;
+            token.end += by;
+        };
+        shift(&mut self.prefix);
+        shift(&mut self.table);
+        self.invalid.iter_mut().for_each(shift);
+        for clause in &mut self.clauses {
+            match clause {
+                Clause::Column(token)
+                | Clause::Limit(token)
+                | Clause::Offset(token)
+                | Clause::Search(token)
+                | Clause::Refresh(token)
+                | Clause::Join(token)
+                | Clause::Json(token) => shift(token),
+                Clause::Where {
+                    field,
+                    operator,
+                    value,
+                    subquery,
+                } => {
+                    shift(field);
+                    operator.iter_mut().for_each(shift);
+                    shift(value);
+                    if let Some(subquery) = subquery {
+                        subquery.shift(by);
+                    }
+                }
+                Clause::OrderBy { field, direction } => {
+                    shift(field);
+                    direction.iter_mut().for_each(shift);
+                }
+                Clause::Window {
+                    function,
+                    field,
+                    direction,
+                    partition,
+                } => {
+                    shift(function);
+                    shift(field);
+                    direction.iter_mut().for_each(shift);
+                    partition.iter_mut().for_each(shift);
+                }
+                Clause::Expression { expression, alias } => {
+                    shift(expression);
+                    shift(alias);
+                }
+                Clause::Case { field, arms } => {
+                    shift(field);
+                    for (value, label) in arms {
+                        shift(value);
+                        shift(label);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Lower the tree into a [`QueryConfig`]. Later `limit`, `offset`,
+    /// `orderby`, `search` and `refresh` clauses override earlier ones.
+    #[must_use]
+    pub fn to_config(&self) -> QueryConfig {
+        let mut config = QueryConfig {
+            table: self.table.text.to_string(),
+            columns: Vec::new(),
+            expressions: Vec::new(),
+            where_clauses: Vec::new(),
+            limit: None,
+            offset: None,
+            order_by: None,
+            joins: Vec::new(),
+            search: None,
+            refresh_seconds: None,
+            identity: Vec::new(),
+        };
+
+        for clause in &self.clauses {
+            match clause {
+                Clause::Column(column) => config.columns.push(column.text.to_string()),
+                Clause::Where {
+                    field,
+                    operator,
+                    value,
+                    subquery,
+                } => config.where_clauses.push(WhereClause {
+                    field: field.text.to_string(),
+                    operator: operator
+                        .and_then(|op| WhereOperator::from_keyword(op.keyword()))
+                        .unwrap_or(WhereOperator::Eq),
+                    value: unbracket(value.text).to_string(),
+                    subquery: subquery.as_ref().map(|ast| Box::new(ast.to_config())),
+                }),
+                Clause::Limit(limit) => config.limit = limit.text.parse().ok(),
+                Clause::Offset(offset) => config.offset = offset.text.parse().ok(),
+                Clause::OrderBy { field, direction } => {
+                    config.order_by = Some(OrderBy {
+                        field: field.text.to_string(),
+                        direction: parse_direction(*direction),
+                    });
+                }
+                Clause::Search(term) => {
+                    config.search = Some(SearchConfig {
+                        term: unbracket(term.text).to_string(),
+                        fts_table: None,
+                        columns: Vec::new(),
+                    });
+                }
+                Clause::Refresh(interval) => {
+                    config.refresh_seconds = interval_seconds(interval.text);
+                }
+                Clause::Join(join) => {
+                    config.joins.extend(parse_join_param(unbracket(join.text)));
+                }
+                Clause::Window {
+                    function,
+                    field,
+                    direction,
+                    partition,
+                } => {
+                    if let Some(function) = WindowFunction::from_keyword(function.text) {
+                        config.expressions.push(ColumnExpr::Window(WindowExpr {
+                            function,
+                            order_by: OrderBy {
+                                field: field.text.to_string(),
+                                direction: parse_direction(*direction),
+                            },
+                            partition_by: partition.map(|p| p.text.to_string()),
+                        }));
+                    }
+                }
+                Clause::Expression { expression, alias } => {
+                    if let Some(expr) = parse_expression(expression.text) {
+                        config.expressions.push(ColumnExpr::Computed(ComputedExpr {
+                            expr,
+                            alias: alias.text.to_string(),
+                        }));
+                    }
+                }
+                Clause::Case { field, arms } => {
+                    config.expressions.push(ColumnExpr::Case(CaseExpr {
+                        column: field.text.to_string(),
+                        arms: arms
+                            .iter()
+                            .map(|(value, label)| (value.text.to_string(), label.text.to_string()))
+                            .collect(),
+                    }));
+                }
+                Clause::Json(field) => {
+                    if let Some(json) = JsonExpr::parse(field.text) {
+                        config.expressions.push(ColumnExpr::Json(json));
+                    }
+                }
+            }
+        }
+
+        config
+    }
+}
+
+/// Ascending unless the direction token says `desc`.
+fn parse_direction(direction: Option<Token<'_>>) -> OrderDirection {
+    direction
+        .and_then(|d| OrderDirection::parse(d.text))
+        .unwrap_or(OrderDirection::Asc)
+}
+
+/// Plain-language description of one part of a class name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Step<'a> {
+    pub text: &'a str,
+    pub start: usize,
+    pub end: usize,
+    pub explanation: String,
+}
+
+impl<'a> ClassAst<'a> {
+    /// Describe every clause in source order, for tutorials and tooltips.
+    #[must_use]
+    pub fn explain(&self, class_name: &'a str) -> Vec<Step<'a>> {
+        let step = |start: usize, end: usize, explanation: String| Step {
+            text: &class_name[start..end],
+            start,
+            end,
+            explanation,
+        };
+
+        let mut steps = vec![step(
+            self.table.start,
+            self.table.end,
+            format!("Selects from table `{}`", self.table.text),
+        )];
+        if !self.clauses.iter().any(|c| matches!(c, Clause::Column(_))) {
+            steps[0].explanation.push_str(", returning every column");
+        }
+
+        for clause in &self.clauses {
+            steps.push(match clause {
+                Clause::Column(column) => step(
+                    column.start,
+                    column.end,
+                    format!("Returns column `{}`", column.text),
+                ),
+                Clause::Where {
+                    field,
+                    operator,
+                    value,
+                    subquery,
+                } => {
+                    let operator = operator
+                        .and_then(|op| WhereOperator::from_keyword(op.keyword()))
+                        .unwrap_or(WhereOperator::Eq);
+                    let value_text = match subquery {
+                        Some(_) => format!(
+                            "the values of subquery `{}`",
+                            &value.text[1..value.text.len() - 1]
+                        ),
+                        None => describe_value(unbracket(value.text)),
+                    };
+                    step(
+                        field.start,
+                        value.end,
+                        format!(
+                            "Adds WHERE `{}` {} {value_text}",
+                            field.text,
+                            match operator {
+                                WhereOperator::Contains => "contains",
+                                other => other.as_sql(),
+                            },
+                        ),
+                    )
+                }
+                Clause::Limit(limit) => step(
+                    limit.start,
+                    limit.end,
+                    format!("Returns at most {} rows (LIMIT {})", limit.text, limit.text),
+                ),
+                Clause::Offset(offset) => step(
+                    offset.start,
+                    offset.end,
+                    format!(
+                        "Skips the first {} rows (OFFSET {})",
+                        offset.text, offset.text
+                    ),
+                ),
+                Clause::OrderBy { field, direction } => {
+                    let (end, order) = match direction {
+                        Some(d) if d.text.eq_ignore_ascii_case("desc") => (d.end, "descending"),
+                        Some(d) => (d.end, "ascending"),
+                        None => (field.end, "ascending"),
+                    };
+                    step(
+                        field.start,
+                        end,
+                        format!("Sorts by `{}` {order}", field.text),
+                    )
+                }
+                Clause::Search(term) => step(
+                    term.start,
+                    term.end,
+                    format!(
+                        "Keeps rows matching the full-text search \"{}\"",
+                        unbracket(term.text)
+                    ),
+                ),
+                Clause::Refresh(interval) => step(
+                    interval.start,
+                    interval.end,
+                    format!(
+                        "Re-fetches fragment output every {} seconds",
+                        interval_seconds(interval.text).unwrap_or_default()
+                    ),
+                ),
+                Clause::Join(join) => {
+                    let table = parse_join_param(unbracket(join.text))
+                        .map(|join| join.table)
+                        .unwrap_or_default();
+                    step(join.start, join.end, format!("Joins table `{table}`"))
+                }
+                Clause::Window {
+                    function,
+                    field,
+                    direction,
+                    partition,
+                } => {
+                    let order = match direction {
+                        Some(d) if d.text.eq_ignore_ascii_case("desc") => "descending",
+                        _ => "ascending",
+                    };
+                    let end = partition.or(*direction).map_or(field.end, |t| t.end);
+                    let restart = partition.map_or_else(String::new, |p| {
+                        format!(", restarting for each `{}`", p.text)
+                    });
+                    step(
+                        function.start,
+                        end,
+                        format!(
+                            "Adds column `{}` numbering rows by `{}` {order}{restart}",
+                            function.text, field.text
+                        ),
+                    )
+                }
+                Clause::Case { field, arms } => {
+                    let mapping: Vec<String> = arms
+                        .iter()
+                        .map(|(value, label)| {
+                            format!("{} as \"{}\"", describe_value(value.text), label.text)
+                        })
+                        .collect();
+                    step(
+                        field.start,
+                        arms.last().map_or(field.end, |(_, label)| label.end),
+                        format!("Shows `{}` with {}", field.text, mapping.join(", ")),
+                    )
+                }
+                Clause::Json(field) => {
+                    let (column, path) = field.text.split_once('.').unwrap_or((field.text, ""));
+                    step(
+                        field.start,
+                        field.end,
+                        format!("Returns `{path}` from the JSON in column `{column}`"),
+                    )
+                }
+                Clause::Expression { expression, alias } => step(
+                    expression.start,
+                    alias.end,
+                    format!(
+                        "Adds column `{}` computed as `{}`",
+                        alias.text,
+                        &expression.text[1..expression.text.len() - 1]
+                    ),
+                ),
+            });
+        }
+
+        steps.extend(self.invalid.iter().map(|token| {
+            step(
+                token.start,
+                token.end,
+                format!("`{}` does not fit here and is ignored", token.text),
+            )
+        }));
+        steps.sort_by_key(|step| step.start);
+        steps
+    }
+}
+
+/// Render a where value the way the builder will bind it.
+fn describe_value(value: &str) -> String {
+    if let Some(int) = value.strip_prefix("int:") {
+        return format!("the integer {int}");
+    }
+    if let Some(real) = value
+        .strip_prefix("real:")
+        .or_else(|| value.strip_prefix("float:"))
+    {
+        return format!("the number {real}");
+    }
+    if let Some(flag) = value.strip_prefix("bool:") {
+        return format!("the boolean {flag}");
+    }
+    match value {
+        "today" => "today (from midnight)".to_string(),
+        "yesterday" => "yesterday".to_string(),
+        _ => match value
+            .strip_prefix("last-")
+            .and_then(|rest| rest.split_once('-'))
+        {
+            Some((amount, unit)) => format!("{amount} {unit} ago"),
+            None => format!("'{}'", value.strip_prefix("text:").unwrap_or(value)),
+        },
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::{HashMap, HashSet};
+
+use thiserror::Error;
+
+use crate::parser::{split_qualified, MutationConfig, MutationKind, QueryConfig};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PolicyError {
+    #[error("table not allowed: {0}")]
+    TableDenied(String),
+    #[error("column not allowed: {table}.{column}")]
+    ColumnDenied { table: String, column: String },
+    #[error("writes are disabled")]
+    WritesDisabled,
+    #[error("destructive operations are disabled")]
+    DestructiveDisabled,
+    #[error("this needs the admin token")]
+    AdminRequired,
+    #[error("render mode not allowed: {0}")]
+    RenderDenied(String),
+}
+
+/// Table and column access rules applied to every table a query touches.
+///
+/// An empty policy allows every read and no writes. Tables can be restricted
+/// to an allow-list, individually denied, or have specific columns hidden;
+/// writes must be enabled with [`AccessPolicy::allow_writes`], and deletes
+/// additionally with [`AccessPolicy::allow_destructive`].
+#[derive(Debug, Clone, Default)]
+pub struct AccessPolicy {
+    allowed_tables: Option<HashSet<String>>,
+    denied_tables: HashSet<String>,
+    denied_columns: HashMap<String, HashSet<String>>,
+    writes: bool,
+    destructive: bool,
+}
+
+impl AccessPolicy {
+    #[must_use]
+    pub fn allow_tables<I, S>(mut self, tables: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_tables
+            .get_or_insert_with(HashSet::new)
+            .extend(tables.into_iter().map(Into::into));
+        self
+    }
+
+    /// Allow only those of `tables` this policy already allows.
+    #[must_use]
+    pub fn restrict_tables<I, S>(mut self, tables: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let allowed = tables
+            .into_iter()
+            .map(Into::into)
+            .filter(|table| self.is_table_allowed(table))
+            .collect();
+        self.allowed_tables = Some(allowed);
+        self
+    }
+
+    #[must_use]
+    pub fn deny_table(mut self, table: impl Into<String>) -> Self {
+        self.denied_tables.insert(table.into());
+        self
+    }
+
+    #[must_use]
+    pub fn deny_column(mut self, table: impl Into<String>, column: impl Into<String>) -> Self {
+        self.denied_columns
+            .entry(table.into())
+            .or_default()
+            .insert(column.into());
+        self
+    }
+
+    /// Permit mutations, still subject to the table and column rules.
+    #[must_use]
+    pub const fn allow_writes(mut self, allow: bool) -> Self {
+        self.writes = allow;
+        self
+    }
+
+    #[must_use]
+    pub const fn writes_allowed(&self) -> bool {
+        self.writes
+    }
+
+    /// Permit deletes on top of [`Self::allow_writes`].
+    #[must_use]
+    pub const fn allow_destructive(mut self, allow: bool) -> Self {
+        self.destructive = allow;
+        self
+    }
+
+    #[must_use]
+    pub const fn destructive_allowed(&self) -> bool {
+        self.writes && self.destructive
+    }
+
+    #[must_use]
+    pub fn is_table_allowed(&self, table: &str) -> bool {
+        if self.denied_tables.contains(table) {
+            return false;
+        }
+        self.allowed_tables
+            .as_ref()
+            .is_none_or(|allowed| allowed.contains(table))
+    }
+
+    #[must_use]
+    pub fn is_column_allowed(&self, table: &str, column: &str) -> bool {
+        self.denied_columns
+            .get(table)
+            .is_none_or(|denied| !denied.contains(column))
+    }
+
+    /// Check the base table, every joined table and every `in-(...)` subquery
+    /// of `config` against the policy.
+    ///
+    /// Wildcard selects are rejected on tables that have hidden columns, since
+    /// `SELECT *` would expose them. Qualified columns (`posts.title`) are
+    /// checked against the table their qualifier names, resolving join aliases.
+    ///
+    /// # Errors
+    /// Returns the first `PolicyError` encountered.
+    pub fn check(&self, config: &QueryConfig) -> Result<(), PolicyError> {
+        let mut references: HashMap<&str, &str> = HashMap::new();
+        references.insert(config.table.as_str(), config.table.as_str());
+
+        self.check_table(&config.table)?;
+        let (qualified, unqualified): (Vec<&String>, Vec<&String>) = config
+            .columns
+            .iter()
+            .partition(|column| split_qualified(column).0.is_some());
+        if config.columns.is_empty() {
+            self.check_column(&config.table, "*")?;
+        }
+        for column in unqualified {
+            self.check_column(&config.table, column)?;
+        }
+        for clause in &config.where_clauses {
+            self.check_column(&config.table, &clause.field)?;
+            if let Some(subquery) = &clause.subquery {
+                self.check(subquery)?;
+            }
+        }
+        if let Some(order_by) = &config.order_by {
+            self.check_column(&config.table, &order_by.field)?;
+        }
+        for expression in &config.expressions {
+            for column in expression.columns() {
+                self.check_column(&config.table, column)?;
+            }
+        }
+
+        for join in &config.joins {
+            self.check_table(&join.table)?;
+            if let Some(through) = &join.through {
+                self.check_table(&through.table)?;
+                self.check_column(&through.table, &through.parent_column)?;
+                self.check_column(&through.table, &through.child_column)?;
+            }
+            self.check_columns(&join.table, &join.columns)?;
+            self.check_column(&join.table, &join.child_column)?;
+
+            let parent_table = join
+                .parent_table
+                .as_deref()
+                .and_then(|parent| references.get(parent).copied())
+                .unwrap_or(config.table.as_str());
+            self.check_column(parent_table, &join.parent_column)?;
+
+            references.insert(join.reference(), join.table.as_str());
+        }
+
+        for column in qualified {
+            let (Some(qualifier), name) = split_qualified(column) else {
+                continue;
+            };
+            let table = references.get(qualifier).copied().unwrap_or(qualifier);
+            self.check_table(table)?;
+            self.check_column(table, name)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check that writes (and for deletes, destructive operations) are
+    /// enabled and `config` only touches allowed tables and columns, including
+    /// the ones it filters on.
+    ///
+    /// # Errors
+    /// Returns `PolicyError::WritesDisabled`, `PolicyError::DestructiveDisabled`,
+    /// or the first denied table or column.
+    pub fn check_mutation(&self, config: &MutationConfig) -> Result<(), PolicyError> {
+        if !self.writes {
+            return Err(PolicyError::WritesDisabled);
+        }
+        if config.kind == MutationKind::Delete && !self.destructive {
+            return Err(PolicyError::DestructiveDisabled);
+        }
+        self.check_table(&config.table)?;
+        config
+            .values
+            .iter()
+            .map(|value| value.column.as_str())
+            .chain(
+                config
+                    .where_clauses
+                    .iter()
+                    .map(|clause| clause.field.as_str()),
+            )
+            .try_for_each(|column| self.check_column(&config.table, column))
+    }
+
+    fn check_table(&self, table: &str) -> Result<(), PolicyError> {
+        if self.is_table_allowed(table) {
+            Ok(())
+        } else {
+            Err(PolicyError::TableDenied(table.to_string()))
+        }
+    }
+
+    fn check_columns(&self, table: &str, columns: &[String]) -> Result<(), PolicyError> {
+        if columns.is_empty() {
+            return self.check_column(table, "*");
+        }
+        columns
+            .iter()
+            .try_for_each(|column| self.check_column(table, column))
+    }
+
+    fn check_column(&self, table: &str, column: &str) -> Result<(), PolicyError> {
+        let hidden = if column == "*" {
+            self.denied_columns
+                .get(table)
+                .is_some_and(|denied| !denied.is_empty())
+        } else {
+            !self.is_column_allowed(table, column)
+        };
+
+        if hidden {
+            Err(PolicyError::ColumnDenied {
+                table: table.to_string(),
+                column: column.to_string(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,25 @@
+//! The supported API: `use tailwindsql::prelude::*;`.
+//!
+//! Everything re-exported here follows semver. [`Backend`] is the datasource
+//! trait; [`SqliteDatabase`] and [`MockDatasource`] implement it, as do the
+//! Postgres and DuckDB backends behind their features.
+
+pub use crate::context::{Deadline, QueryContext};
+pub use crate::db::mock::MockDatasource;
+pub use crate::db::sqlite::SqliteDatabase;
+pub use crate::db::{Backend, DbError, QueryResult};
+pub use crate::engine::Engine;
+pub use crate::error::{Category, Error};
+pub use crate::parser::{
+    parse_class_names, parse_mutation, MutationConfig, ParseError, QueryConfig,
+};
+pub use crate::policy::{AccessPolicy, PolicyError};
+pub use crate::query_builder::{
+    build_mutation, build_query_for, BuiltQuery, QueryBuilderError, QueryPolicy,
+};
+pub use crate::render::{
+    render_results, render_results_with, CellRenderer, Locale, RenderAs, RenderOptions, RowData,
+    Theme,
+};
+pub use crate::schema::{SchemaCatalog, ValidationError};
+pub use crate::tw_sql;
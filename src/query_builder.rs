@@ -1,19 +1,216 @@
 use rusqlite::types::Value;
+use std::collections::HashMap;
 use std::fmt::Write;
 use thiserror::Error;
 
-use crate::parser::QueryConfig;
+use crate::dialect::{SqlDialect, Sqlite};
+use crate::parser::{
+    split_qualified, ColumnExpr, MutationConfig, MutationKind, QueryConfig, SearchConfig,
+    WhereClause, WhereOperator,
+};
+
+/// Result key of a row's identity, selected when [`QueryConfig::identity`]
+/// names key columns.
+pub const ROW_ID: &str = "__id";
 
 #[derive(Debug, Error)]
 pub enum QueryBuilderError {
     #[error("invalid identifier: {0}")]
     InvalidIdentifier(String),
+    #[error("join references unknown table: {0}")]
+    UnknownJoinParent(String),
+    #[error("column qualifier references unknown table: {0}")]
+    UnknownQualifier(String),
+    #[error("invalid {kind} value: {value}")]
+    InvalidValue { kind: &'static str, value: String },
+    #[error("search has no columns to match against")]
+    NoSearchColumns,
+    #[error("{0} is not supported by this SQL dialect")]
+    Unsupported(&'static str),
+    #[error("query has {count} joins; at most {max} allowed")]
+    TooManyJoins { count: usize, max: usize },
+    #[error("mutation sets no columns")]
+    NoValues,
+    #[error("{0} has no where clause; refusing to change every row")]
+    MissingWhere(&'static str),
+    #[error("subquery on {table} must select exactly one column")]
+    SubqueryColumns { table: String },
+    #[error("case-{0} replaces a column of `*`; list the columns to select")]
+    CaseWithWildcard(String),
+}
+
+/// Size limits and output settings [`build_query_with`] applies to every query.
+///
+/// The default policy imposes nothing. A server typically sets a default
+/// limit so `db-users-name` on a large table returns a page rather than
+/// every row, and a maximum that explicit `limit-` tokens are clamped to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryPolicy {
+    /// `LIMIT` for queries without a `limit-` token.
+    pub default_limit: Option<i64>,
+    /// Largest `LIMIT` a query may use; larger limits are lowered to it.
+    pub max_limit: Option<i64>,
+    /// Most joins a query may have; more is an error.
+    pub max_joins: Option<usize>,
+    /// Most rows an executor reads back before stopping and reporting the
+    /// result as truncated. Not part of the SQL, so it also caps queries
+    /// whose `LIMIT` was lifted.
+    pub max_rows: Option<usize>,
+    /// Approximate bytes of converted row data an executor may hold for one
+    /// query before aborting it.
+    pub max_result_bytes: Option<usize>,
+    /// Most bytes of compact JSON a result's rows may serialize to. The
+    /// executor stops before the row that would pass it and reports the
+    /// result as truncated, where `max_result_bytes` fails the query.
+    pub max_payload_bytes: Option<usize>,
+    pub param_style: ParamStyle,
+}
+
+/// How bound values appear in generated SQL, for dialects that offer a choice.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParamStyle {
+    /// `?`, bound by position.
+    #[default]
+    Positional,
+    /// `:name` after the column or clause the value belongs to (`:id`,
+    /// `:limit`), with `_2`, `_3`... suffixes for repeats. Names are unique,
+    /// so binding by position still works.
+    Named,
+}
+
+impl QueryPolicy {
+    #[must_use]
+    pub const fn with_default_limit(mut self, limit: i64) -> Self {
+        self.default_limit = Some(limit);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_max_limit(mut self, limit: i64) -> Self {
+        self.max_limit = Some(limit);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_max_joins(mut self, joins: usize) -> Self {
+        self.max_joins = Some(joins);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_max_rows(mut self, rows: usize) -> Self {
+        self.max_rows = Some(rows);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_max_result_bytes(mut self, bytes: usize) -> Self {
+        self.max_result_bytes = Some(bytes);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_max_payload_bytes(mut self, bytes: usize) -> Self {
+        self.max_payload_bytes = Some(bytes);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_param_style(mut self, style: ParamStyle) -> Self {
+        self.param_style = style;
+        self
+    }
+
+    /// The `LIMIT` a query asking for `requested` rows ends up with.
+    #[must_use]
+    pub fn effective_limit(&self, requested: Option<i64>) -> Option<i64> {
+        match (requested.or(self.default_limit), self.max_limit) {
+            (Some(limit), Some(max)) if limit < 0 || limit > max => Some(max),
+            (None, max) => max,
+            (limit, _) => limit,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct BuiltQuery {
     pub sql: String,
     pub params: Vec<Value>,
+    /// Name of each entry in `params`, recorded whatever the [`ParamStyle`].
+    pub param_names: Vec<String>,
+}
+
+impl BuiltQuery {
+    /// Each bound value with its name, e.g. `(":role", "admin")`, for
+    /// logging or binding through a named-parameter API.
+    #[must_use]
+    pub fn named_params(&self) -> Vec<(String, &Value)> {
+        self.param_names
+            .iter()
+            .map(|name| format!(":{name}"))
+            .zip(&self.params)
+            .collect()
+    }
+}
+
+/// Collects bound values and hands out their placeholders.
+struct Binder<'d> {
+    dialect: &'d dyn SqlDialect,
+    style: ParamStyle,
+    /// Write values into the SQL as literals instead, for statements that
+    /// cannot take parameters such as `CREATE VIEW`.
+    inline: bool,
+    values: Vec<Value>,
+    names: Vec<String>,
+}
+
+impl<'d> Binder<'d> {
+    fn new(dialect: &'d dyn SqlDialect, style: ParamStyle) -> Self {
+        Self {
+            dialect,
+            style,
+            inline: false,
+            values: Vec::new(),
+            names: Vec::new(),
+        }
+    }
+
+    /// Bind `value` under `name` and return the placeholder to put in the SQL.
+    fn bind(&mut self, name: &str, value: Value) -> String {
+        if self.inline {
+            return sql_literal(&value);
+        }
+        let mut unique = name.to_string();
+        let mut suffix = 1;
+        while self.names.contains(&unique) {
+            suffix += 1;
+            unique = format!("{name}_{suffix}");
+        }
+        let placeholder = self
+            .dialect
+            .placeholder(self.values.len() + 1, &unique, self.style);
+        self.names.push(unique);
+        self.values.push(value);
+        placeholder
+    }
+}
+
+/// `value` as a SQL literal.
+fn sql_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(number) => number.to_string(),
+        Value::Real(number) => format!("{number:?}"),
+        Value::Text(text) => format!("'{}'", text.replace('\'', "''")),
+        Value::Blob(bytes) => {
+            let mut literal = String::from("X'");
+            for byte in bytes {
+                write!(&mut literal, "{byte:02X}").expect("writing to SQL buffer should not fail");
+            }
+            literal.push('\'');
+            literal
+        }
+    }
 }
 
 fn is_safe_identifier(name: &str) -> bool {
@@ -39,95 +236,859 @@ fn sanitize_identifier(name: &str) -> Result<&str, QueryBuilderError> {
     Ok(name)
 }
 
+/// An already validated `column`, quoted for `dialect` and qualified with
+/// `table` when `qualify` is set. `*` stays bare.
+fn column_ref(dialect: &dyn SqlDialect, table: &str, column: &str, qualify: bool) -> String {
+    let column = if column == "*" {
+        column.to_string()
+    } else {
+        dialect.quote_identifier(column)
+    };
+    if qualify {
+        format!("{}.{column}", dialect.quote_identifier(table))
+    } else {
+        column
+    }
+}
+
+/// Convert a where value into a bound parameter, honouring type hints such as
+/// `int:100`, `real:9.99` or `text:007`. Unprefixed values are typed by
+/// [`infer_value`].
+fn bind_value(raw: &str) -> Result<Value, QueryBuilderError> {
+    let invalid = |kind| QueryBuilderError::InvalidValue {
+        kind,
+        value: raw.to_string(),
+    };
+
+    if let Some(value) = raw.strip_prefix("int:") {
+        return value
+            .parse::<i64>()
+            .map(Value::Integer)
+            .map_err(|_| invalid("int"));
+    }
+    if let Some(value) = raw
+        .strip_prefix("real:")
+        .or_else(|| raw.strip_prefix("float:"))
+    {
+        return value
+            .parse::<f64>()
+            .map(Value::Real)
+            .map_err(|_| invalid("real"));
+    }
+    if let Some(value) = raw.strip_prefix("bool:") {
+        return match value {
+            "true" | "1" => Ok(Value::Integer(1)),
+            "false" | "0" => Ok(Value::Integer(0)),
+            _ => Err(invalid("bool")),
+        };
+    }
+
+    if let Some(text) = raw.strip_prefix("text:") {
+        return Ok(Value::Text(text.to_string()));
+    }
+    Ok(infer_value(raw))
+}
+
+/// The value an unprefixed where value binds as: integers such as `42` as
+/// integers, decimals such as `9.5` as reals, `true` and `false` as 1 and 0,
+/// and anything else as text. Numbers written any other way (`007`, `1e3`,
+/// `+1`) stay text, since only a text column could tell them apart.
+#[must_use]
+pub fn infer_value(raw: &str) -> Value {
+    let digits = raw.strip_prefix('-').unwrap_or(raw);
+    let (whole, fraction) = match digits.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (digits, None),
+    };
+    let canonical = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    let whole_ok = canonical(whole) && (whole == "0" || !whole.starts_with('0'));
+    match fraction {
+        None if whole_ok => raw.parse().map_or_else(|_| text(raw), Value::Integer),
+        Some(fraction) if whole_ok && canonical(fraction) => {
+            raw.parse().map_or_else(|_| text(raw), Value::Real)
+        }
+        _ => match raw {
+            "true" => Value::Integer(1),
+            "false" => Value::Integer(0),
+            _ => text(raw),
+        },
+    }
+}
+
+fn text(raw: &str) -> Value {
+    Value::Text(raw.to_string())
+}
+
+/// A point in time relative to now, as `datetime('now', ...)` modifiers.
+///
+/// `end` closes the range when the keyword names a whole period (`today`),
+/// so equality matches everything inside it.
+struct RelativeDate {
+    start: Vec<String>,
+    end: Option<Vec<String>>,
+}
+
+/// Recognize `today`, `yesterday` and `last-<n>-<unit>` date keywords.
+fn relative_date(value: &str) -> Option<RelativeDate> {
+    let modifiers = |items: &[&str]| items.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+    match value {
+        "today" => Some(RelativeDate {
+            start: modifiers(&["start of day"]),
+            end: Some(modifiers(&["start of day", "+1 day"])),
+        }),
+        "yesterday" => Some(RelativeDate {
+            start: modifiers(&["start of day", "-1 day"]),
+            end: Some(modifiers(&["start of day"])),
+        }),
+        _ => {
+            let (amount, unit) = value.strip_prefix("last-")?.split_once('-')?;
+            let amount: u32 = amount.parse().ok()?;
+            let (amount, unit) = match unit {
+                "minute" | "minutes" => (amount, "minutes"),
+                "hour" | "hours" => (amount, "hours"),
+                "day" | "days" => (amount, "days"),
+                "week" | "weeks" => (amount.checked_mul(7)?, "days"),
+                "month" | "months" => (amount, "months"),
+                "year" | "years" => (amount, "years"),
+                _ => return None,
+            };
+            Some(RelativeDate {
+                start: vec![format!("-{amount} {unit}")],
+                end: None,
+            })
+        }
+    }
+}
+
+/// Whether `value` is a date keyword such as `today` or `last-7-days`, which
+/// where clauses compare against the current time rather than bind as text.
+#[must_use]
+pub fn is_relative_date(value: &str) -> bool {
+    relative_date(value).is_some()
+}
+
+fn datetime_expr(name: &str, modifiers: &[String], binder: &mut Binder<'_>) -> String {
+    let dialect = binder.dialect;
+    dialect.relative_datetime(modifiers, &mut |value| binder.bind(name, value))
+}
+
+/// Escape `LIKE` wildcards so the value matches literally (with `ESCAPE '\'`).
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        if matches!(ch, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Match rows through the FTS5 index when one was resolved, otherwise `LIKE`
+/// across the search columns.
+fn search_condition(
+    table: &str,
+    has_joins: bool,
+    search: &SearchConfig,
+    binder: &mut Binder<'_>,
+) -> Result<String, QueryBuilderError> {
+    if let Some(fts_table) = &search.fts_table {
+        let fts_table = sanitize_identifier(fts_table)?;
+        let term = binder.bind(
+            "search",
+            Value::Text(format!("\"{}\"", search.term.replace('"', "\"\""))),
+        );
+        let dialect = binder.dialect;
+        return dialect
+            .full_text_match(
+                &dialect.quote_identifier(table),
+                &dialect.quote_identifier(fts_table),
+                &term,
+            )
+            .ok_or(QueryBuilderError::Unsupported("full-text search index"));
+    }
+
+    if search.columns.is_empty() {
+        return Err(QueryBuilderError::NoSearchColumns);
+    }
+
+    let mut matches = Vec::with_capacity(search.columns.len());
+    for column in &search.columns {
+        let column = column_ref(
+            binder.dialect,
+            table,
+            sanitize_identifier(column)?,
+            has_joins,
+        );
+        let pattern = Value::Text(format!("%{}%", escape_like(&search.term)));
+        let term = binder.bind("search", pattern);
+        matches.push(format!("{column} LIKE {term} ESCAPE '\\'"));
+    }
+    Ok(format!("({})", matches.join(" OR ")))
+}
+
+/// Qualify joined columns and alias any name selected from more than one table
+/// (`"posts"."title" AS "posts.title"`) so result rows never silently merge
+/// fields.
+fn qualified_select_list(dialect: &dyn SqlDialect, items: &[(&str, &str)]) -> Vec<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (_, column) in items {
+        if *column != "*" {
+            *counts.entry(column).or_default() += 1;
+        }
+    }
+
+    items
+        .iter()
+        .map(|(qualifier, column)| {
+            let column_ref = column_ref(dialect, qualifier, column, true);
+            if counts.get(column).copied().unwrap_or(0) > 1 {
+                let alias = dialect.quote_identifier(&format!("{qualifier}.{column}"));
+                format!("{column_ref} AS {alias}")
+            } else {
+                column_ref
+            }
+        })
+        .collect()
+}
+
+/// A computed select item, aliased to [`ColumnExpr::alias`]. Columns belong
+/// to the base table and are qualified with it when there are joins. Values
+/// are bound into `binder`, which must not have bound anything later in the
+/// statement yet.
+fn expression_sql(
+    expression: &ColumnExpr,
+    table: &str,
+    has_joins: bool,
+    binder: &mut Binder<'_>,
+) -> Result<String, QueryBuilderError> {
+    let dialect = binder.dialect;
+    let column_ref = |column: &str| -> Result<String, QueryBuilderError> {
+        Ok(column_ref(
+            dialect,
+            table,
+            sanitize_identifier(column)?,
+            has_joins,
+        ))
+    };
+    let alias = dialect.quote_identifier(expression.alias());
+    match expression {
+        ColumnExpr::Window(window) => {
+            let partition = match &window.partition_by {
+                Some(column) => format!("PARTITION BY {} ", column_ref(column)?),
+                None => String::new(),
+            };
+            Ok(format!(
+                "{}() OVER ({partition}ORDER BY {} {}) AS {alias}",
+                window.function.as_sql(),
+                column_ref(&window.order_by.field)?,
+                window.order_by.direction.as_sql()
+            ))
+        }
+        ColumnExpr::Computed(computed) => Ok(format!(
+            "{} AS {alias}",
+            computed.expr.to_sql(&mut |column| column_ref(column))?
+        )),
+        // Unlisted values fall through as text so every branch has one type.
+        ColumnExpr::Case(case) => {
+            let column = sanitize_identifier(&case.column)?;
+            let field_ref = column_ref(column)?;
+            let mut sql = format!("CASE {field_ref}");
+            for (value, label) in &case.arms {
+                let value = binder.bind(column, bind_value(value)?);
+                let label = binder.bind(&format!("{column}_label"), Value::Text(label.clone()));
+                write!(sql, " WHEN {value} THEN {label}")
+                    .expect("writing to SQL buffer should not fail");
+            }
+            write!(sql, " ELSE CAST({field_ref} AS TEXT) END AS {alias}")
+                .expect("writing to SQL buffer should not fail");
+            Ok(sql)
+        }
+        ColumnExpr::Json(json) => {
+            let path: Vec<&str> = json.path().collect();
+            Ok(format!(
+                "{} AS {alias}",
+                binder
+                    .dialect
+                    .json_extract(&column_ref(json.column())?, &path)
+            ))
+        }
+    }
+}
+
 /// Build a parameterized SQL query from a parsed config.
 ///
 /// # Errors
 /// Returns `QueryBuilderError` if any identifier fails validation.
 pub fn build_query(config: &QueryConfig) -> Result<BuiltQuery, QueryBuilderError> {
-    let mut params: Vec<Value> = Vec::new();
+    build_query_with(config, &QueryPolicy::default())
+}
+
+/// [`build_query`], enforcing the limits in `policy`.
+///
+/// # Errors
+/// Returns `QueryBuilderError::TooManyJoins` when the config has more joins
+/// than the policy allows, and otherwise the same errors as [`build_query`].
+pub fn build_query_with(
+    config: &QueryConfig,
+    policy: &QueryPolicy,
+) -> Result<BuiltQuery, QueryBuilderError> {
+    build_query_for(&Sqlite, config, policy)
+}
+
+/// [`build_query_with`] for a specific SQL dialect.
+///
+/// # Errors
+/// Returns `QueryBuilderError::Unsupported` when the query needs a feature
+/// the dialect lacks, and otherwise the same errors as [`build_query_with`].
+pub fn build_query_for(
+    dialect: &dyn SqlDialect,
+    config: &QueryConfig,
+    policy: &QueryPolicy,
+) -> Result<BuiltQuery, QueryBuilderError> {
+    let mut binder = Binder::new(dialect, policy.param_style);
+    let sql = build_select(config, policy, &mut binder)?;
+
+    Ok(BuiltQuery {
+        sql,
+        params: binder.values,
+        param_names: binder.names,
+    })
+}
+
+/// `CREATE VIEW <name> AS` the `SELECT` for `config`, with its values
+/// written inline since a view cannot take parameters.
+///
+/// Only an explicit `limit-` is kept: the policy's default and maximum limits
+/// apply when the view is queried, not when it is defined.
+///
+/// # Errors
+/// Returns `QueryBuilderError::InvalidIdentifier` if `name` is not a plain
+/// identifier or is reserved, and otherwise the errors of [`build_query_for`].
+pub fn build_view(
+    dialect: &dyn SqlDialect,
+    name: &str,
+    config: &QueryConfig,
+    policy: &QueryPolicy,
+) -> Result<String, QueryBuilderError> {
+    let policy = QueryPolicy {
+        default_limit: None,
+        max_limit: None,
+        ..*policy
+    };
+    let (name, select) = inline_select(dialect, name, config, &policy)?;
+    Ok(format!("CREATE VIEW {name} AS {select}"))
+}
 
+/// A script replacing the table `name` with the rows of `config`, values
+/// written inline, to run in one transaction.
+///
+/// The default limit is lifted so the table holds every row a page of the
+/// query could show; the maximum limit still applies.
+///
+/// # Errors
+/// Returns the errors of [`build_view`].
+pub fn build_materialization(
+    dialect: &dyn SqlDialect,
+    name: &str,
+    config: &QueryConfig,
+    policy: &QueryPolicy,
+) -> Result<String, QueryBuilderError> {
+    let policy = QueryPolicy {
+        default_limit: None,
+        ..*policy
+    };
+    let (name, select) = inline_select(dialect, name, config, &policy)?;
+    Ok(format!(
+        "DROP TABLE IF EXISTS {name}; CREATE TABLE {name} AS {select}"
+    ))
+}
+
+/// The quoted `name` of a new table or view, and the `SELECT` for `config`
+/// with its values inline.
+fn inline_select(
+    dialect: &dyn SqlDialect,
+    name: &str,
+    config: &QueryConfig,
+    policy: &QueryPolicy,
+) -> Result<(String, String), QueryBuilderError> {
+    let name = sanitize_identifier(name)?;
+    if name.to_ascii_lowercase().starts_with("sqlite_") {
+        return Err(QueryBuilderError::InvalidIdentifier(name.to_string()));
+    }
+    let mut binder = Binder::new(dialect, policy.param_style);
+    binder.inline = true;
+    let select = build_select(config, policy, &mut binder)?;
+    Ok((dialect.quote_identifier(name), select))
+}
+
+/// The `SELECT` for `config`, binding its values into `binder` so subqueries
+/// share the outer query's placeholders.
+fn build_select(
+    config: &QueryConfig,
+    policy: &QueryPolicy,
+    binder: &mut Binder<'_>,
+) -> Result<String, QueryBuilderError> {
+    check_joins(config, policy)?;
+
+    let dialect = binder.dialect;
     let table = sanitize_identifier(&config.table)?;
     let has_joins = !config.joins.is_empty();
 
-    let mut select_columns: Vec<String> = Vec::new();
+    let mut references = vec![table];
+    for join in &config.joins {
+        if let Some(through) = &join.through {
+            references.push(sanitize_identifier(&through.table)?);
+        }
+        references.push(sanitize_identifier(join.reference())?);
+    }
 
-    if !config.columns.is_empty() {
+    // A `case-` mapping is selected under its column's name, in its place.
+    let mapped: Vec<&str> = config.case_columns().collect();
+    let mut select_items: Vec<(&str, &str)> = Vec::new();
+    if config.columns.is_empty() {
+        if let Some(column) = mapped.first() {
+            return Err(QueryBuilderError::CaseWithWildcard((*column).to_string()));
+        }
+        select_items.push((table, "*"));
+    } else {
         for column in &config.columns {
-            let col = sanitize_identifier(column)?;
-            if has_joins {
-                select_columns.push(format!("{table}.{col}"));
-            } else {
-                select_columns.push(col.to_string());
+            let (qualifier, column) = split_qualified(column);
+            let qualifier = match qualifier {
+                Some(qualifier) => {
+                    let qualifier = sanitize_identifier(qualifier)?;
+                    if !references.contains(&qualifier) {
+                        return Err(QueryBuilderError::UnknownQualifier(qualifier.to_string()));
+                    }
+                    qualifier
+                }
+                None => table,
+            };
+            if qualifier == table && mapped.contains(&column) {
+                continue;
             }
+            select_items.push((qualifier, sanitize_identifier(column)?));
         }
-    } else if has_joins {
-        select_columns.push(format!("{table}.*"));
-    } else {
-        select_columns.push("*".to_string());
     }
 
     for join in &config.joins {
-        let join_table = sanitize_identifier(&join.table)?;
+        let join_table = sanitize_identifier(join.reference())?;
         if join.columns.is_empty() {
-            select_columns.push(format!("{join_table}.*"));
+            select_items.push((join_table, "*"));
         } else {
             for col in &join.columns {
-                let col = sanitize_identifier(col)?;
-                select_columns.push(format!("{join_table}.{col}"));
+                select_items.push((join_table, sanitize_identifier(col)?));
             }
         }
     }
 
+    let mut select_columns = if has_joins {
+        qualified_select_list(dialect, &select_items)
+    } else {
+        select_items
+            .iter()
+            .map(|(_, column)| column_ref(dialect, table, column, false))
+            .collect()
+    };
+
+    for expression in &config.expressions {
+        select_columns.push(expression_sql(expression, table, has_joins, binder)?);
+    }
+    // Views and materialized tables are read through their own queries.
+    if !binder.inline && !config.identity.is_empty() {
+        select_columns.push(identity_sql(dialect, config, table, has_joins)?);
+    }
+
     let columns_sql = select_columns.join(", ");
-    let mut sql = format!("SELECT {columns_sql} FROM {table}");
+    let mut sql = format!("SELECT {columns_sql}");
+    push_from_and_where(&mut sql, config, table, binder)?;
+
+    if let Some(order_by) = &config.order_by {
+        let field = sanitize_identifier(&order_by.field)?;
+        let field_ref = column_ref(dialect, table, field, has_joins);
+        write!(
+            &mut sql,
+            " ORDER BY {field_ref} {}",
+            order_by.direction.as_sql()
+        )
+        .expect("writing to SQL buffer should not fail");
+    }
+
+    let limit = policy
+        .effective_limit(config.limit)
+        .map(|limit| binder.bind("limit", Value::Integer(limit)));
+    let offset = config
+        .offset
+        .filter(|offset| *offset > 0)
+        .map(|offset| binder.bind("offset", Value::Integer(offset)));
+    sql.push_str(&dialect.limit_offset(limit.as_deref(), offset.as_deref()));
+    Ok(sql)
+}
+
+/// `config.identity` selected as [`ROW_ID`]: the key column itself, or the
+/// key columns as text joined by `/`.
+fn identity_sql(
+    dialect: &dyn SqlDialect,
+    config: &QueryConfig,
+    table: &str,
+    has_joins: bool,
+) -> Result<String, QueryBuilderError> {
+    let mut parts = Vec::with_capacity(config.identity.len());
+    for column in &config.identity {
+        let (qualifier, column) = split_qualified(column);
+        let qualifier = qualifier.map_or(Ok(table), sanitize_identifier)?;
+        parts.push(column_ref(
+            dialect,
+            qualifier,
+            sanitize_identifier(column)?,
+            has_joins,
+        ));
+    }
+    let identity = if let [part] = parts.as_slice() {
+        part.clone()
+    } else {
+        parts
+            .iter()
+            .map(|part| format!("COALESCE(CAST({part} AS TEXT), '')"))
+            .collect::<Vec<_>>()
+            .join(" || '/' || ")
+    };
+    Ok(format!(
+        "{identity} AS {}",
+        dialect.quote_identifier(ROW_ID)
+    ))
+}
+
+/// A `SELECT COUNT(*) AS total` over the same tables, joins and filters as
+/// [`build_query_for`], ignoring order, limit and offset: the row count a
+/// pager divides into pages.
+///
+/// # Errors
+/// Returns the same errors as [`build_query_for`].
+pub fn build_count_query_for(
+    dialect: &dyn SqlDialect,
+    config: &QueryConfig,
+    policy: &QueryPolicy,
+) -> Result<BuiltQuery, QueryBuilderError> {
+    check_joins(config, policy)?;
+
+    let mut binder = Binder::new(dialect, policy.param_style);
+    let table = sanitize_identifier(&config.table)?;
+    let mut sql = String::from("SELECT COUNT(*) AS total");
+    push_from_and_where(&mut sql, config, table, &mut binder)?;
+
+    Ok(BuiltQuery {
+        sql,
+        params: binder.values,
+        param_names: binder.names,
+    })
+}
 
+/// [`build_query_for`] wrapped in the dialect's `EXPLAIN`, e.g. SQLite's
+/// `EXPLAIN QUERY PLAN`, so running it shows whether the query uses an index.
+///
+/// # Errors
+/// Returns the same errors as [`build_query_for`].
+pub fn build_explain(
+    dialect: &dyn SqlDialect,
+    config: &QueryConfig,
+    policy: &QueryPolicy,
+) -> Result<BuiltQuery, QueryBuilderError> {
+    let built = build_query_for(dialect, config, policy)?;
+    Ok(BuiltQuery {
+        sql: dialect.explain(&built.sql),
+        ..built
+    })
+}
+
+/// A `through-` join counts as the two joins it emits.
+fn check_joins(config: &QueryConfig, policy: &QueryPolicy) -> Result<(), QueryBuilderError> {
+    let count = config
+        .joins
+        .iter()
+        .map(|join| if join.through.is_some() { 2 } else { 1 })
+        .sum();
+    match policy.max_joins {
+        Some(max) if count > max => Err(QueryBuilderError::TooManyJoins { count, max }),
+        _ => Ok(()),
+    }
+}
+
+/// Append ` FROM`, the joins and ` WHERE` of `config` to `sql`.
+fn push_from_and_where(
+    sql: &mut String,
+    config: &QueryConfig,
+    table: &str,
+    binder: &mut Binder<'_>,
+) -> Result<(), QueryBuilderError> {
+    let has_joins = !config.joins.is_empty();
+    let dialect = binder.dialect;
+    let quote = |name: &str| dialect.quote_identifier(name);
+    write!(sql, " FROM {}", quote(table)).expect("writing to SQL buffer should not fail");
+
+    let mut joined_tables = vec![table];
     for join in &config.joins {
         let join_table = sanitize_identifier(&join.table)?;
+        let join_ref = sanitize_identifier(join.reference())?;
+        let parent_table = match &join.parent_table {
+            Some(parent) => {
+                let parent = sanitize_identifier(parent)?;
+                if !joined_tables.contains(&parent) {
+                    return Err(QueryBuilderError::UnknownJoinParent(parent.to_string()));
+                }
+                parent
+            }
+            None => table,
+        };
         let parent_col = sanitize_identifier(&join.parent_column)?;
         let child_col = sanitize_identifier(&join.child_column)?;
         let join_type = join.join_type.as_sql();
+        // parent -> junction -> table, each hop on one of the junction's keys.
+        let (parent_table, parent_col) = match &join.through {
+            Some(through) => {
+                let junction = sanitize_identifier(&through.table)?;
+                let to_parent = sanitize_identifier(&through.parent_column)?;
+                write!(
+                    sql,
+                    " {join_type} JOIN {} ON {} = {}",
+                    quote(junction),
+                    column_ref(dialect, parent_table, parent_col, true),
+                    column_ref(dialect, junction, to_parent, true)
+                )
+                .expect("writing to SQL buffer should not fail");
+                joined_tables.push(junction);
+                (junction, sanitize_identifier(&through.child_column)?)
+            }
+            None => (parent_table, parent_col),
+        };
+        write!(sql, " {join_type} JOIN {}", quote(join_table))
+            .expect("writing to SQL buffer should not fail");
+        if join.alias.is_some() {
+            write!(sql, " AS {}", quote(join_ref)).expect("writing to SQL buffer should not fail");
+        }
         write!(
-            &mut sql,
-            " {join_type} JOIN {join_table} ON {table}.{parent_col} = {join_table}.{child_col}"
+            sql,
+            " ON {} = {}",
+            column_ref(dialect, parent_table, parent_col, true),
+            column_ref(dialect, join_ref, child_col, true)
         )
         .expect("writing to SQL buffer should not fail");
+        joined_tables.push(join_ref);
     }
 
-    if !config.where_clauses.is_empty() {
-        let mut conditions = Vec::new();
-        for (field, value) in &config.where_clauses {
-            let field = sanitize_identifier(field)?;
-            let field_ref = if has_joins {
-                format!("{table}.{field}")
-            } else {
-                field.to_string()
-            };
-            conditions.push(format!("{field_ref} = ?"));
-            params.push(Value::Text(value.clone()));
-        }
+    let mut conditions = where_conditions(&config.where_clauses, table, has_joins, binder)?;
+
+    if let Some(search) = &config.search {
+        conditions.push(search_condition(table, has_joins, search, binder)?);
+    }
+
+    if !conditions.is_empty() {
         sql.push_str(" WHERE ");
         sql.push_str(&conditions.join(" AND "));
     }
 
-    if let Some(order_by) = &config.order_by {
-        let field = sanitize_identifier(&order_by.field)?;
-        let field_ref = if has_joins {
-            format!("{table}.{field}")
+    Ok(())
+}
+
+/// Build a parameterized `INSERT` from an insert config, one placeholder per
+/// column value. Only `policy.param_style` applies.
+///
+/// # Errors
+/// Returns `QueryBuilderError::NoValues` when no column is set, and
+/// otherwise identifier and value errors as [`build_query`] does.
+pub fn build_insert(
+    dialect: &dyn SqlDialect,
+    config: &MutationConfig,
+    policy: &QueryPolicy,
+) -> Result<BuiltQuery, QueryBuilderError> {
+    if config.values.is_empty() {
+        return Err(QueryBuilderError::NoValues);
+    }
+
+    let mut binder = Binder::new(dialect, policy.param_style);
+    let table = dialect.quote_identifier(sanitize_identifier(&config.table)?);
+    let mut columns = Vec::with_capacity(config.values.len());
+    let mut placeholders = Vec::with_capacity(config.values.len());
+    for assignment in &config.values {
+        let column = sanitize_identifier(&assignment.column)?;
+        columns.push(dialect.quote_identifier(column));
+        placeholders.push(binder.bind(column, bind_value(&assignment.value)?));
+    }
+
+    Ok(BuiltQuery {
+        sql: format!(
+            "INSERT INTO {table} ({}) VALUES ({}){}",
+            columns.join(", "),
+            placeholders.join(", "),
+            returning_clause(config)
+        ),
+        params: binder.values,
+        param_names: binder.names,
+    })
+}
+
+/// One SQL condition per where clause, binding their values.
+fn where_conditions(
+    clauses: &[WhereClause],
+    table: &str,
+    has_joins: bool,
+    binder: &mut Binder<'_>,
+) -> Result<Vec<String>, QueryBuilderError> {
+    let mut conditions = Vec::new();
+    for clause in clauses {
+        let field = sanitize_identifier(&clause.field)?;
+        let field_ref = column_ref(binder.dialect, table, field, has_joins);
+
+        if let Some(subquery) = &clause.subquery {
+            if subquery.columns.len() != 1 || !subquery.expressions.is_empty() {
+                return Err(QueryBuilderError::SubqueryColumns {
+                    table: subquery.table.clone(),
+                });
+            }
+            // Only an explicit `limit-` applies; default limits are for result pages.
+            let policy = QueryPolicy::default().with_param_style(binder.style);
+            let select = build_select(subquery, &policy, binder)?;
+            conditions.push(format!(
+                "{field_ref} {} ({select})",
+                clause.operator.as_sql()
+            ));
+        } else if clause.operator == WhereOperator::In {
+            return Err(QueryBuilderError::InvalidValue {
+                kind: "subquery",
+                value: clause.value.clone(),
+            });
+        } else if clause.operator == WhereOperator::Contains {
+            let pattern = Value::Text(format!("%{}%", escape_like(&clause.value)));
+            let placeholder = binder.bind(field, pattern);
+            conditions.push(format!("{field_ref} LIKE {placeholder} ESCAPE '\\'"));
+        } else if let Some(date) = relative_date(&clause.value) {
+            let start = datetime_expr(&format!("{field}_start"), &date.start, binder);
+            match (clause.operator, &date.end) {
+                (WhereOperator::Eq, Some(end)) => {
+                    let end = datetime_expr(&format!("{field}_end"), end, binder);
+                    conditions.push(format!("({field_ref} >= {start} AND {field_ref} < {end})"));
+                }
+                (WhereOperator::Eq, None) => conditions.push(format!("{field_ref} >= {start}")),
+                (operator, _) => {
+                    conditions.push(format!("{field_ref} {} {start}", operator.as_sql()));
+                }
+            }
         } else {
-            field.to_string()
-        };
-        write!(
-            &mut sql,
-            " ORDER BY {field_ref} {}",
-            order_by.direction.as_sql()
-        )
-        .expect("writing to SQL buffer should not fail");
+            let placeholder = binder.bind(field, bind_value(&clause.value)?);
+            conditions.push(format!(
+                "{field_ref} {} {placeholder}",
+                clause.operator.as_sql()
+            ));
+        }
+    }
+    Ok(conditions)
+}
+
+/// Build a parameterized `UPDATE` from an update config: `SET` binds each
+/// column value and `WHERE` ands the where clauses.
+///
+/// An update without where clauses is refused rather than rewriting every
+/// row of the table.
+///
+/// # Errors
+/// Returns `QueryBuilderError::NoValues` when no column is set,
+/// `QueryBuilderError::MissingWhere` without a where clause, and otherwise
+/// identifier and value errors as [`build_query`] does.
+pub fn build_update(
+    dialect: &dyn SqlDialect,
+    config: &MutationConfig,
+    policy: &QueryPolicy,
+) -> Result<BuiltQuery, QueryBuilderError> {
+    if config.values.is_empty() {
+        return Err(QueryBuilderError::NoValues);
+    }
+    if config.where_clauses.is_empty() {
+        return Err(QueryBuilderError::MissingWhere("update"));
+    }
+
+    let mut binder = Binder::new(dialect, policy.param_style);
+    let table = sanitize_identifier(&config.table)?;
+    let mut assignments = Vec::with_capacity(config.values.len());
+    for assignment in &config.values {
+        let column = sanitize_identifier(&assignment.column)?;
+        let placeholder = binder.bind(column, bind_value(&assignment.value)?);
+        assignments.push(format!(
+            "{} = {placeholder}",
+            dialect.quote_identifier(column)
+        ));
+    }
+    let conditions = where_conditions(&config.where_clauses, table, false, &mut binder)?;
+
+    Ok(BuiltQuery {
+        sql: format!(
+            "UPDATE {} SET {} WHERE {}{}",
+            dialect.quote_identifier(table),
+            assignments.join(", "),
+            conditions.join(" AND "),
+            returning_clause(config)
+        ),
+        params: binder.values,
+        param_names: binder.names,
+    })
+}
+
+/// Build a parameterized `DELETE` from a delete config, anding its where
+/// clauses.
+///
+/// Like [`build_update`], a delete without where clauses is refused rather
+/// than emptying the table.
+///
+/// # Errors
+/// Returns `QueryBuilderError::MissingWhere` without a where clause, and
+/// otherwise identifier and value errors as [`build_query`] does.
+pub fn build_delete(
+    dialect: &dyn SqlDialect,
+    config: &MutationConfig,
+    policy: &QueryPolicy,
+) -> Result<BuiltQuery, QueryBuilderError> {
+    if config.where_clauses.is_empty() {
+        return Err(QueryBuilderError::MissingWhere("delete"));
     }
 
-    if let Some(limit) = config.limit {
-        sql.push_str(" LIMIT ?");
-        params.push(Value::Integer(limit));
+    let mut binder = Binder::new(dialect, policy.param_style);
+    let table = sanitize_identifier(&config.table)?;
+    let conditions = where_conditions(&config.where_clauses, table, false, &mut binder)?;
+
+    Ok(BuiltQuery {
+        sql: format!(
+            "DELETE FROM {} WHERE {}{}",
+            dialect.quote_identifier(table),
+            conditions.join(" AND "),
+            returning_clause(config)
+        ),
+        params: binder.values,
+        param_names: binder.names,
+    })
+}
+
+/// ` RETURNING *` when `config` reads back the rows it changes. SQLite
+/// (3.35+), Postgres and DuckDB all accept it.
+const fn returning_clause(config: &MutationConfig) -> &'static str {
+    if config.returning {
+        " RETURNING *"
+    } else {
+        ""
     }
+}
 
-    Ok(BuiltQuery { sql, params })
+/// Build whichever statement `config.kind` calls for.
+///
+/// # Errors
+/// Returns the errors of [`build_insert`], [`build_update`] or [`build_delete`].
+pub fn build_mutation(
+    dialect: &dyn SqlDialect,
+    config: &MutationConfig,
+    policy: &QueryPolicy,
+) -> Result<BuiltQuery, QueryBuilderError> {
+    match config.kind {
+        MutationKind::Insert => build_insert(dialect, config, policy),
+        MutationKind::Update => build_update(dialect, config, policy),
+        MutationKind::Delete => build_delete(dialect, config, policy),
+    }
 }
@@ -1,9 +1,364 @@
 use serde_json::Value;
 use std::collections::BTreeMap;
 use std::fmt::{self, Write};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::parser::{OrderBy, OrderDirection};
+use crate::query_builder::ROW_ID;
+
+mod locale;
+pub mod tailwind;
+mod theme;
+
+pub use locale::Locale;
+pub use theme::Theme;
 
 pub type RowData = BTreeMap<String, Value>;
 
+/// Custom HTML for the cells of one column.
+///
+/// Returning `None` falls back to the default formatting. Implementations
+/// are responsible for escaping any data they emit.
+pub trait CellRenderer: fmt::Debug + Send + Sync {
+    fn render(&self, value: Option<&Value>, row: &RowData) -> Option<String>;
+}
+
+/// Per-request switches for the HTML renderers.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Columns whose timestamps render as "3 days ago" inside a `<time>` tag.
+    pub relative_time_columns: Vec<String>,
+    /// Unix time treated as "now" for relative times; defaults to the system clock.
+    pub now: Option<i64>,
+    /// Custom renderers keyed by column name.
+    pub cell_renderers: BTreeMap<String, Arc<dyn CellRenderer>>,
+    /// Keep the first table column visible while scrolling horizontally.
+    pub pin_first_column: bool,
+    /// Show at most this many table columns; the rest go behind a "+N more" expander.
+    pub max_columns: Option<usize>,
+    /// Emit htmx attributes so tables can re-sort and refresh themselves.
+    pub interactive: Option<Interactive>,
+    /// Classes for the markup's colors, borders and backgrounds.
+    pub theme: Theme,
+    /// Language of the words around the data, and of numbers and timestamps.
+    pub locale: Locale,
+}
+
+/// Where an interactive fragment fetches its next state from.
+///
+/// The output is wrapped in a `.tailwindsql-fragment` root that htmx swaps
+/// out: table headers get `hx-get` links adding `sort`/`dir` to `url`, a
+/// refresh interval polls the current view with `hx-trigger="every Ns"`, and
+/// paged tables end in previous/next links adding `page`.
+/// Pages embedding the fragment need htmx loaded for these to take effect.
+#[derive(Debug, Clone)]
+pub struct Interactive {
+    /// Fragment URL reproducing this view, without `sort`, `dir` or `page`
+    /// params.
+    pub url: String,
+    /// Columns whose headers may be clicked to sort (and filtered, when enabled).
+    pub sortable: Vec<String>,
+    /// Sort currently applied, used to flip direction and mark the header.
+    pub sort: Option<OrderBy>,
+    pub refresh_seconds: Option<u64>,
+    /// Current `filter.<column>` values; `Some` adds a row of filter inputs.
+    pub filters: Option<BTreeMap<String, String>>,
+    /// The page shown; `Some` adds previous/next links below tables.
+    pub pager: Option<Pager>,
+}
+
+/// Where a table sits in its paged result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pager {
+    /// 1-based page shown.
+    pub page: u64,
+    /// Pages in all, when counted; without it the next page is always offered.
+    pub page_count: Option<u64>,
+}
+
+impl Pager {
+    const fn has_next(&self) -> bool {
+        match self.page_count {
+            Some(count) => self.page < count,
+            None => true,
+        }
+    }
+}
+
+impl Interactive {
+    fn url_with_sort(&self, column: &str, direction: OrderDirection) -> String {
+        let dir = direction.as_keyword();
+        let separator = if self.url.contains('?') { '&' } else { '?' };
+        format!("{}{separator}sort={column}&dir={dir}", self.url)
+    }
+
+    /// URL of the view as currently sorted, from its first page; sorting or
+    /// filtering starts over from there.
+    fn sorted_url(&self) -> String {
+        self.sort.as_ref().map_or_else(
+            || self.url.clone(),
+            |sort| self.url_with_sort(&sort.field, sort.direction),
+        )
+    }
+
+    fn url_with_page(&self, page: u64) -> String {
+        let url = self.sorted_url();
+        if page <= 1 {
+            return url;
+        }
+        let separator = if url.contains('?') { '&' } else { '?' };
+        format!("{url}{separator}page={page}")
+    }
+
+    /// URL of the view as currently sorted and paged, for refresh polling.
+    fn current_url(&self) -> String {
+        match self.pager {
+            Some(pager) => self.url_with_page(pager.page),
+            None => self.sorted_url(),
+        }
+    }
+
+    fn sorted_by(&self, column: &str) -> Option<OrderDirection> {
+        self.sort
+            .as_ref()
+            .filter(|sort| sort.field == column)
+            .map(|sort| sort.direction)
+    }
+
+    /// `hx-*` attributes for a header: clicking sorts ascending, or flips the
+    /// direction when the column is already sorted.
+    fn header_attributes(&self, column: &str) -> Option<String> {
+        if !self.sortable.iter().any(|c| c == column) {
+            return None;
+        }
+        let current = self.sorted_by(column);
+        let next = match current {
+            Some(OrderDirection::Asc) => OrderDirection::Desc,
+            _ => OrderDirection::Asc,
+        };
+        let aria = match current {
+            Some(OrderDirection::Asc) => "ascending",
+            Some(OrderDirection::Desc) => "descending",
+            None => "none",
+        };
+        Some(format!(
+            " hx-get=\"{}\" hx-target=\"closest .tailwindsql-fragment\" hx-swap=\"outerHTML\"{} aria-sort=\"{aria}\"",
+            escape_html(&self.url_with_sort(column, next)),
+            self.include_filters("closest .tailwindsql-fragment"),
+        ))
+    }
+
+    fn wrapper_attributes(&self) -> String {
+        self.refresh_seconds
+            .map(|seconds| {
+                format!(
+                    " hx-get=\"{}\" hx-trigger=\"every {seconds}s\" hx-swap=\"outerHTML\"{}",
+                    escape_html(&self.current_url()),
+                    self.include_filters("this"),
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    /// Filter values travel as inputs rather than in `url`, so every request
+    /// the fragment makes has to include them.
+    fn include_filters(&self, selector: &str) -> String {
+        if self.filters.is_some() {
+            format!(" hx-include=\"{selector}\"")
+        } else {
+            String::new()
+        }
+    }
+
+    fn filter_input(&self, column: &str, theme: &Theme, locale: Locale) -> Option<String> {
+        let filters = self.filters.as_ref()?;
+        if !self.sortable.iter().any(|c| c == column) {
+            return None;
+        }
+        let value = filters.get(column).map_or("", String::as_str);
+        Some(format!(
+            "<input type=\"search\" name=\"filter.{name}\" value=\"{value}\" placeholder=\"{placeholder}\" aria-label=\"{label}\" class=\"w-full min-w-[4rem] {surface} border {border} rounded px-1.5 py-0.5 text-xs font-normal {input_text}\" hx-get=\"{url}\" hx-trigger=\"input changed delay:300ms, search\" hx-target=\"closest .tailwindsql-fragment\" hx-swap=\"outerHTML\" hx-include=\"closest .tailwindsql-fragment\">",
+            name = escape_html(column),
+            placeholder = escape_html(locale.catalog().filter),
+            label = escape_html(&locale::fill(locale.catalog().filter_column, &[column])),
+            value = escape_html(value),
+            url = escape_html(&self.sorted_url()),
+            surface = theme.surface,
+            border = theme.border,
+            input_text = theme.input_text,
+        ))
+    }
+
+    /// Previous/next links under a paged table, with the page between them.
+    /// They work as plain links too, loading the fragment on its own.
+    fn pager_nav(&self, theme: &Theme, locale: Locale) -> Option<String> {
+        let pager = self.pager?;
+        let text = locale.catalog();
+        let link = |label: &str, rel: &str, page: Option<u64>| {
+            match page {
+            Some(page) => {
+                let url = escape_html(&self.url_with_page(page));
+                format!(
+                    "<a href=\"{url}\" rel=\"{rel}\" class=\"px-2 py-1 rounded border {} {} {}\" hx-get=\"{url}\" hx-target=\"closest .tailwindsql-fragment\" hx-swap=\"outerHTML\"{}>{label}</a>",
+                    theme.border,
+                    theme.accent,
+                    theme.accent_hover,
+                    self.include_filters("closest .tailwindsql-fragment"),
+                )
+            }
+            None => format!(
+                "<span aria-disabled=\"true\" class=\"px-2 py-1 rounded border {} {} opacity-50\">{label}</span>",
+                theme.border, theme.muted
+            ),
+        }
+        };
+        let page = pager.page.to_string();
+        let position = match pager.page_count {
+            Some(count) => locale::fill(text.page_of, &[&page, &count.max(1).to_string()]),
+            None => locale::fill(text.page, &[&page]),
+        };
+        Some(format!(
+            "<nav aria-label=\"{}\" class=\"flex items-center justify-between gap-2 mt-2 text-xs sm:text-sm\">{}<span class=\"{}\">{position}</span>{}</nav>",
+            text.pagination,
+            link(text.prev, "prev", (pager.page > 1).then(|| pager.page - 1)),
+            theme.label,
+            link(text.next, "next", pager.has_next().then_some(pager.page + 1)),
+        ))
+    }
+}
+
+impl RenderOptions {
+    #[must_use]
+    pub fn with_cell_renderer(
+        mut self,
+        column: impl Into<String>,
+        renderer: impl CellRenderer + 'static,
+    ) -> Self {
+        self.cell_renderers
+            .insert(column.into(), Arc::new(renderer));
+        self
+    }
+}
+
+/// Renders avatar keywords (`crab`, `rocket`, ...) as emoji badges, and
+/// anything unrecognised as an initials badge drawn from `name_column`.
+#[derive(Debug, Clone)]
+pub struct AvatarRenderer {
+    pub tokens: BTreeMap<String, String>,
+    pub name_column: String,
+}
+
+const AVATAR_EMOJI: &[(&str, &str)] = &[
+    ("coder", "\u{1f469}\u{200d}\u{1f4bb}"),
+    ("builder", "\u{1f477}"),
+    ("hacker", "\u{1f575}\u{fe0f}"),
+    ("explorer", "\u{1f9ed}"),
+    ("penguin", "\u{1f427}"),
+    ("snake", "\u{1f40d}"),
+    ("coffee", "\u{2615}"),
+    ("diamond", "\u{1f48e}"),
+    ("crab", "\u{1f980}"),
+    ("bolt", "\u{26a1}"),
+    ("leaf", "\u{1f343}"),
+    ("rocket", "\u{1f680}"),
+    ("robot", "\u{1f916}"),
+    ("chip", "\u{1f4be}"),
+    ("spark", "\u{2728}"),
+];
+
+impl Default for AvatarRenderer {
+    fn default() -> Self {
+        Self {
+            tokens: AVATAR_EMOJI
+                .iter()
+                .map(|(token, emoji)| ((*token).to_string(), (*emoji).to_string()))
+                .collect(),
+            name_column: "name".to_string(),
+        }
+    }
+}
+
+impl CellRenderer for AvatarRenderer {
+    fn render(&self, value: Option<&Value>, row: &RowData) -> Option<String> {
+        let token = value.and_then(Value::as_str).unwrap_or_default();
+        if let Some(emoji) = self.tokens.get(token) {
+            return Some(format!(
+                "<span class=\"inline-flex items-center justify-center w-6 h-6 rounded-full bg-white/10\" title=\"{}\">{emoji}</span>",
+                escape_html(token)
+            ));
+        }
+
+        let name = row
+            .get(&self.name_column)
+            .and_then(Value::as_str)
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or(token);
+        let initials: String = name
+            .split_whitespace()
+            .filter_map(|word| word.chars().next())
+            .take(2)
+            .flat_map(char::to_uppercase)
+            .collect();
+        if initials.is_empty() {
+            return None;
+        }
+
+        let hue = name.bytes().fold(0_u32, |hash, byte| {
+            hash.wrapping_mul(31).wrapping_add(u32::from(byte))
+        }) % 360;
+        Some(format!(
+            "<svg class=\"inline-block\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\" role=\"img\" aria-label=\"{label}\"><circle cx=\"12\" cy=\"12\" r=\"12\" fill=\"hsl({hue}, 60%, 45%)\"/><text x=\"12\" y=\"16\" text-anchor=\"middle\" font-size=\"10\" font-family=\"sans-serif\" fill=\"#fff\">{initials}</text></svg>",
+            label = escape_html(name),
+            initials = escape_html(&initials),
+        ))
+    }
+}
+
+struct Cells<'a> {
+    options: &'a RenderOptions,
+    now: i64,
+}
+
+impl<'a> Cells<'a> {
+    fn new(options: &'a RenderOptions) -> Self {
+        let now = options.now.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| {
+                    i64::try_from(elapsed.as_secs()).unwrap_or(i64::MAX)
+                })
+        });
+        Self { options, now }
+    }
+
+    fn format(&self, row: &RowData, column: &str) -> String {
+        let value = row.get(column);
+        if let Some(html) = self
+            .options
+            .cell_renderers
+            .get(column)
+            .and_then(|renderer| renderer.render(value, row))
+        {
+            return html;
+        }
+        if self
+            .options
+            .relative_time_columns
+            .iter()
+            .any(|c| c == column)
+        {
+            if let Some(html) = value
+                .and_then(Value::as_str)
+                .and_then(|raw| format_relative_time(raw, self.now, self.options.locale))
+            {
+                return html;
+            }
+        }
+        format_value_in(value, self.options.locale)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum RenderAs {
     Span,
@@ -16,6 +371,31 @@ pub enum RenderAs {
 }
 
 impl RenderAs {
+    /// Every render mode, in declaration order.
+    pub const ALL: [Self; 7] = [
+        Self::Span,
+        Self::Div,
+        Self::Ul,
+        Self::Ol,
+        Self::Table,
+        Self::Json,
+        Self::Code,
+    ];
+
+    /// The `as=` value that selects this mode.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Span => "span",
+            Self::Div => "div",
+            Self::Ul => "ul",
+            Self::Ol => "ol",
+            Self::Table => "table",
+            Self::Json => "json",
+            Self::Code => "code",
+        }
+    }
+
     #[must_use]
     pub fn parse(value: &str) -> Self {
         match value {
@@ -32,8 +412,48 @@ impl RenderAs {
 
 #[must_use]
 pub fn render_results(results: &[RowData], columns: &[String], render_as: RenderAs) -> String {
+    render_results_with(results, columns, render_as, &RenderOptions::default())
+}
+
+#[must_use]
+pub fn render_results_with(
+    results: &[RowData],
+    columns: &[String],
+    render_as: RenderAs,
+    options: &RenderOptions,
+) -> String {
+    let html = render_body(results, columns, render_as, options);
+    match &options.interactive {
+        Some(interactive) => format!(
+            "<div class=\"tailwindsql-fragment\"{}>{html}</div>",
+            interactive.wrapper_attributes()
+        ),
+        None => html,
+    }
+}
+
+fn render_body(
+    results: &[RowData],
+    columns: &[String],
+    render_as: RenderAs,
+    options: &RenderOptions,
+) -> String {
+    let cells = Cells::new(options);
+    let filtering = options
+        .interactive
+        .as_ref()
+        .is_some_and(|interactive| interactive.filters.is_some());
+    if results.is_empty() && filtering && matches!(render_as, RenderAs::Table) && columns.len() > 1
+    {
+        // Keep the filter inputs on screen so an over-narrow filter can be cleared.
+        return render_table(results, columns, &cells);
+    }
     if results.is_empty() {
-        return "<span class=\"text-gray-400 italic\">No results</span>".to_string();
+        return format!(
+            "<span class=\"{} italic\">{}</span>",
+            options.theme.muted,
+            options.locale.catalog().no_results
+        );
     }
 
     let mut display_columns = if columns.is_empty() {
@@ -47,47 +467,88 @@ pub fn render_results(results: &[RowData], columns: &[String], render_as: Render
     }
 
     if display_columns.len() == 1 && results.len() == 1 {
-        return render_single_value(results, &display_columns[0]);
+        return render_single_value(results, &display_columns[0], &cells);
     }
 
     if display_columns.len() == 1 {
-        return render_single_column(results, &display_columns[0], render_as);
+        return render_single_column(results, &display_columns[0], render_as, &cells);
     }
 
     match render_as {
-        RenderAs::Table => render_table(results, &display_columns),
-        RenderAs::Json | RenderAs::Code => render_json_block(results),
+        RenderAs::Table => render_table(results, &display_columns, &cells),
+        RenderAs::Json | RenderAs::Code => render_json_block(results, &options.theme),
         RenderAs::Ul => render_row_list(results, "ul", "list-disc list-inside"),
         RenderAs::Ol => render_row_list(results, "ol", "list-decimal list-inside"),
-        _ => render_default_rows(results, &display_columns),
+        _ => render_default_rows(results, &display_columns, &cells),
     }
 }
 
+/// Inline error message for fragment responses.
+#[must_use]
+pub fn render_error(message: &str) -> String {
+    render_error_with(message, &Theme::default())
+}
+
+/// [`render_error`] in `theme`'s colors.
+#[must_use]
+pub fn render_error_with(message: &str, theme: &Theme) -> String {
+    format!(
+        "<span class=\"{}\">{}</span>",
+        theme.error,
+        escape_html(message)
+    )
+}
+
 fn columns_from_results(results: &[RowData]) -> Vec<String> {
     results
         .first()
-        .map(|row| row.keys().cloned().collect())
+        .map(|row| row.keys().filter(|key| *key != ROW_ID).cloned().collect())
         .unwrap_or_default()
 }
 
-fn render_single_value(results: &[RowData], column: &str) -> String {
-    let value = results[0].get(column);
-    format!("<span>{}</span>", format_value(value))
+/// ` data-id="..."` for a row carrying its identity, so scripts can key or
+/// target the element it renders as.
+fn row_id_attribute(row: &RowData) -> String {
+    match row.get(ROW_ID) {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(id)) => format!(" data-id=\"{}\"", escape_html(id)),
+        Some(id) => format!(" data-id=\"{}\"", escape_html(&id.to_string())),
+    }
+}
+
+fn render_single_value(results: &[RowData], column: &str, cells: &Cells<'_>) -> String {
+    format!("<span>{}</span>", cells.format(&results[0], column))
 }
 
-fn render_single_column(results: &[RowData], column: &str, render_as: RenderAs) -> String {
+fn render_single_column(
+    results: &[RowData],
+    column: &str,
+    render_as: RenderAs,
+    cells: &Cells<'_>,
+) -> String {
     let values = results
         .iter()
-        .map(|row| format_value(row.get(column)))
+        .map(|row| cells.format(row, column))
         .collect::<Vec<_>>();
+    let items = || {
+        results
+            .iter()
+            .map(row_id_attribute)
+            .zip(values.iter().cloned())
+    };
 
     match render_as {
-        RenderAs::Ul => render_list("ul", "list-disc list-inside", values.iter()),
-        RenderAs::Ol => render_list("ol", "list-decimal list-inside", values.iter()),
+        RenderAs::Ul => render_list("ul", "list-disc list-inside", items()),
+        RenderAs::Ol => render_list("ol", "list-decimal list-inside", items()),
         RenderAs::Json | RenderAs::Code => {
+            let values = results
+                .iter()
+                .map(|row| format_value(row.get(column)))
+                .collect::<Vec<_>>();
             let json = serde_json::to_string_pretty(&values).unwrap_or_default();
             format!(
-                "<code class=\"font-mono text-xs sm:text-sm bg-black/40 text-green-400 p-2 sm:p-3 rounded block overflow-x-auto\">{}</code>",
+                "<code class=\"font-mono text-xs sm:text-sm {} p-2 sm:p-3 rounded block overflow-x-auto\">{}</code>",
+                cells.options.theme.code,
                 escape_html(&json)
             )
         }
@@ -95,84 +556,217 @@ fn render_single_column(results: &[RowData], column: &str, render_as: RenderAs)
     }
 }
 
-fn render_table(results: &[RowData], columns: &[String]) -> String {
+fn render_table(results: &[RowData], columns: &[String], cells: &Cells<'_>) -> String {
     let headers = if columns.is_empty() {
         columns_from_results(results)
     } else {
         columns.to_vec()
     };
+    let visible_count = cells
+        .options
+        .max_columns
+        .map_or(headers.len(), |max| max.clamp(1, headers.len().max(1)));
+    let (visible, overflow) = headers.split_at(visible_count.min(headers.len()));
+    let pin = cells.options.pin_first_column;
+    let theme = &cells.options.theme;
+    let border = &theme.border;
+    let text = cells.options.locale.catalog();
+
+    let interactive = cells.options.interactive.as_ref();
 
     let mut html = String::new();
     push_html(
         &mut html,
         format_args!(
-            "<div class=\"overflow-x-auto -mx-2 sm:mx-0\"><table class=\"border-collapse border border-white/10 text-xs sm:text-sm w-full min-w-[400px]\"><thead><tr class=\"bg-white/5\">"
+            "<div class=\"overflow-x-auto -mx-2 sm:mx-0\"><table class=\"border-collapse border {border} text-xs sm:text-sm w-full min-w-[400px]\"><thead><tr class=\"{}\">",
+            theme.raised
         ),
     );
 
-    for header in &headers {
+    for (idx, header) in visible.iter().enumerate() {
         let escaped = escape_html(header);
+        let sticky = pinned_class(pin && idx == 0, theme);
+        let (sort_attrs, sort_class, arrow) =
+            match interactive.and_then(|i| i.header_attributes(header).map(|attrs| (i, attrs))) {
+                Some((i, attrs)) => (
+                    attrs,
+                    format!(" cursor-pointer select-none {}", theme.accent_hover),
+                    match i.sorted_by(header) {
+                        Some(OrderDirection::Asc) => " \u{25b2}",
+                        Some(OrderDirection::Desc) => " \u{25bc}",
+                        None => "",
+                    },
+                ),
+                None => (String::new(), String::new(), ""),
+            };
         push_html(
             &mut html,
             format_args!(
-                "<th class=\"border border-white/10 px-2 sm:px-3 py-1.5 sm:py-2 text-left font-semibold text-cyan-400 whitespace-nowrap\">{escaped}</th>"
+                "<th class=\"border {border} px-2 sm:px-3 py-1.5 sm:py-2 text-left font-semibold {} whitespace-nowrap{sticky}{sort_class}\"{sort_attrs}>{escaped}{arrow}</th>",
+                theme.accent
+            ),
+        );
+    }
+    if !overflow.is_empty() {
+        let hidden = locale::fill(text.more, &[&overflow.len().to_string()]);
+        push_html(
+            &mut html,
+            format_args!(
+                "<th class=\"border {border} px-2 sm:px-3 py-1.5 sm:py-2 text-left font-semibold {} whitespace-nowrap\">{hidden}</th>",
+                theme.label
             ),
         );
     }
 
-    push_html(&mut html, format_args!("</tr></thead><tbody>"));
+    push_html(&mut html, format_args!("</tr>"));
+    if let Some(interactive) = interactive.filter(|i| i.filters.is_some()) {
+        push_html(&mut html, format_args!("<tr class=\"{}\">", theme.raised));
+        for (idx, header) in visible.iter().enumerate() {
+            let input = interactive
+                .filter_input(header, theme, cells.options.locale)
+                .unwrap_or_default();
+            let sticky = pinned_class(pin && idx == 0, theme);
+            push_html(
+                &mut html,
+                format_args!(
+                    "<th class=\"border {border} px-1 sm:px-2 py-1{sticky}\">{input}</th>"
+                ),
+            );
+        }
+        if !overflow.is_empty() {
+            push_html(
+                &mut html,
+                format_args!("<th class=\"border {border}\"></th>"),
+            );
+        }
+        push_html(&mut html, format_args!("</tr>"));
+    }
+    push_html(&mut html, format_args!("</thead><tbody>"));
+
+    if results.is_empty() {
+        let span = visible.len() + usize::from(!overflow.is_empty());
+        push_html(
+            &mut html,
+            format_args!(
+                "<tr><td colspan=\"{span}\" class=\"border {border} px-2 sm:px-3 py-1.5 sm:py-2 {} italic\">{}</td></tr>",
+                theme.muted, text.no_results
+            ),
+        );
+    }
 
     for row in results {
-        push_html(&mut html, format_args!("<tr class=\"hover:bg-white/5 transition-colors\">"));
-        for header in &headers {
-            let value = format_value(row.get(header));
+        push_html(
+            &mut html,
+            format_args!(
+                "<tr class=\"{} transition-colors\"{}>",
+                theme.hover,
+                row_id_attribute(row)
+            ),
+        );
+        for (idx, header) in visible.iter().enumerate() {
+            let value = cells.format(row, header);
+            let sticky = pinned_class(pin && idx == 0, theme);
             push_html(
                 &mut html,
                 format_args!(
-                    "<td class=\"border border-white/10 px-2 sm:px-3 py-1.5 sm:py-2 text-slate-300 break-words max-w-[150px] sm:max-w-none\">{value}</td>"
+                    "<td class=\"border {border} px-2 sm:px-3 py-1.5 sm:py-2 {} break-words max-w-[150px] sm:max-w-none{sticky}\">{value}</td>",
+                    theme.text
                 ),
             );
         }
+        if !overflow.is_empty() {
+            push_overflow_cell(&mut html, row, overflow, cells);
+        }
         push_html(&mut html, format_args!("</tr>"));
     }
 
     push_html(&mut html, format_args!("</tbody></table></div>"));
+    if let Some(nav) = interactive.and_then(|i| i.pager_nav(theme, cells.options.locale)) {
+        html.push_str(&nav);
+    }
     html
 }
 
-fn render_json_block(results: &[RowData]) -> String {
+fn pinned_class(pinned: bool, theme: &Theme) -> String {
+    if pinned {
+        format!(" sticky left-0 z-10 {}", theme.surface)
+    } else {
+        String::new()
+    }
+}
+
+/// Collapse the columns past `max_columns` into a `<details>` expander.
+fn push_overflow_cell(html: &mut String, row: &RowData, columns: &[String], cells: &Cells<'_>) {
+    let theme = &cells.options.theme;
+    let hidden = locale::fill(
+        cells.options.locale.catalog().more,
+        &[&columns.len().to_string()],
+    );
+    push_html(
+        html,
+        format_args!(
+            "<td class=\"border {} px-2 sm:px-3 py-1.5 sm:py-2 {}\"><details><summary class=\"cursor-pointer {} whitespace-nowrap\">{hidden}</summary><dl class=\"mt-1 grid grid-cols-[auto_1fr] gap-x-2\">",
+            theme.border, theme.text, theme.accent
+        ),
+    );
+    for column in columns {
+        push_html(html, format_args!("<dt class=\"{}\">", theme.label));
+        escape_html_into(html, column);
+        push_html(
+            html,
+            format_args!("</dt><dd>{}</dd>", cells.format(row, column)),
+        );
+    }
+    push_html(html, format_args!("</dl></details></td>"));
+}
+
+fn render_json_block(results: &[RowData], theme: &Theme) -> String {
     let json = serde_json::to_string_pretty(results).unwrap_or_default();
-    format!(
-        "<code class=\"font-mono text-xs sm:text-sm bg-black/40 text-green-400 p-2 sm:p-3 rounded block whitespace-pre overflow-x-auto\">{}</code>",
-        escape_html(&json)
-    )
+    let mut html = String::with_capacity(json.len() + 160);
+    push_html(
+        &mut html,
+        format_args!(
+            "<code class=\"font-mono text-xs sm:text-sm {} p-2 sm:p-3 rounded block whitespace-pre overflow-x-auto\">",
+            theme.code
+        ),
+    );
+    escape_html_into(&mut html, &json);
+    html.push_str("</code>");
+    html
 }
 
 fn render_row_list(results: &[RowData], tag: &str, class_name: &str) -> String {
     let mut items = Vec::with_capacity(results.len());
     for row in results {
-        let json = serde_json::to_string(row).unwrap_or_default();
-        items.push(escape_html(&json));
+        let mut fields = row.clone();
+        fields.remove(ROW_ID);
+        let json = serde_json::to_string(&fields).unwrap_or_default();
+        items.push((row_id_attribute(row), escape_html(&json)));
     }
-    render_list(tag, class_name, items.iter())
+    render_list(tag, class_name, items.into_iter())
 }
 
-fn render_list<'a>(tag: &str, class_name: &str, items: impl Iterator<Item = &'a String>) -> String {
+/// A list of `(attributes, html)` items.
+fn render_list(
+    tag: &str,
+    class_name: &str,
+    items: impl Iterator<Item = (String, String)>,
+) -> String {
     let mut html = String::new();
     push_html(
         &mut html,
         format_args!("<{tag} class=\"{class_name}\">"),
     );
 
-    for item in items {
-        push_html(&mut html, format_args!("<li>{item}</li>"));
+    for (attributes, item) in items {
+        push_html(&mut html, format_args!("<li{attributes}>{item}</li>"));
     }
 
     push_html(&mut html, format_args!("</{tag}>"));
     html
 }
 
-fn render_default_rows(results: &[RowData], columns: &[String]) -> String {
+fn render_default_rows(results: &[RowData], columns: &[String], cells: &Cells<'_>) -> String {
     let headers = if columns.is_empty() {
         columns_from_results(results)
     } else {
@@ -186,31 +780,134 @@ fn render_default_rows(results: &[RowData], columns: &[String]) -> String {
             if idx > 0 {
                 line.push_str(", ");
             }
-            line.push_str(&format_value(row.get(header)));
+            line.push_str(&cells.format(row, header));
         }
-        push_html(&mut html, format_args!("<div>{line}</div>"));
+        let id = row_id_attribute(row);
+        push_html(&mut html, format_args!("<div{id}>{line}</div>"));
     }
 
     format!("<div>{html}</div>")
 }
 
 fn format_value(value: Option<&Value>) -> String {
+    format_value_in(value, Locale::default())
+}
+
+fn format_value_in(value: Option<&Value>, locale: Locale) -> String {
     match value {
         None | Some(Value::Null) => String::new(),
-        Some(Value::Bool(v)) => v.to_string(),
-        Some(Value::Number(num)) => num.to_string(),
+        Some(Value::Bool(true)) => "\u{2713}".to_string(),
+        Some(Value::Bool(false)) => "\u{2717}".to_string(),
+        Some(Value::Number(num)) => locale.format_number(num),
         Some(Value::String(s)) => escape_html(s),
         Some(other) => escape_html(&other.to_string()),
     }
 }
 
+/// Render an SQLite timestamp (`YYYY-MM-DD HH:MM:SS`, UTC) as a `<time>` tag
+/// labelled relative to `now`, with the full timestamp as its title.
+fn format_relative_time(raw: &str, now: i64, locale: Locale) -> Option<String> {
+    let timestamp = parse_timestamp(raw)?;
+    let label = relative_label(now - timestamp, locale);
+    let iso = raw.trim().replacen(' ', "T", 1);
+    let iso = if iso.ends_with('Z') {
+        iso
+    } else {
+        format!("{iso}Z")
+    };
+    Some(format!(
+        "<time datetime=\"{}\" title=\"{}\">{label}</time>",
+        escape_html(&iso),
+        escape_html(&locale.format_timestamp(raw))
+    ))
+}
+
+fn parse_timestamp(raw: &str) -> Option<i64> {
+    let raw = raw.trim().trim_end_matches('Z');
+    let (date, time) = raw.split_once([' ', 'T']).unwrap_or((raw, "00:00:00"));
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: i64 = date_parts.next()?.parse().ok()?;
+    let day: i64 = date_parts.next()?.parse().ok()?;
+
+    let mut time_parts = time.splitn(3, ':');
+    let hour: i64 = time_parts.next()?.parse().ok()?;
+    let minute: i64 = time_parts.next()?.parse().ok()?;
+    let second: i64 = time_parts
+        .next()
+        .map_or(Some(0), |s| s.split('.').next()?.parse().ok())?;
+
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    Some(days_from_civil(year, month, day) * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+const fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn relative_label(seconds_ago: i64, locale: Locale) -> String {
+    let text = locale.catalog();
+    let seconds = seconds_ago.abs();
+    let (amount, unit) = match seconds {
+        0..=59 => return text.just_now.to_string(),
+        60..=3_599 => (seconds / 60, 0),
+        3_600..=86_399 => (seconds / 3_600, 1),
+        86_400..=2_591_999 => (seconds / 86_400, 2),
+        2_592_000..=31_535_999 => (seconds / 2_592_000, 3),
+        _ => (seconds / 31_536_000, 4),
+    };
+    let (singular, plural) = text.units[unit];
+    let span = format!("{amount} {}", if amount == 1 { singular } else { plural });
+
+    if seconds_ago < 0 {
+        locale::fill(text.ahead, &[&span])
+    } else {
+        locale::fill(text.ago, &[&span])
+    }
+}
+
 fn escape_html(input: &str) -> String {
-    input
-        .replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&#x27;")
+    let mut escaped = String::with_capacity(input.len() + input.len() / 8);
+    escape_html_into(&mut escaped, input);
+    escaped
+}
+
+/// Append `input` to `buf` with `& < > " '` escaped, in one pass that copies
+/// the runs between special bytes whole.
+fn escape_html_into(buf: &mut String, input: &str) {
+    let bytes = input.as_bytes();
+    let mut start = 0;
+    for (idx, byte) in bytes.iter().enumerate() {
+        let entity = match byte {
+            b'&' => "&amp;",
+            b'<' => "&lt;",
+            b'>' => "&gt;",
+            b'"' => "&quot;",
+            b'\'' => "&#x27;",
+            _ => continue,
+        };
+        // Special bytes are ASCII, so `start..idx` falls on char boundaries.
+        buf.push_str(&input[start..idx]);
+        buf.push_str(entity);
+        start = idx + 1;
+    }
+    buf.push_str(&input[start..]);
 }
 
 fn push_html(buf: &mut String, args: fmt::Arguments<'_>) {
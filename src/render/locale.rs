@@ -0,0 +1,252 @@
+/// The language rendered fragments speak: the words around the data
+/// ("No results", the pager, "3 days ago") and how numbers and timestamps
+/// are written.
+///
+/// Fragments pick one per request from `Accept-Language`, so the same URL
+/// reads naturally to each viewer:
+///
+/// ```
+/// use tailwindsql::render::{render_results_with, Locale, RenderAs, RenderOptions};
+///
+/// let locale = Locale::negotiate("fr-CH, de;q=0.8, en;q=0.5");
+/// assert_eq!(locale, Locale::Fr);
+/// let options = RenderOptions {
+///     locale: Locale::De,
+///     ..RenderOptions::default()
+/// };
+/// let columns = ["id".to_string(), "price".to_string()];
+/// let html = render_results_with(&[], &columns, RenderAs::Table, &options);
+/// assert!(html.contains("Keine Ergebnisse"));
+/// ```
+///
+/// English is the default and writes numbers and timestamps as the database
+/// returns them. The others use their decimal comma and day-first dates;
+/// digits are not grouped, so ids and years read the same in every language.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+    Es,
+}
+
+/// The words one locale renders, with `{}` where a value goes.
+pub(super) struct Catalog {
+    pub no_results: &'static str,
+    pub filter: &'static str,
+    pub filter_column: &'static str,
+    pub pagination: &'static str,
+    pub prev: &'static str,
+    pub next: &'static str,
+    pub page: &'static str,
+    pub page_of: &'static str,
+    pub more: &'static str,
+    pub just_now: &'static str,
+    pub ago: &'static str,
+    pub ahead: &'static str,
+    /// Minute, hour, day, month and year, singular then plural.
+    pub units: [(&'static str, &'static str); 5],
+    decimal: char,
+    /// Date separator for day-first dates; `None` keeps `YYYY-MM-DD`.
+    day_first: Option<char>,
+}
+
+const EN: Catalog = Catalog {
+    no_results: "No results",
+    filter: "Filter",
+    filter_column: "Filter {}",
+    pagination: "Pagination",
+    prev: "\u{2190} Prev",
+    next: "Next \u{2192}",
+    page: "Page {}",
+    page_of: "Page {} of {}",
+    more: "+{} more",
+    just_now: "just now",
+    ago: "{} ago",
+    ahead: "in {}",
+    units: [
+        ("minute", "minutes"),
+        ("hour", "hours"),
+        ("day", "days"),
+        ("month", "months"),
+        ("year", "years"),
+    ],
+    decimal: '.',
+    day_first: None,
+};
+
+const DE: Catalog = Catalog {
+    no_results: "Keine Ergebnisse",
+    filter: "Filtern",
+    filter_column: "{} filtern",
+    pagination: "Seitennavigation",
+    prev: "\u{2190} Zur\u{fc}ck",
+    next: "Weiter \u{2192}",
+    page: "Seite {}",
+    page_of: "Seite {} von {}",
+    more: "+{} weitere",
+    just_now: "gerade eben",
+    ago: "vor {}",
+    ahead: "in {}",
+    // Dative, which both "vor" and "in" take.
+    units: [
+        ("Minute", "Minuten"),
+        ("Stunde", "Stunden"),
+        ("Tag", "Tagen"),
+        ("Monat", "Monaten"),
+        ("Jahr", "Jahren"),
+    ],
+    decimal: ',',
+    day_first: Some('.'),
+};
+
+const FR: Catalog = Catalog {
+    no_results: "Aucun r\u{e9}sultat",
+    filter: "Filtrer",
+    filter_column: "Filtrer {}",
+    pagination: "Pagination",
+    prev: "\u{2190} Pr\u{e9}c.",
+    next: "Suiv. \u{2192}",
+    page: "Page {}",
+    page_of: "Page {} sur {}",
+    more: "+{} de plus",
+    just_now: "\u{e0} l\u{2019}instant",
+    ago: "il y a {}",
+    ahead: "dans {}",
+    units: [
+        ("minute", "minutes"),
+        ("heure", "heures"),
+        ("jour", "jours"),
+        ("mois", "mois"),
+        ("an", "ans"),
+    ],
+    decimal: ',',
+    day_first: Some('/'),
+};
+
+const ES: Catalog = Catalog {
+    no_results: "Sin resultados",
+    filter: "Filtrar",
+    filter_column: "Filtrar {}",
+    pagination: "Paginaci\u{f3}n",
+    prev: "\u{2190} Anterior",
+    next: "Siguiente \u{2192}",
+    page: "P\u{e1}gina {}",
+    page_of: "P\u{e1}gina {} de {}",
+    more: "+{} m\u{e1}s",
+    just_now: "ahora mismo",
+    ago: "hace {}",
+    ahead: "dentro de {}",
+    units: [
+        ("minuto", "minutos"),
+        ("hora", "horas"),
+        ("d\u{ed}a", "d\u{ed}as"),
+        ("mes", "meses"),
+        ("a\u{f1}o", "a\u{f1}os"),
+    ],
+    decimal: ',',
+    day_first: Some('/'),
+};
+
+impl Locale {
+    /// Every locale, the default first.
+    pub const ALL: [Self; 4] = [Self::En, Self::De, Self::Fr, Self::Es];
+
+    /// The language tag, as in `Accept-Language`.
+    #[must_use]
+    pub const fn tag(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::De => "de",
+            Self::Fr => "fr",
+            Self::Es => "es",
+        }
+    }
+
+    /// The locale for a language tag such as `de` or `fr-CA`, by its
+    /// language alone.
+    #[must_use]
+    pub fn parse(tag: &str) -> Option<Self> {
+        let language = tag.trim().split(['-', '_']).next()?;
+        Self::ALL
+            .into_iter()
+            .find(|locale| locale.tag().eq_ignore_ascii_case(language))
+    }
+
+    /// The locale an `Accept-Language` header prefers most: the highest
+    /// `q` among the languages known here, the first listed on a tie, and
+    /// the default when none are.
+    #[must_use]
+    pub fn negotiate(accept_language: &str) -> Self {
+        let mut best: Option<(Self, f32)> = None;
+        for range in accept_language.split(',') {
+            let mut parts = range.split(';');
+            let Some(locale) = parts.next().and_then(Self::parse) else {
+                continue;
+            };
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())
+                .unwrap_or(0.0);
+            if quality > 0.0 && best.is_none_or(|(_, top)| quality > top) {
+                best = Some((locale, quality));
+            }
+        }
+        best.map_or_else(Self::default, |(locale, _)| locale)
+    }
+
+    pub(super) const fn catalog(self) -> &'static Catalog {
+        match self {
+            Self::En => &EN,
+            Self::De => &DE,
+            Self::Fr => &FR,
+            Self::Es => &ES,
+        }
+    }
+
+    /// `number` with this locale's decimal separator.
+    pub(super) fn format_number(self, number: &serde_json::Number) -> String {
+        let text = number.to_string();
+        match self.catalog().decimal {
+            '.' => text,
+            decimal => text.replace('.', &decimal.to_string()),
+        }
+    }
+
+    /// An SQLite timestamp (`YYYY-MM-DD HH:MM:SS`) written the locale's way;
+    /// anything else comes back unchanged.
+    pub(super) fn format_timestamp(self, raw: &str) -> String {
+        let Some(separator) = self.catalog().day_first else {
+            return raw.to_string();
+        };
+        let trimmed = raw.trim();
+        let (date, time) = match trimmed.split_once([' ', 'T']) {
+            Some((date, time)) => (date, Some(time)),
+            None => (trimmed, None),
+        };
+        let mut parts = date.splitn(3, '-');
+        let (Some(year), Some(month), Some(day)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return raw.to_string();
+        };
+        let date = format!("{day}{separator}{month}{separator}{year}");
+        match time {
+            Some(time) => format!("{date} {time}"),
+            None => date,
+        }
+    }
+}
+
+/// `template` with each `{}` replaced by the next of `values`.
+pub(super) fn fill(template: &str, values: &[&str]) -> String {
+    let mut text = String::with_capacity(template.len() + 16);
+    let mut values = values.iter();
+    for (index, piece) in template.split("{}").enumerate() {
+        if index > 0 {
+            text.push_str(values.next().copied().unwrap_or_default());
+        }
+        text.push_str(piece);
+    }
+    text
+}
@@ -0,0 +1,356 @@
+//! A purged Tailwind stylesheet for rendered fragments.
+//!
+//! Host pages that embed fragments without loading Tailwind would see them
+//! unstyled. [`stylesheet`] writes CSS for just the utility classes it is
+//! given, and [`fragment_stylesheet`] gives it every class the renderers can
+//! emit in the default [`Theme`], found by rendering each mode with each
+//! option and scanning the output with [`classes_in`]. The server serves the result as
+//! `/static/tailwind.css`.
+//!
+//! Only the utilities the renderers use are known, with Tailwind's default
+//! theme values and its `sm:` and `hover:` variants; other classes are left
+//! out of the stylesheet.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::sync::{Arc, OnceLock};
+
+use serde_json::json;
+
+use super::{
+    render_error, render_results_with, AvatarRenderer, Interactive, Locale, Pager, RenderAs,
+    RenderOptions, RowData, Theme,
+};
+use crate::parser::{OrderBy, OrderDirection};
+
+/// Tailwind's `sm` breakpoint.
+const SM_MIN_WIDTH: &str = "640px";
+
+/// Utilities that always mean the same declarations.
+const STATIC_UTILITIES: &[(&str, &str)] = &[
+    ("block", "display: block"),
+    ("inline-block", "display: inline-block"),
+    ("inline-flex", "display: inline-flex"),
+    ("flex", "display: flex"),
+    ("grid", "display: grid"),
+    ("hidden", "display: none"),
+    ("sticky", "position: sticky"),
+    ("left-0", "left: 0px"),
+    ("z-10", "z-index: 10"),
+    ("italic", "font-style: italic"),
+    ("font-mono", "font-family: ui-monospace, SFMono-Regular, Menlo, Monaco, Consolas, \"Liberation Mono\", \"Courier New\", monospace"),
+    ("font-normal", "font-weight: 400"),
+    ("font-semibold", "font-weight: 600"),
+    ("font-bold", "font-weight: 700"),
+    ("text-left", "text-align: left"),
+    ("text-center", "text-align: center"),
+    ("text-right", "text-align: right"),
+    ("text-xs", "font-size: 0.75rem; line-height: 1rem"),
+    ("text-sm", "font-size: 0.875rem; line-height: 1.25rem"),
+    ("text-base", "font-size: 1rem; line-height: 1.5rem"),
+    ("text-lg", "font-size: 1.125rem; line-height: 1.75rem"),
+    // Without Tailwind's preflight, borders need their style set too.
+    ("border", "border-width: 1px; border-style: solid"),
+    ("border-collapse", "border-collapse: collapse"),
+    ("rounded", "border-radius: 0.25rem"),
+    ("rounded-full", "border-radius: 9999px"),
+    ("break-words", "overflow-wrap: break-word"),
+    ("whitespace-nowrap", "white-space: nowrap"),
+    ("whitespace-pre", "white-space: pre"),
+    ("overflow-x-auto", "overflow-x: auto"),
+    ("cursor-pointer", "cursor: pointer"),
+    ("select-none", "user-select: none"),
+    ("items-center", "align-items: center"),
+    ("justify-center", "justify-content: center"),
+    ("justify-between", "justify-content: space-between"),
+    ("opacity-50", "opacity: 0.5"),
+    ("list-disc", "list-style-type: disc"),
+    ("list-decimal", "list-style-type: decimal"),
+    ("list-inside", "list-style-position: inside"),
+    ("transition-colors", "transition-property: color, background-color, border-color, text-decoration-color, fill, stroke; transition-timing-function: cubic-bezier(0.4, 0, 0.2, 1); transition-duration: 150ms"),
+];
+
+/// Spacing and sizing prefixes, and the properties they set.
+const SPACING_UTILITIES: &[(&str, &[&str])] = &[
+    ("p", &["padding"]),
+    ("px", &["padding-left", "padding-right"]),
+    ("py", &["padding-top", "padding-bottom"]),
+    ("pt", &["padding-top"]),
+    ("pb", &["padding-bottom"]),
+    ("m", &["margin"]),
+    ("mx", &["margin-left", "margin-right"]),
+    ("my", &["margin-top", "margin-bottom"]),
+    ("mt", &["margin-top"]),
+    ("mb", &["margin-bottom"]),
+    ("gap", &["gap"]),
+    ("gap-x", &["column-gap"]),
+    ("gap-y", &["row-gap"]),
+    ("w", &["width"]),
+    ("h", &["height"]),
+    ("min-w", &["min-width"]),
+    ("max-w", &["max-width"]),
+];
+
+/// Color utility prefixes, and the property each sets.
+const COLOR_UTILITIES: &[(&str, &str)] = &[
+    ("text", "color"),
+    ("bg", "background-color"),
+    ("border", "border-color"),
+];
+
+/// Shades 50 to 950 of the default palette's colors the renderers use.
+const PALETTE: &[(&str, [&str; 11])] = &[
+    (
+        "slate",
+        [
+            "#f8fafc", "#f1f5f9", "#e2e8f0", "#cbd5e1", "#94a3b8", "#64748b", "#475569", "#334155",
+            "#1e293b", "#0f172a", "#020617",
+        ],
+    ),
+    (
+        "gray",
+        [
+            "#f9fafb", "#f3f4f6", "#e5e7eb", "#d1d5db", "#9ca3af", "#6b7280", "#4b5563", "#374151",
+            "#1f2937", "#111827", "#030712",
+        ],
+    ),
+    (
+        "red",
+        [
+            "#fef2f2", "#fee2e2", "#fecaca", "#fca5a5", "#f87171", "#ef4444", "#dc2626", "#b91c1c",
+            "#991b1b", "#7f1d1d", "#450a0a",
+        ],
+    ),
+    (
+        "green",
+        [
+            "#f0fdf4", "#dcfce7", "#bbf7d0", "#86efac", "#4ade80", "#22c55e", "#16a34a", "#15803d",
+            "#166534", "#14532d", "#052e16",
+        ],
+    ),
+    (
+        "cyan",
+        [
+            "#ecfeff", "#cffafe", "#a5f3fc", "#67e8f9", "#22d3ee", "#06b6d4", "#0891b2", "#0e7490",
+            "#155e75", "#164e63", "#083344",
+        ],
+    ),
+];
+
+const SHADES: [&str; 11] = [
+    "50", "100", "200", "300", "400", "500", "600", "700", "800", "900", "950",
+];
+
+/// Every class named in a `class="..."` attribute of `html`.
+#[must_use]
+pub fn classes_in(html: &str) -> BTreeSet<String> {
+    html.split("class=\"")
+        .skip(1)
+        .filter_map(|rest| rest.split_once('"'))
+        .flat_map(|(classes, _)| classes.split_whitespace())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// CSS for the known utilities among `classes`: plain rules first, then
+/// `hover:` rules, then an `sm:` media query, as Tailwind orders them.
+#[must_use]
+pub fn stylesheet<'a>(classes: impl IntoIterator<Item = &'a str>) -> String {
+    let mut base = String::new();
+    let mut hover = String::new();
+    let mut small = String::new();
+    let classes: BTreeSet<&str> = classes.into_iter().collect();
+    for class in classes {
+        let (responsive, rest) = match class.strip_prefix("sm:") {
+            Some(rest) => (true, rest),
+            None => (false, class),
+        };
+        let (hovered, utility) = match rest.strip_prefix("hover:") {
+            Some(utility) => (true, utility),
+            None => (false, rest),
+        };
+        let Some(declarations) = declarations(utility) else {
+            continue;
+        };
+        let pseudo = if hovered { ":hover" } else { "" };
+        let out = match (responsive, hovered) {
+            (true, _) => &mut small,
+            (false, true) => &mut hover,
+            (false, false) => &mut base,
+        };
+        let indent = if responsive { "  " } else { "" };
+        let _ = writeln!(
+            out,
+            "{indent}.{}{pseudo} {{ {declarations} }}",
+            escape_selector(class)
+        );
+    }
+    let mut css = base + &hover;
+    if !small.is_empty() {
+        let _ = write!(css, "@media (min-width: {SM_MIN_WIDTH}) {{\n{small}}}\n");
+    }
+    css
+}
+
+/// The stylesheet for every class the renderers emit, built once.
+pub fn fragment_stylesheet() -> &'static str {
+    static CSS: OnceLock<String> = OnceLock::new();
+    CSS.get_or_init(|| {
+        let html = sample_fragments();
+        let classes = classes_in(&html);
+        stylesheet(classes.iter().map(String::as_str))
+    })
+}
+
+/// Fragments in every mode, with every option that adds markup.
+fn sample_fragments() -> String {
+    let row = |id: i64, name: &str, avatar: &str| -> RowData {
+        [
+            ("id".to_string(), json!(id)),
+            ("name".to_string(), json!(name)),
+            ("avatar".to_string(), json!(avatar)),
+            ("created_at".to_string(), json!("2024-01-01 12:00:00")),
+        ]
+        .into_iter()
+        .collect()
+    };
+    let rows = vec![row(1, "Ada Lovelace", "crab"), row(2, "Grace Hopper", "")];
+    let columns: Vec<String> = ["id", "name", "avatar", "created_at"]
+        .into_iter()
+        .map(ToString::to_string)
+        .collect();
+    let name = [columns[1].clone()];
+    let interactive = Interactive {
+        url: "/api/fragment".to_string(),
+        sortable: columns.clone(),
+        sort: Some(OrderBy {
+            field: "id".to_string(),
+            direction: OrderDirection::Asc,
+        }),
+        refresh_seconds: Some(30),
+        filters: Some(BTreeMap::new()),
+        // A first page: the previous link disabled, the next one live.
+        pager: Some(Pager {
+            page: 1,
+            page_count: None,
+        }),
+    };
+    let options = RenderOptions {
+        relative_time_columns: vec!["created_at".to_string()],
+        now: Some(0),
+        cell_renderers: BTreeMap::from([(
+            "avatar".to_string(),
+            Arc::new(AvatarRenderer::default()) as Arc<_>,
+        )]),
+        pin_first_column: true,
+        max_columns: Some(2),
+        interactive: Some(interactive),
+        theme: Theme::default(),
+        locale: Locale::default(),
+    };
+
+    let mut html = render_error("");
+    for options in [RenderOptions::default(), options] {
+        for render_as in RenderAs::ALL {
+            html.push_str(&render_results_with(&rows, &columns, render_as, &options));
+            html.push_str(&render_results_with(&rows, &name, render_as, &options));
+            html.push_str(&render_results_with(&[], &columns, render_as, &options));
+        }
+    }
+    html
+}
+
+/// The declarations for `utility`, if it is one this module knows.
+fn declarations(utility: &str) -> Option<String> {
+    if let Some((_, declarations)) = STATIC_UTILITIES.iter().find(|(name, _)| *name == utility) {
+        return Some((*declarations).to_string());
+    }
+    if let Some(arbitrary) = utility
+        .strip_prefix("grid-cols-[")
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        return Some(format!(
+            "grid-template-columns: {}",
+            arbitrary.replace('_', " ")
+        ));
+    }
+    spacing(utility).or_else(|| color(utility))
+}
+
+/// `px-1.5`, `-mx-2`, `w-full`, `max-w-[150px]` and the like.
+fn spacing(utility: &str) -> Option<String> {
+    let (negative, utility) = match utility.strip_prefix('-') {
+        Some(utility) => (true, utility),
+        None => (false, utility),
+    };
+    SPACING_UTILITIES.iter().find_map(|(prefix, properties)| {
+        let value = utility.strip_prefix(prefix)?.strip_prefix('-')?;
+        let value = match value {
+            "0" => "0px".to_string(),
+            "full" => "100%".to_string(),
+            "none" => "none".to_string(),
+            "auto" => "auto".to_string(),
+            _ => match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                Some(arbitrary) => arbitrary.replace('_', " "),
+                None => {
+                    let steps: f64 = value.parse().ok()?;
+                    format!("{}rem", steps / 4.0)
+                }
+            },
+        };
+        let value = if negative { format!("-{value}") } else { value };
+        Some(
+            properties
+                .iter()
+                .map(|property| format!("{property}: {value}"))
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    })
+}
+
+/// `text-slate-300`, `bg-white/5`, `border-white/10` and the like.
+fn color(utility: &str) -> Option<String> {
+    COLOR_UTILITIES.iter().find_map(|(prefix, property)| {
+        let color = utility.strip_prefix(prefix)?.strip_prefix('-')?;
+        let (color, opacity) = match color.split_once('/') {
+            Some((color, opacity)) => (color, Some(opacity.parse::<u8>().ok()?)),
+            None => (color, None),
+        };
+        let hex = match color {
+            "white" => "#ffffff",
+            "black" => "#000000",
+            _ => {
+                let (name, shade) = color.rsplit_once('-')?;
+                let (_, shades) = PALETTE.iter().find(|(palette, _)| *palette == name)?;
+                shades[SHADES.iter().position(|s| *s == shade)?]
+            }
+        };
+        let value = match opacity {
+            Some(opacity) => {
+                let channel = |at: usize| u8::from_str_radix(&hex[at..at + 2], 16).unwrap_or(0);
+                format!(
+                    "rgb({} {} {} / {})",
+                    channel(1),
+                    channel(3),
+                    channel(5),
+                    f64::from(opacity.min(100)) / 100.0
+                )
+            }
+            None => hex.to_string(),
+        };
+        Some(format!("{property}: {value}"))
+    })
+}
+
+/// `class` as a CSS class selector, with Tailwind's punctuation escaped.
+fn escape_selector(class: &str) -> String {
+    let mut escaped = String::with_capacity(class.len() + 4);
+    for c in class.chars() {
+        if matches!(c, ':' | '/' | '.' | '[' | ']' | '%' | '#' | '(' | ')' | ',') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,92 @@
+use std::borrow::Cow;
+
+/// The classes the renderers give each visual role, kept apart from the
+/// layout utilities around them.
+///
+/// The default is the bundled Tailwind palette. Pages styled some other way
+/// can swap in their own classes, or use [`Theme::semantic`] and write CSS
+/// for its `tailwindsql-*` names:
+///
+/// ```
+/// use tailwindsql::render::{render_results_with, RenderAs, RenderOptions, Theme};
+///
+/// let options = RenderOptions {
+///     theme: Theme {
+///         accent: "brand".into(),
+///         ..Theme::semantic()
+///     },
+///     ..RenderOptions::default()
+/// };
+/// let columns = ["id".to_string(), "name".to_string()];
+/// let html = render_results_with(&[], &columns, RenderAs::Table, &options);
+/// assert!(html.contains("tailwindsql-muted"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Background of pinned cells and filter inputs.
+    pub surface: Cow<'static, str>,
+    /// Background of header rows.
+    pub raised: Cow<'static, str>,
+    /// Background of a body row under the pointer.
+    pub hover: Cow<'static, str>,
+    /// Color of table and input borders.
+    pub border: Cow<'static, str>,
+    /// Cell text.
+    pub text: Cow<'static, str>,
+    /// Text typed into filter inputs.
+    pub input_text: Cow<'static, str>,
+    /// Secondary labels, such as the names of collapsed columns.
+    pub label: Cow<'static, str>,
+    /// Placeholders such as "No results".
+    pub muted: Cow<'static, str>,
+    /// Column headers and expanders.
+    pub accent: Cow<'static, str>,
+    /// A sortable header under the pointer.
+    pub accent_hover: Cow<'static, str>,
+    /// JSON and code blocks, background and text.
+    pub code: Cow<'static, str>,
+    /// Inline error messages.
+    pub error: Cow<'static, str>,
+}
+
+impl Theme {
+    /// One class per role, named after it: `tailwindsql-surface`,
+    /// `tailwindsql-border` and so on. Hover roles are plain classes too,
+    /// left for the stylesheet to apply under `:hover`.
+    #[must_use]
+    pub fn semantic() -> Self {
+        Self {
+            surface: "tailwindsql-surface".into(),
+            raised: "tailwindsql-raised".into(),
+            hover: "tailwindsql-hover".into(),
+            border: "tailwindsql-border".into(),
+            text: "tailwindsql-text".into(),
+            input_text: "tailwindsql-input-text".into(),
+            label: "tailwindsql-label".into(),
+            muted: "tailwindsql-muted".into(),
+            accent: "tailwindsql-accent".into(),
+            accent_hover: "tailwindsql-accent-hover".into(),
+            code: "tailwindsql-code".into(),
+            error: "tailwindsql-error".into(),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            surface: "bg-slate-900".into(),
+            raised: "bg-white/5".into(),
+            hover: "hover:bg-white/5".into(),
+            border: "border-white/10".into(),
+            text: "text-slate-300".into(),
+            input_text: "text-slate-200".into(),
+            label: "text-slate-400".into(),
+            muted: "text-gray-400".into(),
+            accent: "text-cyan-400".into(),
+            accent_hover: "hover:text-cyan-300".into(),
+            code: "bg-black/40 text-green-400".into(),
+            error: "text-red-400".into(),
+        }
+    }
+}
@@ -0,0 +1,956 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use rusqlite::types::Value;
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+
+use crate::parser::{
+    split_qualified, ColumnExpr, MutationConfig, MutationKind, QueryConfig, WhereOperator,
+};
+use crate::query_builder::{infer_value, is_relative_date};
+use crate::render::RowData;
+
+const BOOLEAN_COLUMN_NAMES: &[&str] = &[
+    "published",
+    "active",
+    "enabled",
+    "archived",
+    "deleted",
+    "verified",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnInfo {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub col_type: String,
+    pub boolean: bool,
+    #[serde(skip_serializing_if = "Privacy::is_public")]
+    pub privacy: Privacy,
+}
+
+/// Who may see a column's values, set with [`SchemaCatalog::mark_privacy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Privacy {
+    #[default]
+    Public,
+    /// Fine for API clients, but not for pages that embed results.
+    Internal,
+    /// Personal data, redacted everywhere but admin calls.
+    Pii,
+}
+
+impl Privacy {
+    #[must_use]
+    pub const fn is_public(&self) -> bool {
+        matches!(self, Self::Public)
+    }
+}
+
+/// The kind of value a column's where clauses compare against, from its
+/// declared type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueFormat {
+    Integer,
+    Number,
+    Boolean,
+    /// Compared with `after`/`before`, and relative keywords such as
+    /// `last-7-days`.
+    Date,
+    Text,
+}
+
+impl ColumnInfo {
+    /// A column of declared `col_type`, flagged boolean by the usual heuristics.
+    #[must_use]
+    pub fn new(name: impl Into<String>, col_type: impl Into<String>) -> Self {
+        let name = name.into();
+        let col_type = col_type.into();
+        Self {
+            boolean: looks_boolean(&name, &col_type),
+            privacy: Privacy::Public,
+            name,
+            col_type,
+        }
+    }
+
+    /// What [`SchemaCatalog::type_where_values`] binds this column's values
+    /// as, with dates told apart from text.
+    #[must_use]
+    pub fn value_format(&self) -> ValueFormat {
+        let col_type = self.col_type.to_ascii_uppercase();
+        if self.boolean {
+            ValueFormat::Boolean
+        } else if col_type.contains("INT") {
+            ValueFormat::Integer
+        } else if NUMBER_TYPES.iter().any(|name| col_type.contains(name)) {
+            ValueFormat::Number
+        } else if col_type.contains("DATE") || col_type.contains("TIME") {
+            ValueFormat::Date
+        } else {
+            ValueFormat::Text
+        }
+    }
+}
+
+/// Whether a column holds 0/1 flags: declared `BOOL*`, or an integer column
+/// named like `published`, `is_*`, `has_*` or `*_flag`.
+fn looks_boolean(name: &str, col_type: &str) -> bool {
+    let col_type = col_type.to_ascii_uppercase();
+    if col_type.contains("BOOL") {
+        return true;
+    }
+    col_type.contains("INT")
+        && (name.starts_with("is_")
+            || name.starts_with("has_")
+            || name.ends_with("_flag")
+            || BOOLEAN_COLUMN_NAMES.contains(&name))
+}
+
+fn is_text_type(col_type: &str) -> bool {
+    let col_type = col_type.to_ascii_uppercase();
+    col_type.contains("TEXT") || col_type.contains("CHAR") || col_type.contains("CLOB")
+}
+
+/// Prefix of the cache tables the server materializes query results into.
+/// They copy columns whatever their privacy or access policy, so the catalog
+/// leaves them out and class names cannot read them directly.
+pub const MATERIALIZED_PREFIX: &str = "materialized_";
+
+/// Declared types, in part, of columns holding decimals.
+const NUMBER_TYPES: [&str; 5] = ["REAL", "FLOA", "DOUB", "NUMERIC", "DECIMAL"];
+
+/// Prefixes that fix the type a where value binds as.
+const VALUE_HINTS: [&str; 5] = ["int", "real", "float", "bool", "text"];
+
+/// `value` with the type hint `column`'s declared type calls for, or `None`
+/// when it already has one or binds right without.
+fn typed_value(column: &ColumnInfo, value: &str) -> Option<String> {
+    if value
+        .split_once(':')
+        .is_some_and(|(hint, _)| VALUE_HINTS.contains(&hint))
+    {
+        return None;
+    }
+    let col_type = column.col_type.to_ascii_uppercase();
+    let hint = if column.boolean && matches!(value, "true" | "false" | "0" | "1") {
+        "bool"
+    } else if col_type.contains("INT") && value.parse::<i64>().is_ok() {
+        "int"
+    } else if NUMBER_TYPES.iter().any(|name| col_type.contains(name))
+        && value.parse::<f64>().is_ok_and(f64::is_finite)
+    {
+        "real"
+    } else if is_text_type(&col_type) && !matches!(infer_value(value), Value::Text(_)) {
+        "text"
+    } else if column.value_format() != ValueFormat::Date && is_relative_date(value) {
+        // Only date columns compare against `today` or `last-7-days`.
+        "text"
+    } else {
+        return None;
+    };
+    Some(format!("{hint}:{value}"))
+}
+
+/// A single-column foreign key: `column` holds values of
+/// `foreign_table.foreign_column`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ForeignKey {
+    pub column: String,
+    pub foreign_table: String,
+    pub foreign_column: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnInfo>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub foreign_keys: Vec<ForeignKey>,
+    /// Primary key columns in key order; `rowid` for a SQLite table that
+    /// declares none. Empty for views and backends that do not report keys.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub primary_key: Vec<String>,
+}
+
+impl TableSchema {
+    #[must_use]
+    pub fn has_column(&self, column: &str) -> bool {
+        self.columns.iter().any(|c| c.name == column)
+    }
+}
+
+/// Snapshot of the user tables and their columns in a database.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaCatalog {
+    tables: BTreeMap<String, TableSchema>,
+    /// Full-text indexes and the columns each covers.
+    fts_tables: BTreeMap<String, Vec<String>>,
+}
+
+impl SchemaCatalog {
+    /// Build a catalog from tables introspected elsewhere, e.g. another backend.
+    #[must_use]
+    pub fn from_tables(tables: impl IntoIterator<Item = TableSchema>) -> Self {
+        Self {
+            tables: tables
+                .into_iter()
+                .map(|table| (table.name.clone(), table))
+                .collect(),
+            fts_tables: BTreeMap::new(),
+        }
+    }
+
+    /// Introspect every ordinary table and view via `pragma_table_list`, `PRAGMA
+    /// table_info` and `PRAGMA foreign_key_list`.
+    ///
+    /// A table's primary key is the one it declares, or else its implicit
+    /// `rowid` unless a column of that name hides it.
+    ///
+    /// Virtual tables are recorded as full-text indexes; their shadow tables,
+    /// SQLite internals and [`MATERIALIZED_PREFIX`] cache tables are skipped.
+    ///
+    /// # Errors
+    /// Returns `rusqlite::Error` if introspection fails.
+    pub fn load(conn: &Connection) -> Result<Self, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT name, type FROM pragma_table_list WHERE schema = 'main' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?;
+        let listed = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut table_names = Vec::new();
+        let mut fts_tables = BTreeMap::new();
+        for (name, kind) in listed {
+            if name.starts_with(MATERIALIZED_PREFIX) {
+                continue;
+            }
+            match kind.as_str() {
+                "table" | "view" => table_names.push((name, kind == "table")),
+                "virtual" => {
+                    let mut pragma = conn.prepare("SELECT name FROM pragma_table_info(?)")?;
+                    let columns = pragma
+                        .query_map([&name], |row| row.get(0))?
+                        .collect::<Result<Vec<String>, _>>()?;
+                    fts_tables.insert(name, columns);
+                }
+                _ => {}
+            }
+        }
+
+        let mut tables = BTreeMap::new();
+        for (name, is_table) in table_names {
+            let mut pragma = conn.prepare("SELECT name, type FROM pragma_table_info(?)")?;
+            let columns = pragma
+                .query_map([&name], |row| {
+                    let name: String = row.get(0)?;
+                    let col_type: String = row.get(1)?;
+                    Ok(ColumnInfo {
+                        boolean: looks_boolean(&name, &col_type),
+                        privacy: Privacy::Public,
+                        name,
+                        col_type: if col_type.is_empty() {
+                            "TEXT".to_string()
+                        } else {
+                            col_type
+                        },
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // A key without a target column references the parent's primary key.
+            let mut pragma = conn.prepare(
+                "SELECT fk.\"from\", fk.\"table\", COALESCE(fk.\"to\",
+                     (SELECT name FROM pragma_table_info(fk.\"table\") WHERE pk = 1))
+                 FROM pragma_foreign_key_list(?) AS fk ORDER BY fk.id",
+            )?;
+            let foreign_keys = pragma
+                .query_map([&name], |row| {
+                    Ok(ForeignKey {
+                        column: row.get(0)?,
+                        foreign_table: row.get(1)?,
+                        foreign_column: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut pragma =
+                conn.prepare("SELECT name FROM pragma_table_info(?) WHERE pk > 0 ORDER BY pk")?;
+            let mut primary_key = pragma
+                .query_map([&name], |row| row.get(0))?
+                .collect::<Result<Vec<String>, _>>()?;
+            let rowid_hidden = columns
+                .iter()
+                .any(|column| column.name.eq_ignore_ascii_case("rowid"));
+            if primary_key.is_empty() && is_table && !rowid_hidden {
+                primary_key.push("rowid".to_string());
+            }
+
+            tables.insert(
+                name.clone(),
+                TableSchema {
+                    name,
+                    columns,
+                    foreign_keys,
+                    primary_key,
+                },
+            );
+        }
+
+        Ok(Self { tables, fts_tables })
+    }
+
+    #[must_use]
+    pub fn table(&self, name: &str) -> Option<&TableSchema> {
+        self.tables.get(name)
+    }
+
+    pub fn tables(&self) -> impl Iterator<Item = &TableSchema> {
+        self.tables.values()
+    }
+
+    /// Flag a column as boolean regardless of its name or declared type.
+    pub fn mark_boolean(&mut self, table: &str, column: &str) {
+        if let Some(info) = self
+            .tables
+            .get_mut(table)
+            .and_then(|schema| schema.columns.iter_mut().find(|c| c.name == column))
+        {
+            info.boolean = true;
+        }
+    }
+
+    #[must_use]
+    pub fn is_boolean(&self, table: &str, column: &str) -> bool {
+        self.column(table, column).is_some_and(|info| info.boolean)
+    }
+
+    /// Classify a column; columns are [`Privacy::Public`] until marked.
+    pub fn mark_privacy(&mut self, table: &str, column: &str, privacy: Privacy) {
+        if let Some(info) = self
+            .tables
+            .get_mut(table)
+            .and_then(|schema| schema.columns.iter_mut().find(|c| c.name == column))
+        {
+            info.privacy = privacy;
+        }
+    }
+
+    #[must_use]
+    pub fn privacy(&self, table: &str, column: &str) -> Privacy {
+        self.column(table, column)
+            .map_or(Privacy::Public, |info| info.privacy)
+    }
+
+    fn column(&self, table: &str, column: &str) -> Option<&ColumnInfo> {
+        self.table(table)
+            .and_then(|schema| schema.columns.iter().find(|c| c.name == column))
+    }
+
+    /// Turn 0/1 values in boolean columns into JSON `true`/`false`.
+    ///
+    /// Result keys are resolved against the base table first, then joined
+    /// tables; `ref.column` keys produced by collision aliasing are resolved
+    /// through the join's table or alias.
+    pub fn coerce_booleans(&self, config: &QueryConfig, rows: &mut [RowData]) {
+        let Some(first) = rows.first() else {
+            return;
+        };
+
+        let boolean_keys: Vec<String> = first
+            .keys()
+            .filter(|key| self.is_boolean_output(config, key))
+            .cloned()
+            .collect();
+        if boolean_keys.is_empty() {
+            return;
+        }
+
+        for row in rows.iter_mut() {
+            for key in &boolean_keys {
+                if let Some(value) = row.get_mut(key) {
+                    match value.as_i64() {
+                        Some(0) => *value = JsonValue::Bool(false),
+                        Some(1) => *value = JsonValue::Bool(true),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether the result key `key` of `config` holds a boolean column,
+    /// resolved as in [`Self::coerce_booleans`].
+    #[must_use]
+    pub fn is_boolean_output(&self, config: &QueryConfig, key: &str) -> bool {
+        self.output_column(config, key)
+            .is_some_and(|(table, column)| self.is_boolean(table, column))
+    }
+
+    /// The privacy of the column behind the result key `key` of `config`;
+    /// computed columns count as public.
+    #[must_use]
+    pub fn output_privacy(&self, config: &QueryConfig, key: &str) -> Privacy {
+        self.output_column(config, key)
+            .map_or(Privacy::Public, |(table, column)| {
+                self.privacy(table, column)
+            })
+    }
+
+    /// The table and column the result key `key` of `config` reads, or `None`
+    /// for computed columns.
+    ///
+    /// Result keys are resolved against the base table first, then joined
+    /// tables; `ref.column` keys produced by collision aliasing are resolved
+    /// through the join's table or alias.
+    fn output_column<'a>(
+        &'a self,
+        config: &'a QueryConfig,
+        key: &'a str,
+    ) -> Option<(&'a str, &'a str)> {
+        if config.expressions.iter().any(|e| e.alias() == key) {
+            return None;
+        }
+        if let Some((reference, column)) = key.split_once('.') {
+            let table = config
+                .joins
+                .iter()
+                .find(|join| join.reference() == reference)
+                .map_or(reference, |join| join.table.as_str());
+            return Some((table, column));
+        }
+
+        if let Some(qualifier) =
+            config
+                .columns
+                .iter()
+                .find_map(|column| match split_qualified(column) {
+                    (Some(qualifier), name) if name == key => Some(qualifier),
+                    _ => None,
+                })
+        {
+            let table = config
+                .joins
+                .iter()
+                .find(|join| join.reference() == qualifier)
+                .map_or(qualifier, |join| join.table.as_str());
+            return Some((table, key));
+        }
+
+        let selects = |table: &str, columns: &[String]| {
+            if columns.is_empty() {
+                self.table(table)
+                    .is_some_and(|schema| schema.has_column(key))
+            } else {
+                columns.iter().any(|column| column == key)
+            }
+        };
+
+        if selects(&config.table, &config.columns) {
+            return Some((&config.table, key));
+        }
+        config
+            .joins
+            .iter()
+            .find(|join| selects(&join.table, &join.columns))
+            .map(|join| (join.table.as_str(), key))
+    }
+
+    /// The foreign keys of `junction` that reference `parent` and `child`, in
+    /// that order. When both are the same table, two different keys are used.
+    #[must_use]
+    pub fn junction_keys(
+        &self,
+        junction: &str,
+        parent: &str,
+        child: &str,
+    ) -> Option<(&ForeignKey, &ForeignKey)> {
+        let keys = &self.table(junction)?.foreign_keys;
+        let to_parent = keys.iter().find(|key| key.foreign_table == parent)?;
+        let to_child = keys
+            .iter()
+            .find(|key| key.foreign_table == child && !std::ptr::eq(*key, to_parent))?;
+        Some((to_parent, to_child))
+    }
+
+    /// The columns joining `child` to `parent`, as `(parent column, child
+    /// column)`: from the first foreign key of `child` that references
+    /// `parent`, or else the first of `parent` that references `child`.
+    #[must_use]
+    pub fn foreign_key_columns(&self, parent: &str, child: &str) -> Option<(&str, &str)> {
+        let references = |from: &str, to: &str| {
+            self.table(from)?
+                .foreign_keys
+                .iter()
+                .find(|key| key.foreign_table == to)
+        };
+        if let Some(key) = references(child, parent) {
+            return Some((&key.foreign_column, &key.column));
+        }
+        references(parent, child).map(|key| (key.column.as_str(), key.foreign_column.as_str()))
+    }
+
+    /// Fill in the key columns of `through-` joins from the junction table's
+    /// foreign keys, and those of joins that name no columns from a foreign
+    /// key between the two tables. Joins whose keys cannot be found are left
+    /// as-is for [`validate`] to report.
+    #[must_use]
+    pub fn resolve_joins(&self, config: &QueryConfig) -> QueryConfig {
+        let mut resolved = config.clone();
+        let mut references = HashMap::from([(config.table.clone(), config.table.clone())]);
+        for join in &mut resolved.joins {
+            let parent = join
+                .parent_table
+                .as_ref()
+                .and_then(|parent| references.get(parent))
+                .unwrap_or(&config.table);
+            if let Some(through) = join.through.as_mut() {
+                if let Some((to_parent, to_child)) =
+                    self.junction_keys(&through.table, parent, &join.table)
+                {
+                    through.parent_column.clone_from(&to_parent.column);
+                    through.child_column.clone_from(&to_child.column);
+                    join.parent_column.clone_from(&to_parent.foreign_column);
+                    join.child_column.clone_from(&to_child.foreign_column);
+                }
+            } else if join.is_automatic() {
+                if let Some((parent_column, child_column)) =
+                    self.foreign_key_columns(parent, &join.table)
+                {
+                    join.parent_column = parent_column.to_string();
+                    join.child_column = child_column.to_string();
+                }
+            }
+            references.insert(join.reference().to_string(), join.table.clone());
+        }
+        resolved
+    }
+
+    /// Point a `search-` token, and those of any subqueries, at the table's
+    /// `<table>_fts` index, or at its text columns when no index exists.
+    #[must_use]
+    pub fn resolve_search(&self, config: &QueryConfig) -> QueryConfig {
+        self.resolve_search_visible(config, &|_, _| true)
+    }
+
+    /// [`Self::resolve_search`], matching only the columns `visible` allows
+    /// as `(table, column)`. An index covering any other column is passed
+    /// over for `LIKE`, since a match would reveal what that column holds.
+    #[must_use]
+    pub fn resolve_search_visible(
+        &self,
+        config: &QueryConfig,
+        visible: &dyn Fn(&str, &str) -> bool,
+    ) -> QueryConfig {
+        let mut resolved = config.clone();
+        for clause in &mut resolved.where_clauses {
+            if let Some(subquery) = clause.subquery.as_mut() {
+                **subquery = self.resolve_search_visible(subquery, visible);
+            }
+        }
+        let Some(search) = resolved.search.as_mut() else {
+            return resolved;
+        };
+
+        let table = config.table.as_str();
+        let fts_table = format!("{table}_fts");
+        let indexed = self.fts_tables.get(&fts_table);
+        if indexed.is_some_and(|columns| columns.iter().all(|column| visible(table, column))) {
+            search.fts_table = Some(fts_table);
+            return resolved;
+        }
+        if search.columns.is_empty() {
+            search.columns = self
+                .table(table)
+                .map(|schema| {
+                    schema
+                        .columns
+                        .iter()
+                        .filter(|c| is_text_type(&c.col_type))
+                        .map(|c| c.name.clone())
+                        .collect()
+                })
+                .unwrap_or_default();
+        }
+        search.columns.retain(|column| visible(table, column));
+
+        resolved
+    }
+
+    /// Select the primary key of `config`'s table, and of each table it
+    /// joins, as the row identity `__id`. A query reading any table without a
+    /// known key, or whose key includes a column that is not public, gets
+    /// none.
+    #[must_use]
+    pub fn identify_rows(&self, config: &QueryConfig) -> QueryConfig {
+        let mut identified = config.clone();
+        identified.identity.clear();
+        let key = |table: &str| {
+            self.table(table)
+                .map(|schema| &schema.primary_key)
+                .filter(|key| !key.is_empty())
+                .filter(|key| {
+                    key.iter()
+                        .all(|column| self.privacy(table, column) == Privacy::Public)
+                })
+        };
+        let Some(base) = key(&config.table) else {
+            return identified;
+        };
+        let mut identity = base.clone();
+        for join in &config.joins {
+            let Some(joined) = key(&join.table) else {
+                return identified;
+            };
+            identity.extend(
+                joined
+                    .iter()
+                    .map(|column| format!("{}.{column}", join.reference())),
+            );
+        }
+        identified.identity = identity;
+        identified
+    }
+
+    /// Give each where value the type of its column, as a type hint: `where-id-1`
+    /// binds an integer however the value would be inferred on its own, and
+    /// `where-zip-02134` or `where-code-42` on a text column stays text, as do
+    /// date keywords such as `today` on columns that do not hold dates. Values
+    /// that already have a hint, `contains` filters and columns the catalog
+    /// does not know are left as they are; subqueries are typed by their own
+    /// table.
+    #[must_use]
+    pub fn type_where_values(&self, config: &QueryConfig) -> QueryConfig {
+        let mut typed = config.clone();
+        let table = self.table(&config.table);
+        for clause in &mut typed.where_clauses {
+            if let Some(subquery) = clause.subquery.as_mut() {
+                **subquery = self.type_where_values(subquery);
+                continue;
+            }
+            if clause.operator == WhereOperator::Contains {
+                continue;
+            }
+            let column = table.and_then(|table| {
+                table
+                    .columns
+                    .iter()
+                    .find(|column| column.name == clause.field)
+            });
+            if let Some(value) = column.and_then(|column| typed_value(column, &clause.value)) {
+                clause.value = value;
+            }
+        }
+        typed
+    }
+
+    /// Replace `table.*` selections in a joined query with the table's concrete
+    /// columns so the builder can alias names that collide across tables.
+    ///
+    /// The base table's `*` is also expanded when a `case-` mapping selects one
+    /// of its columns, leaving that column to the mapping. Other queries
+    /// without joins, and tables missing from the catalog, are left as-is.
+    #[must_use]
+    pub fn expand_wildcards(&self, config: &QueryConfig) -> QueryConfig {
+        let mut expanded = config.clone();
+        let mapped: Vec<&str> = config.case_columns().collect();
+        if expanded.columns.is_empty() && !mapped.is_empty() {
+            expanded.columns = self.column_names(&expanded.table);
+            expanded
+                .columns
+                .retain(|column| !mapped.contains(&column.as_str()));
+        }
+        if expanded.joins.is_empty() {
+            return expanded;
+        }
+
+        if expanded.columns.is_empty() {
+            expanded.columns = self.column_names(&expanded.table);
+        }
+        for join in &mut expanded.joins {
+            if join.columns.is_empty() {
+                join.columns = self.column_names(&join.table);
+            }
+        }
+
+        expanded
+    }
+
+    fn column_names(&self, table: &str) -> Vec<String> {
+        self.table(table)
+            .map(|schema| schema.columns.iter().map(|c| c.name.clone()).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// The current [`SchemaCatalog`] of a database, swapped in whole by the
+/// background refresher so requests never introspect on their own.
+#[derive(Debug)]
+pub struct SchemaCache {
+    current: RwLock<Arc<SchemaCatalog>>,
+}
+
+impl SchemaCache {
+    #[must_use]
+    pub fn new(catalog: SchemaCatalog) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(catalog)),
+        }
+    }
+
+    /// The latest catalog. Hold on to it for the length of a request so every
+    /// check sees the same schema.
+    #[must_use]
+    pub fn current(&self) -> Arc<SchemaCatalog> {
+        // A writer cannot panic mid-swap, so a poisoned lock still holds a whole catalog.
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn replace(&self, catalog: SchemaCatalog) {
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(catalog);
+    }
+}
+
+/// Where in a query an unknown column was referenced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnUse {
+    Select,
+    Where,
+    OrderBy,
+    Search,
+    Window,
+    Expression,
+    JoinOn,
+    Insert,
+    Update,
+}
+
+impl fmt::Display for ColumnUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Select => "select list",
+            Self::Where => "where clause",
+            Self::OrderBy => "order by",
+            Self::Search => "search",
+            Self::Window => "window function",
+            Self::Expression => "expression",
+            Self::JoinOn => "join condition",
+            Self::Insert => "insert",
+            Self::Update => "update",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum ValidationError {
+    #[error("unknown table: {0}")]
+    UnknownTable(String),
+    #[error("unknown column {table}.{column} in {usage}")]
+    UnknownColumn {
+        table: String,
+        column: String,
+        usage: ColumnUse,
+    },
+    #[error("join references unknown table: {0}")]
+    UnknownJoinParent(String),
+    #[error("{junction} has no foreign keys linking {parent} to {table}")]
+    NoJunctionKeys {
+        junction: String,
+        parent: String,
+        table: String,
+    },
+    #[error("no foreign key links {parent} and {table}; name the join columns")]
+    NoForeignKey { parent: String, table: String },
+}
+
+/// Check every table and column `config` and its subqueries reference
+/// against `schema`.
+///
+/// All problems are collected rather than stopping at the first, so callers
+/// can report them together. Columns of unknown tables are not reported
+/// separately.
+#[must_use]
+pub fn validate(config: &QueryConfig, schema: &SchemaCatalog) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let mut check = |table: &str, column: &str, usage: ColumnUse| {
+        if let Some(info) = schema.table(table) {
+            if !info.has_column(column) {
+                errors.push(ValidationError::UnknownColumn {
+                    table: table.to_string(),
+                    column: column.to_string(),
+                    usage,
+                });
+            }
+        }
+    };
+
+    let base = config.table.as_str();
+    let references =
+        config
+            .joins
+            .iter()
+            .fold(HashMap::from([(base, base)]), |mut references, join| {
+                references.insert(join.reference(), join.table.as_str());
+                references
+            });
+    let mut qualifiers = Vec::new();
+    for column in &config.columns {
+        match split_qualified(column) {
+            (Some(qualifier), name) => match references.get(qualifier) {
+                Some(table) => check(table, name, ColumnUse::Select),
+                None => qualifiers.push(ValidationError::UnknownTable(qualifier.to_string())),
+            },
+            (None, name) => check(base, name, ColumnUse::Select),
+        }
+    }
+    let mut nested = Vec::new();
+    for clause in &config.where_clauses {
+        check(base, &clause.field, ColumnUse::Where);
+        if let Some(subquery) = &clause.subquery {
+            nested.extend(validate(subquery, schema));
+        }
+    }
+    if let Some(order_by) = &config.order_by {
+        check(base, &order_by.field, ColumnUse::OrderBy);
+    }
+    if let Some(search) = &config.search {
+        for column in &search.columns {
+            check(base, column, ColumnUse::Search);
+        }
+    }
+    for expression in &config.expressions {
+        let usage = match expression {
+            ColumnExpr::Window(_) => ColumnUse::Window,
+            ColumnExpr::Computed(_) => ColumnUse::Expression,
+            ColumnExpr::Case(_) | ColumnExpr::Json(_) => ColumnUse::Select,
+        };
+        for column in expression.columns() {
+            check(base, column, usage);
+        }
+    }
+
+    let mut joined: HashMap<&str, &str> = HashMap::from([(base, base)]);
+    let mut parents = Vec::new();
+    let mut unlinked = Vec::new();
+    for join in &config.joins {
+        let parent = match join.parent_table.as_deref() {
+            Some(parent) => match joined.get(parent) {
+                Some(table) => Some(*table),
+                None => {
+                    parents.push(ValidationError::UnknownJoinParent(parent.to_string()));
+                    None
+                }
+            },
+            None => Some(base),
+        };
+        match &join.through {
+            // The keys come from the junction's foreign keys, so only their
+            // existence needs checking.
+            Some(through) => {
+                let linked = parent.is_none_or(|parent| {
+                    schema.table(&through.table).is_none()
+                        || schema.table(&join.table).is_none()
+                        || schema
+                            .junction_keys(&through.table, parent, &join.table)
+                            .is_some()
+                });
+                if !linked {
+                    unlinked.push(ValidationError::NoJunctionKeys {
+                        junction: through.table.clone(),
+                        parent: parent.unwrap_or(base).to_string(),
+                        table: join.table.clone(),
+                    });
+                }
+            }
+            None if join.is_automatic() => {
+                let linked = parent.is_none_or(|parent| {
+                    schema.table(parent).is_none()
+                        || schema.table(&join.table).is_none()
+                        || schema.foreign_key_columns(parent, &join.table).is_some()
+                });
+                if !linked {
+                    unlinked.push(ValidationError::NoForeignKey {
+                        parent: parent.unwrap_or(base).to_string(),
+                        table: join.table.clone(),
+                    });
+                }
+            }
+            None => {
+                if let Some(parent) = parent {
+                    check(parent, &join.parent_column, ColumnUse::JoinOn);
+                }
+                check(&join.table, &join.child_column, ColumnUse::JoinOn);
+            }
+        }
+        for column in &join.columns {
+            check(&join.table, column, ColumnUse::Select);
+        }
+        joined.insert(join.reference(), join.table.as_str());
+    }
+
+    let tables = std::iter::once(base).chain(config.joins.iter().flat_map(|join| {
+        join.through
+            .iter()
+            .map(|through| through.table.as_str())
+            .chain([join.table.as_str()])
+    }));
+    let mut missing: Vec<ValidationError> = Vec::new();
+    for table in tables {
+        let error = ValidationError::UnknownTable(table.to_string());
+        if schema.table(table).is_none() && !missing.contains(&error) {
+            missing.push(error);
+        }
+    }
+
+    missing
+        .into_iter()
+        .chain(qualifiers)
+        .chain(parents)
+        .chain(unlinked)
+        .chain(errors)
+        .chain(nested)
+        .collect()
+}
+
+/// [`validate`] for a mutation: its table, every column it writes and every
+/// column it filters on.
+#[must_use]
+pub fn validate_mutation(config: &MutationConfig, schema: &SchemaCatalog) -> Vec<ValidationError> {
+    let Some(table) = schema.table(&config.table) else {
+        return vec![ValidationError::UnknownTable(config.table.clone())];
+    };
+    let written = match config.kind {
+        MutationKind::Insert => ColumnUse::Insert,
+        // Deletes set no values, so only their where clause is checked.
+        MutationKind::Update | MutationKind::Delete => ColumnUse::Update,
+    };
+    let values = config
+        .values
+        .iter()
+        .map(|value| (value.column.as_str(), written));
+    let filters = config
+        .where_clauses
+        .iter()
+        .map(|clause| (clause.field.as_str(), ColumnUse::Where));
+    values
+        .chain(filters)
+        .filter(|(column, _)| !table.has_column(column))
+        .map(|(column, usage)| ValidationError::UnknownColumn {
+            table: config.table.clone(),
+            column: column.to_string(),
+            usage,
+        })
+        .collect()
+}
@@ -0,0 +1,3250 @@
+//! The HTTP server: routes, handlers and the state they share.
+
+mod chaos;
+pub mod config;
+mod embed;
+mod live;
+pub mod logging;
+mod materialize;
+pub mod pages;
+mod privacy;
+mod proxy;
+mod request_id;
+mod result_cache;
+mod vocabulary;
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::env;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Bytes,
+    extract::{Extension, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+    middleware,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use futures_util::StreamExt;
+use serde::ser::{Error as _, SerializeMap, SerializeSeq};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::value::RawValue;
+use serde_json::Value as JsonValue;
+use tower_http::services::ServeDir;
+#[cfg(feature = "postgres")]
+use tracing::Instrument;
+use tracing::{error, info, info_span, warn};
+
+use self::chaos::Chaos;
+use self::embed::{FragmentSigner, Scope, ScopeToken, SignedUrl};
+use self::live::{LiveHub, LiveMetrics};
+use self::materialize::Materializations;
+use self::pages::{
+    AskamaPages, ExampleCard, ExplorerPage, ExplorerTable, IndexPage, PageError, PageQueries,
+    PageRenderer,
+};
+use self::privacy::{Audience, Redaction};
+use self::proxy::Proxy;
+use self::request_id::RequestId;
+use self::result_cache::{ResultCache, ResultCacheMetrics};
+
+use crate::advisor::{self, Advice};
+use crate::context::{Deadline, QueryContext};
+#[cfg(feature = "duckdb")]
+use crate::db::duckdb::DuckDatabase;
+use crate::db::mock::MockDatasource;
+#[cfg(feature = "postgres")]
+use crate::db::postgres::PgDatabase;
+use crate::db::sqlite::{PooledConnection, SqliteDatabase};
+#[cfg(feature = "postgres")]
+use crate::db::RowCollector;
+use crate::db::{
+    self, Backend, DbError, Execution, PoolMetrics, QueryResult, StatementCacheMetrics, WorkerPool,
+};
+#[cfg(feature = "postgres")]
+use crate::dialect::Postgres;
+use crate::dialect::SqlDialect;
+use crate::error::{Category, Error};
+use crate::history::{self, HistoryEntry, HistoryError, HistoryLog, ReplayReport};
+use crate::parser::tokens::{self, ClassAst, GrammarConfig, GrammarVersion, Step, Token};
+use crate::parser::{
+    config_with_join, grammar_warnings, parse_class_names, parse_class_names_strict_with,
+    parse_class_names_with, parse_join_params, parse_mutation, ColumnValue, JoinConfig,
+    MutationConfig, MutationKind, OrderBy, OrderDirection, QueryConfig, WhereClause, WhereOperator,
+};
+use crate::policy::{AccessPolicy, PolicyError};
+use crate::query_builder::{
+    build_count_query_for, build_explain, build_mutation, build_query_for, build_query_with,
+    build_view, BuiltQuery, ParamStyle, QueryBuilderError, QueryPolicy, ROW_ID,
+};
+use crate::render::{
+    render_error, render_results, render_results_with, tailwind, AvatarRenderer, Interactive,
+    Locale, Pager, RenderAs, RenderOptions, RowData,
+};
+use crate::schema::{
+    validate, validate_mutation, Privacy, SchemaCache, SchemaCatalog, TableSchema, ValidationError,
+};
+use crate::sql_diff::{diff_sql, ClauseDiff};
+use crate::subject::{erase_subject, export_subject, Erasure, ErasureReport, SubjectRows};
+
+const DEFAULT_HOST: &str = "0.0.0.0";
+const DEFAULT_PORT: u16 = 3000;
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+const MAX_JOINS: usize = 4;
+const MAX_ROWS: usize = 10_000;
+const MAX_RESULT_BYTES: usize = 64 * 1024 * 1024;
+const MAX_PAYLOAD_BYTES: usize = 16 * 1024 * 1024;
+const WAL_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+const SCHEMA_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const SCHEMA_REFRESH_SECONDS: u64 = 300;
+const QUERY_TIMEOUT_SECONDS: u64 = 30;
+const DEFAULT_SIGNED_TTL: u64 = 3600;
+
+#[derive(Clone)]
+pub(crate) struct AppState {
+    /// The database queries run against; an empty in-memory SQLite
+    /// placeholder when `postgres` is set.
+    backend: Arc<dyn Backend>,
+    /// `backend` again when it is SQLite, for rows streamed straight to JSON
+    /// and WAL maintenance.
+    sqlite: Option<Arc<SqliteDatabase>>,
+    /// Threads blocking database work runs on.
+    workers: Arc<WorkerPool>,
+    policy: Arc<AccessPolicy>,
+    /// Kept current by [`refresh_schemas`]; read it through [`Self::schema`].
+    schema: Arc<SchemaCache>,
+    limits: QueryPolicy,
+    /// How long a request's database work may take, from `QUERY_TIMEOUT`.
+    query_timeout: Option<Duration>,
+    /// This request's deadline, caller and limits; set with
+    /// [`Self::for_caller`], and the bare limits until then.
+    context: QueryContext,
+    strict: bool,
+    grammar: GrammarConfig,
+    /// Databases for class prefixes registered with their own URL, keyed by prefix.
+    datasources: Arc<HashMap<String, Datasource>>,
+    /// The primary database when `DATABASE_URL` names a PostgreSQL server.
+    #[cfg(feature = "postgres")]
+    postgres: Option<Arc<PgDatabase>>,
+    /// Where `/api/query` logs each query when `QUERY_HISTORY` is set.
+    history: Option<Arc<HistoryLog>>,
+    /// Cache tables for the primary database's `MATERIALIZE` queries.
+    materialized: Option<Arc<Materializations>>,
+    /// The admin token, and how columns classified private are redacted.
+    redaction: Arc<Redaction>,
+    /// Who this request's results are for; set with [`Self::for_caller`],
+    /// and the most restricted audience until then.
+    audience: Audience,
+    /// Signs fragment URLs when `FRAGMENT_SIGNING_KEY` is set, and then
+    /// requires unsigned fragment requests to come from an admin.
+    signer: Option<Arc<FragmentSigner>>,
+    /// Results kept for requests that opt in with `cache=1` or `cache:`.
+    results: Arc<ResultCache>,
+    /// Whether this request opted in; set by [`Self::routed`].
+    cache_results: bool,
+    /// Whether every read is cached, as when proxying a PostgreSQL
+    /// `PROXY_UPSTREAM`.
+    cache_reads: bool,
+    /// The instance requests are forwarded to, for an `http://`
+    /// `PROXY_UPSTREAM`.
+    proxy: Option<Arc<Proxy>>,
+    /// Renders the landing page and explorer.
+    pages: Arc<dyn PageRenderer>,
+    /// The `/api/live` subscriptions, shared by viewers of the same fragment.
+    live: Arc<LiveHub>,
+}
+
+#[derive(Clone)]
+struct Datasource {
+    db: Arc<SqliteDatabase>,
+    schema: Arc<SchemaCache>,
+}
+
+impl AppState {
+    /// State serving a single SQLite database under `policy`, with the
+    /// default grammar and limits from the environment as usual.
+    pub(crate) fn for_sqlite(db: SqliteDatabase, policy: AccessPolicy) -> Result<Self, AppError> {
+        let schema = load_schema(&db)?;
+        let query_timeout = db.query_timeout();
+        let limits = query_policy_from_env();
+        let db = Arc::new(db);
+        Ok(Self {
+            backend: db.clone(),
+            sqlite: Some(db),
+            workers: Arc::new(WorkerPool::new(2)?),
+            policy: Arc::new(policy),
+            schema: Arc::new(SchemaCache::new(schema)),
+            limits,
+            query_timeout,
+            context: QueryContext::new(limits),
+            strict: false,
+            grammar: GrammarConfig::default(),
+            datasources: Arc::new(HashMap::new()),
+            #[cfg(feature = "postgres")]
+            postgres: None,
+            history: None,
+            materialized: None,
+            redaction: Arc::new(Redaction::from_env()),
+            audience: Audience::Embed,
+            signer: FragmentSigner::from_env().map(Arc::new),
+            results: Arc::new(ResultCache::from_env()),
+            cache_results: false,
+            cache_reads: false,
+            proxy: Proxy::from_env()?.map(Arc::new),
+            pages: Arc::new(AskamaPages),
+            live: Arc::new(LiveHub::from_env()),
+        })
+    }
+
+    /// This state rendering its HTML pages with `pages`.
+    pub(crate) fn with_pages(self, pages: impl PageRenderer + 'static) -> Self {
+        Self {
+            pages: Arc::new(pages),
+            ..self
+        }
+    }
+
+    /// This state answering the caller `headers` identify, for `embed`ded
+    /// output or an API response, with `QUERY_TIMEOUT` from now to do it in.
+    fn for_caller(&self, headers: &HeaderMap, embed: bool) -> Self {
+        self.for_audience(self.redaction.audience(headers, embed))
+    }
+
+    /// This state answering `audience`, with `QUERY_TIMEOUT` from now to do
+    /// it in.
+    fn for_audience(&self, audience: Audience) -> Self {
+        let mut context = QueryContext::new(self.limits).with_principal(audience.name());
+        context.deadline = self.query_timeout.map(Deadline::after);
+        context.request_id = RequestId::current().map(|id| id.to_string());
+        Self {
+            audience,
+            context,
+            ..self.clone()
+        }
+    }
+
+    /// The grammar for this request, honouring a `grammar=` version override.
+    fn grammar_for(&self, params: &QueryParams) -> GrammarConfig {
+        match params.grammar {
+            Some(version) => self.grammar.clone().with_version(version),
+            None => self.grammar.clone(),
+        }
+    }
+
+    /// This state with its database and `schema` switched to the datasource registered
+    /// for the prefix of the requested class name, caching results if `params` ask to.
+    fn routed(&self, params: &QueryParams) -> Self {
+        let datasource = params.class_name.as_deref().and_then(|class_name| {
+            let prefix = self
+                .grammar
+                .match_prefix(first_class(&self.grammar, class_name))?;
+            self.datasources.get(prefix)
+        });
+        match datasource {
+            Some(datasource) => Self {
+                backend: datasource.db.clone(),
+                sqlite: Some(datasource.db.clone()),
+                schema: datasource.schema.clone(),
+                #[cfg(feature = "postgres")]
+                postgres: None,
+                materialized: None,
+                cache_results: params.cache || self.cache_reads,
+                ..self.clone()
+            },
+            None => Self {
+                cache_results: params.cache || self.cache_reads,
+                ..self.clone()
+            },
+        }
+    }
+
+    /// The current schema of whichever database this state queries.
+    fn schema(&self) -> Arc<SchemaCatalog> {
+        self.schema.current()
+    }
+
+    /// The dialect of whichever database this state queries.
+    fn dialect(&self) -> &dyn SqlDialect {
+        #[cfg(feature = "postgres")]
+        if self.postgres.is_some() {
+            return &Postgres;
+        }
+        self.backend.dialect()
+    }
+
+    /// `config`, or a read of its materialization when that is fresh.
+    fn materialized(&self, config: QueryConfig) -> QueryConfig {
+        self.materialized
+            .as_ref()
+            .and_then(|materialized| materialized.lookup(&config))
+            .unwrap_or(config)
+    }
+
+    /// Build `config` for whichever database this state queries.
+    fn build_query(&self, config: &QueryConfig) -> Result<BuiltQuery, QueryBuilderError> {
+        let _span = info_span!("build").entered();
+        build_query_for(self.dialect(), config, &self.context.limits)
+    }
+}
+
+/// [`Error`] plus the server's own startup failures.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error(transparent)]
+    Error(#[from] Error),
+    #[error("invalid query configuration")]
+    InvalidConfig,
+    #[error("UNIX_SOCKET {} exists and is not a socket", .0.display())]
+    NotASocket(PathBuf),
+    #[error(transparent)]
+    Page(#[from] PageError),
+    #[error("PROXY_UPSTREAM must be an http://host:port or postgres:// URL, not `{0}`")]
+    ProxyUpstream(String),
+    #[cfg(feature = "postgres")]
+    #[error("replay needs a SQLite, DuckDB or mock DATABASE_URL")]
+    ReplayTarget,
+    #[cfg(not(all(feature = "postgres", feature = "duckdb")))]
+    #[error("DATABASE_URL needs the `{0}` feature; rebuild with `--features {0}`")]
+    FeatureDisabled(&'static str),
+}
+
+macro_rules! app_error_from {
+    ($($source:ty),*) => {$(
+        impl From<$source> for AppError {
+            fn from(error: $source) -> Self {
+                Self::Error(error.into())
+            }
+        }
+    )*};
+}
+
+app_error_from!(
+    DbError,
+    rusqlite::Error,
+    std::io::Error,
+    serde_json::Error,
+    QueryBuilderError,
+    HistoryError
+);
+
+impl AppError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Error(error) => error.status(),
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn category(&self) -> Category {
+        match self {
+            Self::Error(error) => error.category(),
+            _ => Category::System,
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> axum::response::Response {
+        match self.category() {
+            Category::User => warn!("{self}"),
+            Category::System => error!("{self}"),
+        }
+        (self.status(), self.to_string()).into_response()
+    }
+}
+
+/// Open the databases named by the environment and serve on `HOST`/`PORT`,
+/// or `UNIX_SOCKET`, until the listener fails.
+///
+/// # Errors
+/// Returns `AppError` if a database cannot be opened or the listener fails.
+pub async fn run() -> Result<(), AppError> {
+    run_with_pages(AskamaPages).await
+}
+
+/// [`run`], rendering the HTML pages with `pages` instead of the bundled
+/// templates.
+///
+/// # Errors
+/// Returns `AppError` if a database cannot be opened or the listener fails.
+pub async fn run_with_pages(pages: impl PageRenderer + 'static) -> Result<(), AppError> {
+    let primary = open_primary().await?;
+    let (grammar, datasources) = grammar_from_env()?;
+    let limits = query_policy_from_env();
+    let state = AppState {
+        backend: primary.backend,
+        sqlite: primary.sqlite,
+        workers: Arc::new(worker_pool_from_env()?),
+        policy: Arc::new(access_policy_from_env()),
+        schema: Arc::new(SchemaCache::new(primary.schema)),
+        limits,
+        query_timeout: query_timeout_from_env(),
+        context: QueryContext::new(limits),
+        strict: env_flag("STRICT_PARSING"),
+        grammar,
+        datasources: Arc::new(datasources),
+        #[cfg(feature = "postgres")]
+        postgres: primary.postgres,
+        history: history_from_env()?,
+        materialized: None,
+        redaction: Arc::new(Redaction::from_env()),
+        audience: Audience::Embed,
+        signer: FragmentSigner::from_env().map(Arc::new),
+        results: Arc::new(ResultCache::from_env()),
+        cache_results: false,
+        cache_reads: db::is_postgres_url(&env::var("PROXY_UPSTREAM").unwrap_or_default()),
+        proxy: Proxy::from_env()?.map(Arc::new),
+        pages: Arc::new(pages),
+        live: Arc::new(LiveHub::from_env()),
+    };
+    let state = AppState {
+        materialized: Materializations::from_env(&state).map(Arc::new),
+        ..state
+    };
+
+    if let (Some(writer), Some(sqlite)) = (primary.writer, state.sqlite.clone()) {
+        if writer.lock.is_some() {
+            info!("Elected as database writer; running WAL maintenance");
+        }
+        tokio::spawn(run_maintenance(state.workers.clone(), sqlite, writer));
+    }
+    tokio::spawn(refresh_schemas(state.clone(), schema_refresh_from_env()));
+    if let (Some(materialized), Some(sqlite)) = (state.materialized.clone(), state.sqlite.clone()) {
+        tokio::spawn(materialize::refresh(
+            materialized,
+            state.workers.clone(),
+            sqlite,
+            state.limits,
+        ));
+    }
+
+    let mut app = routes(state);
+    if let Some(chaos) = Chaos::from_env() {
+        warn!("Injecting faults into /api/ requests: {}", chaos.describe());
+        app = app.layer(middleware::from_fn_with_state(
+            Arc::new(chaos),
+            chaos::inject,
+        ));
+    }
+    // Outermost, so injected faults carry a request ID too.
+    let app = app.layer(middleware::from_fn(request_id::assign));
+
+    #[cfg(unix)]
+    if let Ok(socket_path) = env::var("UNIX_SOCKET") {
+        let listener = bind_unix(std::path::Path::new(&socket_path))?;
+        info!("Listening on unix:{socket_path}");
+        return serve_unix(listener, app).await;
+    }
+
+    let host = env::var("HOST").unwrap_or_else(|_| DEFAULT_HOST.to_string());
+    let port = env::var("PORT")
+        .ok()
+        .and_then(|value| value.parse::<u16>().ok())
+        .unwrap_or(DEFAULT_PORT);
+
+    let listener = tokio::net::TcpListener::bind((host.as_str(), port)).await?;
+    info!("Listening on http://{host}:{port}");
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+/// Re-run the history at `path` against the database `DATABASE_URL` names,
+/// `concurrency` queries at a time, for `tailwindsql replay`.
+///
+/// # Errors
+/// Returns `AppError` if the history cannot be read or the database opened.
+pub async fn replay(path: &std::path::Path, concurrency: usize) -> Result<ReplayReport, AppError> {
+    let entries = history::read_history(path)?;
+    let primary = open_primary().await?;
+    #[cfg(feature = "postgres")]
+    if primary.postgres.is_some() {
+        return Err(AppError::ReplayTarget);
+    }
+    info!(
+        "Replaying {} queries, {concurrency} at a time",
+        entries.len()
+    );
+    Ok(history::replay(
+        primary.backend,
+        entries,
+        concurrency,
+        query_policy_from_env(),
+    )
+    .await?)
+}
+
+/// Every route, serving `state`.
+pub(crate) fn router(state: AppState) -> Router {
+    routes(state).layer(middleware::from_fn(request_id::assign))
+}
+
+fn routes(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(index_handler))
+        .route("/explorer", get(explorer_handler))
+        .route("/api/query", get(query_api_handler))
+        .route("/api/query/compare", get(compare_api_handler))
+        .route("/api/mutate", post(mutate_api_handler))
+        .route("/api/views", post(create_view_handler))
+        .route(
+            "/api/subjects/:table/:id",
+            get(export_subject_handler).delete(erase_subject_handler),
+        )
+        .route(
+            "/api/fragment",
+            get(fragment_handler)
+                .route_layer(middleware::from_fn_with_state(state.clone(), embed::verify)),
+        )
+        .route("/api/live", get(live_handler))
+        .route("/api/sign", get(sign_handler))
+        .route("/api/scope", get(scope_handler))
+        .route("/api/schema", get(schema_api_handler))
+        .route("/api/schema/:table", get(schema_table_handler))
+        .route("/api/vocabulary/:table", get(vocabulary_handler))
+        .route("/api/tokens", get(tokens_api_handler))
+        .route("/api/explain-class", get(explain_class_handler))
+        .route("/api/advise", get(advise_handler))
+        .route("/api/metrics", get(metrics_handler))
+        .route(
+            "/api/admin/log-level",
+            get(logging::current).put(logging::change),
+        )
+        .route("/static/tailwind.css", get(tailwind_css_handler))
+        .nest_service("/static", ServeDir::new("static"))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            proxy::forward,
+        ))
+        .with_state(state)
+}
+
+/// The primary database named by `DATABASE_URL`.
+///
+/// For PostgreSQL, `backend` is an empty in-memory SQLite placeholder and
+/// queries go to `postgres` instead.
+struct Primary {
+    backend: Arc<dyn Backend>,
+    sqlite: Option<Arc<SqliteDatabase>>,
+    schema: SchemaCatalog,
+    /// Set for a writable SQLite file, whose WAL one process checkpoints.
+    writer: Option<WriterElection>,
+    #[cfg(feature = "postgres")]
+    postgres: Option<Arc<PgDatabase>>,
+}
+
+async fn open_primary() -> Result<Primary, AppError> {
+    let url = database_url();
+    if db::is_postgres_url(&url) {
+        #[cfg(feature = "postgres")]
+        {
+            let (postgres, schema) = open_postgres(&url).await?;
+            return Ok(Primary {
+                backend: Arc::new(SqliteDatabase::open_in_memory()?),
+                sqlite: None,
+                schema,
+                writer: None,
+                postgres: Some(Arc::new(postgres)),
+            });
+        }
+        #[cfg(not(feature = "postgres"))]
+        return Err(AppError::FeatureDisabled("postgres"));
+    }
+    if let Some(path) = db::duckdb_path(&url) {
+        #[cfg(feature = "duckdb")]
+        {
+            let (duckdb, schema) = open_duckdb(&path)?;
+            return Ok(Primary {
+                backend: Arc::new(duckdb),
+                sqlite: None,
+                schema,
+                writer: None,
+                #[cfg(feature = "postgres")]
+                postgres: None,
+            });
+        }
+        #[cfg(not(feature = "duckdb"))]
+        {
+            let _ = path;
+            return Err(AppError::FeatureDisabled("duckdb"));
+        }
+    }
+    if let Some(path) = db::mock_path(&url) {
+        let mock = MockDatasource::from_json(&std::fs::read_to_string(&path)?)?;
+        info!("Database: mock tables from {path:?}");
+        let mut schema = mock.load_schema()?;
+        apply_column_settings(&mut schema);
+        return Ok(Primary {
+            backend: Arc::new(mock),
+            sqlite: None,
+            schema,
+            writer: None,
+            #[cfg(feature = "postgres")]
+            postgres: None,
+        });
+    }
+
+    let (sqlite, schema, writer) = open_sqlite()?;
+    let sqlite = Arc::new(sqlite);
+    Ok(Primary {
+        backend: sqlite.clone(),
+        sqlite: Some(sqlite),
+        schema,
+        writer,
+        #[cfg(feature = "postgres")]
+        postgres: None,
+    })
+}
+
+/// `DATABASE_URL`, or a PostgreSQL `PROXY_UPSTREAM` standing in for it.
+fn database_url() -> String {
+    env::var("PROXY_UPSTREAM")
+        .ok()
+        .filter(|url| db::is_postgres_url(url))
+        .or_else(|| env::var("DATABASE_URL").ok())
+        .unwrap_or_default()
+}
+
+/// Open (and seed, if new) the SQLite database named by `DATABASE_PATH` or
+/// `DATABASE_URL`.
+fn open_sqlite() -> Result<(SqliteDatabase, SchemaCatalog, Option<WriterElection>), AppError> {
+    let config = db::DbConfig::from_env()?;
+    let db_init = db::init_db(&config)?;
+    info!("Database path: {:?}", db_init.path);
+    if config.read_only {
+        info!("Database opened read-only");
+    }
+    if db_init.seeded {
+        info!("Database seeded on startup");
+    }
+    let sqlite = open_pool(db_init.connection, &config)?;
+    let schema = load_schema(&sqlite)?;
+    let writer = (!config.read_only && !config.is_memory()).then(|| WriterElection {
+        path: db_init.path.clone(),
+        lock: db_init.writer_lock,
+    });
+    Ok((sqlite, schema, writer))
+}
+
+/// `writer`, with `DB_READERS` read-only connections to the file `config`
+/// names for queries: as many as there are database workers by default,
+/// since only workers query. An in-memory database has only its writer.
+fn open_pool(
+    writer: rusqlite::Connection,
+    config: &db::DbConfig,
+) -> Result<SqliteDatabase, AppError> {
+    let count = if config.is_memory() {
+        0
+    } else {
+        env::var("DB_READERS")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or_else(worker_count)
+    };
+    let path = &config.path;
+    let readers = (0..count)
+        .map(|_| {
+            let reader = db::open_reader(path)?;
+            reader.busy_timeout(config.busy_timeout)?;
+            Ok(reader)
+        })
+        .collect::<Result<Vec<_>, DbError>>()?;
+    info!("Reading {path:?} through {count} connections");
+    let sqlite = SqliteDatabase::pooled(writer, readers);
+    Ok(match query_timeout_from_env() {
+        Some(timeout) => sqlite.with_query_timeout(timeout)?,
+        None => sqlite,
+    })
+}
+
+/// How long a SQLite read may run before it is cancelled, from
+/// `QUERY_TIMEOUT` (an interval such as `30s` or `2m`; `0` lifts it).
+fn query_timeout_from_env() -> Option<Duration> {
+    let seconds = match env::var("QUERY_TIMEOUT") {
+        Ok(value) => tokens::interval_seconds(value.trim()),
+        Err(_) => Some(QUERY_TIMEOUT_SECONDS),
+    };
+    seconds.map(Duration::from_secs)
+}
+
+/// Connect to the PostgreSQL server at `url`, seeding the demo tables if missing.
+#[cfg(feature = "postgres")]
+async fn open_postgres(url: &str) -> Result<(PgDatabase, SchemaCatalog), AppError> {
+    let mut postgres = PgDatabase::connect(url).await?;
+    info!("Database: PostgreSQL");
+    if postgres.seed_if_empty().await? {
+        info!("Database seeded on startup");
+    }
+    let mut schema = postgres.load_schema().await?;
+    apply_column_settings(&mut schema);
+    Ok((postgres, schema))
+}
+
+/// Open the DuckDB database at `path`, registering each `DATA_FILES` entry
+/// (`name=path`, comma-separated) as a view.
+#[cfg(feature = "duckdb")]
+fn open_duckdb(path: &std::path::Path) -> Result<(DuckDatabase, SchemaCatalog), AppError> {
+    let duckdb = DuckDatabase::open(path)?;
+    info!("Database: DuckDB at {path:?}");
+    for entry in env_list("DATA_FILES") {
+        match entry.split_once('=') {
+            Some((name, file)) => duckdb.register_file(name.trim(), file.trim().as_ref())?,
+            None => warn!("Ignoring data file {entry:?}; expected name=<path>"),
+        }
+    }
+    let mut schema = duckdb.load_schema()?;
+    apply_column_settings(&mut schema);
+    Ok((duckdb, schema))
+}
+
+/// Read a comma-separated list from an environment variable.
+fn env_list(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// The query history log named by `QUERY_HISTORY`, if set.
+fn history_from_env() -> Result<Option<Arc<HistoryLog>>, AppError> {
+    let Some(path) = env::var_os("QUERY_HISTORY") else {
+        return Ok(None);
+    };
+    info!("Recording query history to {path:?}");
+    Ok(Some(Arc::new(HistoryLog::open(path.as_ref())?)))
+}
+
+fn env_flag(name: &str) -> bool {
+    env::var(name).is_ok_and(|value| matches!(value.trim(), "1" | "true" | "yes"))
+}
+
+/// Build the table/column policy from `ALLOWED_TABLES`, `DENIED_TABLES`
+/// and `DENIED_COLUMNS` (comma-separated, columns as `table.column`), and
+/// enable writes when `ALLOW_WRITES` is set and deletes when
+/// `ALLOW_DESTRUCTIVE` is too.
+fn access_policy_from_env() -> AccessPolicy {
+    let mut policy = AccessPolicy::default()
+        .allow_writes(env_flag("ALLOW_WRITES"))
+        .allow_destructive(env_flag("ALLOW_DESTRUCTIVE"));
+    let allowed = env_list("ALLOWED_TABLES");
+    if !allowed.is_empty() {
+        policy = policy.allow_tables(allowed);
+    }
+    for table in env_list("DENIED_TABLES") {
+        policy = policy.deny_table(table);
+    }
+    for column in env_list("DENIED_COLUMNS") {
+        if let Some((table, column)) = column.split_once('.') {
+            policy = policy.deny_column(table, column);
+        }
+    }
+    policy
+}
+
+/// How often every schema is reloaded in full, from `SCHEMA_REFRESH_SECONDS`
+/// (`0` leaves only DDL detection).
+fn schema_refresh_from_env() -> Option<Duration> {
+    let seconds = env::var("SCHEMA_REFRESH_SECONDS")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(SCHEMA_REFRESH_SECONDS);
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
+/// Start the database worker pool with `DB_WORKERS` threads, one per CPU by default.
+fn worker_pool_from_env() -> Result<WorkerPool, AppError> {
+    let size = worker_count();
+    info!("Running database work on {size} worker threads");
+    Ok(WorkerPool::new(size)?)
+}
+
+fn worker_count() -> usize {
+    env::var("DB_WORKERS")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, usize::from))
+}
+
+/// Build query size limits from `DEFAULT_LIMIT`, `MAX_LIMIT`, `MAX_JOINS`,
+/// `MAX_ROWS`, `MAX_PAYLOAD_BYTES` and `MAX_RESULT_BYTES` (`0` lifts the
+/// corresponding limit), and
+/// the placeholder style from `PARAM_STYLE` (`named` or `positional`).
+fn query_policy_from_env() -> QueryPolicy {
+    let setting = |name: &str, default: usize| match env::var(name) {
+        Ok(value) => value.trim().parse().ok().filter(|value| *value > 0),
+        Err(_) => Some(default),
+    };
+    QueryPolicy {
+        default_limit: setting("DEFAULT_LIMIT", DEFAULT_LIMIT).and_then(|l| i64::try_from(l).ok()),
+        max_limit: setting("MAX_LIMIT", MAX_LIMIT).and_then(|l| i64::try_from(l).ok()),
+        max_joins: setting("MAX_JOINS", MAX_JOINS),
+        max_rows: setting("MAX_ROWS", MAX_ROWS),
+        max_result_bytes: setting("MAX_RESULT_BYTES", MAX_RESULT_BYTES),
+        max_payload_bytes: setting("MAX_PAYLOAD_BYTES", MAX_PAYLOAD_BYTES),
+        param_style: match env::var("PARAM_STYLE").as_deref().map(str::trim) {
+            Ok("named") => ParamStyle::Named,
+            _ => ParamStyle::Positional,
+        },
+    }
+}
+
+/// Load a schema catalog, applying `BOOLEAN_COLUMNS`, `INTERNAL_COLUMNS` and
+/// `PII_COLUMNS` (`table.column`, comma-separated).
+fn load_schema(backend: &dyn Backend) -> Result<SchemaCatalog, AppError> {
+    let mut schema = backend.load_schema()?;
+    apply_column_settings(&mut schema);
+    Ok(schema)
+}
+
+fn apply_column_settings(schema: &mut SchemaCatalog) {
+    for column in env_list("BOOLEAN_COLUMNS") {
+        if let Some((table, column)) = column.split_once('.') {
+            schema.mark_boolean(table, column);
+        }
+    }
+    for (name, privacy) in [
+        ("INTERNAL_COLUMNS", Privacy::Internal),
+        ("PII_COLUMNS", Privacy::Pii),
+    ] {
+        for column in env_list(name) {
+            if let Some((table, column)) = column.split_once('.') {
+                schema.mark_privacy(table, column, privacy);
+            }
+        }
+    }
+}
+
+/// Build the grammar from `GRAMMAR_VERSION`, `CLASS_SNIPPETS`, `CLASS_VARIABLES`,
+/// `CLASS_ALIASES` and `CLASS_PREFIXES`.
+///
+/// `CLASS_ALIASES` is a comma-separated list of `alias=target` pairs such as
+/// `sort=orderby,top=limit,eq=eq`. `CLASS_SNIPPETS` holds `name=segments`
+/// pairs such as `active-users=where-status-active-limit-50`, and
+/// `CLASS_VARIABLES` the `NAME=value` constants behind `$NAME` where values.
+///
+/// `CLASS_PREFIXES` is a comma-separated list of `prefix` or
+/// `prefix=<database url>` entries, e.g. `db,q,sql=sqlite:///data/reports.db`.
+/// Bare prefixes query the primary database; the rest get their own datasource.
+fn grammar_from_env() -> Result<(GrammarConfig, HashMap<String, Datasource>), AppError> {
+    let mut grammar = GrammarConfig::default().with_version(
+        env::var("GRAMMAR_VERSION")
+            .ok()
+            .and_then(|value| GrammarVersion::parse(&value))
+            .unwrap_or_default(),
+    );
+    for snippet in env_list("CLASS_SNIPPETS") {
+        match snippet.split_once('=') {
+            Some((name, body)) => grammar = grammar.with_snippet(name.trim(), body.trim()),
+            None => warn!("Ignoring class snippet {snippet:?}; expected name=<class segments>"),
+        }
+    }
+    for variable in env_list("CLASS_VARIABLES") {
+        match variable.split_once('=') {
+            Some((name, value)) => grammar = grammar.with_variable(name.trim(), value.trim()),
+            None => warn!("Ignoring class variable {variable:?}; expected NAME=value"),
+        }
+    }
+    for alias in env_list("CLASS_ALIASES") {
+        match alias.split_once('=') {
+            Some((alias, target)) if tokens::ALIAS_TARGETS.contains(&target.trim()) => {
+                grammar = grammar.with_alias(alias.trim(), target.trim());
+            }
+            _ => warn!("Ignoring class alias {alias:?}; expected alias=<keyword or operator>"),
+        }
+    }
+    let entries = env_list("CLASS_PREFIXES");
+    if entries.is_empty() {
+        return Ok((grammar, HashMap::new()));
+    }
+
+    let mut prefixes = Vec::new();
+    let mut datasources = HashMap::new();
+    for entry in entries {
+        let (prefix, url) = match entry.split_once('=') {
+            Some((prefix, url)) => (prefix.trim().to_string(), Some(url)),
+            None => (entry, None),
+        };
+        if let Some(path) = url.and_then(db::parse_database_url) {
+            let sqlite = open_pool(db::open_database(&path)?, &db::DbConfig::new(&path))?;
+            info!("Class prefix {prefix}- queries {path:?}");
+            datasources.insert(
+                prefix.clone(),
+                Datasource {
+                    schema: Arc::new(SchemaCache::new(load_schema(&sqlite)?)),
+                    db: Arc::new(sqlite),
+                },
+            );
+        }
+        prefixes.push(prefix);
+    }
+    grammar = grammar.with_prefixes(prefixes);
+    Ok((grammar, datasources))
+}
+
+/// Listen on a Unix socket at `path`, replacing a socket left there by an
+/// earlier run. Anything else at `path` is left alone.
+///
+/// # Errors
+/// Returns `AppError::NotASocket` if `path` exists and is not a socket, or
+/// the I/O error from removing the old socket or binding the new one.
+#[cfg(unix)]
+pub(crate) fn bind_unix(path: &std::path::Path) -> Result<tokio::net::UnixListener, AppError> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+        Ok(_) => return Err(AppError::NotASocket(path.to_path_buf())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+    Ok(tokio::net::UnixListener::bind(path)?)
+}
+
+/// Serve `app` on every connection `listener` accepts, until accepting fails.
+#[cfg(unix)]
+pub(crate) async fn serve_unix(
+    listener: tokio::net::UnixListener,
+    app: Router,
+) -> Result<(), AppError> {
+    use hyper_util::rt::TokioIo;
+    use hyper_util::service::TowerToHyperService;
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            if let Err(err) = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                error!("unix socket connection error: {err}");
+            }
+        });
+    }
+}
+
+/// This process's standing in the writer election for a database file.
+struct WriterElection {
+    path: PathBuf,
+    /// Held while this process is the writer.
+    lock: Option<db::WriterLock>,
+}
+
+/// Checkpoint the WAL every [`WAL_CHECKPOINT_INTERVAL`] while this process
+/// is the writer; until then, stand for election again each time, so a
+/// surviving instance takes over when the writer exits.
+async fn run_maintenance(
+    workers: Arc<WorkerPool>,
+    sqlite: Arc<SqliteDatabase>,
+    mut writer: WriterElection,
+) {
+    let mut interval = tokio::time::interval(WAL_CHECKPOINT_INTERVAL);
+    loop {
+        interval.tick().await;
+        if writer.lock.is_none() {
+            match db::try_elect_writer(&writer.path) {
+                Ok(Some(lock)) => {
+                    info!("Elected as database writer; running WAL maintenance");
+                    writer.lock = Some(lock);
+                }
+                Ok(None) => continue,
+                Err(err) => {
+                    error!("Writer election failed: {err}");
+                    continue;
+                }
+            }
+        }
+        let checkpoint = with_writer(&workers, sqlite.clone(), |conn| {
+            Ok(db::checkpoint_wal(conn)?)
+        });
+        if let Err(err) = checkpoint.await {
+            error!("WAL checkpoint failed: {err}");
+        }
+    }
+}
+
+/// A database whose [`SchemaCache`] the refresher keeps current.
+struct SchemaSource {
+    backend: Arc<dyn Backend>,
+    cache: Arc<SchemaCache>,
+    /// The `schema_version` the cached catalog was loaded at.
+    version: Option<i64>,
+}
+
+/// Keep every cached schema current so requests never pay for introspection.
+///
+/// Backends with a schema version (SQLite) are polled every
+/// `SCHEMA_POLL_INTERVAL` and reloaded as soon as DDL bumps it; every database
+/// is also reloaded each `full_every`, which is all PostgreSQL gets.
+async fn refresh_schemas(state: AppState, full_every: Option<Duration>) {
+    #[cfg(feature = "postgres")]
+    let primary_is_backend = state.postgres.is_none();
+    #[cfg(not(feature = "postgres"))]
+    let primary_is_backend = true;
+
+    let mut sources = Vec::new();
+    let primary = primary_is_backend.then(|| (state.backend.clone(), state.schema.clone()));
+    let datasources = state.datasources.values().map(|datasource| {
+        (
+            datasource.db.clone() as Arc<dyn Backend>,
+            datasource.schema.clone(),
+        )
+    });
+    for (backend, cache) in primary.into_iter().chain(datasources) {
+        let reader = backend.clone();
+        let version = state
+            .workers
+            .run(move || reader.schema_version())
+            .await
+            .and_then(|version| version)
+            .unwrap_or_else(|err| {
+                warn!("Reading schema version failed: {err}");
+                None
+            });
+        sources.push(SchemaSource {
+            backend,
+            cache,
+            version,
+        });
+    }
+
+    let mut interval = tokio::time::interval(SCHEMA_POLL_INTERVAL);
+    let mut last_full = tokio::time::Instant::now();
+    loop {
+        interval.tick().await;
+        let full = full_every.is_some_and(|every| last_full.elapsed() >= every);
+        if full {
+            last_full = tokio::time::Instant::now();
+        }
+
+        for source in &mut sources {
+            let (backend, seen) = (source.backend.clone(), source.version);
+            let refreshed = state
+                .workers
+                .run(move || reload_if_changed(&*backend, seen, full))
+                .await
+                .map_err(AppError::from)
+                .and_then(|refreshed| refreshed);
+            match refreshed {
+                Ok((version, Some(schema))) => {
+                    if version != seen {
+                        info!(
+                            "Schema changed; reloaded {} tables",
+                            schema.tables().count()
+                        );
+                    }
+                    source.cache.replace(schema);
+                    source.version = version;
+                }
+                Ok((_, None)) => {}
+                Err(err) => warn!("Schema refresh failed: {err}"),
+            }
+        }
+
+        #[cfg(feature = "postgres")]
+        if let (true, Some(postgres)) = (full, &state.postgres) {
+            match postgres.load_schema().await {
+                Ok(mut schema) => {
+                    apply_column_settings(&mut schema);
+                    state.schema.replace(schema);
+                }
+                Err(err) => warn!("Schema refresh failed: {err}"),
+            }
+        }
+    }
+}
+
+/// Reload `backend`'s schema if its version moved past `seen`, or regardless
+/// when `full`. Returns the current version and the new catalog, if loaded.
+fn reload_if_changed(
+    backend: &dyn Backend,
+    seen: Option<i64>,
+    full: bool,
+) -> Result<(Option<i64>, Option<SchemaCatalog>), AppError> {
+    let version = backend.schema_version()?;
+    if !full && version == seen {
+        return Ok((version, None));
+    }
+    Ok((version, Some(load_schema(backend)?)))
+}
+
+async fn index_handler(State(state): State<AppState>) -> Result<Html<String>, AppError> {
+    let config = parse_class_names("db-users-name-where-id-1").ok_or(AppError::InvalidConfig)?;
+    let output = run_config(state.clone(), config).await?;
+    let hero_value = strip_tags(&render_results(
+        &output.rows,
+        &output.display_columns,
+        RenderAs::Span,
+    ));
+
+    let page = IndexPage {
+        hero_value,
+        examples: example_specs().into_iter().map(example_card).collect(),
+        queries: page_queries(&state),
+    };
+    // The template runs its queries as it renders, so it renders off the
+    // async workers; the queries still take turns on the database workers.
+    let pages = state.pages.clone();
+    let html = tokio::task::spawn_blocking(move || pages.index(&page))
+        .await
+        .map_err(|_| DbError::Join)??;
+    Ok(Html(html))
+}
+
+/// The queries pages embed, each rendered as the fragment endpoint would
+/// render it for the embed audience.
+fn page_queries(state: &AppState) -> PageQueries {
+    let (state, runtime) = (state.clone(), tokio::runtime::Handle::current());
+    PageQueries::new(move |class_name, attributes| {
+        runtime
+            .block_on(embedded_html(state.clone(), class_name, attributes))
+            .unwrap_or_else(|error| render_error(&error.to_string()))
+    })
+}
+
+/// `class_name` with the fragment params in `attributes`, rendered without
+/// interactive controls.
+async fn embedded_html(
+    state: AppState,
+    class_name: &str,
+    attributes: &str,
+) -> Result<String, AppError> {
+    let mut pairs: Vec<(String, String)> = serde_urlencoded::from_str(attributes)
+        .map_err(|error| Error::Request(format!("Invalid attributes `{attributes}`: {error}")))?;
+    pairs.push(("className".to_string(), class_name.to_string()));
+    let params = QueryParams::from_pairs(pairs);
+    let state = state.routed(&params);
+    let config = resolve_config(&state, &params)?;
+    let (render_as, options) = fragment_options(&params, &HeaderMap::new());
+    let output = run_config(state, config).await?;
+    Ok(render_results_with(
+        &output.rows,
+        &output.display_columns,
+        render_as,
+        &options,
+    ))
+}
+
+/// CSS for the Tailwind utilities fragments use, for host pages that embed
+/// them without loading Tailwind.
+async fn tailwind_css_handler() -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "text/css; charset=utf-8"),
+            (header::CACHE_CONTROL, "public, max-age=86400"),
+        ],
+        tailwind::fragment_stylesheet(),
+    )
+}
+
+async fn explorer_handler(State(state): State<AppState>) -> Result<Html<String>, AppError> {
+    let page = ExplorerPage {
+        tables: state
+            .schema()
+            .tables()
+            .map(|table| ExplorerTable {
+                name: table.name.clone(),
+                columns: catalog_columns(table),
+            })
+            .collect(),
+        writable: state.policy.writes_allowed(),
+    };
+    Ok(Html(state.pages.explorer(&page)?))
+}
+
+#[derive(Default, Clone)]
+struct QueryParams {
+    class_name: Option<String>,
+    joins: Vec<JoinConfig>,
+    render_as: Option<String>,
+    relative: Vec<String>,
+    avatar: Vec<String>,
+    pin: bool,
+    max_columns: Option<usize>,
+    sort: Option<String>,
+    /// `dir` for `sort`, ascending unless it reads `desc`.
+    sort_direction: Option<OrderDirection>,
+    refresh: Option<u64>,
+    strict: Option<bool>,
+    grammar: Option<GrammarVersion>,
+    show_filters: bool,
+    /// `returning=1` on `/api/mutate`: respond with the rows a write changed.
+    returning: bool,
+    /// 1-based page of `limit` rows to show, for pagers.
+    page: Option<u64>,
+    /// `total=1`: report pagination metadata even without `page`.
+    total: bool,
+    /// `explain=1` on `/api/query`: return the query plan instead of rows.
+    explain: bool,
+    /// `name=` on `/api/views`: the view to save the query as.
+    view_name: Option<String>,
+    /// `sig=` on `/api/fragment`: a signed URL, which sorting or filtering
+    /// would no longer match.
+    signed: bool,
+    /// `cache=1`, or a `cache:` class name: serve results from the result cache.
+    cache: bool,
+    /// `filter.<column>` values from an interactive table's filter row.
+    filters: BTreeMap<String, String>,
+    /// Every pair except `sort`/`dir`/`filter.*`, to rebuild the URL of this view.
+    view_pairs: Vec<(String, String)>,
+}
+
+impl QueryParams {
+    /// Collect params from raw pairs so `join` may repeat.
+    fn from_pairs(pairs: Vec<(String, String)>) -> Self {
+        let mut params = Self::default();
+        for (key, value) in pairs {
+            if let Some(column) = key.strip_prefix("filter.") {
+                if !value.trim().is_empty() {
+                    params.filters.insert(column.to_string(), value);
+                }
+                continue;
+            }
+            if !matches!(key.as_str(), "sort" | "dir") {
+                params.view_pairs.push((key.clone(), value.clone()));
+            }
+            match key.as_str() {
+                "className" => match value.strip_prefix("cache:") {
+                    Some(class_name) => {
+                        params.cache = true;
+                        params.class_name = Some(class_name.to_string());
+                    }
+                    None => params.class_name = Some(value),
+                },
+                "cache" => params.cache = matches!(value.as_str(), "1" | "true"),
+                "join" => params.joins.extend(parse_join_params(&value)),
+                "as" => params.render_as = Some(value),
+                "relative" => params.relative.extend(split_columns(&value)),
+                "avatar" => params.avatar.extend(split_columns(&value)),
+                "pin" => params.pin = matches!(value.as_str(), "1" | "true" | "first"),
+                "maxColumns" => params.max_columns = value.parse().ok(),
+                "sort" => params.sort = Some(value),
+                "dir" => params.sort_direction = OrderDirection::parse(&value),
+                "refresh" => params.refresh = value.parse().ok().filter(|s| *s > 0),
+                "strict" => params.strict = Some(matches!(value.as_str(), "1" | "true")),
+                "grammar" => params.grammar = GrammarVersion::parse(&value),
+                "filters" => params.show_filters = matches!(value.as_str(), "1" | "true"),
+                "returning" => params.returning = matches!(value.as_str(), "1" | "true"),
+                "page" => params.page = value.parse().ok().filter(|page| *page > 0),
+                "total" => params.total = matches!(value.as_str(), "1" | "true"),
+                "explain" => params.explain = matches!(value.as_str(), "1" | "true"),
+                "name" => params.view_name = Some(value),
+                "sig" => params.signed = true,
+                _ => {}
+            }
+        }
+        params
+    }
+
+    /// Sort requested by an interactive table header, overriding `orderby-`.
+    fn order_by(&self) -> Option<OrderBy> {
+        self.sort.as_ref().map(|field| OrderBy {
+            field: field.clone(),
+            direction: self.sort_direction.unwrap_or(OrderDirection::Asc),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct QueryResponse {
+    success: bool,
+    query: String,
+    params: Vec<JsonValue>,
+    results: Box<RawValue>,
+    count: usize,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+    /// The rows kept when `truncated` is set, by the row cap or the payload cap.
+    #[serde(rename = "rowCap", skip_serializing_if = "Option::is_none")]
+    row_cap: Option<usize>,
+    #[serde(flatten)]
+    pagination: Option<Pagination>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+}
+
+/// Pager metadata for `page=` or `total=1` requests.
+#[derive(Serialize)]
+struct Pagination {
+    /// Rows matching the query's filters, ignoring its limit.
+    total: u64,
+    /// 1-based page shown.
+    page: u64,
+    #[serde(rename = "pageCount")]
+    page_count: u64,
+}
+
+/// `/api/query?explain=1`: the SQL that would run and the plan the database
+/// chose for it.
+#[derive(Serialize)]
+struct PlanResponse {
+    success: bool,
+    query: String,
+    params: Vec<JsonValue>,
+    plan: Vec<RowData>,
+}
+
+#[derive(Serialize)]
+struct AdviseResponse {
+    success: bool,
+    query: String,
+    /// The `detail` line of each step of the query plan.
+    plan: Vec<String>,
+    #[serde(flatten)]
+    advice: Advice,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+    #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+    request_id: Option<RequestId>,
+}
+
+impl ErrorResponse {
+    /// `error`, tagged with the ID of the request being handled.
+    fn new(error: impl ToString) -> Self {
+        Self {
+            error: error.to_string(),
+            request_id: RequestId::current(),
+        }
+    }
+}
+
+fn split_columns(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(ToString::to_string)
+}
+
+/// The first class with a registered prefix in a whitespace-separated list,
+/// or the whole input.
+fn first_class<'a>(grammar: &GrammarConfig, class_names: &'a str) -> &'a str {
+    class_names
+        .split_whitespace()
+        .find(|class| tokens::tokenize_with(class, grammar).is_some())
+        .unwrap_or(class_names)
+}
+
+/// Grammar deprecation notes for the requested class name, if any.
+fn grammar_notes(state: &AppState, params: &QueryParams) -> Vec<String> {
+    params
+        .class_name
+        .as_deref()
+        .map_or_else(Vec::new, |class_name| {
+            let grammar = state.grammar_for(params);
+            grammar_warnings(first_class(&grammar, class_name), &grammar)
+        })
+}
+
+/// Parse the class name and joins from request params, enforce the access
+/// policy, and resolve schema-dependent parts of the config.
+fn resolve_config(state: &AppState, params: &QueryParams) -> Result<QueryConfig, Error> {
+    let _span = info_span!("parse").entered();
+    let Some(class_name) = params.class_name.as_deref() else {
+        return Err(Error::Request("Missing className parameter".to_string()));
+    };
+
+    let grammar = state.grammar_for(params);
+    let schema = state.schema();
+    let config = if params.strict.unwrap_or(state.strict) {
+        parse_class_names_strict_with(class_name, &grammar, |table, column| {
+            schema
+                .table(table)
+                .is_some_and(|schema| schema.has_column(column))
+        })?
+    } else {
+        parse_class_names_with(class_name, &grammar)
+            .ok_or_else(|| Error::Request(format!("Invalid TailwindSQL class: {class_name}")))?
+    };
+
+    let mut config = params.joins.iter().cloned().fold(config, config_with_join);
+    if let Some(order_by) = params.order_by() {
+        config.order_by = Some(order_by);
+    }
+    if let Some(page) = params.page {
+        let skipped_pages = i64::try_from(page - 1).unwrap_or(i64::MAX);
+        config.offset = state
+            .context
+            .limits
+            .effective_limit(config.limit)
+            .map(|size| size.saturating_mul(skipped_pages));
+    }
+    config
+        .where_clauses
+        .extend(params.filters.iter().map(|(column, value)| WhereClause {
+            field: column.clone(),
+            operator: WhereOperator::Contains,
+            value: value.clone(),
+            subquery: None,
+        }));
+
+    state.policy.check(&config)?;
+    let errors = validate(&config, &schema);
+    if !errors.is_empty() {
+        return Err(Error::Invalid(errors));
+    }
+
+    let config = schema.resolve_joins(&config);
+    privacy::check(&schema, &config, state.audience)?;
+    let visible = |table: &str, column: &str| {
+        state.policy.is_column_allowed(table, column)
+            && !state.audience.hides(schema.privacy(table, column))
+    };
+    let config = schema.resolve_search_visible(&schema.expand_wildcards(&config), &visible);
+    // Join keys taken from foreign keys and `through-` tables are only known now.
+    state.policy.check(&config)?;
+    Ok(schema.identify_rows(&schema.type_where_values(&config)))
+}
+
+async fn query_api_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> axum::response::Response {
+    let failed =
+        |error: AppError| (error.status(), Json(ErrorResponse::new(error))).into_response();
+    let params = QueryParams::from_pairs(pairs);
+    let state = state.routed(&params).for_caller(&headers, false);
+    let config = match resolve_config(&state, &params) {
+        Ok(config) => config,
+        Err(error) => return failed(error.into()),
+    };
+    request_id::record_query(&config);
+    let warnings = grammar_notes(&state, &params);
+
+    if params.explain {
+        return match run_explain(state, config).await {
+            Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+            Err(error) => failed(error),
+        };
+    }
+
+    let history = state
+        .history
+        .clone()
+        .map(|history| (history, config.to_class_name()));
+    let pager = (params.page.is_some() || params.total).then(|| (state.clone(), config.clone()));
+    let started = Instant::now();
+    match run_config_json(state, config).await {
+        Ok(result) => {
+            request_id::record_rows(result.count);
+            if let Some((history, class_name)) = history {
+                let entry = HistoryEntry {
+                    class_name,
+                    sql: result.sql.clone(),
+                    params: result.params.clone(),
+                    rows: result.count,
+                    elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+                };
+                if let Err(error) = history.record(&entry) {
+                    warn!("Could not record query history: {error}");
+                }
+            }
+            let pagination = match pager {
+                Some((state, config)) => {
+                    match paginate(state, config, params.page.unwrap_or(1)).await {
+                        Ok(pagination) => Some(pagination),
+                        Err(error) => return failed(error),
+                    }
+                }
+                None => None,
+            };
+            (
+                StatusCode::OK,
+                Json(QueryResponse {
+                    success: true,
+                    query: result.sql,
+                    params: result.params,
+                    results: result.results,
+                    count: result.count,
+                    truncated: result.truncated_at.is_some(),
+                    row_cap: result.truncated_at,
+                    pagination,
+                    warnings,
+                }),
+            )
+                .into_response()
+        }
+        Err(error) => failed(error),
+    }
+}
+
+/// Pagination metadata for `config` showing `page`: the rows it matches
+/// without its limit, and how many pages of its limit they fill.
+async fn paginate(state: AppState, config: QueryConfig, page: u64) -> Result<Pagination, AppError> {
+    let page_size = state
+        .context
+        .limits
+        .effective_limit(config.limit)
+        .and_then(|limit| u64::try_from(limit).ok())
+        .filter(|limit| *limit > 0);
+    let total = run_count(state, config).await?;
+    Ok(Pagination {
+        total,
+        page,
+        page_count: page_size.map_or(u64::from(total > 0), |size| total.div_ceil(size)),
+    })
+}
+
+/// JSON equivalent of an `ins-` or `upd-` class name for `/api/mutate`.
+///
+/// A body with `where` is an update of the rows matching every pair in it.
+#[derive(Deserialize)]
+struct MutationBody {
+    table: String,
+    values: serde_json::Map<String, JsonValue>,
+    #[serde(rename = "where")]
+    filter: Option<serde_json::Map<String, JsonValue>>,
+    #[serde(default)]
+    returning: bool,
+}
+
+impl MutationBody {
+    fn into_config(self) -> Result<MutationConfig, String> {
+        let (kind, where_clauses) = match self.filter {
+            Some(filter) => {
+                let clauses = hinted_values(filter)?
+                    .into_iter()
+                    .map(|pair| WhereClause {
+                        field: pair.column,
+                        operator: WhereOperator::Eq,
+                        value: pair.value,
+                        subquery: None,
+                    })
+                    .collect();
+                (MutationKind::Update, clauses)
+            }
+            None => (MutationKind::Insert, Vec::new()),
+        };
+        Ok(MutationConfig {
+            kind,
+            table: self.table,
+            values: hinted_values(self.values)?,
+            where_clauses,
+            returning: self.returning,
+        })
+    }
+}
+
+/// JSON values as type-hinted strings (`int:42`, `text:Ada`) so their types
+/// survive binding.
+fn hinted_values(values: serde_json::Map<String, JsonValue>) -> Result<Vec<ColumnValue>, String> {
+    values
+        .into_iter()
+        .map(|(column, value)| {
+            let value = match &value {
+                JsonValue::String(text) => format!("text:{text}"),
+                JsonValue::Number(number) if number.is_f64() => format!("real:{number}"),
+                JsonValue::Number(number) => format!("int:{number}"),
+                JsonValue::Bool(flag) => format!("bool:{flag}"),
+                _ => return Err(format!("unsupported value for column {column}: {value}")),
+            };
+            Ok(ColumnValue { column, value })
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct MutationResponse {
+    success: bool,
+    query: String,
+    params: Vec<JsonValue>,
+    #[serde(rename = "rowsAffected")]
+    rows_affected: u64,
+    /// Row id of an inserted row, where the database reports one.
+    id: Option<i64>,
+    /// The changed rows, for `returning=1` or a JSON body with `"returning": true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rows: Option<Vec<RowData>>,
+    /// The changed rows rendered like `/api/fragment`, for `as=<mode>`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html: Option<String>,
+}
+
+/// Insert, update or delete rows from an `ins-`/`upd-`/`del-` class name in
+/// `className`, or insert or update them from a JSON body of the form
+/// `{"table": "users", "values": {"name": "Ada"}}`, plus `"where": {"id": 1}`
+/// for an update.
+async fn mutate_api_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(pairs): Query<Vec<(String, String)>>,
+    body: Bytes,
+) -> axum::response::Response {
+    let error = |error: AppError| (error.status(), Json(ErrorResponse::new(error))).into_response();
+    let params = QueryParams::from_pairs(pairs);
+    let config = match params.class_name.as_deref() {
+        Some(class_name) => parse_mutation(class_name).map_err(Error::from),
+        None if body.is_empty() => Err(Error::Request(
+            "Missing className parameter or JSON body".to_string(),
+        )),
+        None => serde_json::from_slice::<MutationBody>(&body)
+            .map_err(|err| format!("Invalid mutation body: {err}"))
+            .and_then(MutationBody::into_config)
+            .map_err(Error::Request),
+    };
+    let mut config = match config {
+        Ok(config) => config,
+        Err(err) => return error(err.into()),
+    };
+    config.returning |= params.returning || params.render_as.is_some();
+
+    if let Err(err) = state.policy.check_mutation(&config) {
+        return error(Error::from(err).into());
+    }
+    let errors = validate_mutation(&config, &state.schema());
+    if !errors.is_empty() {
+        return error(Error::Invalid(errors).into());
+    }
+
+    let (kind, table) = (config.kind, config.table.clone());
+    let (schema, materialized) = (state.schema(), state.materialized.clone());
+    let results = state.results.clone();
+    let (redaction, audience) = (
+        state.redaction.clone(),
+        state.redaction.audience(&headers, false),
+    );
+    match run_mutation(state, config).await {
+        Ok(MutationOutput {
+            built,
+            execution,
+            returned,
+        }) => {
+            if let Some(materialized) = materialized {
+                materialized.invalidate(&table);
+            }
+            results.clear();
+            let returned = returned.map(|mut result| {
+                coerce_table_booleans(&schema, &table, &mut result.rows);
+                redaction.redact(audience, &mut result.rows, &mut result.columns, |column| {
+                    schema.privacy(&table, column)
+                });
+                result
+            });
+            let html =
+                params
+                    .render_as
+                    .as_deref()
+                    .zip(returned.as_ref())
+                    .map(|(render_as, result)| {
+                        render_results(&result.rows, &result.columns, RenderAs::parse(render_as))
+                    });
+            Json(MutationResponse {
+                success: true,
+                params: built.params.into_iter().map(db::value_to_json).collect(),
+                query: built.sql,
+                rows_affected: execution.rows_affected,
+                // Writes with `RETURNING` report the new id in the row instead.
+                id: execution
+                    .last_insert_id
+                    .or_else(|| {
+                        let row = returned.as_ref()?.rows.first()?;
+                        row.get("id")?.as_i64()
+                    })
+                    .filter(|_| kind == MutationKind::Insert),
+                rows: returned.map(|result| result.rows),
+                html,
+            })
+            .into_response()
+        }
+        Err(err) => error(err),
+    }
+}
+
+/// Turn 0/1 values in boolean columns of `table` into JSON booleans, for rows
+/// a write read back rather than a parsed query.
+fn coerce_table_booleans(schema: &SchemaCatalog, table: &str, rows: &mut [RowData]) {
+    for row in rows {
+        for (column, value) in row.iter_mut() {
+            if !schema.is_boolean(table, column) {
+                continue;
+            }
+            match value.as_i64() {
+                Some(0) => *value = JsonValue::Bool(false),
+                Some(1) => *value = JsonValue::Bool(true),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// What [`run_mutation`] ran and did, with the changed rows when
+/// `config.returning` asked for them.
+struct MutationOutput {
+    built: BuiltQuery,
+    execution: Execution,
+    returned: Option<QueryResult>,
+}
+
+/// Build and execute `config` against the primary database.
+async fn run_mutation(state: AppState, config: MutationConfig) -> Result<MutationOutput, AppError> {
+    let built = build_mutation(state.dialect(), &config, &state.context.limits)?;
+    #[cfg(feature = "postgres")]
+    if let Some(postgres) = state.postgres.clone() {
+        if !config.returning {
+            let execution = postgres.execute(&built.sql, &built.params).await?;
+            return Ok(MutationOutput {
+                built,
+                execution,
+                returned: None,
+            });
+        }
+        let mut rows = RowCollector::new(&state.context.limits);
+        let columns = postgres
+            .for_each_row(&built.sql, &built.params, |row| {
+                rows.push(row).map(|_| true)
+            })
+            .await?;
+        let execution = Execution {
+            rows_affected: rows.seen(),
+            last_insert_id: None,
+        };
+        return Ok(MutationOutput {
+            built,
+            execution,
+            returned: Some(rows.finish(columns)),
+        });
+    }
+
+    let (backend, limits) = (state.backend.clone(), state.context.limits);
+    state
+        .workers
+        .run(move || {
+            let (execution, returned) = if config.returning {
+                let (execution, rows) =
+                    backend.execute_returning(&built.sql, &built.params, &limits)?;
+                (execution, Some(rows))
+            } else {
+                (backend.execute(&built.sql, &built.params)?, None)
+            };
+            Ok(MutationOutput {
+                built,
+                execution,
+                returned,
+            })
+        })
+        .await?
+}
+
+#[derive(Serialize)]
+struct ViewResponse {
+    success: bool,
+    view: String,
+    query: String,
+}
+
+/// Save the query in `className` as a SQLite view called `name`, which class
+/// names can then read like any table (`db-<name>-...`). Needs
+/// `ALLOW_WRITES=1`.
+async fn create_view_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> axum::response::Response {
+    let failed =
+        |error: AppError| (error.status(), Json(ErrorResponse::new(error))).into_response();
+    let params = QueryParams::from_pairs(pairs);
+    let state = state.routed(&params).for_caller(&headers, false);
+    if !state.policy.writes_allowed() {
+        return failed(Error::from(PolicyError::WritesDisabled).into());
+    }
+    let Some(db) = state.sqlite.clone() else {
+        return failed(Error::Request("views need a SQLite database".to_string()).into());
+    };
+    let Some(name) = params.view_name.clone() else {
+        return failed(Error::Request("Missing name parameter".to_string()).into());
+    };
+    if state.schema().table(&name).is_some() {
+        return failed(Error::Request(format!("{name} already exists")).into());
+    }
+    let config = match resolve_config(&state, &params) {
+        Ok(config) => config,
+        Err(error) => return failed(error.into()),
+    };
+    // A view's columns are not classified, so it would serve hidden ones to anyone.
+    if let Some((table, column)) = privacy::first_hidden(&state.schema(), &config, state.audience) {
+        let denied = PolicyError::ColumnDenied {
+            table: table.to_string(),
+            column: column.to_string(),
+        };
+        return failed(Error::from(denied).into());
+    }
+    let sql = match build_view(state.dialect(), &name, &config, &state.context.limits) {
+        Ok(sql) => sql,
+        Err(error) => return failed(error.into()),
+    };
+
+    let statement = sql.clone();
+    let created = state
+        .workers
+        .run(move || {
+            db.execute(&statement, &[])?;
+            load_schema(&*db)
+        })
+        .await
+        .map_err(AppError::from)
+        .and_then(|schema| schema);
+    match created {
+        Ok(schema) => {
+            // Reload now rather than on the next poll, so the view can be
+            // queried as soon as this returns.
+            state.schema.replace(schema);
+            info!("Created view {name}");
+            Json(ViewResponse {
+                success: true,
+                view: name,
+                query: sql,
+            })
+            .into_response()
+        }
+        Err(error) => failed(error),
+    }
+}
+
+/// The SQLite database behind a subject request from an admin caller.
+fn subject_database(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<Arc<SqliteDatabase>, AppError> {
+    if state.redaction.audience(headers, false) != Audience::Admin {
+        return Err(Error::from(PolicyError::AdminRequired).into());
+    }
+    state
+        .sqlite
+        .clone()
+        .ok_or_else(|| Error::Request("subject requests need a SQLite database".to_string()).into())
+}
+
+/// A key from the URL: an integer when it parses as one, otherwise text.
+fn subject_key(id: String) -> rusqlite::types::Value {
+    id.parse().map_or(
+        rusqlite::types::Value::Text(id),
+        rusqlite::types::Value::Integer,
+    )
+}
+
+/// Every row tied to the `table` row with primary key `id`, following
+/// foreign keys that reference it. Admin only.
+async fn export_subject_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((table, id)): Path<(String, String)>,
+) -> axum::response::Response {
+    let exported = async {
+        let db = subject_database(&state, &headers)?;
+        let schema = state.schema();
+        with_db(&state.workers, db, state.context.deadline, move |conn| {
+            Ok(export_subject(conn, &schema, &table, &subject_key(id))?)
+        })
+        .await
+    };
+    match exported.await {
+        Ok(rows) => Json::<SubjectRows>(rows).into_response(),
+        Err(error) => (error.status(), Json(ErrorResponse::new(error))).into_response(),
+    }
+}
+
+/// Delete the rows [`export_subject_handler`] returns, or with
+/// `mode=anonymize` blank their PII columns. Admin only, and needs
+/// `ALLOW_WRITES` (and `ALLOW_DESTRUCTIVE` to delete).
+async fn erase_subject_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((table, id)): Path<(String, String)>,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> axum::response::Response {
+    let erasure = match pairs.iter().find(|(key, _)| key == "mode") {
+        Some((_, mode)) if mode == "anonymize" => Erasure::Anonymize,
+        _ => Erasure::Delete,
+    };
+    let erased = async {
+        let db = subject_database(&state, &headers)?;
+        if !state.policy.writes_allowed() {
+            return Err(Error::from(PolicyError::WritesDisabled).into());
+        }
+        if erasure == Erasure::Delete && !state.policy.destructive_allowed() {
+            return Err(Error::from(PolicyError::DestructiveDisabled).into());
+        }
+        let schema = state.schema();
+        let (materialized, erased_from) = (state.materialized.clone(), table.clone());
+        let results = state.results.clone();
+        let report = with_writer(&state.workers, db, move |conn| {
+            Ok(erase_subject(
+                conn,
+                &schema,
+                &table,
+                &subject_key(id),
+                erasure,
+            )?)
+        })
+        .await?;
+        if let Some(materialized) = materialized {
+            for table in report.tables.keys() {
+                materialized.invalidate(table);
+            }
+        }
+        results.clear();
+        info!("Erased subject {erased_from}: {:?}", report.tables);
+        Ok::<_, AppError>(report)
+    };
+    match erased.await {
+        Ok(report) => Json::<ErasureReport>(report).into_response(),
+        Err(error) => (error.status(), Json(ErrorResponse::new(error))).into_response(),
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum CompareSide {
+    Ok {
+        query: String,
+        params: Vec<JsonValue>,
+        count: usize,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        truncated: bool,
+    },
+    Err {
+        error: String,
+    },
+}
+
+#[derive(Serialize)]
+struct AlignedRow {
+    left: Option<RowData>,
+    right: Option<RowData>,
+}
+
+#[derive(Serialize)]
+struct CompareResponse {
+    left: CompareSide,
+    right: CompareSide,
+    #[serde(rename = "sqlDiff")]
+    sql_diff: Vec<ClauseDiff>,
+    #[serde(rename = "paramsChanged")]
+    params_changed: bool,
+    rows: Vec<AlignedRow>,
+}
+
+/// Run `left` and `right` class names side by side. Rows are aligned by
+/// position, and the generated SQL is diffed clause by clause.
+async fn compare_api_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> axum::response::Response {
+    let state = state.for_caller(&headers, false);
+    let params = QueryParams::from_pairs(pairs.clone());
+    let class_param = |name: &str| {
+        pairs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, v)| v.clone())
+    };
+    let (Some(left), Some(right)) = (class_param("left"), class_param("right")) else {
+        let error = "Missing left or right parameter".to_string();
+        return (StatusCode::BAD_REQUEST, Json(ErrorResponse::new(error))).into_response();
+    };
+
+    // Each side may use a different prefix, and so a different datasource.
+    let run = |class_name: String| {
+        let params = QueryParams {
+            class_name: Some(class_name),
+            ..params.clone()
+        };
+        let state = state.routed(&params);
+        let config = resolve_config(&state, &params).map_err(|error| error.to_string());
+        async move {
+            run_config(state, config?)
+                .await
+                .map_err(|error| error.to_string())
+        }
+    };
+    let (left, right) = tokio::join!(run(left), run(right));
+
+    let sql_diff = match (&left, &right) {
+        (Ok(left), Ok(right)) => diff_sql(&left.sql, &right.sql),
+        _ => Vec::new(),
+    };
+    let params_changed = match (&left, &right) {
+        (Ok(left), Ok(right)) => left.params != right.params,
+        _ => false,
+    };
+    let side = |output: Result<QueryOutput, String>| match output {
+        Ok(output) => (
+            CompareSide::Ok {
+                count: output.rows.len(),
+                query: output.sql,
+                params: output.params,
+                truncated: output.truncated_at.is_some(),
+            },
+            output.rows,
+        ),
+        Err(error) => (CompareSide::Err { error }, Vec::new()),
+    };
+    let (left, left_rows) = side(left);
+    let (right, right_rows) = side(right);
+
+    let mut left_rows = left_rows.into_iter();
+    let mut right_rows = right_rows.into_iter();
+    let mut rows = Vec::new();
+    loop {
+        let (left, right) = (left_rows.next(), right_rows.next());
+        if left.is_none() && right.is_none() {
+            break;
+        }
+        rows.push(AlignedRow { left, right });
+    }
+
+    Json(CompareResponse {
+        left,
+        right,
+        sql_diff,
+        params_changed,
+        rows,
+    })
+    .into_response()
+}
+
+/// Render a class name server-side as an HTML fragment for embedding. A
+/// scope token, checked by [`embed::verify`], narrows the tables, rows and
+/// render modes the fragment may use.
+async fn fragment_handler(
+    State(state): State<AppState>,
+    uri: Uri,
+    headers: HeaderMap,
+    scope: Option<Extension<Scope>>,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> axum::response::Response {
+    let params = QueryParams::from_pairs(pairs);
+    let mut state = state.routed(&params).for_caller(&headers, true);
+    let failed = |error: Error| (error.status(), Html(render_error(&error.to_string())));
+    let (render_as, mut options) = fragment_options(&params, &headers);
+    if let Some(Extension(scope)) = &scope {
+        if let Err(error) = scope.check_render(render_as) {
+            return failed(error.into()).into_response();
+        }
+        state = scope.narrow(state);
+    }
+    let config = match resolve_config(&state, &params) {
+        Ok(config) => config,
+        Err(error) => return failed(error).into_response(),
+    };
+    request_id::record_query(&config);
+    let mut warnings = grammar_notes(&state, &params);
+
+    let refresh_seconds = params.refresh.or(config.refresh_seconds);
+    // A signed URL cannot take another `page`.
+    let paged = matches!(render_as, RenderAs::Table)
+        && !params.signed
+        && (params.page.is_some() || config.limit.is_some());
+    if matches!(render_as, RenderAs::Table) || refresh_seconds.is_some() {
+        let pairs: Vec<_> = params
+            .view_pairs
+            .iter()
+            .filter(|(key, _)| !(paged && key == "page"))
+            .collect();
+        let query = serde_urlencoded::to_string(&pairs).unwrap_or_default();
+        let sortable = if !matches!(render_as, RenderAs::Table) || params.signed {
+            Vec::new()
+        } else if config.columns.is_empty() {
+            state
+                .schema()
+                .table(&config.table)
+                .map(|table| table.columns.iter().map(|c| c.name.clone()).collect())
+                .unwrap_or_default()
+        } else {
+            config.columns.clone()
+        };
+        options.interactive = Some(Interactive {
+            url: format!("{}?{query}", uri.path()),
+            sortable,
+            sort: config.order_by.clone(),
+            refresh_seconds,
+            filters: (params.show_filters && !params.signed).then(|| params.filters.clone()),
+            pager: None,
+        });
+    }
+
+    let context = state.context.clone();
+    let pager = paged.then(|| (state.clone(), config.clone()));
+    // Rows that arrive past the deadline are not worth rendering.
+    let output = run_config(state, config)
+        .await
+        .and_then(|output| Ok(context.check().map(|()| output)?));
+    match output {
+        Ok(output) => {
+            if let Some((state, config)) = pager {
+                let page = params.page.unwrap_or(1);
+                let page_count = match paginate(state, config, page).await {
+                    Ok(pagination) => Some(pagination.page_count),
+                    Err(error) => {
+                        warn!("Could not count pages: {error}");
+                        None
+                    }
+                };
+                if let Some(interactive) = options.interactive.as_mut() {
+                    interactive.pager = Some(Pager { page, page_count });
+                }
+            }
+            request_id::record_rows(output.rows.len());
+            if let Some(cap) = output.truncated_at {
+                warnings.push(format!("results truncated to {cap} rows"));
+            }
+            let html = info_span!("render").in_scope(|| {
+                render_results_with(&output.rows, &output.display_columns, render_as, &options)
+            });
+            let etag = embed::content_etag(&html);
+            let mut response = if embed::not_modified(&headers, &etag) {
+                StatusCode::NOT_MODIFIED.into_response()
+            } else {
+                Html(html).into_response()
+            };
+            if let Ok(value) = HeaderValue::from_str(&etag) {
+                response.headers_mut().insert(header::ETAG, value);
+            }
+            response
+                .headers_mut()
+                .insert(header::VARY, HeaderValue::from_static("accept-language"));
+            for warning in warnings {
+                let value = format!("299 - \"{}\"", warning.replace('"', "'"));
+                if let Ok(value) = HeaderValue::from_str(&value) {
+                    response.headers_mut().append(header::WARNING, value);
+                }
+            }
+            response
+        }
+        Err(error) => (error.status(), Html(render_error(&error.to_string()))).into_response(),
+    }
+}
+
+/// How `params` ask for a fragment to be rendered, before any interactive
+/// controls, in the language the viewer's `Accept-Language` prefers.
+fn fragment_options(params: &QueryParams, headers: &HeaderMap) -> (RenderAs, RenderOptions) {
+    let render_as = params
+        .render_as
+        .as_deref()
+        .map_or(RenderAs::Span, RenderAs::parse);
+    let mut options = RenderOptions {
+        relative_time_columns: params.relative.clone(),
+        pin_first_column: params.pin,
+        max_columns: params.max_columns,
+        locale: headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map_or_else(Locale::default, Locale::negotiate),
+        ..RenderOptions::default()
+    };
+    for column in &params.avatar {
+        options = options.with_cell_renderer(column.clone(), AvatarRenderer::default());
+    }
+    (render_as, options)
+}
+
+/// Stream the fragment these parameters describe as server-sent `update`
+/// events, re-rendered every `refresh` seconds and sent when it changes.
+/// Viewers of the same URL share one query. With `FRAGMENT_SIGNING_KEY` set,
+/// only admins may watch.
+async fn live_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> axum::response::Response {
+    let params = QueryParams::from_pairs(pairs.clone());
+    let state = state.routed(&params).for_caller(&headers, true);
+    let failed = |error: Error| (error.status(), Html(render_error(&error.to_string())));
+    if state.signer.is_some() && state.audience != Audience::Admin {
+        return failed(PolicyError::AdminRequired.into()).into_response();
+    }
+    let config = match resolve_config(&state, &params) {
+        Ok(config) => config,
+        Err(error) => return failed(error).into_response(),
+    };
+    request_id::record_query(&config);
+
+    let every = params
+        .refresh
+        .or(config.refresh_seconds)
+        .map_or(live::DEFAULT_INTERVAL, Duration::from_secs);
+    let (render_as, options) = fragment_options(&params, &headers);
+    let hub = state.live.clone();
+    let key = live::key(state.audience, options.locale, &pairs);
+    let subscription = hub.subscribe(key, every, move || {
+        let (state, config, options) = (
+            state.for_audience(state.audience),
+            config.clone(),
+            options.clone(),
+        );
+        async move {
+            match run_config(state, config).await {
+                Ok(output) => {
+                    render_results_with(&output.rows, &output.display_columns, render_as, &options)
+                }
+                Err(error) => render_error(&error.to_string()),
+            }
+        }
+    });
+    let events = subscription
+        .into_stream()
+        .map(|update| Ok::<_, Infallible>(Event::default().event("update").data(&*update)));
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Sign the fragment these parameters describe, for embedding on public
+/// pages. The URL expires after `ttl` (`30m`, `24h`; default `1h`). Admin
+/// only, and needs `FRAGMENT_SIGNING_KEY`.
+async fn sign_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> axum::response::Response {
+    let signed = || -> Result<SignedUrl, Error> {
+        if state.redaction.audience(&headers, false) != Audience::Admin {
+            return Err(PolicyError::AdminRequired.into());
+        }
+        let Some(signer) = &state.signer else {
+            return Err(Error::Request(
+                "signing fragments needs FRAGMENT_SIGNING_KEY".to_string(),
+            ));
+        };
+        let ttl = match pairs.iter().find(|(key, _)| key == "ttl") {
+            Some((_, ttl)) => tokens::interval_seconds(ttl.trim())
+                .ok_or_else(|| Error::Request(format!("invalid ttl {ttl:?}")))?,
+            None => DEFAULT_SIGNED_TTL,
+        };
+        // Catch a bad class name now rather than on the page it is embedded in.
+        let params = QueryParams::from_pairs(pairs.clone());
+        resolve_config(&state.routed(&params), &params)?;
+        Ok(signer.sign(&pairs, Duration::from_secs(ttl)))
+    };
+    match signed() {
+        Ok(signed) => Json(signed).into_response(),
+        Err(error) => (error.status(), Json(ErrorResponse::new(error))).into_response(),
+    }
+}
+
+/// Issue a scope token limiting fragments to `tables`, `maxRows` rows and
+/// the `as` render modes, for third parties to embed with as `scope=`. It
+/// expires after `ttl` (default `1h`). Admin only, and needs
+/// `FRAGMENT_SIGNING_KEY`.
+async fn scope_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> axum::response::Response {
+    let issued = || -> Result<ScopeToken, Error> {
+        if state.redaction.audience(&headers, false) != Audience::Admin {
+            return Err(PolicyError::AdminRequired.into());
+        }
+        let Some(signer) = &state.signer else {
+            return Err(Error::Request(
+                "scope tokens need FRAGMENT_SIGNING_KEY".to_string(),
+            ));
+        };
+        let ttl = match pairs.iter().find(|(key, _)| key == "ttl") {
+            Some((_, ttl)) => tokens::interval_seconds(ttl.trim())
+                .ok_or_else(|| Error::Request(format!("invalid ttl {ttl:?}")))?,
+            None => DEFAULT_SIGNED_TTL,
+        };
+        let scope = Scope::from_pairs(&pairs).map_err(Error::Request)?;
+        let schema = state.schema();
+        if let Some(table) = scope
+            .tables
+            .iter()
+            .flatten()
+            .find(|table| schema.table(table).is_none())
+        {
+            let error = ValidationError::UnknownTable(table.clone());
+            return Err(Error::Invalid(vec![error]));
+        }
+        Ok(signer.issue(&scope, Duration::from_secs(ttl)))
+    };
+    match issued() {
+        Ok(token) => Json(token).into_response(),
+        Err(error) => (error.status(), Json(ErrorResponse::new(error))).into_response(),
+    }
+}
+
+#[derive(Serialize)]
+struct TokensResponse<'a> {
+    /// The class name after snippet expansion, when a snippet was used.
+    /// Token spans index into this text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expanded: Option<&'a str>,
+    tokens: Vec<Token<'a>>,
+    ast: ClassAst<'a>,
+}
+
+/// Lex a class name without running it, for syntax highlighting and linting.
+async fn tokens_api_handler(
+    State(state): State<AppState>,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> axum::response::Response {
+    let params = QueryParams::from_pairs(pairs);
+    let Some(class_name) = params.class_name.as_deref() else {
+        let error = "Missing className parameter".to_string();
+        return (StatusCode::BAD_REQUEST, Json(ErrorResponse::new(error))).into_response();
+    };
+
+    let grammar = state.grammar_for(&params);
+    let expanded = grammar.expand_snippets(class_name);
+    match (
+        tokens::tokenize_with(&expanded, &grammar),
+        tokens::parse_with(&expanded, &grammar),
+    ) {
+        (Some(tokens), Some(ast)) => Json(TokensResponse {
+            expanded: (*expanded != *class_name).then_some(&*expanded),
+            tokens,
+            ast,
+        })
+        .into_response(),
+        _ => {
+            let error = format!("Invalid TailwindSQL class: {class_name}");
+            (StatusCode::BAD_REQUEST, Json(ErrorResponse::new(error))).into_response()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ExplainResponse<'a> {
+    #[serde(rename = "className")]
+    class_name: &'a str,
+    /// The class name after snippet expansion; step spans index into it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expanded: Option<&'a str>,
+    steps: Vec<Step<'a>>,
+    sql: Option<String>,
+}
+
+/// Break a class name down token by token, with the SQL it would generate.
+async fn explain_class_handler(
+    State(state): State<AppState>,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> axum::response::Response {
+    let params = QueryParams::from_pairs(pairs);
+    let Some(class_name) = params.class_name.as_deref() else {
+        let error = "Missing className parameter".to_string();
+        return (StatusCode::BAD_REQUEST, Json(ErrorResponse::new(error))).into_response();
+    };
+    let grammar = state.grammar_for(&params);
+    let expanded = grammar.expand_snippets(class_name);
+    let Some(ast) = tokens::parse_with(&expanded, &grammar) else {
+        let error = format!("Invalid TailwindSQL class: {class_name}");
+        return (StatusCode::BAD_REQUEST, Json(ErrorResponse::new(error))).into_response();
+    };
+
+    let routed = state.routed(&params);
+    let sql = resolve_config(&routed, &params)
+        .ok()
+        .and_then(|config| routed.build_query(&config).ok())
+        .map(|built| built.sql);
+    Json(ExplainResponse {
+        class_name,
+        expanded: (*expanded != *class_name).then_some(&*expanded),
+        steps: ast.explain(&expanded),
+        sql,
+    })
+    .into_response()
+}
+
+/// Run a class name's query plan and suggest indexes for the tables it scans
+/// in full. SQLite only.
+async fn advise_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(pairs): Query<Vec<(String, String)>>,
+) -> axum::response::Response {
+    let params = QueryParams::from_pairs(pairs);
+    let state = state.routed(&params).for_caller(&headers, false);
+    let advised = async {
+        if state.sqlite.is_none() {
+            return Err(Error::Request("index advice needs a SQLite database".to_string()).into());
+        }
+        let config = resolve_config(&state, &params)?;
+        request_id::record_query(&config);
+        let explained = run_explain(state, config.clone()).await?;
+        let plan: Vec<String> = explained
+            .plan
+            .iter()
+            .filter_map(|step| step.get("detail").and_then(JsonValue::as_str))
+            .map(ToString::to_string)
+            .collect();
+        Ok::<_, AppError>(AdviseResponse {
+            success: true,
+            advice: advisor::advise(&config, &plan),
+            query: explained.query,
+            plan,
+        })
+    };
+    match advised.await {
+        Ok(response) => Json(response).into_response(),
+        Err(error) => (error.status(), Json(ErrorResponse::new(error))).into_response(),
+    }
+}
+
+#[derive(Serialize)]
+struct MetricsResponse {
+    #[serde(rename = "dbWorkers")]
+    db_workers: PoolMetrics,
+    /// Prepared statement reuse on the SQLite database, if there is one.
+    #[serde(rename = "statementCache", skip_serializing_if = "Option::is_none")]
+    statement_cache: Option<StatementCacheMetrics>,
+    /// Use of the cache `cache=1` queries are served from.
+    #[serde(rename = "resultCache")]
+    result_cache: ResultCacheMetrics,
+    /// Use of the upstream responses kept in proxy mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proxy: Option<ProxyMetrics>,
+    /// Subscriptions and viewers of `/api/live`, and updates they lost.
+    live: LiveMetrics,
+}
+
+#[derive(Serialize)]
+struct ProxyMetrics {
+    upstream: String,
+    #[serde(flatten)]
+    responses: ResultCacheMetrics,
+}
+
+/// Database worker pool load: queue depth, wait times and completed jobs,
+/// statement, result and proxy cache hits and misses, and live viewers.
+async fn metrics_handler(State(state): State<AppState>) -> Json<MetricsResponse> {
+    Json(MetricsResponse {
+        db_workers: state.workers.metrics(),
+        statement_cache: state
+            .sqlite
+            .as_ref()
+            .map(|sqlite| sqlite.statements().metrics()),
+        result_cache: state.results.metrics(),
+        proxy: state.proxy.as_ref().map(|proxy| ProxyMetrics {
+            upstream: proxy.upstream().to_string(),
+            responses: proxy.metrics(),
+        }),
+        live: state.live.metrics(),
+    })
+}
+
+#[derive(Serialize)]
+struct SchemaResponse {
+    tables: Vec<TableInfo>,
+    /// Whether `/api/mutate` accepts writes, so the explorer can offer editing.
+    writable: bool,
+}
+
+#[derive(Serialize)]
+struct TableInfo {
+    name: String,
+    columns: Vec<ColumnInfo>,
+    #[serde(rename = "rowCount")]
+    row_count: i64,
+    data: Vec<RowData>,
+}
+
+/// A column as `/api/schema` and the explorer page describe it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnInfo {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub col_type: String,
+    #[serde(skip_serializing_if = "Privacy::is_public")]
+    pub privacy: Privacy,
+}
+
+async fn schema_api_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    match schema_response(state.for_caller(&headers, false)).await {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(error) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new(error)),
+        )
+            .into_response(),
+    }
+}
+
+async fn schema_response(state: AppState) -> Result<SchemaResponse, AppError> {
+    let writable = state.policy.writes_allowed();
+    let schema = state.schema();
+    let names: Vec<String> = schema.tables().map(|table| table.name.clone()).collect();
+    let tables = sample_tables(&state, &schema, names, SAMPLE_ROWS).await?;
+    Ok(SchemaResponse { tables, writable })
+}
+
+/// Rows `/api/schema` samples from each table.
+const SAMPLE_ROWS: u32 = 20;
+/// The most rows `/api/schema/:table?limit=N` samples.
+const MAX_SAMPLE_ROWS: u32 = 500;
+
+#[derive(Deserialize)]
+struct SchemaTableParams {
+    limit: Option<u32>,
+}
+
+/// One table of `/api/schema`, sampling `limit` rows (default 20, at most
+/// 500). Tables the loaded catalog does not know are refused before any SQL
+/// is built, so the name never reaches the database unchecked.
+async fn schema_table_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(table): Path<String>,
+    Query(params): Query<SchemaTableParams>,
+) -> axum::response::Response {
+    let state = state.for_caller(&headers, false);
+    let schema = state.schema();
+    if schema.table(&table).is_none() {
+        let error = Error::Invalid(vec![ValidationError::UnknownTable(table)]);
+        return (error.status(), Json(ErrorResponse::new(error))).into_response();
+    }
+    let limit = params
+        .limit
+        .unwrap_or(SAMPLE_ROWS)
+        .clamp(1, MAX_SAMPLE_ROWS);
+    match sample_tables(&state, &schema, vec![table], limit).await {
+        Ok(mut tables) => Json(tables.remove(0)).into_response(),
+        Err(error) => (error.status(), Json(ErrorResponse::new(error))).into_response(),
+    }
+}
+
+/// The filters each column of `table` takes, leaving out the columns the
+/// caller may not filter on. Tables the catalog does not list, or the access
+/// policy denies, are refused.
+async fn vocabulary_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(table): Path<String>,
+) -> axum::response::Response {
+    let state = state.for_caller(&headers, false);
+    let schema = state.schema();
+    let Some(found) = schema.table(&table) else {
+        let error = Error::Invalid(vec![ValidationError::UnknownTable(table)]);
+        return (error.status(), Json(ErrorResponse::new(error))).into_response();
+    };
+    if !state.policy.is_table_allowed(&table) {
+        let error = Error::from(PolicyError::TableDenied(table));
+        return (error.status(), Json(ErrorResponse::new(error))).into_response();
+    }
+    Json(vocabulary::describe(found, |column| {
+        state.policy.is_column_allowed(&table, &column.name)
+            && !state.audience.hides(column.privacy)
+    }))
+    .into_response()
+}
+
+/// `/api/schema` entries for the catalog tables `names`, each with `limit`
+/// sample rows redacted for `state`'s audience.
+async fn sample_tables(
+    state: &AppState,
+    schema: &Arc<SchemaCatalog>,
+    names: Vec<String>,
+    limit: u32,
+) -> Result<Vec<TableInfo>, AppError> {
+    #[cfg(feature = "postgres")]
+    if let Some(postgres) = state.postgres.clone() {
+        let tables = postgres_schema(&postgres, schema, &names, limit).await?;
+        return Ok(redact_samples(state, schema, tables));
+    }
+    let (backend, catalog) = (state.backend.clone(), schema.clone());
+    let tables = state
+        .workers
+        .run(move || backend_schema(&*backend, &catalog, &names, limit))
+        .await??;
+    Ok(redact_samples(state, schema, tables))
+}
+
+/// `tables` with the sample rows redacted for `state`'s audience.
+fn redact_samples(
+    state: &AppState,
+    schema: &SchemaCatalog,
+    mut tables: Vec<TableInfo>,
+) -> Vec<TableInfo> {
+    for table in &mut tables {
+        state
+            .redaction
+            .redact(state.audience, &mut table.data, &mut Vec::new(), |column| {
+                schema.privacy(&table.name, column)
+            });
+    }
+    tables
+}
+
+/// The `/api/schema` tables `names` of a PostgreSQL database, from its
+/// loaded catalog.
+#[cfg(feature = "postgres")]
+async fn postgres_schema(
+    postgres: &PgDatabase,
+    schema: &SchemaCatalog,
+    names: &[String],
+    limit: u32,
+) -> Result<Vec<TableInfo>, AppError> {
+    let mut tables = Vec::new();
+    for table in names.iter().filter_map(|name| schema.table(name)) {
+        let mut data = RowCollector::new(&QueryPolicy::default());
+        let name = Postgres.quote_identifier(&table.name);
+        postgres
+            .for_each_row(
+                &format!("SELECT * FROM {name} LIMIT $1"),
+                &[rusqlite::types::Value::Integer(limit.into())],
+                |row| data.push(row),
+            )
+            .await?;
+        tables.push(TableInfo {
+            name: table.name.clone(),
+            columns: catalog_columns(table),
+            row_count: postgres.row_count(&table.name).await?,
+            data: data.finish(Vec::new()).rows,
+        });
+    }
+    Ok(tables)
+}
+
+/// The `/api/schema` tables `names` of a [`Backend`] database, from its
+/// loaded catalog. Names are quoted and the limit bound as a parameter.
+fn backend_schema(
+    backend: &dyn Backend,
+    schema: &SchemaCatalog,
+    names: &[String],
+    limit: u32,
+) -> Result<Vec<TableInfo>, AppError> {
+    let limits = QueryPolicy::default();
+    let dialect = backend.dialect();
+    let mut tables = Vec::new();
+    for table in names.iter().filter_map(|name| schema.table(name)) {
+        let name = dialect.quote_identifier(&table.name);
+        let placeholder = dialect.placeholder(1, "limit", ParamStyle::Positional);
+        let data = backend.query(
+            &format!("SELECT * FROM {name} LIMIT {placeholder}"),
+            &[rusqlite::types::Value::Integer(limit.into())],
+            &limits,
+        )?;
+        let count = backend.query(&format!("SELECT COUNT(*) AS n FROM {name}"), &[], &limits)?;
+        tables.push(TableInfo {
+            name: table.name.clone(),
+            columns: catalog_columns(table),
+            row_count: count
+                .rows
+                .first()
+                .and_then(|row| row.get("n"))
+                .and_then(JsonValue::as_i64)
+                .unwrap_or_default(),
+            data: data.rows,
+        });
+    }
+    Ok(tables)
+}
+
+fn catalog_columns(table: &TableSchema) -> Vec<ColumnInfo> {
+    table
+        .columns
+        .iter()
+        .map(|column| ColumnInfo {
+            name: column.name.clone(),
+            col_type: column.col_type.clone(),
+            privacy: column.privacy,
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+struct QueryOutput {
+    sql: String,
+    params: Vec<JsonValue>,
+    rows: Vec<RowData>,
+    display_columns: Vec<String>,
+    /// The row cap that cut the result short, if any.
+    truncated_at: Option<usize>,
+}
+
+impl QueryOutput {
+    /// Rows keep their [`ROW_ID`], which is not a column to display.
+    fn new(sql: String, params: &[rusqlite::types::Value], mut result: QueryResult) -> Self {
+        result.columns.retain(|column| column != ROW_ID);
+        Self {
+            sql,
+            params: params.iter().cloned().map(db::value_to_json).collect(),
+            rows: result.rows,
+            display_columns: result.columns,
+            truncated_at: result.truncated_at,
+        }
+    }
+}
+
+/// Run `config` against the database `state` routes to, redacted for its
+/// audience.
+async fn run_config(state: AppState, config: QueryConfig) -> Result<QueryOutput, AppError> {
+    let schema = state.schema();
+    if privacy::first_hidden(&schema, &config, state.audience).is_none() {
+        return read_config(state, config).await;
+    }
+    let (redaction, audience) = (state.redaction.clone(), state.audience);
+    let mut output = read_config(state, config.clone()).await?;
+    redaction.redact(
+        audience,
+        &mut output.rows,
+        &mut output.display_columns,
+        |key| schema.output_privacy(&config, key),
+    );
+    Ok(output)
+}
+
+/// [`run_config`] without redaction, from the result cache if the request
+/// opted in, and then shared with identical queries running alongside.
+async fn read_config(state: AppState, config: QueryConfig) -> Result<QueryOutput, AppError> {
+    let config = state.materialized(config);
+    if !state.cache_results {
+        return query_config(state, config).await;
+    }
+    let built = state.build_query(&config)?;
+    let key = result_cache::key(&state.backend, &built.sql, &built.params);
+    let (results, deadline) = (state.results.clone(), state.context.deadline);
+    within(
+        deadline,
+        results.get_or_run(key, query_config(state, config)),
+    )
+    .await
+}
+
+/// `future`, failing with `DbError::Timeout` if `deadline` passes first.
+async fn within<T>(
+    deadline: Option<Deadline>,
+    future: impl Future<Output = Result<T, AppError>>,
+) -> Result<T, AppError> {
+    let Some(deadline) = deadline else {
+        return future.await;
+    };
+    tokio::time::timeout(deadline.remaining(), future)
+        .await
+        .map_err(|_| DbError::Timeout(deadline.budget()))?
+}
+
+/// Run `config` against the database.
+async fn query_config(state: AppState, config: QueryConfig) -> Result<QueryOutput, AppError> {
+    #[cfg(feature = "postgres")]
+    if let Some(postgres) = state.postgres.clone() {
+        let built = state.build_query(&config)?;
+        let mut rows = RowCollector::in_context(&state.context);
+        let columns = postgres
+            .for_each_row(&built.sql, &built.params, |row| rows.push(row))
+            .instrument(info_span!("execute"))
+            .await?;
+        let mut output = QueryOutput::new(built.sql, &built.params, rows.finish(columns));
+        state.schema().coerce_booleans(&config, &mut output.rows);
+        return Ok(output);
+    }
+
+    let built = state.build_query(&config)?;
+    let (backend, schema, context) = (state.backend.clone(), state.schema(), state.context);
+    state
+        .workers
+        .run_until(context.deadline, move || {
+            let _span = info_span!("execute").entered();
+            let result = backend.query_in(&built.sql, &built.params, &context)?;
+            let mut output = QueryOutput::new(built.sql, &built.params, result);
+            schema.coerce_booleans(&config, &mut output.rows);
+            Ok(output)
+        })
+        .await?
+}
+
+/// Count the rows `config` matches before its limit, like [`run_config`].
+async fn run_count(state: AppState, config: QueryConfig) -> Result<u64, AppError> {
+    let config = state.materialized(config);
+    let built = info_span!("build")
+        .in_scope(|| build_count_query_for(state.dialect(), &config, &state.context.limits))?;
+    let total = |row: &RowData| row.get("total").and_then(JsonValue::as_u64);
+    #[cfg(feature = "postgres")]
+    if let Some(postgres) = state.postgres.clone() {
+        let mut count = None;
+        postgres
+            .for_each_row(&built.sql, &built.params, |row| {
+                count = total(&row);
+                Ok::<_, DbError>(false)
+            })
+            .instrument(info_span!("execute"))
+            .await?;
+        return Ok(count.unwrap_or(0));
+    }
+
+    let (backend, context) = (state.backend.clone(), state.context);
+    let result = state
+        .workers
+        .run_until(context.deadline, move || {
+            let _span = info_span!("execute").entered();
+            backend.query_in(&built.sql, &built.params, &context)
+        })
+        .await??;
+    Ok(result.rows.first().and_then(total).unwrap_or(0))
+}
+
+/// Run `config` under the dialect's `EXPLAIN` and collect the plan rows.
+async fn run_explain(state: AppState, config: QueryConfig) -> Result<PlanResponse, AppError> {
+    let built = info_span!("build")
+        .in_scope(|| build_explain(state.dialect(), &config, &state.context.limits))?;
+    let params = built
+        .params
+        .iter()
+        .cloned()
+        .map(db::value_to_json)
+        .collect();
+    #[cfg(feature = "postgres")]
+    if let Some(postgres) = state.postgres.clone() {
+        let mut plan = Vec::new();
+        postgres
+            .for_each_row(&built.sql, &built.params, |row| {
+                plan.push(row);
+                Ok::<_, DbError>(true)
+            })
+            .instrument(info_span!("execute"))
+            .await?;
+        return Ok(PlanResponse {
+            success: true,
+            query: built.sql,
+            params,
+            plan,
+        });
+    }
+
+    let (backend, context, sql) = (state.backend.clone(), state.context, built.sql.clone());
+    let result = state
+        .workers
+        .run_until(context.deadline, move || {
+            let _span = info_span!("execute").entered();
+            backend.query_in(&sql, &built.params, &context)
+        })
+        .await??;
+    Ok(PlanResponse {
+        success: true,
+        query: built.sql,
+        params,
+        plan: result.rows,
+    })
+}
+
+/// Query results already serialized as a JSON array, for `/api/query`.
+struct JsonOutput {
+    sql: String,
+    params: Vec<JsonValue>,
+    results: Box<RawValue>,
+    count: usize,
+    truncated_at: Option<usize>,
+}
+
+/// Run `config` like [`run_config`], serializing the rows as they are read.
+async fn run_config_json(state: AppState, config: QueryConfig) -> Result<JsonOutput, AppError> {
+    // Only SQLite rows are streamed; other backends hand back converted rows,
+    // as do results that need redacting or may be cached.
+    let sqlite = state.sqlite.clone().filter(|_| {
+        !state.cache_results
+            && privacy::first_hidden(&state.schema(), &config, state.audience).is_none()
+    });
+    #[cfg(feature = "postgres")]
+    let sqlite = sqlite.filter(|_| state.postgres.is_none());
+    let Some(sqlite) = sqlite else {
+        let output = run_config(state, config).await?;
+        return Ok(JsonOutput {
+            results: serde_json::value::to_raw_value(&output.rows)?,
+            count: output.rows.len(),
+            sql: output.sql,
+            params: output.params,
+            truncated_at: output.truncated_at,
+        });
+    };
+
+    let config = state.materialized(config);
+    let (schema, context) = (state.schema(), state.context);
+    with_db(&state.workers, sqlite, context.deadline, move |conn| {
+        execute_query_json(conn, &schema, &context, &config)
+    })
+    .await
+}
+
+/// Run `func` on a worker with one of `sqlite`'s read connections, which
+/// fails with `DbError::Timeout` if the query timeout or `deadline` cancels
+/// it.
+async fn with_db<T, F>(
+    workers: &WorkerPool,
+    sqlite: Arc<SqliteDatabase>,
+    deadline: Option<Deadline>,
+    func: F,
+) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    F: FnOnce(&PooledConnection<'_>) -> Result<T, AppError> + Send + 'static,
+{
+    workers
+        .run_until(deadline, move || {
+            func(&sqlite.read_until(deadline)?).map_err(|error| match error {
+                AppError::Error(Error::Db(DbError::Sqlite(error))) => {
+                    sqlite.timed_out_by(error, deadline).into()
+                }
+                error => error,
+            })
+        })
+        .await?
+}
+
+/// Run `func` on a worker with `sqlite`'s writer.
+async fn with_writer<T, F>(
+    workers: &WorkerPool,
+    sqlite: Arc<SqliteDatabase>,
+    func: F,
+) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    F: FnOnce(&rusqlite::Connection) -> Result<T, AppError> + Send + 'static,
+{
+    workers.run(move || func(&*sqlite.lock()?)).await?
+}
+
+/// Run `config` and serialize its rows straight from the statement into a
+/// JSON array, without building a map per row first.
+fn execute_query_json(
+    conn: &PooledConnection<'_>,
+    schema: &SchemaCatalog,
+    context: &QueryContext,
+    config: &QueryConfig,
+) -> Result<JsonOutput, AppError> {
+    let BuiltQuery { sql, params, .. } =
+        info_span!("build").in_scope(|| build_query_with(config, &context.limits))?;
+    let _span = info_span!("execute").entered();
+    let mut stmt = conn.prepare_cached(&sql)?;
+    let names = stmt.column_names();
+    let columns: Vec<JsonColumn> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| JsonColumn {
+            name: (*name).to_string(),
+            boolean: schema.is_boolean_output(config, name),
+            // Like a map insert, a repeated name keeps the last column's value.
+            shadowed: names[i + 1..].contains(name),
+        })
+        .collect();
+
+    let rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+    let stream = RowStream {
+        rows: RefCell::new(rows),
+        columns: &columns,
+        limits: &context.limits,
+        deadline: context.deadline,
+        count: Cell::new(0),
+        truncated: Cell::new(false),
+        error: RefCell::new(None),
+    };
+    let results = serde_json::value::to_raw_value(&stream);
+    if let Some(error) = stream.error.take() {
+        return Err(error);
+    }
+
+    Ok(JsonOutput {
+        results: results?,
+        count: stream.count.get(),
+        truncated_at: stream.truncated.get().then(|| stream.count.get()),
+        params: params.into_iter().map(db::value_to_json).collect(),
+        sql,
+    })
+}
+
+struct JsonColumn {
+    name: String,
+    boolean: bool,
+    shadowed: bool,
+}
+
+/// Serializes statement rows as a JSON array of objects, under the same row
+/// cap, payload cap, memory budget and deadline as [`RowCollector`]. Rows are
+/// only read once, so serialize it once; a failure is kept in `error`.
+struct RowStream<'a, 'stmt> {
+    rows: RefCell<rusqlite::Rows<'stmt>>,
+    columns: &'a [JsonColumn],
+    limits: &'a QueryPolicy,
+    deadline: Option<Deadline>,
+    count: Cell<usize>,
+    truncated: Cell<bool>,
+    error: RefCell<Option<AppError>>,
+}
+
+impl RowStream<'_, '_> {
+    fn fail<E: serde::ser::Error>(&self, error: AppError) -> E {
+        let message = error.to_string();
+        *self.error.borrow_mut() = Some(error);
+        E::custom(message)
+    }
+}
+
+impl Serialize for RowStream<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(None)?;
+        let mut rows = self.rows.borrow_mut();
+        let mut bytes = 0_usize;
+        let mut payload = 0_usize;
+        loop {
+            let row = match rows.next() {
+                Ok(Some(row)) => row,
+                Ok(None) => break,
+                Err(error) => return Err(self.fail(error.into())),
+            };
+            if let Err(error) = self.deadline.as_ref().map_or(Ok(()), Deadline::check) {
+                return Err(self.fail(error.into()));
+            }
+            if self
+                .limits
+                .max_rows
+                .is_some_and(|max| self.count.get() >= max)
+            {
+                self.truncated.set(true);
+                break;
+            }
+            if let Some(budget) = self.limits.max_result_bytes {
+                for (i, column) in self.columns.iter().enumerate() {
+                    let value = row.get_ref(i).map_err(|error| self.fail(error.into()))?;
+                    bytes = bytes.saturating_add(cell_size(&column.name, value));
+                }
+                if bytes > budget {
+                    return Err(self.fail(DbError::MemoryBudget { budget }.into()));
+                }
+            }
+            let row = JsonRow {
+                row,
+                columns: self.columns,
+            };
+            if let Some(max) = self.limits.max_payload_bytes {
+                let size = db::json_size(&row) + usize::from(self.count.get() > 0);
+                if payload.saturating_add(size) > max {
+                    self.truncated.set(true);
+                    break;
+                }
+                payload += size;
+            }
+            seq.serialize_element(&row)?;
+            self.count.set(self.count.get() + 1);
+        }
+        seq.end()
+    }
+}
+
+struct JsonRow<'a> {
+    row: &'a rusqlite::Row<'a>,
+    columns: &'a [JsonColumn],
+}
+
+impl Serialize for JsonRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        for (i, column) in self.columns.iter().enumerate() {
+            if column.shadowed {
+                continue;
+            }
+            let value = self.row.get_ref(i).map_err(S::Error::custom)?;
+            map.serialize_entry(&column.name, &JsonCell(value, column.boolean))?;
+        }
+        map.end()
+    }
+}
+
+/// One SQLite value, written as [`db::value_to_json`] would convert it;
+/// the flag renders 0/1 as `false`/`true`.
+struct JsonCell<'a>(rusqlite::types::ValueRef<'a>, bool);
+
+impl Serialize for JsonCell<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use rusqlite::types::ValueRef;
+        match self.0 {
+            ValueRef::Null => serializer.serialize_unit(),
+            ValueRef::Integer(v @ (0 | 1)) if self.1 => serializer.serialize_bool(v == 1),
+            ValueRef::Integer(v) => serializer.serialize_i64(v),
+            ValueRef::Real(v) if v.is_finite() => serializer.serialize_f64(v),
+            ValueRef::Real(_) => serializer.serialize_unit(),
+            ValueRef::Text(text) => serializer.serialize_str(&String::from_utf8_lossy(text)),
+            ValueRef::Blob(bytes) => serializer.serialize_str(&db::blob_hex(bytes)),
+        }
+    }
+}
+
+/// [`db::row_size`] for one cell read straight from SQLite.
+fn cell_size(name: &str, value: rusqlite::types::ValueRef<'_>) -> usize {
+    let value = match value {
+        rusqlite::types::ValueRef::Text(text) => text.len(),
+        rusqlite::types::ValueRef::Blob(bytes) => bytes.len() * 2 + 2,
+        _ => 0,
+    };
+    name.len() + value + std::mem::size_of::<JsonValue>()
+}
+
+fn strip_tags(input: &str) -> String {
+    let mut out = String::new();
+    let mut in_tag = false;
+    for ch in input.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => out.push(ch),
+            _ => {}
+        }
+    }
+    out
+}
+
+struct ExampleSpec {
+    title: &'static str,
+    description: &'static str,
+    class_name: &'static str,
+    /// Fragment URL params, as the template passes them to `db`.
+    attributes: &'static str,
+}
+
+impl ExampleSpec {
+    const fn new(
+        title: &'static str,
+        description: &'static str,
+        class_name: &'static str,
+        attributes: &'static str,
+    ) -> Self {
+        Self {
+            title,
+            description,
+            class_name,
+            attributes,
+        }
+    }
+}
+
+fn example_specs() -> [ExampleSpec; 6] {
+    [
+        ExampleSpec::new(
+            "Get User Name",
+            "Fetch a single user's name by ID",
+            "db-users-name-where-id-1",
+            "",
+        ),
+        ExampleSpec::new(
+            "Product List",
+            "Display products as an unordered list",
+            "db-products-title-limit-5",
+            "as=ul",
+        ),
+        ExampleSpec::new(
+            "Top Posts by Likes",
+            "Posts ordered by popularity",
+            "db-posts-title-orderby-likes-desc-limit-3",
+            "as=ol",
+        ),
+        ExampleSpec::new(
+            "Users with Posts (JOIN)",
+            "Join users with their posts",
+            "db-users-name-limit-5",
+            "as=table&join=posts:id-author_id:title:left",
+        ),
+        ExampleSpec::new(
+            "Posts with Tags (many-to-many)",
+            "Join through the post_tags junction table",
+            "db-posts-title-limit-6",
+            "as=table&join=tags:through-post_tags:name:inner",
+        ),
+        ExampleSpec::new(
+            "User Avatars",
+            "Custom cell renderer for avatar keywords",
+            "db-users-avatar-name-role-limit-4",
+            "as=table&avatar=avatar",
+        ),
+    ]
+}
+
+/// The card for `spec`, whose output the template renders.
+fn example_card(spec: ExampleSpec) -> ExampleCard {
+    let ExampleSpec {
+        title,
+        description,
+        class_name,
+        attributes,
+    } = spec;
+    let params =
+        QueryParams::from_pairs(serde_urlencoded::from_str(attributes).unwrap_or_default());
+    let (render_as, _) = fragment_options(&params, &HeaderMap::new());
+    let joins = params.joins;
+
+    let code_html = if joins.is_empty() {
+        let as_fragment = if matches!(render_as, RenderAs::Span) {
+            String::new()
+        } else {
+            let label = render_as.as_str();
+            format!(
+                " <span><span class=\"text-slate-300\">as=</span><span class=\"text-green-400\">\"{label}\"</span></span>"
+            )
+        };
+
+        format!(
+            "<div class=\"flex flex-wrap items-baseline gap-x-1\"><span class=\"text-pink-400\">&lt;DB</span><span><span class=\"text-slate-300\">className=</span><span class=\"text-green-400\">\"{class_name}\"</span></span>{as_fragment}<span class=\"text-pink-400\">/&gt;</span></div>"
+        )
+    } else {
+        join_code_preview(class_name, render_as, &joins)
+    };
+
+    ExampleCard {
+        title: title.to_string(),
+        description: description.to_string(),
+        code_html,
+        class_name: class_name.to_string(),
+        attributes: attributes.to_string(),
+    }
+}
+
+/// A `<DB>` element wrapping one `<Join>` per join, for example cards.
+fn join_code_preview(class_name: &str, render_as: RenderAs, joins: &[JoinConfig]) -> String {
+    let attribute = |name: &str, color: &str, value: &str| {
+        format!(
+            "<span><span class=\"text-slate-300\">{name}=</span><span class=\"{color}\">\"{value}\"</span></span>"
+        )
+    };
+    let mut html = format!(
+        "<div class=\"flex flex-col\">\
+        <div class=\"flex flex-wrap items-baseline gap-x-1\">\
+        <span class=\"text-pink-400\">&lt;DB</span>{}{}\
+        <span class=\"text-pink-400\">&gt;</span>\
+        </div>",
+        attribute("className", "text-green-400", class_name),
+        attribute("as", "text-green-400", render_as.as_str()),
+    );
+    for join in joins {
+        let on = match (&join.parent_table, &join.through) {
+            (_, Some(through)) => format!("through-{}", through.table),
+            (Some(parent), None) => {
+                format!("{parent}.{}-{}", join.parent_column, join.child_column)
+            }
+            (None, None) => format!("{}-{}", join.parent_column, join.child_column),
+        };
+        html.push_str(
+            "<div class=\"flex flex-wrap items-baseline gap-x-1 pl-4\">\
+            <span class=\"text-purple-400\">&lt;Join</span>",
+        );
+        html.push_str(&attribute("table", "text-green-400", &join.table));
+        html.push_str(&attribute("on", "text-yellow-400", &on));
+        if !join.columns.is_empty() {
+            html.push_str(&attribute(
+                "select",
+                "text-green-400",
+                &join.columns.join(","),
+            ));
+        }
+        html.push_str("<span class=\"text-purple-400\">/&gt;</span></div>");
+    }
+    html.push_str("<span class=\"text-pink-400\">&lt;/DB&gt;</span></div>");
+    html
+}
@@ -0,0 +1,136 @@
+//! Fault injection for resilience testing in staging.
+//!
+//! When any `CHAOS_*` rate is set, [`inject`] sits in front of every `/api/`
+//! route. Each request may be delayed, fail with the error a locked SQLite
+//! database produces, or lose its connection without a response, so clients'
+//! retries, timeouts and load shedding can be exercised on purpose.
+
+use std::env;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Json, Response};
+use http_body::Frame;
+use rand::Rng;
+
+use super::{AppError, ErrorResponse};
+use crate::db::DbError;
+
+/// Injection rates, each the chance (0 to 1) that a request is affected.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Chaos {
+    latency_rate: f64,
+    max_latency: Duration,
+    busy_rate: f64,
+    drop_rate: f64,
+}
+
+enum Fault {
+    Busy,
+    Drop,
+}
+
+impl Chaos {
+    /// Rates from `CHAOS_LATENCY_RATE`, `CHAOS_BUSY_RATE` and
+    /// `CHAOS_DROP_RATE`, with delays of up to `CHAOS_LATENCY_MS` (default
+    /// 1000); `None` when every rate is zero.
+    pub(super) fn from_env() -> Option<Self> {
+        let rate = |name: &str| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .map_or(0.0, |rate| rate.clamp(0.0, 1.0))
+        };
+        let chaos = Self {
+            latency_rate: rate("CHAOS_LATENCY_RATE"),
+            max_latency: Duration::from_millis(
+                env::var("CHAOS_LATENCY_MS")
+                    .ok()
+                    .and_then(|value| value.trim().parse().ok())
+                    .unwrap_or(1000),
+            ),
+            busy_rate: rate("CHAOS_BUSY_RATE"),
+            drop_rate: rate("CHAOS_DROP_RATE"),
+        };
+        (chaos.latency_rate > 0.0 || chaos.busy_rate > 0.0 || chaos.drop_rate > 0.0)
+            .then_some(chaos)
+    }
+
+    /// Roll for this request: a delay, then at most one fault.
+    fn roll(&self) -> (Option<Duration>, Option<Fault>) {
+        let mut rng = rand::thread_rng();
+        let delay = rng
+            .gen_bool(self.latency_rate)
+            .then(|| rng.gen_range(Duration::ZERO..=self.max_latency));
+        let fault = if rng.gen_bool(self.drop_rate) {
+            Some(Fault::Drop)
+        } else if rng.gen_bool(self.busy_rate) {
+            Some(Fault::Busy)
+        } else {
+            None
+        };
+        (delay, fault)
+    }
+
+    pub(super) fn describe(&self) -> String {
+        format!(
+            "latency {:.0}% (up to {:?}), SQLITE_BUSY {:.0}%, dropped connections {:.0}%",
+            self.latency_rate * 100.0,
+            self.max_latency,
+            self.busy_rate * 100.0,
+            self.drop_rate * 100.0
+        )
+    }
+}
+
+/// Middleware injecting the faults `chaos` rolls into `/api/` requests.
+pub(super) async fn inject(
+    State(chaos): State<Arc<Chaos>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !request.uri().path().starts_with("/api/") {
+        return next.run(request).await;
+    }
+
+    let (delay, fault) = chaos.roll();
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+    match fault {
+        Some(Fault::Busy) => {
+            let error = AppError::from(DbError::Sqlite(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+                Some("database is locked (injected)".to_string()),
+            )));
+            (error.status(), Json(ErrorResponse::new(error))).into_response()
+        }
+        Some(Fault::Drop) => Response::new(Body::new(Dropped)),
+        None => next.run(request).await,
+    }
+}
+
+/// A body that fails before its first byte, so the server aborts the
+/// connection instead of finishing the response.
+struct Dropped;
+
+impl http_body::Body for Dropped {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Poll::Ready(Some(Err(io::Error::new(
+            io::ErrorKind::ConnectionAborted,
+            "connection dropped (injected)",
+        ))))
+    }
+}
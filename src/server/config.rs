@@ -0,0 +1,217 @@
+//! Server settings from a `tailwindsql.toml` file.
+//!
+//! Every setting the server reads from the environment can also be written in
+//! the file, under the variable's name in lower case:
+//!
+//! ```toml
+//! host = "127.0.0.1"
+//! port = 8080
+//! database_path = "data/demo.db"
+//! default_limit = 50
+//! result_cache_ttl = "1m"
+//! allowed_tables = ["users", "posts"]
+//! allow_writes = true
+//! ```
+//!
+//! [`load`] reads `TAILWINDSQL_CONFIG`, or `./tailwindsql.toml` when that is
+//! unset and the file exists. [`ConfigFile::apply`] then exports each setting
+//! whose variable is not already set, so the environment always wins over the
+//! file. Lists are joined with commas and `true`/`false` become `1`/`0`, the
+//! way the variables are written. An unknown key is an error, so a typo does
+//! not silently fall back to a default.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+/// The file read when `TAILWINDSQL_CONFIG` is unset.
+pub const DEFAULT_PATH: &str = "tailwindsql.toml";
+
+/// The variables a config file may set.
+const SETTINGS: &[&str] = &[
+    "HOST",
+    "PORT",
+    "UNIX_SOCKET",
+    "DATABASE_URL",
+    "DATABASE_PATH",
+    "DATA_FILES",
+    "ALLOWED_TABLES",
+    "DENIED_TABLES",
+    "DENIED_COLUMNS",
+    "BOOLEAN_COLUMNS",
+    "INTERNAL_COLUMNS",
+    "PII_COLUMNS",
+    "PII_REDACTION",
+    "PII_HASH_SALT",
+    "ADMIN_TOKEN",
+    "FRAGMENT_SIGNING_KEY",
+    "DEFAULT_LIMIT",
+    "MAX_LIMIT",
+    "MAX_JOINS",
+    "MAX_ROWS",
+    "MAX_PAYLOAD_BYTES",
+    "MAX_RESULT_BYTES",
+    "QUERY_TIMEOUT",
+    "ALLOW_WRITES",
+    "ALLOW_DESTRUCTIVE",
+    "RUST_LOG",
+    "LOG_FORMAT",
+    "LOG_SPANS",
+    "MATERIALIZE",
+    "MATERIALIZE_REFRESH",
+    "RESULT_CACHE_TTL",
+    "RESULT_CACHE_SIZE",
+    "LIVE_BUFFER",
+    "LIVE_DROP_POLICY",
+    "PROXY_UPSTREAM",
+    "QUERY_HISTORY",
+    "CHAOS_LATENCY_RATE",
+    "CHAOS_LATENCY_MS",
+    "CHAOS_BUSY_RATE",
+    "CHAOS_DROP_RATE",
+    "DB_WORKERS",
+    "DB_READERS",
+    "DB_JOURNAL_MODE",
+    "DB_BUSY_TIMEOUT",
+    "DB_FOREIGN_KEYS",
+    "DB_READ_ONLY",
+    "SEED_MODE",
+    "SEED_USERS",
+    "SEED_PRODUCTS",
+    "SEED_POSTS",
+    "SEED_TABLES",
+    "SEED_SPEC",
+    "SCHEMA_REFRESH_SECONDS",
+    "PARAM_STYLE",
+    "GRAMMAR_VERSION",
+    "CLASS_PREFIXES",
+    "CLASS_SNIPPETS",
+    "CLASS_VARIABLES",
+    "CLASS_ALIASES",
+    "STRICT_PARSING",
+];
+
+/// Why a config file could not be used.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("cannot read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("invalid config: {0}")]
+    Syntax(#[from] basic_toml::Error),
+    #[error("unknown config key `{0}`")]
+    UnknownKey(String),
+    #[error("config key `{0}` must be a string, number, boolean or list of them")]
+    Nested(String),
+}
+
+/// One value in the file.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Setting {
+    Flag(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    List(Vec<Setting>),
+}
+
+impl Setting {
+    fn to_env(&self, key: &str) -> Result<String, ConfigError> {
+        Ok(match self {
+            Self::Flag(flag) => if *flag { "1" } else { "0" }.to_string(),
+            Self::Integer(number) => number.to_string(),
+            Self::Float(number) => number.to_string(),
+            Self::Text(text) => text.clone(),
+            Self::List(items) => items
+                .iter()
+                .map(|item| match item {
+                    Self::List(_) => Err(ConfigError::Nested(key.to_string())),
+                    item => item.to_env(key),
+                })
+                .collect::<Result<Vec<_>, _>>()?
+                .join(","),
+        })
+    }
+}
+
+/// A config file that was read, as the variables it sets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    /// Variable names and their values, by name.
+    pub settings: BTreeMap<String, String>,
+}
+
+impl ConfigFile {
+    /// Read and check the file at `path`.
+    ///
+    /// # Errors
+    /// Returns `ConfigError` if the file cannot be read or holds an unknown
+    /// key or a value no variable could take.
+    pub fn read(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            settings: parse(&text)?,
+        })
+    }
+
+    /// Export every setting whose variable is unset, returning how many were.
+    ///
+    /// Call this before any other thread starts: it changes the process
+    /// environment.
+    pub fn apply(&self) -> usize {
+        let mut applied = 0;
+        for (name, value) in &self.settings {
+            if env::var_os(name).is_none() {
+                env::set_var(name, value);
+                applied += 1;
+            }
+        }
+        applied
+    }
+}
+
+/// The variables the TOML `text` sets, by name.
+///
+/// # Errors
+/// Returns `ConfigError` if `text` is not TOML, or holds an unknown key or a
+/// value no variable could take.
+pub fn parse(text: &str) -> Result<BTreeMap<String, String>, ConfigError> {
+    let table: BTreeMap<String, Setting> = basic_toml::from_str(text)?;
+    table
+        .iter()
+        .map(|(key, value)| {
+            let name = key.to_ascii_uppercase();
+            if key.to_ascii_lowercase() != *key || !SETTINGS.contains(&name.as_str()) {
+                return Err(ConfigError::UnknownKey(key.clone()));
+            }
+            Ok((name, value.to_env(key)?))
+        })
+        .collect()
+}
+
+/// The file `TAILWINDSQL_CONFIG` names, or `./tailwindsql.toml` if it exists.
+///
+/// # Errors
+/// Returns `ConfigError` if the file is unusable, or `TAILWINDSQL_CONFIG`
+/// names a file that does not exist.
+pub fn load() -> Result<Option<ConfigFile>, ConfigError> {
+    if let Some(path) = env::var_os("TAILWINDSQL_CONFIG") {
+        return ConfigFile::read(Path::new(&path)).map(Some);
+    }
+    let path = Path::new(DEFAULT_PATH);
+    if path.exists() {
+        ConfigFile::read(path).map(Some)
+    } else {
+        Ok(None)
+    }
+}
@@ -0,0 +1,350 @@
+//! Public embeds: signed fragment URLs and content hashes.
+//!
+//! With `FRAGMENT_SIGNING_KEY` set, `/api/fragment` only runs URLs signed
+//! with that key, so widgets can sit on public pages without letting visitors
+//! run class names of their own. Admins get signed URLs from `/api/sign`;
+//! each carries an `expires` time and, last, a `sig` over the path and every
+//! parameter before it, which [`verify`] checks before the fragment runs.
+//! Fragments also carry an `ETag` hashed from their HTML, so a widget whose
+//! rows have not changed is answered with `304 Not Modified`.
+//!
+//! A scope token, from `/api/scope`, signs what its holder may embed instead
+//! of a single URL: the tables class names may read, the most rows a fragment
+//! shows and the `as=` modes it renders with. Fragments carrying `scope=` run
+//! any class name inside the scope until the token expires, so a third party
+//! can build its own widgets without an account or the admin token.
+
+use std::env;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{Html, IntoResponse, Response};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::warn;
+
+use super::privacy::constant_time_eq;
+use super::{AppState, Audience};
+use crate::policy::PolicyError;
+use crate::render::{render_error, RenderAs};
+
+/// The route signed URLs point at.
+const FRAGMENT_PATH: &str = "/api/fragment";
+/// The route scope tokens come from, which their signatures cover instead of
+/// a fragment path, so neither kind passes for the other.
+const SCOPE_PATH: &str = "/api/scope";
+/// HMAC-SHA256 works on 64-byte blocks.
+const BLOCK: usize = 64;
+
+/// Signs and checks fragment URLs with the `FRAGMENT_SIGNING_KEY` secret.
+pub(super) struct FragmentSigner {
+    key: Vec<u8>,
+}
+
+/// A signed fragment URL, for `/api/sign`.
+#[derive(Debug, Serialize)]
+pub(super) struct SignedUrl {
+    url: String,
+    /// Unix time after which the URL is refused.
+    expires: u64,
+}
+
+/// What a scope token lets fragments do. Each part left unset leaves the
+/// server's own policy and limits as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct Scope {
+    /// Tables class names may read, among those the access policy allows.
+    pub(super) tables: Option<Vec<String>>,
+    /// Most rows a fragment shows; larger limits are lowered to it.
+    pub(super) max_rows: Option<usize>,
+    /// `as=` values fragments may render with.
+    pub(super) render_modes: Option<Vec<String>>,
+}
+
+/// A scope token, for `/api/scope`.
+#[derive(Debug, Serialize)]
+pub(super) struct ScopeToken {
+    token: String,
+    /// Unix time after which the token is refused.
+    expires: u64,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+enum SignatureError {
+    #[error("fragments need a signed URL")]
+    Missing,
+    #[error("the URL's signature does not match")]
+    Invalid,
+    #[error("the signed URL has expired")]
+    Expired,
+}
+
+impl FragmentSigner {
+    /// The signer for `FRAGMENT_SIGNING_KEY`, or `None` when it is unset and
+    /// fragments run unsigned.
+    pub(super) fn from_env() -> Option<Self> {
+        let key = env::var("FRAGMENT_SIGNING_KEY").ok()?;
+        let key = key.trim();
+        (!key.is_empty()).then(|| Self {
+            key: key.as_bytes().to_vec(),
+        })
+    }
+
+    /// `/api/fragment` with `pairs` (less any `ttl`, `expires` or `sig`),
+    /// signed to expire `ttl` from now.
+    pub(super) fn sign(&self, pairs: &[(String, String)], ttl: Duration) -> SignedUrl {
+        let expires = unix_now().saturating_add(ttl.as_secs());
+        let mut pairs: Vec<(&str, &str)> = pairs
+            .iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "ttl" | "expires" | "sig"))
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        let expires_text = expires.to_string();
+        pairs.push(("expires", &expires_text));
+        let query = serde_urlencoded::to_string(&pairs).unwrap_or_default();
+        let sig = self.signature(FRAGMENT_PATH, &query);
+        SignedUrl {
+            url: format!("{FRAGMENT_PATH}?{query}&sig={sig}"),
+            expires,
+        }
+    }
+
+    /// A token for `scope` that expires `ttl` from now: the scope's params
+    /// in hex, a `.`, and their signature.
+    pub(super) fn issue(&self, scope: &Scope, ttl: Duration) -> ScopeToken {
+        let expires = unix_now().saturating_add(ttl.as_secs());
+        let mut pairs = scope.to_pairs();
+        pairs.push(("expires", expires.to_string()));
+        let payload = serde_urlencoded::to_string(&pairs).unwrap_or_default();
+        let sig = self.signature(SCOPE_PATH, &payload);
+        ScopeToken {
+            token: format!("{}.{sig}", hex(payload.as_bytes())),
+            expires,
+        }
+    }
+
+    /// The scope `token` grants, once its signature and expiry check out.
+    fn scope(&self, token: &str) -> Result<Scope, SignatureError> {
+        let (payload, sig) = token.split_once('.').ok_or(SignatureError::Invalid)?;
+        let payload = unhex(payload)
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or(SignatureError::Invalid)?;
+        if !constant_time_eq(&self.signature(SCOPE_PATH, &payload), sig) {
+            return Err(SignatureError::Invalid);
+        }
+        let pairs: Vec<(String, String)> =
+            serde_urlencoded::from_str(&payload).map_err(|_| SignatureError::Invalid)?;
+        let expires = pairs
+            .iter()
+            .find(|(key, _)| key == "expires")
+            .and_then(|(_, value)| value.parse::<u64>().ok())
+            .ok_or(SignatureError::Invalid)?;
+        if expires <= unix_now() {
+            return Err(SignatureError::Expired);
+        }
+        Scope::from_pairs(&pairs).map_err(|_| SignatureError::Invalid)
+    }
+
+    /// Check the `sig` ending `query` against `path` and the rest of the
+    /// query, then its `expires` time.
+    fn check(&self, path: &str, query: &str) -> Result<(), SignatureError> {
+        let (signed, sig) = query.rsplit_once("&sig=").ok_or(SignatureError::Missing)?;
+        if !constant_time_eq(&self.signature(path, signed), sig) {
+            return Err(SignatureError::Invalid);
+        }
+        let expires = signed
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("expires="))
+            .and_then(|value| value.parse::<u64>().ok())
+            .ok_or(SignatureError::Invalid)?;
+        if expires <= unix_now() {
+            return Err(SignatureError::Expired);
+        }
+        Ok(())
+    }
+
+    /// HMAC-SHA256 of `path?query`, in hex.
+    fn signature(&self, path: &str, query: &str) -> String {
+        let mut key = [0_u8; BLOCK];
+        if self.key.len() > BLOCK {
+            key[..32].copy_from_slice(&Sha256::digest(&self.key));
+        } else {
+            key[..self.key.len()].copy_from_slice(&self.key);
+        }
+        let pad = |byte: u8| key.map(|k| k ^ byte);
+        let inner = Sha256::new()
+            .chain_update(pad(0x36))
+            .chain_update(path)
+            .chain_update("?")
+            .chain_update(query)
+            .finalize();
+        let outer = Sha256::new()
+            .chain_update(pad(0x5c))
+            .chain_update(inner)
+            .finalize();
+        hex(&outer)
+    }
+}
+
+impl Scope {
+    /// A scope from `tables`, `maxRows` and `as` params, the lists
+    /// comma-separated.
+    ///
+    /// # Errors
+    /// Returns a message naming the first param that is not usable.
+    pub(super) fn from_pairs(pairs: &[(String, String)]) -> Result<Self, String> {
+        let list = |value: &str| -> Vec<String> {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        let mut scope = Self::default();
+        for (key, value) in pairs {
+            match key.as_str() {
+                "tables" => scope.tables = Some(list(value)),
+                "maxRows" => {
+                    let rows = value.trim().parse().ok().filter(|rows| *rows > 0);
+                    scope.max_rows =
+                        Some(rows.ok_or_else(|| format!("invalid maxRows {value:?}"))?);
+                }
+                "as" => {
+                    let modes = list(value);
+                    if let Some(mode) = modes
+                        .iter()
+                        .find(|mode| !RenderAs::ALL.iter().any(|known| known.as_str() == *mode))
+                    {
+                        return Err(format!("unknown render mode {mode:?}"));
+                    }
+                    scope.render_modes = Some(modes);
+                }
+                _ => {}
+            }
+        }
+        Ok(scope)
+    }
+
+    fn to_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let Some(tables) = &self.tables {
+            pairs.push(("tables", tables.join(",")));
+        }
+        if let Some(rows) = self.max_rows {
+            pairs.push(("maxRows", rows.to_string()));
+        }
+        if let Some(modes) = &self.render_modes {
+            pairs.push(("as", modes.join(",")));
+        }
+        pairs
+    }
+
+    /// `state` inside this scope: its tables narrow the access policy and
+    /// its row cap lowers the limits.
+    pub(super) fn narrow(&self, mut state: AppState) -> AppState {
+        if let Some(tables) = &self.tables {
+            let policy = state.policy.as_ref().clone();
+            state.policy = Arc::new(policy.restrict_tables(tables.iter().cloned()));
+        }
+        if let Some(rows) = self.max_rows {
+            let limits = &mut state.context.limits;
+            let limit = i64::try_from(rows).unwrap_or(i64::MAX);
+            limits.max_limit = Some(limits.max_limit.map_or(limit, |max| max.min(limit)));
+            limits.default_limit = Some(limits.default_limit.map_or(limit, |n| n.min(limit)));
+            limits.max_rows = Some(limits.max_rows.map_or(rows, |max| max.min(rows)));
+        }
+        state
+    }
+
+    /// Refuse rendering `as` a mode the scope does not list.
+    ///
+    /// # Errors
+    /// Returns `PolicyError::RenderDenied` for such a mode.
+    pub(super) fn check_render(&self, render_as: RenderAs) -> Result<(), PolicyError> {
+        match &self.render_modes {
+            Some(modes) if !modes.iter().any(|mode| mode == render_as.as_str()) => {
+                Err(PolicyError::RenderDenied(render_as.as_str().to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Middleware refusing fragment requests without a valid, unexpired
+/// signature or scope token when signing is on, and handing the scope of a
+/// token on to the fragment. Admins may still request any fragment.
+pub(super) async fn verify(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(signer) = &state.signer else {
+        return next.run(request).await;
+    };
+    if state.redaction.audience(request.headers(), true) == Audience::Admin {
+        return next.run(request).await;
+    }
+    let uri = request.uri().clone();
+    let query = uri.query().unwrap_or_default();
+    let token = serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+        .ok()
+        .and_then(|pairs| pairs.into_iter().find(|(key, _)| key == "scope"));
+    let checked = match token {
+        Some((_, token)) => signer.scope(&token).map(|scope| {
+            request.extensions_mut().insert(scope);
+        }),
+        None => signer.check(uri.path(), query),
+    };
+    match checked {
+        Ok(()) => next.run(request).await,
+        Err(error) => {
+            warn!("Refused fragment {uri}: {error}");
+            (
+                StatusCode::FORBIDDEN,
+                Html(render_error(&error.to_string())),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// A strong `ETag` for `body`: its SHA-256, truncated to 128 bits.
+pub(super) fn content_etag(body: &str) -> String {
+    format!("\"{}\"", hex(&Sha256::digest(body)[..16]))
+}
+
+/// Whether the client's `If-None-Match` already names `etag`.
+pub(super) fn not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == etag || tag == "*")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(text.get(at..at + 2)?, 16).ok())
+        .collect()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
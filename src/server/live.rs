@@ -0,0 +1,322 @@
+//! Live fragments over server-sent events, shared between viewers.
+//!
+//! `/api/live` takes the parameters of `/api/fragment` and streams that
+//! fragment as `update` events: once on connecting, then whenever its HTML
+//! changes. Viewers of the same URL who see the same columns share one
+//! subscription, whose query runs once every `refresh` seconds (default 5)
+//! however many are watching, and stops when the last one leaves.
+//!
+//! Each viewer has room for `LIVE_BUFFER` updates (default 8) it has not read
+//! yet. When a slow viewer's buffer is full, `LIVE_DROP_POLICY` decides what
+//! gives: `oldest` (the default) drops its oldest waiting update, `newest`
+//! the new one, and `disconnect` ends its stream.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use futures_util::Stream;
+use serde::Serialize;
+use tokio::sync::Notify;
+use tokio::time::MissedTickBehavior;
+
+use super::Audience;
+use crate::render::Locale;
+
+/// How often a subscription re-runs its query without `refresh`.
+pub(super) const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_BUFFER: usize = 8;
+
+/// What a viewer whose buffer is full loses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum DropPolicy {
+    /// Its oldest waiting update, so it catches up to the latest.
+    Oldest,
+    /// The new update, keeping the ones it has yet to read.
+    Newest,
+    /// Its stream, which ends once the updates waiting are read.
+    Disconnect,
+}
+
+impl DropPolicy {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "oldest" => Some(Self::Oldest),
+            "newest" => Some(Self::Newest),
+            "disconnect" => Some(Self::Disconnect),
+            _ => None,
+        }
+    }
+}
+
+/// The subscriptions under way, keyed by what their viewers see.
+pub(super) struct LiveHub {
+    buffer: usize,
+    policy: DropPolicy,
+    topics: Mutex<HashMap<String, Topic>>,
+    next_topic: AtomicU64,
+    published: AtomicU64,
+    dropped: AtomicU64,
+    disconnected: AtomicU64,
+}
+
+/// One query shared by everyone watching it.
+struct Topic {
+    /// Tells this topic from one started under the same key after it ended,
+    /// whose poller must not be confused with this one's.
+    id: u64,
+    subscribers: Vec<Arc<Subscriber>>,
+    /// The latest update, which new viewers start from.
+    last: Option<Arc<str>>,
+}
+
+/// One viewer's buffer of updates it has not read yet.
+#[derive(Default)]
+struct Subscriber {
+    queue: Mutex<VecDeque<Arc<str>>>,
+    ready: Notify,
+    closed: AtomicBool,
+}
+
+/// A viewer's place in a [`LiveHub`] topic, given up when dropped.
+pub(super) struct Subscription {
+    hub: Arc<LiveHub>,
+    key: String,
+    topic: u64,
+    subscriber: Arc<Subscriber>,
+}
+
+/// A snapshot of [`LiveHub`] use.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct LiveMetrics {
+    /// Distinct queries being watched, each run once per interval.
+    subscriptions: usize,
+    /// Viewers connected across all of them.
+    subscribers: usize,
+    buffer_size: usize,
+    drop_policy: DropPolicy,
+    /// Updates sent out, each counted once however many viewers got it.
+    published: u64,
+    /// Updates a full buffer lost, under any policy.
+    dropped: u64,
+    /// Viewers cut off by the `disconnect` policy.
+    disconnected: u64,
+}
+
+impl LiveHub {
+    /// A hub giving each viewer `LIVE_BUFFER` updates of room and treating
+    /// full buffers by `LIVE_DROP_POLICY`.
+    pub(super) fn from_env() -> Self {
+        let buffer = env::var("LIVE_BUFFER")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(DEFAULT_BUFFER);
+        let policy = env::var("LIVE_DROP_POLICY")
+            .ok()
+            .and_then(|value| DropPolicy::parse(&value))
+            .unwrap_or(DropPolicy::Oldest);
+        Self::new(buffer, policy)
+    }
+
+    fn new(buffer: usize, policy: DropPolicy) -> Self {
+        Self {
+            buffer: buffer.max(1),
+            policy,
+            topics: Mutex::new(HashMap::new()),
+            next_topic: AtomicU64::new(0),
+            published: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            disconnected: AtomicU64::new(0),
+        }
+    }
+
+    /// Watch the topic `key`, starting it if no one is yet: `poll` then runs
+    /// every `every` until the last viewer leaves, and each result that
+    /// differs from the one before is sent to every viewer.
+    pub(super) fn subscribe<F, Fut>(
+        self: &Arc<Self>,
+        key: String,
+        every: Duration,
+        poll: F,
+    ) -> Subscription
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = String> + Send + 'static,
+    {
+        let subscriber = Arc::new(Subscriber::default());
+        let (topic, started) = {
+            let mut topics = lock(&self.topics);
+            let (topic, started) = match topics.entry(key.clone()) {
+                Entry::Occupied(entry) => (entry.into_mut(), false),
+                Entry::Vacant(entry) => (
+                    entry.insert(Topic {
+                        id: self.next_topic.fetch_add(1, Ordering::Relaxed),
+                        subscribers: Vec::new(),
+                        last: None,
+                    }),
+                    true,
+                ),
+            };
+            if let Some(last) = &topic.last {
+                lock(&subscriber.queue).push_back(last.clone());
+                subscriber.ready.notify_one();
+            }
+            topic.subscribers.push(subscriber.clone());
+            (topic.id, started)
+        };
+        if started {
+            tokio::spawn(self.clone().poll(key.clone(), topic, every, poll));
+        }
+        Subscription {
+            hub: self.clone(),
+            key,
+            topic,
+            subscriber,
+        }
+    }
+
+    /// Run `poll` for topic `id` every `every` while anyone watches it.
+    async fn poll<F, Fut>(self: Arc<Self>, key: String, id: u64, every: Duration, poll: F)
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = String>,
+    {
+        let mut interval = tokio::time::interval(every);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if !self.watched(&key, id) || !self.publish(&key, id, poll().await) {
+                break;
+            }
+        }
+    }
+
+    fn watched(&self, key: &str, id: u64) -> bool {
+        lock(&self.topics)
+            .get(key)
+            .is_some_and(|topic| topic.id == id)
+    }
+
+    /// Send `update` to topic `id`'s viewers unless it is what they last
+    /// got; returns whether anyone is still watching.
+    fn publish(&self, key: &str, id: u64, update: String) -> bool {
+        let mut topics = lock(&self.topics);
+        let Some(topic) = topics.get_mut(key).filter(|topic| topic.id == id) else {
+            return false;
+        };
+        if topic.last.as_deref() == Some(update.as_str()) {
+            return true;
+        }
+        let update: Arc<str> = update.into();
+        topic.last = Some(update.clone());
+        self.published.fetch_add(1, Ordering::Relaxed);
+        topic
+            .subscribers
+            .retain(|subscriber| self.deliver(subscriber, &update));
+        if topic.subscribers.is_empty() {
+            topics.remove(key);
+            return false;
+        }
+        true
+    }
+
+    /// Queue `update` for `subscriber` by the drop policy; returns whether
+    /// it stays subscribed.
+    fn deliver(&self, subscriber: &Subscriber, update: &Arc<str>) -> bool {
+        let mut queue = lock(&subscriber.queue);
+        if queue.len() >= self.buffer {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            match self.policy {
+                DropPolicy::Oldest => {
+                    queue.pop_front();
+                }
+                DropPolicy::Newest => return true,
+                DropPolicy::Disconnect => {
+                    self.disconnected.fetch_add(1, Ordering::Relaxed);
+                    subscriber.closed.store(true, Ordering::Relaxed);
+                    drop(queue);
+                    subscriber.ready.notify_one();
+                    return false;
+                }
+            }
+        }
+        queue.push_back(update.clone());
+        drop(queue);
+        subscriber.ready.notify_one();
+        true
+    }
+
+    pub(super) fn metrics(&self) -> LiveMetrics {
+        let topics = lock(&self.topics);
+        LiveMetrics {
+            subscriptions: topics.len(),
+            subscribers: topics.values().map(|topic| topic.subscribers.len()).sum(),
+            buffer_size: self.buffer,
+            drop_policy: self.policy,
+            published: self.published.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            disconnected: self.disconnected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Subscription {
+    /// The next update, waiting for one if need be; `None` once the stream
+    /// has been cut off.
+    pub(super) async fn next(&self) -> Option<Arc<str>> {
+        loop {
+            if let Some(update) = lock(&self.subscriber.queue).pop_front() {
+                return Some(update);
+            }
+            if self.subscriber.closed.load(Ordering::Relaxed) {
+                return None;
+            }
+            self.subscriber.ready.notified().await;
+        }
+    }
+
+    /// Every update, as a stream that unsubscribes when dropped.
+    pub(super) fn into_stream(self) -> impl Stream<Item = Arc<str>> {
+        futures_util::stream::unfold(self, |subscription| async move {
+            let update = subscription.next().await?;
+            Some((update, subscription))
+        })
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let mut topics = lock(&self.hub.topics);
+        let Some(topic) = topics
+            .get_mut(&self.key)
+            .filter(|topic| topic.id == self.topic)
+        else {
+            return;
+        };
+        topic
+            .subscribers
+            .retain(|subscriber| !Arc::ptr_eq(subscriber, &self.subscriber));
+        if topic.subscribers.is_empty() {
+            topics.remove(&self.key);
+        }
+    }
+}
+
+fn lock<T>(value: &Mutex<T>) -> MutexGuard<'_, T> {
+    // Queues and topics are changed whole under the lock, so a poisoned one
+    // is still consistent.
+    value.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The topic for `pairs` as seen by `audience`, who may be shown different
+/// columns from the same URL, in `locale`.
+pub(super) fn key(audience: Audience, locale: Locale, pairs: &[(String, String)]) -> String {
+    let query = serde_urlencoded::to_string(pairs).unwrap_or_default();
+    format!("{}/{}?{query}", audience.name(), locale.tag())
+}
@@ -0,0 +1,190 @@
+//! Log output, and changing its filter while the server runs.
+//!
+//! [`init`] installs the subscriber the binary logs through: `RUST_LOG`
+//! picks what is logged, `LOG_FORMAT` the layout and `LOG_SPANS=1` adds a
+//! line per closed span. The filter sits behind a reload layer, so an admin
+//! can swap it with `PUT /api/admin/log-level`, e.g. raising
+//! `tailwindsql=debug` for five minutes to follow one bad class name, and it
+//! goes back on its own once the time is up.
+
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Json, Response};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
+
+use super::privacy::Audience;
+use super::{AppError, AppState, ErrorResponse};
+use crate::error::Error;
+use crate::parser::tokens::interval_seconds;
+use crate::policy::PolicyError;
+
+/// The filter in force, set once by [`init`].
+static FILTER: OnceLock<Filter> = OnceLock::new();
+
+struct Filter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// Bumped on every change, so a pending revert can tell it was overtaken.
+    generation: AtomicU64,
+}
+
+/// Install the global subscriber, with its filter from `RUST_LOG`, its layout
+/// from `LOG_FORMAT` (`full`, `pretty`, `compact`, or `json` for one object per
+/// line with the request span's fields alongside each event) and span
+/// timings when `LOG_SPANS=1`.
+///
+/// # Panics
+/// Panics if a global subscriber is already installed.
+pub fn init() {
+    let span_events = if env::var("LOG_SPANS").is_ok_and(|value| value.trim() == "1") {
+        FmtSpan::CLOSE
+    } else {
+        FmtSpan::NONE
+    };
+    let (filter, handle) = reload::Layer::new(EnvFilter::from_default_env());
+    let logs = fmt::layer().with_span_events(span_events);
+    let format = env::var("LOG_FORMAT").unwrap_or_default();
+    let logs = match format.trim() {
+        "json" => logs
+            .json()
+            .flatten_event(true)
+            .with_span_list(false)
+            .boxed(),
+        "pretty" => logs.pretty().boxed(),
+        "compact" => logs.compact().boxed(),
+        _ => logs.boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(logs)
+        .init();
+    if !matches!(format.trim(), "" | "full" | "json" | "pretty" | "compact") {
+        warn!("Unknown LOG_FORMAT {format:?}; expected pretty, compact or json");
+    }
+    let _ = FILTER.set(Filter {
+        handle,
+        generation: AtomicU64::new(0),
+    });
+}
+
+/// A new filter, in `RUST_LOG` syntax, and how long it lasts.
+#[derive(Deserialize)]
+pub(super) struct LevelChange {
+    filter: String,
+    /// An interval such as `30s` or `5m`, after which the previous filter
+    /// comes back; without one the change stays.
+    #[serde(rename = "for")]
+    lasts: Option<String>,
+}
+
+#[derive(Serialize)]
+struct LevelResponse {
+    filter: String,
+    /// When set, the filter in force before this one and when it returns.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reverts: Option<Revert>,
+}
+
+#[derive(Serialize)]
+struct Revert {
+    to: String,
+    #[serde(rename = "inSeconds")]
+    in_seconds: u64,
+}
+
+/// `GET /api/admin/log-level`: the filter in force. Admin only.
+pub(super) async fn current(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    respond(admin_filter(&state, &headers).and_then(|filter| {
+        Ok(LevelResponse {
+            filter: current_filter(filter)?,
+            reverts: None,
+        })
+    }))
+}
+
+/// `PUT /api/admin/log-level`: replace the filter, for a while when `for` is
+/// given. Admin only.
+pub(super) async fn change(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(change): Json<LevelChange>,
+) -> Response {
+    respond(admin_filter(&state, &headers).and_then(|filter| apply(filter, change)))
+}
+
+fn apply(filter: &'static Filter, change: LevelChange) -> Result<LevelResponse, AppError> {
+    let next = EnvFilter::try_new(change.filter.trim())
+        .map_err(|error| Error::Request(format!("invalid log filter: {error}")))?;
+    let lasts = change
+        .lasts
+        .map(|lasts| {
+            interval_seconds(lasts.trim())
+                .ok_or_else(|| Error::Request(format!("invalid duration {lasts:?}")))
+        })
+        .transpose()?;
+
+    let previous = current_filter(filter)?;
+    filter.handle.reload(next).map_err(reload_failed)?;
+    let generation = filter.generation.fetch_add(1, Ordering::SeqCst) + 1;
+    let now = current_filter(filter)?;
+    info!(from = %previous, to = %now, "log filter changed");
+
+    let reverts = lasts.map(|seconds| {
+        let to = previous.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(seconds)).await;
+            if filter.generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            match EnvFilter::try_new(&to).map(|restored| filter.handle.reload(restored)) {
+                Ok(Ok(())) => info!(to = %to, "log filter restored"),
+                _ => warn!("Could not restore the log filter {to:?}"),
+            }
+        });
+        Revert {
+            to: previous,
+            in_seconds: seconds,
+        }
+    });
+    Ok(LevelResponse {
+        filter: now,
+        reverts,
+    })
+}
+
+/// The reloadable filter, for an admin caller.
+fn admin_filter(state: &AppState, headers: &HeaderMap) -> Result<&'static Filter, AppError> {
+    if state.redaction.audience(headers, false) != Audience::Admin {
+        return Err(Error::from(PolicyError::AdminRequired).into());
+    }
+    FILTER.get().ok_or_else(|| {
+        Error::Request("changing the log filter needs server::logging::init".to_string()).into()
+    })
+}
+
+fn current_filter(filter: &Filter) -> Result<String, AppError> {
+    filter
+        .handle
+        .with_current(ToString::to_string)
+        .map_err(reload_failed)
+}
+
+fn reload_failed(error: reload::Error) -> AppError {
+    Error::Request(format!("the log filter cannot be changed: {error}")).into()
+}
+
+fn respond(response: Result<LevelResponse, AppError>) -> Response {
+    match response {
+        Ok(response) => Json(response).into_response(),
+        Err(error) => (error.status(), Json(ErrorResponse::new(error))).into_response(),
+    }
+}
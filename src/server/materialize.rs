@@ -0,0 +1,248 @@
+//! Materialized results: expensive queries answered from cache tables.
+//!
+//! `MATERIALIZE=top_posts=db-posts-title-likes-orderby-likes-desc` keeps the
+//! rows of that query in the table `materialized_top_posts`. [`refresh`]
+//! rebuilds each table every `MATERIALIZE_REFRESH` (default 5m), and as soon
+//! as a write through `/api/mutate` touches a table the query reads. While a
+//! table is fresh, [`Materializations::lookup`] points the same query at it.
+//! The tables are left out of the schema catalog, so class names cannot read
+//! them directly around the redaction of the query they stand in for.
+
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use super::{env_list, resolve_config, AppState, Audience, QueryParams};
+use crate::db::sqlite::SqliteDatabase;
+use crate::db::{DbError, WorkerPool};
+use crate::dialect::Sqlite;
+use crate::parser::tokens::interval_seconds;
+use crate::parser::QueryConfig;
+use crate::query_builder::{build_materialization, QueryPolicy};
+use crate::schema::MATERIALIZED_PREFIX;
+
+const DEFAULT_REFRESH: Duration = Duration::from_secs(300);
+/// How often [`refresh`] looks for tables due a scheduled rebuild.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Every configured materialization, and the refresh schedule they share.
+pub(super) struct Materializations {
+    entries: Vec<Materialization>,
+    every: Duration,
+    /// Wakes [`refresh`] when a write makes a table stale.
+    changed: Notify,
+}
+
+struct Materialization {
+    table: String,
+    /// [`query_key`] of the query the table holds.
+    key: String,
+    config: QueryConfig,
+    /// Tables the query reads; a write to any of them makes it stale.
+    reads: Vec<String>,
+    status: Mutex<Status>,
+}
+
+#[derive(Default)]
+struct Status {
+    /// When the rows now in the table were read; `None` while it is missing
+    /// or stale.
+    refreshed_at: Option<Instant>,
+    /// Bumped by every write to `reads`, so a rebuild that raced one is not
+    /// taken for fresh.
+    generation: u64,
+}
+
+impl Materializations {
+    /// The `name=<class name>` entries of `MATERIALIZE`, resolved against
+    /// `state`'s schema and policy like a request. `None` when there are none,
+    /// or the primary database is not SQLite.
+    pub(super) fn from_env(state: &AppState) -> Option<Self> {
+        let entries = env_list("MATERIALIZE");
+        if entries.is_empty() {
+            return None;
+        }
+        if state.sqlite.is_none() {
+            warn!("Ignoring MATERIALIZE; materialized results need a SQLite database");
+            return None;
+        }
+
+        // Configured by the operator, so resolved without redaction; reads
+        // of the cache are redacted like the query they stand in for.
+        let state = AppState {
+            audience: Audience::Admin,
+            ..state.clone()
+        };
+        let mut materializations = Vec::new();
+        for entry in entries {
+            let Some((name, class_name)) = entry.split_once('=') else {
+                warn!("Ignoring materialization {entry:?}; expected name=<class name>");
+                continue;
+            };
+            let params = QueryParams {
+                class_name: Some(class_name.trim().to_string()),
+                ..QueryParams::default()
+            };
+            match resolve_config(&state, &params) {
+                Ok(config) => materializations.push(Materialization {
+                    table: format!("{MATERIALIZED_PREFIX}{}", name.trim()),
+                    key: query_key(&config),
+                    reads: tables_read(&config),
+                    config,
+                    status: Mutex::new(Status::default()),
+                }),
+                Err(error) => warn!("Ignoring materialization {name:?}: {error}"),
+            }
+        }
+        let every = std::env::var("MATERIALIZE_REFRESH")
+            .ok()
+            .and_then(|value| interval_seconds(value.trim()))
+            .map_or(DEFAULT_REFRESH, Duration::from_secs);
+        Some(Self {
+            entries: materializations,
+            every,
+            changed: Notify::new(),
+        })
+    }
+
+    /// `config` reading from its materialization instead, if one is fresh.
+    ///
+    /// The cache table holds the query's rows in order, so only the page
+    /// (`LIMIT`/`OFFSET`) is applied again. A query with its own `limit-`
+    /// is only served from the table from its first row.
+    pub(super) fn lookup(&self, config: &QueryConfig) -> Option<QueryConfig> {
+        let key = query_key(config);
+        let entry = self.entries.iter().find(|entry| entry.key == key)?;
+        if entry.config.limit.is_some() && config.offset.unwrap_or(0) != 0 {
+            return None;
+        }
+        let fresh = lock(&entry.status)
+            .refreshed_at
+            .is_some_and(|at| at.elapsed() < self.every);
+        fresh.then(|| QueryConfig {
+            table: entry.table.clone(),
+            columns: Vec::new(),
+            expressions: Vec::new(),
+            where_clauses: Vec::new(),
+            limit: config.limit,
+            offset: config.offset,
+            order_by: None,
+            joins: Vec::new(),
+            search: None,
+            refresh_seconds: config.refresh_seconds,
+            identity: Vec::new(),
+        })
+    }
+
+    /// Mark every materialization reading `table` stale after a write.
+    pub(super) fn invalidate(&self, table: &str) {
+        let mut stale = false;
+        for entry in &self.entries {
+            if entry.reads.iter().any(|read| read == table) {
+                let mut status = lock(&entry.status);
+                status.refreshed_at = None;
+                status.generation += 1;
+                stale = true;
+            }
+        }
+        if stale {
+            self.changed.notify_one();
+        }
+    }
+}
+
+/// Rebuild each materialization when it is stale or due, for as long as the
+/// server runs.
+pub(super) async fn refresh(
+    materializations: Arc<Materializations>,
+    workers: Arc<WorkerPool>,
+    sqlite: Arc<SqliteDatabase>,
+    limits: QueryPolicy,
+) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            () = materializations.changed.notified() => {}
+        }
+
+        for entry in &materializations.entries {
+            let generation = {
+                let status = lock(&entry.status);
+                let due = status
+                    .refreshed_at
+                    .is_none_or(|at| at.elapsed() >= materializations.every);
+                if !due {
+                    continue;
+                }
+                status.generation
+            };
+            let script = match build_materialization(&Sqlite, &entry.table, &entry.config, &limits)
+            {
+                Ok(script) => script,
+                Err(error) => {
+                    warn!("Cannot materialize {}: {error}", entry.table);
+                    continue;
+                }
+            };
+
+            let started = Instant::now();
+            let db = sqlite.clone();
+            let rebuilt = workers
+                .run(move || -> Result<(), DbError> {
+                    let conn = db.lock()?;
+                    let transaction = conn.unchecked_transaction()?;
+                    transaction.execute_batch(&script)?;
+                    Ok(transaction.commit()?)
+                })
+                .await
+                .and_then(|rebuilt| rebuilt);
+            match rebuilt {
+                Ok(()) => {
+                    let mut status = lock(&entry.status);
+                    if status.generation == generation {
+                        status.refreshed_at = Some(started);
+                    }
+                    info!(
+                        "Materialized {} in {:.1}ms",
+                        entry.table,
+                        started.elapsed().as_secs_f64() * 1000.0
+                    );
+                }
+                Err(error) => warn!("Materializing {} failed: {error}", entry.table),
+            }
+        }
+    }
+}
+
+/// What identifies a query for matching it to a materialization: its class
+/// name, joins included, without the page it asks for.
+fn query_key(config: &QueryConfig) -> String {
+    QueryConfig {
+        offset: None,
+        ..config.clone()
+    }
+    .to_class_name()
+}
+
+/// Every table `config` reads, including joined and subquery tables.
+fn tables_read(config: &QueryConfig) -> Vec<String> {
+    let mut tables = vec![config.table.clone()];
+    for join in &config.joins {
+        tables.push(join.table.clone());
+        tables.extend(join.through.as_ref().map(|through| through.table.clone()));
+    }
+    for clause in &config.where_clauses {
+        if let Some(subquery) = &clause.subquery {
+            tables.extend(tables_read(subquery));
+        }
+    }
+    tables
+}
+
+fn lock(status: &Mutex<Status>) -> std::sync::MutexGuard<'_, Status> {
+    // Status updates cannot panic halfway, so a poisoned lock is still consistent.
+    status.lock().unwrap_or_else(PoisonError::into_inner)
+}
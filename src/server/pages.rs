@@ -0,0 +1,168 @@
+//! The HTML pages: the landing page and the schema explorer.
+//!
+//! Handlers gather each page's data into an [`IndexPage`] or
+//! [`ExplorerPage`] and hand it to the server's [`PageRenderer`], which is the
+//! bundled Askama templates ([`AskamaPages`]) unless another is passed to
+//! [`super::run_with_pages`]. The page models derive `Serialize`, so a
+//! runtime engine such as minijinja can take them as its context directly:
+//!
+//! ```no_run
+//! use tailwindsql::server::pages::{ExplorerPage, IndexPage, PageError, PageRenderer};
+//!
+//! struct Plain;
+//!
+//! impl PageRenderer for Plain {
+//!     fn index(&self, page: &IndexPage) -> Result<String, PageError> {
+//!         Ok(format!("<h1>{}</h1>", page.hero_value))
+//!     }
+//!
+//!     fn explorer(&self, page: &ExplorerPage) -> Result<String, PageError> {
+//!         Ok(format!("<p>{} tables</p>", page.tables.len()))
+//!     }
+//! }
+//!
+//! # async fn serve() -> Result<(), tailwindsql::server::AppError> {
+//! tailwindsql::server::run_with_pages(Plain).await
+//! # }
+//! ```
+//!
+//! Example outputs are not part of the data: a page runs them while it
+//! renders, through [`IndexPage::queries`]. The bundled index template calls
+//! it as `db`, with a class name and the attributes a fragment URL takes:
+//!
+//! ```text
+//! {{ db("db-posts-title-limit-3", "as=ul")|safe }}
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Arc;
+
+use askama::Template;
+use serde::Serialize;
+
+pub use super::ColumnInfo;
+
+/// Renders the HTML pages from their data.
+pub trait PageRenderer: Send + Sync {
+    /// The landing page.
+    ///
+    /// # Errors
+    /// Returns `PageError` if the page cannot be rendered.
+    fn index(&self, page: &IndexPage) -> Result<String, PageError>;
+
+    /// The schema explorer, which loads rows from `/api/schema` itself.
+    ///
+    /// # Errors
+    /// Returns `PageError` if the page cannot be rendered.
+    fn explorer(&self, page: &ExplorerPage) -> Result<String, PageError>;
+}
+
+/// A failure from a [`PageRenderer`], whatever its template engine.
+#[derive(Debug, thiserror::Error)]
+#[error("page failed to render: {0}")]
+pub struct PageError(#[from] pub Box<dyn StdError + Send + Sync>);
+
+impl From<askama::Error> for PageError {
+    fn from(error: askama::Error) -> Self {
+        Self(Box::new(error))
+    }
+}
+
+/// The landing page's data.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexPage {
+    /// The hero's live value: the first user's name.
+    pub hero_value: String,
+    pub examples: Vec<ExampleCard>,
+    /// Runs the examples, and any other query the template embeds.
+    #[serde(skip)]
+    pub queries: PageQueries,
+}
+
+/// One example on the landing page: a class name, as highlighted `<DB>`
+/// markup, beside the output [`PageQueries::render`] gives for it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExampleCard {
+    pub title: String,
+    pub description: String,
+    pub code_html: String,
+    pub class_name: String,
+    /// Fragment URL params for the example, such as `as=ul` or `join=…`.
+    pub attributes: String,
+}
+
+/// A class name and its attributes to HTML.
+type RenderFn = dyn Fn(&str, &str) -> String + Send + Sync;
+
+/// Renders class names to HTML while a page renders.
+#[derive(Clone)]
+pub struct PageQueries(Arc<RenderFn>);
+
+impl PageQueries {
+    /// Queries answered by `render`, which takes a class name and its
+    /// attributes in fragment URL form.
+    pub fn new(render: impl Fn(&str, &str) -> String + Send + Sync + 'static) -> Self {
+        Self(Arc::new(render))
+    }
+
+    /// `class_name` rendered as `/api/fragment` would render it with the
+    /// params in `attributes`, such as `as=table&join=posts`. A query that
+    /// fails renders as the fragment's error message.
+    #[must_use]
+    pub fn render(&self, class_name: &str, attributes: &str) -> String {
+        (self.0)(class_name, attributes)
+    }
+}
+
+impl fmt::Debug for PageQueries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PageQueries").finish_non_exhaustive()
+    }
+}
+
+/// The explorer's data: the tables the caller may query.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExplorerPage {
+    pub tables: Vec<ExplorerTable>,
+    /// Whether `/api/mutate` accepts writes, so cells can be edited.
+    pub writable: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExplorerTable {
+    pub name: String,
+    pub columns: Vec<ColumnInfo>,
+}
+
+/// The bundled templates, `templates/index.html` and `templates/explorer.html`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AskamaPages;
+
+#[derive(Template)]
+#[template(path = "index.html")]
+struct IndexTemplate<'a> {
+    hero_value: &'a str,
+    examples: &'a [ExampleCard],
+    /// `{{ db(class_name, attributes) }}`.
+    db: &'a dyn Fn(&str, &str) -> String,
+}
+
+#[derive(Template)]
+#[template(path = "explorer.html")]
+struct ExplorerTemplate;
+
+impl PageRenderer for AskamaPages {
+    fn index(&self, page: &IndexPage) -> Result<String, PageError> {
+        let template = IndexTemplate {
+            hero_value: &page.hero_value,
+            examples: &page.examples,
+            db: &|class_name, attributes| page.queries.render(class_name, attributes),
+        };
+        Ok(template.render()?)
+    }
+
+    fn explorer(&self, _page: &ExplorerPage) -> Result<String, PageError> {
+        Ok(ExplorerTemplate.render()?)
+    }
+}
@@ -0,0 +1,268 @@
+//! Column privacy: redacting `INTERNAL_COLUMNS` and `PII_COLUMNS` by caller.
+//!
+//! Every request has an [`Audience`]. Calls carrying `Authorization: Bearer
+//! <ADMIN_TOKEN>` see everything. Other API calls lose their PII columns,
+//! and embeds (`/api/fragment` and the home page) lose internal columns too.
+//! PII is dropped from results, or replaced by a salted hash when
+//! `PII_REDACTION=hash` so values can still be told apart. Hidden columns
+//! cannot be filtered, sorted or computed on, which would reveal them anyway.
+
+use std::env;
+
+use axum::http::{header, HeaderMap};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::parser::{split_qualified, QueryConfig};
+use crate::policy::PolicyError;
+use crate::render::RowData;
+use crate::schema::{Privacy, SchemaCatalog};
+
+/// Who a response is for, from most to least trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Audience {
+    Admin,
+    Api,
+    Embed,
+}
+
+impl Audience {
+    /// The principal a query runs for, in its [`crate::context::QueryContext`].
+    pub(super) const fn name(self) -> &'static str {
+        match self {
+            Self::Admin => "admin",
+            Self::Api => "api",
+            Self::Embed => "embed",
+        }
+    }
+
+    pub(super) const fn hides(self, privacy: Privacy) -> bool {
+        match self {
+            Self::Admin => false,
+            Self::Api => matches!(privacy, Privacy::Pii),
+            Self::Embed => !matches!(privacy, Privacy::Public),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PiiRedaction {
+    Drop,
+    Hash,
+}
+
+/// The admin token and how PII is redacted for everyone else.
+#[derive(Debug)]
+pub(super) struct Redaction {
+    admin_token: Option<String>,
+    pii: PiiRedaction,
+    salt: String,
+}
+
+impl Redaction {
+    /// Settings from `ADMIN_TOKEN`, `PII_REDACTION` (`drop` or `hash`,
+    /// default `drop`) and `PII_HASH_SALT`. Without a salt, a random one is
+    /// chosen at startup, so hashes only match within one server run.
+    pub(super) fn from_env() -> Self {
+        let pii = match env::var("PII_REDACTION").as_deref().map(str::trim) {
+            Ok("hash") => PiiRedaction::Hash,
+            Ok("drop") | Err(_) => PiiRedaction::Drop,
+            Ok(other) => {
+                warn!("Unknown PII_REDACTION {other:?}; dropping PII columns");
+                PiiRedaction::Drop
+            }
+        };
+        Self {
+            admin_token: env::var("ADMIN_TOKEN")
+                .ok()
+                .map(|token| token.trim().to_string())
+                .filter(|token| !token.is_empty()),
+            pii,
+            salt: env::var("PII_HASH_SALT")
+                .unwrap_or_else(|_| format!("{:032x}", rand::random::<u128>())),
+        }
+    }
+
+    /// [`Audience::Admin`] for a request bearing the admin token, otherwise
+    /// `Api` or, for `embed`ded output, `Embed`.
+    pub(super) fn audience(&self, headers: &HeaderMap, embed: bool) -> Audience {
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim);
+        match (&self.admin_token, bearer) {
+            (Some(token), Some(bearer)) if constant_time_eq(token, bearer) => Audience::Admin,
+            _ if embed => Audience::Embed,
+            _ => Audience::Api,
+        }
+    }
+
+    /// Drop or hash the result columns `privacy_of` classifies as hidden from
+    /// `audience`. Internal columns are always dropped.
+    pub(super) fn redact(
+        &self,
+        audience: Audience,
+        rows: &mut [RowData],
+        columns: &mut Vec<String>,
+        privacy_of: impl Fn(&str) -> Privacy,
+    ) {
+        let mut hidden: Vec<(String, bool)> = Vec::new();
+        let mut keys: Vec<&String> = columns.iter().collect();
+        if keys.is_empty() {
+            keys.extend(rows.first().into_iter().flat_map(|row| row.keys()));
+        }
+        for key in keys {
+            let privacy = privacy_of(key);
+            if audience.hides(privacy) {
+                let hash = privacy == Privacy::Pii && self.pii == PiiRedaction::Hash;
+                hidden.push((key.clone(), hash));
+            }
+        }
+        if hidden.is_empty() {
+            return;
+        }
+
+        columns.retain(|column| !hidden.iter().any(|(key, hash)| key == column && !hash));
+        for row in rows {
+            for (key, hash) in &hidden {
+                if !hash {
+                    row.remove(key);
+                } else if let Some(value) = row.get_mut(key) {
+                    *value = self.hash(value);
+                }
+            }
+        }
+    }
+
+    /// A salted SHA-256 of `value`'s text, truncated to 128 bits; `null` stays
+    /// `null`.
+    fn hash(&self, value: &JsonValue) -> JsonValue {
+        let text = match value {
+            JsonValue::Null => return JsonValue::Null,
+            JsonValue::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        let digest = Sha256::new()
+            .chain_update(&self.salt)
+            .chain_update(text)
+            .finalize();
+        JsonValue::String(
+            digest[..16]
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+        )
+    }
+}
+
+/// Reject `config` if it filters, sorts, joins or computes on a
+/// column hidden from `audience`, including anywhere in a subquery. Hidden
+/// columns may still be selected; [`Redaction::redact`] handles those.
+///
+/// # Errors
+/// Returns `PolicyError::ColumnDenied` for the first such column.
+pub(super) fn check(
+    schema: &SchemaCatalog,
+    config: &QueryConfig,
+    audience: Audience,
+) -> Result<(), PolicyError> {
+    if audience == Audience::Admin {
+        return Ok(());
+    }
+    let hidden = |table: &str, column: &str| {
+        if audience.hides(schema.privacy(table, column)) {
+            Err(PolicyError::ColumnDenied {
+                table: table.to_string(),
+                column: column.to_string(),
+            })
+        } else {
+            Ok(())
+        }
+    };
+    let check_column = |column: &str| {
+        let (table, name) = table_of(config, column);
+        hidden(table, name)
+    };
+
+    for clause in &config.where_clauses {
+        check_column(&clause.field)?;
+        if let Some(subquery) = &clause.subquery {
+            check(schema, subquery, audience)?;
+            for column in &subquery.columns {
+                let (table, name) = table_of(subquery, column);
+                hidden(table, name)?;
+            }
+        }
+    }
+    if let Some(order_by) = &config.order_by {
+        check_column(&order_by.field)?;
+    }
+    for expression in &config.expressions {
+        expression
+            .columns()
+            .into_iter()
+            .try_for_each(check_column)?;
+    }
+    for join in &config.joins {
+        let parent = join
+            .parent_table
+            .as_deref()
+            .and_then(|parent| {
+                config
+                    .joins
+                    .iter()
+                    .find(|other| other.reference() == parent)
+            })
+            .map_or(config.table.as_str(), |parent| parent.table.as_str());
+        hidden(parent, &join.parent_column)?;
+        hidden(&join.table, &join.child_column)?;
+    }
+    Ok(())
+}
+
+/// The first column hidden from `audience` in any table `config` reads, so
+/// its results need [`Redaction::redact`].
+pub(super) fn first_hidden<'a>(
+    schema: &'a SchemaCatalog,
+    config: &'a QueryConfig,
+    audience: Audience,
+) -> Option<(&'a str, &'a str)> {
+    std::iter::once(config.table.as_str())
+        .chain(config.joins.iter().map(|join| join.table.as_str()))
+        .filter_map(|table| schema.table(table))
+        .find_map(|table| {
+            table
+                .columns
+                .iter()
+                .find(|column| audience.hides(column.privacy))
+                .map(|column| (table.name.as_str(), column.name.as_str()))
+        })
+}
+
+/// The table a possibly qualified `column` of `config` belongs to, resolving
+/// join aliases, and the column's own name.
+fn table_of<'a>(config: &'a QueryConfig, column: &'a str) -> (&'a str, &'a str) {
+    match split_qualified(column) {
+        (Some(qualifier), name) => {
+            let table = config
+                .joins
+                .iter()
+                .find(|join| join.reference() == qualifier)
+                .map_or(qualifier, |join| join.table.as_str());
+            (table, name)
+        }
+        (None, name) => (config.table.as_str(), name),
+    }
+}
+
+/// Compare secrets without stopping at the first differing byte.
+pub(super) fn constant_time_eq(left: &str, right: &str) -> bool {
+    left.len() == right.len()
+        && left
+            .bytes()
+            .zip(right.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
@@ -0,0 +1,273 @@
+//! Proxy mode: serving another TailwindSQL instance from the edge.
+//!
+//! With `PROXY_UPSTREAM` set to an `http://host:port` URL, [`forward`] sends
+//! every request except `/api/metrics`, `/api/admin/log-level` and `/static`
+//! on to that instance instead of answering it here. `GET` responses are
+//! kept in a [`ResultCache`] sized and timed like the result cache, per URL,
+//! `Accept-Language` and `Authorization` header, so pages and fragments loaded over and over
+//! reach the upstream once per interval, and identical requests arriving
+//! together share one upstream request. Cached fragments keep their `ETag`, so a
+//! client already holding one is answered `304 Not Modified` here. Server-sent
+//! event streams such as `/api/live` never end, so are passed on as they arrive
+//! and never cached. Any other method passes straight through, and empties the
+//! cache once it succeeds.
+//!
+//! A `postgres://` `PROXY_UPSTREAM` instead stands in for `DATABASE_URL`, with
+//! every read query cached and coalesced the same way.
+
+use std::env;
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Json, Response};
+use hyper_util::rt::TokioIo;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::net::TcpStream;
+use tracing::{info_span, warn, Instrument};
+
+use super::embed::not_modified;
+use super::request_id::{RequestId, X_REQUEST_ID};
+use super::result_cache::{ResultCache, ResultCacheMetrics};
+use super::{AppError, AppState, ErrorResponse, MAX_RESULT_BYTES};
+use crate::db;
+
+/// Headers that describe one connection rather than the message, so are not
+/// passed between the client and the upstream.
+const HOP_BY_HOP: [HeaderName; 6] = [
+    header::CONNECTION,
+    header::HOST,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// The upstream instance and the responses kept from it.
+pub(super) struct Proxy {
+    /// The upstream's `host:port`.
+    authority: String,
+    responses: ResultCache<ResponseKey, Upstreamed>,
+}
+
+/// A request's path and query, its `Accept-Language`, which fragments are
+/// rendered in, and a hash of its `Authorization` header, since admins are
+/// shown more than other callers.
+type ResponseKey = (String, Vec<u8>, Vec<u8>);
+
+/// A buffered upstream response.
+#[derive(Clone)]
+struct Upstreamed {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+#[derive(Debug, Error)]
+enum ProxyError {
+    #[error("cannot reach the upstream: {0}")]
+    Connect(#[from] std::io::Error),
+    #[error("upstream request failed: {0}")]
+    Http(#[from] hyper::Error),
+    #[error("invalid upstream request: {0}")]
+    Request(#[from] axum::http::Error),
+    #[error("cannot read the upstream response: {0}")]
+    Body(#[from] axum::Error),
+}
+
+impl Proxy {
+    /// The proxy for an `http://` `PROXY_UPSTREAM`, or `None` when it is
+    /// unset or names a PostgreSQL server.
+    ///
+    /// # Errors
+    /// Returns `AppError::ProxyUpstream` for any other URL.
+    pub(super) fn from_env() -> Result<Option<Self>, AppError> {
+        let Ok(url) = env::var("PROXY_UPSTREAM") else {
+            return Ok(None);
+        };
+        let url = url.trim();
+        if url.is_empty() || db::is_postgres_url(url) {
+            return Ok(None);
+        }
+        let authority = url
+            .strip_prefix("http://")
+            .map(|rest| rest.trim_end_matches('/'))
+            .filter(|authority| !authority.is_empty() && !authority.contains('/'))
+            .ok_or_else(|| AppError::ProxyUpstream(url.to_string()))?;
+        Ok(Some(Self {
+            authority: authority.to_string(),
+            responses: ResultCache::from_env(),
+        }))
+    }
+
+    pub(super) fn upstream(&self) -> &str {
+        &self.authority
+    }
+
+    pub(super) fn metrics(&self) -> ResultCacheMetrics {
+        self.responses.metrics()
+    }
+
+    /// Send `request` upstream, answering once the response headers arrive.
+    async fn open(&self, request: Request) -> Result<Response, ProxyError> {
+        let stream = TcpStream::connect(&self.authority).await?;
+        let (mut sender, connection) =
+            hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(connection);
+
+        let (parts, body) = request.into_parts();
+        let path_and_query = parts
+            .uri
+            .path_and_query()
+            .map_or("/", |path_and_query| path_and_query.as_str());
+        let mut upstream = Request::builder()
+            .method(parts.method)
+            .uri(path_and_query)
+            .body(body)?;
+        *upstream.headers_mut() = without_hop_by_hop(parts.headers);
+        let host = HeaderValue::from_str(&self.authority).map_err(axum::http::Error::from)?;
+        upstream.headers_mut().insert(header::HOST, host);
+        if let Some(id) =
+            RequestId::current().and_then(|id| HeaderValue::from_str(&id.to_string()).ok())
+        {
+            upstream.headers_mut().insert(X_REQUEST_ID.clone(), id);
+        }
+
+        let (mut parts, body) = sender.send_request(upstream).await?.into_parts();
+        parts.headers = without_hop_by_hop(parts.headers);
+        Ok(Response::from_parts(parts, Body::new(body)))
+    }
+
+    /// Send `request` upstream and buffer the response.
+    async fn send(&self, request: Request) -> Result<Upstreamed, ProxyError> {
+        buffer(self.open(request).await?).await
+    }
+}
+
+/// Middleware answering requests from the upstream when proxy mode is on.
+pub(super) async fn forward(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(proxy) = state.proxy.clone() else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path();
+    if matches!(path, "/api/metrics" | "/api/admin/log-level") || path.starts_with("/static/") {
+        return next.run(request).await;
+    }
+    let live = path == "/api/live" || is_event_stream(request.headers(), header::ACCEPT);
+    let span = info_span!("upstream", upstream = proxy.upstream());
+
+    if request.method() == Method::GET && live {
+        return proxy
+            .open(request)
+            .instrument(span)
+            .await
+            .unwrap_or_else(|error| respond(Err(error)));
+    }
+    if request.method() != Method::GET {
+        let response = proxy.send(request).instrument(span).await;
+        if response
+            .as_ref()
+            .is_ok_and(|response| response.status.is_success())
+        {
+            proxy.responses.clear();
+        }
+        return respond(response);
+    }
+
+    let key = response_key(&request);
+    let conditional = request.headers().clone();
+    let (mut parts, body) = request.into_parts();
+    parts.headers.remove(header::IF_NONE_MATCH);
+    let request = Request::from_parts(parts, body);
+    let response = proxy
+        .responses
+        .get_or_run(key, async {
+            let response = match proxy.open(request).await {
+                Ok(response) if is_event_stream(response.headers(), header::CONTENT_TYPE) => {
+                    return Err(response);
+                }
+                Ok(response) => buffer(response).await,
+                Err(error) => Err(error),
+            };
+            // Failed responses are passed on but not kept.
+            match response {
+                Ok(response) if response.status.is_success() => Ok(response),
+                response => Err(respond(response)),
+            }
+        })
+        .instrument(span)
+        .await;
+    match response {
+        Ok(response) => {
+            let etag = response
+                .headers
+                .get(header::ETAG)
+                .and_then(|etag| etag.to_str().ok());
+            if etag.is_some_and(|etag| not_modified(&conditional, etag)) {
+                return StatusCode::NOT_MODIFIED.into_response();
+            }
+            respond(Ok(response))
+        }
+        Err(response) => response,
+    }
+}
+
+async fn buffer(response: Response) -> Result<Upstreamed, ProxyError> {
+    let (parts, body) = response.into_parts();
+    Ok(Upstreamed {
+        status: parts.status,
+        headers: parts.headers,
+        body: axum::body::to_bytes(body, MAX_RESULT_BYTES).await?,
+    })
+}
+
+/// Whether the `name` header of `headers` names a server-sent event stream.
+fn is_event_stream(headers: &HeaderMap, name: HeaderName) -> bool {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("text/event-stream"))
+}
+
+fn response_key(request: &Request) -> ResponseKey {
+    let path_and_query = request
+        .uri()
+        .path_and_query()
+        .map_or_else(String::new, ToString::to_string);
+    let language = request
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .map_or_else(Vec::new, |value| value.as_bytes().to_vec());
+    let credentials = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .map_or_else(Vec::new, |value| Sha256::digest(value.as_bytes()).to_vec());
+    (path_and_query, language, credentials)
+}
+
+fn respond(response: Result<Upstreamed, ProxyError>) -> Response {
+    match response {
+        Ok(upstreamed) => {
+            let mut response = (upstreamed.status, upstreamed.body).into_response();
+            response.headers_mut().extend(upstreamed.headers);
+            response
+        }
+        Err(error) => {
+            warn!("{error}");
+            (StatusCode::BAD_GATEWAY, Json(ErrorResponse::new(error))).into_response()
+        }
+    }
+}
+
+fn without_hop_by_hop(mut headers: HeaderMap) -> HeaderMap {
+    for name in &HOP_BY_HOP {
+        headers.remove(name);
+    }
+    headers
+}
@@ -0,0 +1,119 @@
+//! Request IDs for following one API call through the logs.
+//!
+//! [`assign`] gives every request an ID, taken from an inbound `X-Request-Id`
+//! when a client SDK or proxy already chose one, and runs the request inside
+//! a `request` span carrying it. The parse, build, execute and render spans
+//! nest under that span, including on the database workers, so every log
+//! line of the request names its ID. The ID is echoed in the `X-Request-Id`
+//! response header and in JSON error bodies.
+//!
+//! Query handlers add the class name, table and row count to the span with
+//! [`record_query`] and [`record_rows`], and each request ends with one
+//! `request finished` line giving its status and duration, so `LOG_FORMAT=json`
+//! output can be filtered on any of them without parsing messages.
+
+use std::fmt;
+use std::time::Instant;
+
+use axum::extract::Request;
+use axum::http::{HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::{Serialize, Serializer};
+use tracing::field::Empty;
+use tracing::{info, info_span, Instrument, Span};
+
+use crate::parser::QueryConfig;
+
+pub(super) static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Inbound IDs longer than this are replaced rather than logged.
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT: RequestId;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct RequestId(String);
+
+impl RequestId {
+    /// A random 128-bit ID in hex.
+    fn generate() -> Self {
+        Self(format!("{:032x}", rand::random::<u128>()))
+    }
+
+    /// The client's ID if it is short and printable, so it cannot forge log
+    /// lines or bloat them.
+    fn from_header(value: &HeaderValue) -> Option<Self> {
+        let value = value.to_str().ok()?.trim();
+        let valid = !value.is_empty()
+            && value.len() <= MAX_LEN
+            && value.bytes().all(|byte| byte.is_ascii_graphic());
+        valid.then(|| Self(value.to_string()))
+    }
+
+    /// The ID of the request being handled, if any.
+    pub(super) fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for RequestId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+/// Middleware running each request under its ID.
+pub(super) async fn assign(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(RequestId::from_header)
+        .unwrap_or_else(RequestId::generate);
+    let span = info_span!(
+        "request",
+        id = %id,
+        method = %request.method(),
+        path = request.uri().path(),
+        class_name = Empty,
+        table = Empty,
+        rows = Empty,
+    );
+    let header = HeaderValue::from_str(&id.0).ok();
+    let started = Instant::now();
+    let mut response = CURRENT
+        .scope(id, next.run(request))
+        .instrument(span.clone())
+        .await;
+    span.in_scope(|| {
+        info!(
+            status = response.status().as_u16(),
+            elapsed_ms = started.elapsed().as_secs_f64() * 1000.0,
+            "request finished"
+        );
+    });
+    if let Some(header) = header {
+        response.headers_mut().insert(X_REQUEST_ID.clone(), header);
+    }
+    response
+}
+
+/// Note the query the current request runs on its `request` span.
+pub(super) fn record_query(config: &QueryConfig) {
+    let span = Span::current();
+    span.record("class_name", config.to_class_name());
+    span.record("table", config.table.as_str());
+}
+
+/// Note how many rows the current request's query returned.
+pub(super) fn record_rows(rows: usize) {
+    Span::current().record("rows", rows);
+}
@@ -0,0 +1,209 @@
+//! Cached query results for hot, opted-in queries.
+//!
+//! A request opts in with `cache=1`, or by writing its class name as
+//! `cache:db-...`. Its rows are then kept for `RESULT_CACHE_TTL` (default
+//! 30s), keyed on the database, SQL and bound values, so a demo page loaded
+//! over and over reads the database once per interval. At most
+//! `RESULT_CACHE_SIZE` results (default 256) are kept, and any write through
+//! the server empties the cache. Identical queries that arrive while one is
+//! already running wait for its result instead of running again.
+//!
+//! The proxy mode keeps upstream responses in the same kind of cache.
+
+use std::collections::HashMap;
+use std::env;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use rusqlite::types::Value;
+use serde::Serialize;
+use tokio::sync::OnceCell;
+
+use super::QueryOutput;
+use crate::db::Backend;
+use crate::parser::tokens::interval_seconds;
+
+const DEFAULT_TTL: Duration = Duration::from_secs(30);
+const DEFAULT_SIZE: usize = 256;
+
+/// Which database a query ran on, its SQL and its bound values.
+type Key = (usize, String, String);
+
+pub(super) struct ResultCache<K = Key, V = QueryOutput> {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<K, Entry<V>>>,
+    /// Runs under way, which identical requests wait on.
+    running: Mutex<HashMap<K, Arc<OnceCell<V>>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    coalesced: AtomicU64,
+}
+
+struct Entry<V> {
+    output: V,
+    stored_at: Instant,
+}
+
+/// A snapshot of [`ResultCache`] use.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub(super) struct ResultCacheMetrics {
+    capacity: usize,
+    #[serde(rename = "ttlSeconds")]
+    ttl_seconds: u64,
+    cached: usize,
+    hits: u64,
+    misses: u64,
+    /// Requests that waited on an identical one already running.
+    coalesced: u64,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> ResultCache<K, V> {
+    /// A cache sized by `RESULT_CACHE_SIZE` (`0` turns caching off) whose
+    /// results live for `RESULT_CACHE_TTL`.
+    pub(super) fn from_env() -> Self {
+        let ttl = env::var("RESULT_CACHE_TTL")
+            .ok()
+            .and_then(|value| interval_seconds(value.trim()))
+            .map_or(DEFAULT_TTL, Duration::from_secs);
+        let capacity = env::var("RESULT_CACHE_SIZE")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(DEFAULT_SIZE);
+        Self::new(ttl, capacity)
+    }
+
+    fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+            running: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+        }
+    }
+
+    /// The result stored under `key`, or else the result of `run`, stored.
+    ///
+    /// While `run` is under way, callers with the same `key` wait for it
+    /// rather than running their own. Errors are not shared: when `run`
+    /// fails, the next waiting caller runs instead.
+    pub(super) async fn get_or_run<E>(
+        &self,
+        key: K,
+        run: impl Future<Output = Result<V, E>>,
+    ) -> Result<V, E> {
+        let cell = {
+            // Checked under `running`, which a run leaves only once its
+            // result is stored, so one result never runs twice.
+            let mut running = lock(&self.running);
+            if let Some(output) = self.get(&key) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(output);
+            }
+            match running.get(&key) {
+                Some(cell) => {
+                    self.coalesced.fetch_add(1, Ordering::Relaxed);
+                    cell.clone()
+                }
+                None => {
+                    self.misses.fetch_add(1, Ordering::Relaxed);
+                    running.entry(key.clone()).or_default().clone()
+                }
+            }
+        };
+        let output = cell
+            .get_or_try_init(|| async {
+                let output = run.await?;
+                self.insert(key.clone(), output.clone());
+                Ok(output)
+            })
+            .await
+            .cloned();
+        let mut running = lock(&self.running);
+        if running
+            .get(&key)
+            .is_some_and(|running| Arc::ptr_eq(running, &cell))
+        {
+            running.remove(&key);
+        }
+        output
+    }
+
+    /// The unexpired result stored under `key`, if any.
+    fn get(&self, key: &K) -> Option<V> {
+        let mut entries = lock(&self.entries);
+        let fresh = entries
+            .get(key)
+            .filter(|entry| entry.stored_at.elapsed() < self.ttl)
+            .map(|entry| entry.output.clone());
+        if fresh.is_none() {
+            entries.remove(key);
+        }
+        fresh
+    }
+
+    /// Store `output` under `key`, making room by dropping expired results,
+    /// then the oldest.
+    fn insert(&self, key: K, output: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = lock(&self.entries);
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            entries.retain(|_, entry| entry.stored_at.elapsed() < self.ttl);
+            if entries.len() >= self.capacity {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.stored_at)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                output,
+                stored_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Forget every result, after a write may have changed any of them.
+    pub(super) fn clear(&self) {
+        lock(&self.entries).clear();
+    }
+
+    pub(super) fn metrics(&self) -> ResultCacheMetrics {
+        ResultCacheMetrics {
+            capacity: self.capacity,
+            ttl_seconds: self.ttl.as_secs(),
+            cached: lock(&self.entries).len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn lock<T>(map: &Mutex<T>) -> MutexGuard<'_, T> {
+    // Entries are replaced whole, so a poisoned map is still consistent.
+    map.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The key for running `sql` with `params` on `database`.
+pub(super) fn key(database: &Arc<dyn Backend>, sql: &str, params: &[Value]) -> Key {
+    // `Debug` tells apart values that print alike, such as `1` and `'1'`.
+    (
+        Arc::as_ptr(database).cast::<()>() as usize,
+        sql.to_string(),
+        format!("{params:?}"),
+    )
+}
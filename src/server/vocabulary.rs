@@ -0,0 +1,117 @@
+//! `/api/vocabulary/:table`: the filters each column of a table takes, so a
+//! query builder can check what is typed before submitting it.
+//!
+//! Operators follow the column's [`ValueFormat`]: numbers compare with
+//! `gt`/`lt` (written `after`/`before` in class names), dates with
+//! `after`/`before` and relative keywords, text with `contains`. Each comes
+//! with the class name syntax that writes it and, where the format has one, a
+//! pattern values must match.
+
+use serde::Serialize;
+
+use crate::parser::WhereOperator;
+use crate::schema::{ColumnInfo, TableSchema, ValueFormat};
+
+#[derive(Debug, Serialize)]
+pub(super) struct Vocabulary {
+    table: String,
+    columns: Vec<ColumnVocabulary>,
+}
+
+#[derive(Debug, Serialize)]
+struct ColumnVocabulary {
+    name: String,
+    #[serde(rename = "type")]
+    col_type: String,
+    format: ValueFormat,
+    /// A regular expression whole values match; absent for free text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pattern: Option<&'static str>,
+    operators: Vec<Operator>,
+}
+
+#[derive(Debug, Serialize)]
+struct Operator {
+    name: &'static str,
+    sql: &'static str,
+    /// The where clause in a class name, with `{value}` to fill in.
+    syntax: String,
+}
+
+/// The vocabulary of `table`'s columns that `filterable` allows.
+pub(super) fn describe(
+    table: &TableSchema,
+    filterable: impl Fn(&ColumnInfo) -> bool,
+) -> Vocabulary {
+    Vocabulary {
+        table: table.name.clone(),
+        columns: table
+            .columns
+            .iter()
+            .filter(|column| filterable(column))
+            .map(describe_column)
+            .collect(),
+    }
+}
+
+fn describe_column(column: &ColumnInfo) -> ColumnVocabulary {
+    let format = column.value_format();
+    let name = &column.name;
+    let operators = operators(format)
+        .iter()
+        .map(|&(operator_name, operator)| Operator {
+            name: operator_name,
+            sql: operator.as_sql(),
+            syntax: match operator {
+                WhereOperator::Eq => format!("where-{name}-{{value}}"),
+                WhereOperator::In => format!("where-{name}-in-({{class name}})"),
+                WhereOperator::After => format!("where-{name}-after-{{value}}"),
+                WhereOperator::Before => format!("where-{name}-before-{{value}}"),
+                WhereOperator::Contains => format!("where-{name}-contains-{{value}}"),
+            },
+        })
+        .collect();
+    ColumnVocabulary {
+        name: name.clone(),
+        col_type: column.col_type.clone(),
+        format,
+        pattern: pattern(format),
+        operators,
+    }
+}
+
+/// The operators that make sense for `format`, by the name clients know
+/// them by.
+const fn operators(format: ValueFormat) -> &'static [(&'static str, WhereOperator)] {
+    match format {
+        ValueFormat::Integer | ValueFormat::Number => &[
+            ("eq", WhereOperator::Eq),
+            ("gt", WhereOperator::After),
+            ("lt", WhereOperator::Before),
+            ("in", WhereOperator::In),
+        ],
+        ValueFormat::Date => &[
+            ("eq", WhereOperator::Eq),
+            ("after", WhereOperator::After),
+            ("before", WhereOperator::Before),
+        ],
+        ValueFormat::Boolean => &[("eq", WhereOperator::Eq)],
+        ValueFormat::Text => &[
+            ("eq", WhereOperator::Eq),
+            ("contains", WhereOperator::Contains),
+            ("in", WhereOperator::In),
+        ],
+    }
+}
+
+const fn pattern(format: ValueFormat) -> Option<&'static str> {
+    match format {
+        ValueFormat::Integer => Some("^[0-9]+$"),
+        ValueFormat::Number => Some("^[0-9]+(\\.[0-9]+)?$"),
+        ValueFormat::Boolean => Some("^(true|false|0|1)$"),
+        ValueFormat::Date => {
+            Some("^(today|yesterday|last-[0-9]+-(minute|hour|day|week|month|year)s?)$")
+        }
+        ValueFormat::Text => None,
+    }
+}
@@ -0,0 +1,161 @@
+use serde::Serialize;
+
+/// Clause keywords the builder emits, longest first so `LEFT JOIN` wins over `JOIN`.
+const CLAUSE_KEYWORDS: &[&str] = &[
+    "INNER JOIN",
+    "LEFT JOIN",
+    "RIGHT JOIN",
+    "ORDER BY",
+    "SELECT",
+    "FROM",
+    "WHERE",
+    "LIMIT",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffStatus {
+    Same,
+    Changed,
+    Added,
+    Removed,
+}
+
+/// One clause (or one `WHERE` predicate / join) compared across two queries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClauseDiff {
+    pub clause: String,
+    pub status: DiffStatus,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+/// Split generated SQL into `(keyword, body)` clauses.
+///
+/// Keywords inside parentheses or quotes (subqueries, string literals) do not
+/// start a clause, and a `WHERE` body is further split on top-level `AND` so
+/// predicates can be compared one by one.
+#[must_use]
+pub fn sql_clauses(sql: &str) -> Vec<(String, String)> {
+    let mut starts: Vec<(usize, &str)> = Vec::new();
+    let mut depth = 0_usize;
+    let mut quote: Option<u8> = None;
+    let bytes = sql.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        match quote {
+            Some(q) if byte == q => quote = None,
+            Some(_) => {}
+            None => match byte {
+                b'\'' | b'"' => quote = Some(byte),
+                b'(' => depth += 1,
+                b')' => depth = depth.saturating_sub(1),
+                _ if depth == 0 && (i == 0 || bytes[i - 1] == b' ') => {
+                    if let Some(keyword) = CLAUSE_KEYWORDS.iter().find(|keyword| {
+                        sql[i..].starts_with(*keyword)
+                            && bytes
+                                .get(i + keyword.len())
+                                .is_none_or(|next| *next == b' ')
+                    }) {
+                        starts.push((i, keyword));
+                        i += keyword.len();
+                        continue;
+                    }
+                }
+                _ => {}
+            },
+        }
+        i += 1;
+    }
+
+    let mut clauses = Vec::new();
+    for (index, (start, keyword)) in starts.iter().enumerate() {
+        let end = starts.get(index + 1).map_or(sql.len(), |(next, _)| *next);
+        let body = sql[start + keyword.len()..end].trim();
+        if *keyword == "WHERE" {
+            clauses.extend(
+                split_top_level_and(body)
+                    .into_iter()
+                    .map(|predicate| ((*keyword).to_string(), predicate)),
+            );
+        } else {
+            clauses.push(((*keyword).to_string(), body.to_string()));
+        }
+    }
+    clauses
+}
+
+/// Split on ` AND ` outside parentheses.
+fn split_top_level_and(body: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0_usize;
+    let mut last = 0;
+    for (i, byte) in body.bytes().enumerate() {
+        match byte {
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            b' ' if depth == 0 && body[i..].starts_with(" AND ") => {
+                parts.push(body[last..i].trim().to_string());
+                last = i + " AND ".len();
+            }
+            _ => {}
+        }
+    }
+    parts.push(body[last..].trim().to_string());
+    parts
+}
+
+/// Compare two generated queries clause by clause.
+///
+/// `SELECT`, `FROM`, `ORDER BY` and `LIMIT` pair up by keyword; joins and
+/// `WHERE` predicates are matched by text, so a reordered predicate shows as
+/// unchanged and a new one as added.
+#[must_use]
+pub fn diff_sql(left: &str, right: &str) -> Vec<ClauseDiff> {
+    let left = sql_clauses(left);
+    let mut right: Vec<Option<(String, String)>> =
+        sql_clauses(right).into_iter().map(Some).collect();
+
+    let mut diffs = Vec::new();
+    for (clause, body) in left {
+        let repeatable = clause == "WHERE" || clause.ends_with("JOIN");
+        let matched = right.iter_mut().find(|candidate| {
+            candidate.as_ref().is_some_and(|(other, other_body)| {
+                *other == clause && (!repeatable || *other_body == body)
+            })
+        });
+        let diff = match matched.and_then(Option::take) {
+            Some((_, other_body)) => ClauseDiff {
+                status: if other_body == body {
+                    DiffStatus::Same
+                } else {
+                    DiffStatus::Changed
+                },
+                clause,
+                left: Some(body),
+                right: Some(other_body),
+            },
+            None => ClauseDiff {
+                clause,
+                status: DiffStatus::Removed,
+                left: Some(body),
+                right: None,
+            },
+        };
+        diffs.push(diff);
+    }
+
+    diffs.extend(
+        right
+            .into_iter()
+            .flatten()
+            .map(|(clause, body)| ClauseDiff {
+                clause,
+                status: DiffStatus::Added,
+                left: None,
+                right: Some(body),
+            }),
+    );
+    diffs
+}
@@ -0,0 +1,262 @@
+//! Data subject requests: export or erase everything tied to one record.
+//!
+//! Starting from one row, the schema's foreign keys are followed to every row
+//! that references it, directly or through other referencing rows: a user's
+//! posts, and the comments on those posts. [`export_subject`] returns those
+//! rows as JSON; [`erase_subject`] deletes them, or blanks their
+//! [`Privacy::Pii`] columns, in one transaction. SQLite only.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use rusqlite::types::Value;
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::db::sqlite::for_each_row;
+use crate::db::value_to_json;
+use crate::dialect::{SqlDialect, Sqlite};
+use crate::error::Error;
+use crate::render::RowData;
+use crate::schema::{Privacy, SchemaCatalog, ValidationError};
+
+/// Row IDs are bound this many at a time, well under SQLite's parameter cap.
+const CHUNK: usize = 500;
+
+/// Every row tied to a subject, by table.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SubjectRows {
+    pub tables: BTreeMap<String, Vec<RowData>>,
+}
+
+/// What [`erase_subject`] does to the subject's rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Erasure {
+    Delete,
+    /// Set PII columns to `NULL`, or to `erased-<rowid>` where they are
+    /// `NOT NULL`, and keep the rows.
+    Anonymize,
+}
+
+/// Rows changed per table by [`erase_subject`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ErasureReport {
+    pub tables: BTreeMap<String, u64>,
+}
+
+/// The row of `table` whose primary key is `key`, and every row referencing
+/// it through foreign keys.
+///
+/// # Errors
+/// Returns `Error::Invalid` for an unknown table, `Error::Request` if it has
+/// no single-column primary key or no such row, and `Error::Db` if a query
+/// fails.
+pub fn export_subject(
+    conn: &Connection,
+    schema: &SchemaCatalog,
+    table: &str,
+    key: &Value,
+) -> Result<SubjectRows, Error> {
+    Ok(collect(conn, schema, table, key)?.rows)
+}
+
+/// Delete or anonymize the rows [`export_subject`] returns, all or nothing.
+///
+/// # Errors
+/// Returns the errors of [`export_subject`], and `Error::Request` when
+/// anonymizing rows that have no PII columns.
+pub fn erase_subject(
+    conn: &Connection,
+    schema: &SchemaCatalog,
+    table: &str,
+    key: &Value,
+    erasure: Erasure,
+) -> Result<ErasureReport, Error> {
+    let transaction = conn.unchecked_transaction()?;
+    let collected = collect(&transaction, schema, table, key)?;
+    let mut report = ErasureReport::default();
+
+    // Referencing rows go first, so no row is left pointing at a deleted one.
+    for table in collected.order.iter().rev() {
+        let rowids: Vec<i64> = collected.rowids[table].iter().copied().collect();
+        let quoted = Sqlite.quote_identifier(table);
+        let assignments = match erasure {
+            Erasure::Delete => None,
+            Erasure::Anonymize => {
+                let assignments = pii_assignments(&transaction, schema, table)?;
+                if assignments.is_empty() {
+                    continue;
+                }
+                Some(assignments)
+            }
+        };
+        let mut changed = 0;
+        for chunk in rowids.chunks(CHUNK) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let sql = match &assignments {
+                None => format!("DELETE FROM {quoted} WHERE rowid IN ({placeholders})"),
+                Some(assignments) => {
+                    format!("UPDATE {quoted} SET {assignments} WHERE rowid IN ({placeholders})")
+                }
+            };
+            changed += transaction.execute(&sql, rusqlite::params_from_iter(chunk))? as u64;
+        }
+        report.tables.insert(table.clone(), changed);
+    }
+
+    if erasure == Erasure::Anonymize && report.tables.is_empty() {
+        return Err(Error::Request(format!(
+            "nothing to anonymize: no PII columns in {}",
+            collected.order.join(", ")
+        )));
+    }
+    transaction.commit()?;
+    Ok(report)
+}
+
+/// The subject's rows, with their row IDs and the order tables were reached.
+struct Collected {
+    rows: SubjectRows,
+    rowids: BTreeMap<String, BTreeSet<i64>>,
+    order: Vec<String>,
+}
+
+fn collect(
+    conn: &Connection,
+    schema: &SchemaCatalog,
+    table: &str,
+    key: &Value,
+) -> Result<Collected, Error> {
+    if schema.table(table).is_none() {
+        return Err(Error::Invalid(vec![ValidationError::UnknownTable(
+            table.to_string(),
+        )]));
+    }
+    let primary_key = primary_key(conn, table)?;
+    let mut collected = Collected {
+        rows: SubjectRows::default(),
+        rowids: BTreeMap::new(),
+        order: Vec::new(),
+    };
+    let sql = format!(
+        "SELECT rowid AS \"__rowid\", * FROM {} WHERE {} = ?",
+        Sqlite.quote_identifier(table),
+        Sqlite.quote_identifier(&primary_key)
+    );
+    if !collected.add(conn, table, &sql, std::slice::from_ref(key))? {
+        return Err(Error::Request(format!(
+            "no {table} row with {primary_key} = {}",
+            value_to_json(key.clone())
+        )));
+    }
+
+    // A table is revisited whenever it gains rows, until nothing new turns up.
+    let mut queue = VecDeque::from([table.to_string()]);
+    while let Some(parent) = queue.pop_front() {
+        let parent_rowids: Vec<Value> = collected.rowids[&parent]
+            .iter()
+            .map(|rowid| Value::Integer(*rowid))
+            .collect();
+        for child in schema.tables() {
+            for key in child
+                .foreign_keys
+                .iter()
+                .filter(|key| key.foreign_table == parent)
+            {
+                let mut found = false;
+                for chunk in parent_rowids.chunks(CHUNK) {
+                    let sql = format!(
+                        "SELECT rowid AS \"__rowid\", * FROM {} WHERE {} IN \
+                         (SELECT {} FROM {} WHERE rowid IN ({}))",
+                        Sqlite.quote_identifier(&child.name),
+                        Sqlite.quote_identifier(&key.column),
+                        Sqlite.quote_identifier(&key.foreign_column),
+                        Sqlite.quote_identifier(&parent),
+                        vec!["?"; chunk.len()].join(", ")
+                    );
+                    found |= collected.add(conn, &child.name, &sql, chunk)?;
+                }
+                if found && !queue.contains(&child.name) {
+                    queue.push_back(child.name.clone());
+                }
+            }
+        }
+    }
+    Ok(collected)
+}
+
+impl Collected {
+    /// Add the rows `sql` returns to `table`'s, returning whether any were new.
+    fn add(
+        &mut self,
+        conn: &Connection,
+        table: &str,
+        sql: &str,
+        params: &[Value],
+    ) -> Result<bool, Error> {
+        let mut found = false;
+        for_each_row(conn, sql, params, &mut |mut row| {
+            let Some(rowid) = row.remove("__rowid").and_then(|id| id.as_i64()) else {
+                return Ok(true);
+            };
+            if self
+                .rowids
+                .entry(table.to_string())
+                .or_default()
+                .insert(rowid)
+            {
+                found = true;
+                self.rows
+                    .tables
+                    .entry(table.to_string())
+                    .or_default()
+                    .push(row);
+            }
+            Ok(true)
+        })?;
+        if found && !self.order.iter().any(|seen| seen == table) {
+            self.order.push(table.to_string());
+        }
+        Ok(found)
+    }
+}
+
+/// The single-column primary key of `table`.
+fn primary_key(conn: &Connection, table: &str) -> Result<String, Error> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?) WHERE pk > 0")?;
+    let columns = stmt
+        .query_map([table], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    match <[String; 1]>::try_from(columns) {
+        Ok([column]) => Ok(column),
+        Err(_) => Err(Error::Request(format!(
+            "{table} has no single-column primary key"
+        ))),
+    }
+}
+
+/// `SET` assignments blanking the PII columns of `table`.
+fn pii_assignments(
+    conn: &Connection,
+    schema: &SchemaCatalog,
+    table: &str,
+) -> Result<String, Error> {
+    let mut stmt = conn.prepare("SELECT name, \"notnull\" FROM pragma_table_info(?)")?;
+    let columns = stmt
+        .query_map([table], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let assignments: Vec<String> = columns
+        .into_iter()
+        .filter(|(name, _)| schema.privacy(table, name) == Privacy::Pii)
+        .map(|(name, not_null)| {
+            let value = if not_null {
+                "'erased-' || rowid"
+            } else {
+                "NULL"
+            };
+            format!("{} = {value}", Sqlite.quote_identifier(&name))
+        })
+        .collect();
+    Ok(assignments.join(", "))
+}
@@ -0,0 +1,417 @@
+//! An in-process server for end-to-end tests.
+//!
+//! [`TestServer::start`] serves the full router on a random local port over a
+//! freshly seeded in-memory database, and its helpers send plain HTTP/1.1
+//! requests, so tests exercise the same parsing, policy, rendering and JSON
+//! paths as a deployed server.
+//!
+//! [`render_cases`] covers the renderer on its own: every [`RenderAs`] mode
+//! over fixed rows, for snapshot suites that make markup changes reviewable.
+//!
+//! ```no_run
+//! # async fn demo() -> Result<(), tailwindsql::test_support::TestError> {
+//! use tailwindsql::test_support::TestServer;
+//!
+//! let server = TestServer::start().await?;
+//! let response = server.query("db-users-name-limit-3").await?;
+//! assert_eq!(response.json()?["count"], 3);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::net::{Ipv4Addr, SocketAddr};
+#[cfg(unix)]
+use std::path::Path;
+
+use axum::body::{Body, BodyDataStream};
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::Router;
+use futures_util::StreamExt;
+use hyper_util::rt::TokioIo;
+use rusqlite::Connection;
+use serde_json::{json, Value as JsonValue};
+use thiserror::Error;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::db::sqlite::SqliteDatabase;
+use crate::db::{self, DbError};
+use crate::policy::AccessPolicy;
+use crate::render::{render_results, RenderAs, RowData};
+use crate::server::pages::PageRenderer;
+use crate::server::{self, router, AppError, AppState};
+
+#[derive(Debug, Error)]
+pub enum TestError {
+    #[error("server error: {0}")]
+    Server(#[from] AppError),
+    #[error("db error: {0}")]
+    Db(#[from] DbError),
+    #[error("sql error: {0}")]
+    Sql(#[from] rusqlite::Error),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("http error: {0}")]
+    Http(#[from] hyper::Error),
+    #[error("request error: {0}")]
+    Request(#[from] axum::http::Error),
+    #[error("body error: {0}")]
+    Body(#[from] axum::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// The router serving a seeded in-memory SQLite database on `127.0.0.1`.
+///
+/// The server stops when this is dropped.
+pub struct TestServer {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+/// A server-sent event stream from a [`TestServer`], closed when dropped.
+pub struct TestEvents {
+    body: BodyDataStream,
+    buffer: String,
+}
+
+impl TestEvents {
+    /// The `data` of the next event, or `None` once the stream ends.
+    ///
+    /// # Errors
+    /// Returns `TestError` if the body cannot be read.
+    pub async fn next(&mut self) -> Result<Option<String>, TestError> {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let event: String = self.buffer.drain(..end + 2).collect();
+                let data: Vec<&str> = event
+                    .lines()
+                    .filter_map(|line| line.strip_prefix("data:"))
+                    .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                    .collect();
+                // Keep-alive comments carry no data.
+                if !data.is_empty() {
+                    return Ok(Some(data.join("\n")));
+                }
+                continue;
+            }
+            match self.body.next().await {
+                Some(chunk) => self.buffer.push_str(&String::from_utf8_lossy(&chunk?)),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// A buffered response from a [`TestServer`].
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl TestResponse {
+    /// The body parsed as JSON.
+    ///
+    /// # Errors
+    /// Returns `TestError::Json` if the body is not JSON.
+    pub fn json(&self) -> Result<JsonValue, TestError> {
+        Ok(serde_json::from_str(&self.body)?)
+    }
+}
+
+impl TestServer {
+    /// Serve the demo seed data under the default access policy.
+    ///
+    /// # Errors
+    /// Returns `TestError` if seeding or binding the listener fails.
+    pub async fn start() -> Result<Self, TestError> {
+        Self::start_with(AccessPolicy::default()).await
+    }
+
+    /// Serve the demo seed data under `policy`, e.g. with writes allowed.
+    ///
+    /// # Errors
+    /// Returns `TestError` if seeding or binding the listener fails.
+    pub async fn start_with(policy: AccessPolicy) -> Result<Self, TestError> {
+        let mut conn = Connection::open_in_memory()?;
+        db::seed_database(&mut conn)?;
+        Self::serve(SqliteDatabase::new(conn), policy).await
+    }
+
+    /// Serve the demo seed data, rendering the HTML pages with `pages`.
+    ///
+    /// # Errors
+    /// Returns `TestError` if seeding or binding the listener fails.
+    pub async fn start_with_pages(pages: impl PageRenderer + 'static) -> Result<Self, TestError> {
+        let mut conn = Connection::open_in_memory()?;
+        db::seed_database(&mut conn)?;
+        let state = AppState::for_sqlite(SqliteDatabase::new(conn), AccessPolicy::default())?;
+        Self::listen(router(state.with_pages(pages))).await
+    }
+
+    /// Serve the demo seed data on a Unix socket at `path`, replacing a
+    /// socket left there the way `UNIX_SOCKET` does. Abort the returned task
+    /// to stop serving.
+    ///
+    /// # Errors
+    /// Returns `TestError` if seeding fails, or `path` holds something other
+    /// than a socket or cannot be bound.
+    #[cfg(unix)]
+    pub async fn serve_unix(path: &Path) -> Result<JoinHandle<()>, TestError> {
+        let mut conn = Connection::open_in_memory()?;
+        db::seed_database(&mut conn)?;
+        let state = AppState::for_sqlite(SqliteDatabase::new(conn), AccessPolicy::default())?;
+        let listener = server::bind_unix(path)?;
+        let app = router(state);
+        Ok(tokio::spawn(async move {
+            let _ = server::serve_unix(listener, app).await;
+        }))
+    }
+
+    /// Serve `db`, for tests that bring their own fixture.
+    ///
+    /// # Errors
+    /// Returns `TestError` if the schema cannot be loaded or binding fails.
+    pub async fn serve(db: SqliteDatabase, policy: AccessPolicy) -> Result<Self, TestError> {
+        Self::listen(router(AppState::for_sqlite(db, policy)?)).await
+    }
+
+    async fn listen(app: Router) -> Result<Self, TestError> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let addr = listener.local_addr()?;
+        let task = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        Ok(Self { addr, task })
+    }
+
+    #[must_use]
+    pub const fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The absolute URL of `path_and_query` on this server.
+    #[must_use]
+    pub fn url(&self, path_and_query: &str) -> String {
+        format!("http://{}{path_and_query}", self.addr)
+    }
+
+    /// `GET path_and_query`.
+    ///
+    /// # Errors
+    /// Returns `TestError` if the request cannot be sent or read.
+    pub async fn get(&self, path_and_query: &str) -> Result<TestResponse, TestError> {
+        self.send("GET", path_and_query, &[], None).await
+    }
+
+    /// `GET path_and_query` with extra request `headers`.
+    ///
+    /// # Errors
+    /// Returns `TestError` if the request cannot be sent or read.
+    pub async fn get_with(
+        &self,
+        path_and_query: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<TestResponse, TestError> {
+        self.send("GET", path_and_query, headers, None).await
+    }
+
+    /// `POST path_and_query`, with `body` as JSON when given.
+    ///
+    /// # Errors
+    /// Returns `TestError` if the request cannot be sent or read.
+    pub async fn post(
+        &self,
+        path_and_query: &str,
+        body: Option<&JsonValue>,
+    ) -> Result<TestResponse, TestError> {
+        self.send("POST", path_and_query, &[], body).await
+    }
+
+    /// `method path_and_query` with extra request `headers`, and `body` as
+    /// JSON when given.
+    ///
+    /// # Errors
+    /// Returns `TestError` if the request cannot be sent or read.
+    pub async fn request(
+        &self,
+        method: &str,
+        path_and_query: &str,
+        headers: &[(&str, &str)],
+        body: Option<&JsonValue>,
+    ) -> Result<TestResponse, TestError> {
+        self.send(method, path_and_query, headers, body).await
+    }
+
+    /// `GET /api/query` for `class_name`.
+    ///
+    /// # Errors
+    /// Returns `TestError` if the request cannot be sent or read.
+    pub async fn query(&self, class_name: &str) -> Result<TestResponse, TestError> {
+        self.get(&api_path("/api/query", &[("className", class_name)]))
+            .await
+    }
+
+    /// `GET /api/fragment` for `class_name`, rendered `as`.
+    ///
+    /// # Errors
+    /// Returns `TestError` if the request cannot be sent or read.
+    pub async fn fragment(
+        &self,
+        class_name: &str,
+        render_as: &str,
+    ) -> Result<TestResponse, TestError> {
+        self.get(&api_path(
+            "/api/fragment",
+            &[("className", class_name), ("as", render_as)],
+        ))
+        .await
+    }
+
+    /// `GET path_and_query` as a server-sent event stream, once its headers
+    /// arrive.
+    ///
+    /// # Errors
+    /// Returns `TestError` if the request cannot be sent.
+    pub async fn events(&self, path_and_query: &str) -> Result<TestEvents, TestError> {
+        let stream = TcpStream::connect(self.addr).await?;
+        let (mut sender, connection) =
+            hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(connection);
+        let request = Request::builder()
+            .uri(path_and_query)
+            .header(header::HOST, self.addr.to_string())
+            .body(Body::empty())?;
+        let body = sender.send_request(request).await?.into_body();
+        Ok(TestEvents {
+            body: Body::new(body).into_data_stream(),
+            buffer: String::new(),
+        })
+    }
+
+    async fn send(
+        &self,
+        method: &str,
+        path_and_query: &str,
+        headers: &[(&str, &str)],
+        json: Option<&JsonValue>,
+    ) -> Result<TestResponse, TestError> {
+        let stream = TcpStream::connect(self.addr).await?;
+        let (mut sender, connection) =
+            hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(connection);
+
+        let request = headers.iter().fold(
+            Request::builder()
+                .method(method)
+                .uri(path_and_query)
+                .header(header::HOST, self.addr.to_string()),
+            |request, (name, value)| request.header(*name, *value),
+        );
+        let request = match json {
+            Some(json) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(json)?))?,
+            None => request.body(Body::empty())?,
+        };
+
+        let (parts, body) = sender.send_request(request).await?.into_parts();
+        let body = axum::body::to_bytes(Body::new(body), usize::MAX).await?;
+        Ok(TestResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn api_path(path: &str, params: &[(&str, &str)]) -> String {
+    format!(
+        "{path}?{}",
+        serde_urlencoded::to_string(params).unwrap_or_default()
+    )
+}
+
+/// One renderer input from [`render_cases`].
+#[derive(Debug, Clone)]
+pub struct RenderCase {
+    pub render_as: RenderAs,
+    pub rows: Vec<RowData>,
+    pub columns: Vec<String>,
+}
+
+impl RenderCase {
+    /// A stable name such as `table_many_rows_multi_column`, for snapshot files.
+    #[must_use]
+    pub fn name(&self) -> String {
+        let rows = match self.rows.len() {
+            0 => "no_rows",
+            1 => "one_row",
+            _ => "many_rows",
+        };
+        let columns = if self.columns.len() == 1 {
+            "single_column"
+        } else {
+            "multi_column"
+        };
+        format!("{}_{rows}_{columns}", self.render_as.as_str())
+    }
+
+    #[must_use]
+    pub fn render(&self) -> String {
+        render_results(&self.rows, &self.columns, self.render_as)
+    }
+
+    /// [`Self::render`] with each tag on its own line, so snapshot diffs
+    /// point at the element that changed.
+    #[must_use]
+    pub fn snapshot(&self) -> String {
+        // Text is escaped, so `><` only ever sits between two tags.
+        self.render().replace("><", ">\n<")
+    }
+}
+
+/// Every [`RenderAs`] mode over no rows, one row and several rows, each with
+/// a single column and with several.
+///
+/// The rows are fixed, and include markup, a `NULL` and a boolean so escaping
+/// and value formatting show up in the output.
+#[must_use]
+pub fn render_cases() -> Vec<RenderCase> {
+    let rows = fixture_rows();
+    let row_counts = [0, 1, rows.len()];
+    let column_sets = [vec!["name"], vec!["id", "name", "role", "active"]];
+
+    let mut cases = Vec::new();
+    for render_as in RenderAs::ALL {
+        for count in row_counts {
+            for columns in &column_sets {
+                cases.push(RenderCase {
+                    render_as,
+                    rows: rows[..count].to_vec(),
+                    columns: columns.iter().map(ToString::to_string).collect(),
+                });
+            }
+        }
+    }
+    cases
+}
+
+fn fixture_rows() -> Vec<RowData> {
+    [
+        json!({"id": 1, "name": "Ada Lovelace", "role": "admin", "active": true}),
+        json!({"id": 2, "name": "Grace <Hopper>", "role": "user", "active": false}),
+        json!({"id": 3, "name": "Alan & \"Co\"", "role": null, "active": true}),
+    ]
+    .into_iter()
+    .map(|row| serde_json::from_value(row).expect("fixture rows are objects"))
+    .collect()
+}
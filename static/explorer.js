@@ -7,6 +7,8 @@
     activeTable: null,
     loading: true,
     error: null,
+    writable: false,
+    notice: null,
   };
 
   function render() {
@@ -77,14 +79,21 @@
         .join('');
 
       dataRowsHtml = current.data
-        .map((row) => {
+        .map((row, index) => {
+          const editable = state.writable && row.id !== undefined && row.id !== null;
           const cells = current.columns
-            .map(
-              (col) => `
-              <td class="border border-white/10 px-2 sm:px-3 py-1.5 sm:py-2 text-slate-300 font-mono text-xs break-words max-w-[150px] sm:max-w-none">
+            .map((col) => {
+              const edit =
+                editable && col.name !== 'id'
+                  ? ` data-row="${index}" data-column="${escapeHtml(col.name)}" title="Double-click to edit"`
+                  : '';
+              return `
+              <td${edit} class="border border-white/10 px-2 sm:px-3 py-1.5 sm:py-2 text-slate-300 font-mono text-xs break-words max-w-[150px] sm:max-w-none${
+                edit ? ' cursor-text' : ''
+              }">
                 ${escapeHtml(formatValue(row[col.name]))}
-              </td>`
-            )
+              </td>`;
+            })
             .join('');
           return `<tr class="hover:bg-white/5 transition-colors">${cells}</tr>`;
         })
@@ -136,7 +145,10 @@
               </div>
             </div>
             <div class="px-3 sm:px-4 py-2 border-t border-white/10 bg-white/[0.02] text-xs text-slate-500">
-              Showing ${current.data.length} of ${current.rowCount} rows
+              Showing ${current.data.length} of ${current.rowCount} rows${
+                state.writable ? ' &middot; double-click a cell to edit it' : ''
+              }
+              ${state.notice ? `<span class="text-red-400 ml-2">${escapeHtml(state.notice)}</span>` : ''}
             </div>
           </div>`
               : ''
@@ -180,6 +192,38 @@
     render();
   });
 
+  root.addEventListener('dblclick', async (event) => {
+    const cell = event.target.closest('[data-column]');
+    if (!cell) return;
+    const current = state.tables.find((table) => table.name === state.activeTable);
+    const row = current && current.data[Number(cell.dataset.row)];
+    if (!row) return;
+    const column = cell.dataset.column;
+    const previous = row[column];
+    const input = window.prompt(`New value for ${column}`, previous === null ? '' : String(previous));
+    if (input === null || input === String(previous)) return;
+
+    const value = typeof previous === 'number' && input.trim() !== '' && !Number.isNaN(Number(input))
+      ? Number(input)
+      : input;
+    try {
+      const response = await fetch('/api/mutate', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ table: current.name, values: { [column]: value }, where: { id: row.id } }),
+      });
+      const data = await response.json();
+      if (!response.ok) {
+        throw new Error(data.error || `Update failed (${response.status})`);
+      }
+      row[column] = value;
+      state.notice = null;
+    } catch (error) {
+      state.notice = error instanceof Error ? error.message : 'Update failed';
+    }
+    render();
+  });
+
   async function fetchSchema() {
     state.loading = true;
     render();
@@ -187,6 +231,7 @@
       const response = await fetch('/api/schema');
       const data = await response.json();
       state.tables = data.tables || [];
+      state.writable = Boolean(data.writable);
       state.activeTable = state.tables.length ? state.tables[0].name : null;
       state.error = null;
     } catch (error) {
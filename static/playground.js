@@ -181,7 +181,7 @@
     const startIndex = (state.currentPage - 1) * ITEMS_PER_PAGE;
     const endIndex = startIndex + ITEMS_PER_PAGE;
     const paginated = results.slice(startIndex, endIndex);
-    const headers = Object.keys(results[0]);
+    const headers = Object.keys(results[0]).filter((key) => key !== '__id');
 
     let contentHtml = '';
 
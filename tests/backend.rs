@@ -0,0 +1,1182 @@
+use std::sync::Arc;
+
+use rusqlite::types::Value;
+use serde_json::json;
+use tailwindsql::db::mock::MockDatasource;
+use tailwindsql::db::sqlite::SqliteDatabase;
+#[cfg(feature = "seed")]
+use tailwindsql::db::{
+    init_db, seed_spec, try_elect_writer, DbConfig, SeedMode, SeedOptions, SeedSpec,
+};
+use tailwindsql::db::{open_reader, Backend};
+use tailwindsql::dialect::Sqlite;
+use tailwindsql::parser::{parse_class_names, parse_mutation};
+use tailwindsql::query_builder::{
+    build_delete, build_insert, build_materialization, build_query_for, build_update,
+    QueryBuilderError, QueryPolicy,
+};
+use tailwindsql::schema::Privacy;
+use tailwindsql::subject::{erase_subject, export_subject, Erasure};
+
+fn users_database() -> SqliteDatabase {
+    let db = SqliteDatabase::open_in_memory().expect("in-memory database should open");
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute_batch(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, role TEXT);
+             INSERT INTO users (name, role) VALUES ('Ada', 'admin'), ('Alan', 'user'), ('Grace', 'admin');",
+        )
+        .expect("fixture should load");
+    db
+}
+
+#[test]
+fn built_query_runs_against_in_memory_sqlite() {
+    let db = users_database();
+    let config = parse_class_names("db-users-name-where-role-admin-orderby-name-asc")
+        .expect("class name should parse");
+    let built = build_query_for(db.dialect(), &config, &QueryPolicy::default())
+        .expect("query should build");
+
+    let result = db
+        .query(&built.sql, &built.params, &QueryPolicy::default())
+        .expect("query should run");
+
+    assert_eq!(result.columns, ["name"]);
+    let names: Vec<_> = result.rows.iter().map(|row| row["name"].clone()).collect();
+    assert_eq!(names, [json!("Ada"), json!("Grace")]);
+    assert_eq!(result.truncated_at, None);
+}
+
+#[test]
+fn row_cap_truncates_result() {
+    let db = users_database();
+    let limits = QueryPolicy {
+        max_rows: Some(2),
+        ..QueryPolicy::default()
+    };
+
+    let result = db
+        .query("SELECT id FROM users", &[], &limits)
+        .expect("query should run");
+
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.truncated_at, Some(2));
+}
+
+#[test]
+fn search_without_an_index_matches_the_term_literally() {
+    let db = SqliteDatabase::open_in_memory().expect("in-memory database should open");
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute_batch(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT);
+             INSERT INTO notes (body) VALUES ('50% off'), ('500 items'), ('a_c'), ('abc');",
+        )
+        .expect("fixture should load");
+    let schema = db.load_schema().expect("schema should load");
+    let ids = |class_name: &str| {
+        let config = parse_class_names(class_name).expect("class name should parse");
+        let built = build_query_for(
+            db.dialect(),
+            &schema.resolve_search(&config),
+            &QueryPolicy::default(),
+        )
+        .expect("query should build");
+        assert!(built.sql.contains("LIKE ? ESCAPE '\\'"), "{}", built.sql);
+        let result = db
+            .query(&built.sql, &built.params, &QueryPolicy::default())
+            .expect("query should run");
+        result
+            .rows
+            .iter()
+            .map(|row| row["id"].clone())
+            .collect::<Vec<_>>()
+    };
+
+    // `%` and `_` are not wildcards in a search term.
+    assert_eq!(ids("db-notes-id-search-50%"), [json!(1)]);
+    assert_eq!(ids("db-notes-id-search-a_c"), [json!(3)]);
+}
+
+#[test]
+fn database_urls_name_sqlite_files_or_other_backends() {
+    use std::path::PathBuf;
+    use tailwindsql::db::{duckdb_path, is_postgres_url, mock_path, parse_database_url};
+
+    let path = |url: &str| parse_database_url(url).map(|path| path.display().to_string());
+    assert_eq!(
+        path("sqlite:///data/app.db").as_deref(),
+        Some("/data/app.db")
+    );
+    assert_eq!(path("sqlite://data/app.db").as_deref(), Some("data/app.db"));
+    assert_eq!(path("sqlite:app.db?mode=rwc").as_deref(), Some("app.db"));
+    assert_eq!(path("file:///tmp/app.db").as_deref(), Some("/tmp/app.db"));
+    assert_eq!(path("file:app.db").as_deref(), Some("app.db"));
+    assert_eq!(path("  data/app.db  ").as_deref(), Some("data/app.db"));
+    assert_eq!(path("sqlite::memory:").as_deref(), Some(":memory:"));
+    assert_eq!(path(":memory:").as_deref(), Some(":memory:"));
+
+    for other in [
+        "",
+        "sqlite://",
+        "?mode=rwc",
+        "postgres://localhost/app",
+        "postgresql://localhost/app",
+        "duckdb://data.duckdb",
+        "mock://tables.json",
+    ] {
+        assert_eq!(path(other), None, "{other:?}");
+    }
+    assert!(is_postgres_url(" postgresql://localhost/app"));
+    assert_eq!(duckdb_path("duckdb:"), Some(PathBuf::from(":memory:")));
+    assert_eq!(
+        mock_path("mock:tables.json"),
+        Some(PathBuf::from("tables.json"))
+    );
+}
+
+#[test]
+fn payload_cap_stops_before_the_row_that_would_pass_it() {
+    let db = users_database();
+    // `{"id":1}` and `,{"id":2}` fit; a third row would not.
+    let limits = QueryPolicy::default().with_max_payload_bytes(20);
+
+    let result = db
+        .query("SELECT id FROM users ORDER BY id", &[], &limits)
+        .expect("query should run");
+
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.truncated_at, Some(2));
+    let all = db
+        .query("SELECT id FROM users", &[], &QueryPolicy::default())
+        .expect("query should run");
+    assert_eq!(all.truncated_at, None);
+}
+
+#[test]
+fn repeated_queries_reuse_cached_statements() {
+    let conn = rusqlite::Connection::open_in_memory().expect("in-memory database should open");
+    let db = SqliteDatabase::with_statement_cache(conn, 2);
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute_batch("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, role TEXT)")
+        .expect("fixture should load");
+    let limits = QueryPolicy::default();
+    let sql = |column: &str| format!("SELECT {column} FROM users WHERE id = ?");
+    for (column, id) in [
+        ("name", 1),
+        ("name", 2),
+        ("role", 1),
+        ("id", 1),
+        ("name", 3),
+    ] {
+        db.query(&sql(column), &[id.into()], &limits)
+            .expect("query should run");
+    }
+
+    // `name` was evicted by `role` and `id` before its last use.
+    let metrics = db.statements().metrics();
+    assert_eq!((metrics.hits, metrics.misses), (1, 4));
+    assert_eq!(metrics.cached, 2);
+}
+
+#[test]
+fn pooled_readers_query_while_the_writer_is_busy() {
+    let path = std::env::temp_dir().join(format!("tailwindsql-pool-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let writer = rusqlite::Connection::open(&path).expect("database should open");
+    writer
+        .pragma_update(None, "journal_mode", "WAL")
+        .expect("WAL should be enabled");
+    writer
+        .execute_batch(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, role TEXT);
+             INSERT INTO users (name, role) VALUES ('Ada', 'admin'), ('Alan', 'user'), ('Grace', 'admin');",
+        )
+        .expect("fixture should load");
+    let readers = (0..2)
+        .map(|_| open_reader(&path).expect("reader should open"))
+        .collect();
+    let db = Arc::new(SqliteDatabase::pooled(writer, readers));
+    assert_eq!(db.readers(), 2);
+
+    let busy = db.lock().expect("lock should not be poisoned");
+    let reader = {
+        let db = db.clone();
+        std::thread::spawn(move || {
+            db.query("SELECT name FROM users", &[], &QueryPolicy::default())
+                .map(|result| result.rows.len())
+        })
+    };
+    let read = reader.join().expect("reader thread should not panic");
+    assert_eq!(read.expect("query should run"), 3);
+    drop(busy);
+
+    // Writes, even ones returning rows, go through the writer.
+    let (execution, returned) = db
+        .execute_returning(
+            "UPDATE users SET role = 'guest' WHERE id = 1 RETURNING role",
+            &[],
+            &QueryPolicy::default(),
+        )
+        .expect("update should run");
+    assert_eq!(execution.rows_affected, 1);
+    assert_eq!(returned.rows[0]["role"], json!("guest"));
+    let result = db
+        .query(
+            "SELECT role FROM users WHERE id = 1",
+            &[],
+            &QueryPolicy::default(),
+        )
+        .expect("query should run");
+    assert_eq!(result.rows[0]["role"], json!("guest"));
+
+    drop(db);
+    for suffix in ["", "-wal", "-shm"] {
+        let mut file = path.clone().into_os_string();
+        file.push(suffix);
+        let _ = std::fs::remove_file(file);
+    }
+}
+
+#[cfg(feature = "seed")]
+#[test]
+fn db_config_opens_memory_seeded_and_files_read_only() {
+    let memory = init_db(&DbConfig::in_memory()).expect("in-memory database should open");
+    assert!(memory.seeded && !memory.is_writer());
+    let users: i64 = memory
+        .connection
+        .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))
+        .expect("users should be seeded");
+    assert!(users > 0);
+
+    let path = std::env::temp_dir().join(format!("tailwindsql-ro-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let read_only = DbConfig {
+        read_only: true,
+        ..DbConfig::new(&path)
+    };
+    assert!(
+        init_db(&read_only).is_err(),
+        "a missing file is not created"
+    );
+
+    rusqlite::Connection::open(&path)
+        .and_then(|conn| conn.execute_batch("CREATE TABLE notes (body TEXT)"))
+        .expect("fixture should load");
+    let opened = init_db(&read_only).expect("existing file should open");
+    assert!(!opened.seeded && !opened.is_writer());
+    assert!(opened
+        .connection
+        .execute("INSERT INTO notes (body) VALUES ('hi')", [])
+        .is_err());
+    let foreign_keys: bool = DbConfig {
+        foreign_keys: true,
+        ..DbConfig::new(&path)
+    }
+    .open()
+    .and_then(|conn| Ok(conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?))
+    .expect("pragma should read");
+    assert!(foreign_keys);
+
+    drop(opened);
+    for suffix in ["", "-wal", "-shm", ".seed.lock"] {
+        let mut file = path.clone().into_os_string();
+        file.push(suffix);
+        let _ = std::fs::remove_file(file);
+    }
+}
+
+#[cfg(feature = "seed")]
+#[test]
+fn one_process_is_writer_and_seeding_is_all_or_nothing() {
+    let path = std::env::temp_dir().join(format!("tailwindsql-writer-{}.db", std::process::id()));
+    let sibling = |suffix: &str| {
+        let mut file = path.clone().into_os_string();
+        file.push(suffix);
+        std::path::PathBuf::from(file)
+    };
+    let _ = std::fs::remove_file(&path);
+    // A seed that died part way through left its staging file behind.
+    std::fs::write(sibling(".seeding"), "not a database").expect("staging file should write");
+
+    let config = DbConfig {
+        seed: SeedOptions {
+            users: 3,
+            products: 2,
+            posts: 2,
+            ..SeedOptions::default()
+        },
+        ..DbConfig::new(&path)
+    };
+    let first = init_db(&config).expect("database should seed");
+    assert!(first.seeded && first.is_writer());
+    assert!(!sibling(".seeding").exists());
+    let users: i64 = first
+        .connection
+        .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))
+        .expect("users should be seeded");
+    assert_eq!(users, 3);
+
+    let second = init_db(&config).expect("database should open");
+    assert!(!second.seeded && !second.is_writer());
+    assert!(try_elect_writer(&path)
+        .expect("election should run")
+        .is_none());
+
+    // Once the writer is gone, a running instance can take over.
+    drop(first);
+    let elected = try_elect_writer(&path).expect("election should run");
+    assert!(elected.is_some());
+
+    drop((second, elected));
+    for suffix in ["", "-wal", "-shm", ".seed.lock", ".writer.lock"] {
+        let _ = std::fs::remove_file(sibling(suffix));
+    }
+}
+
+#[cfg(feature = "seed")]
+#[test]
+fn scenario_seeding_spreads_rows_over_time() {
+    let config = DbConfig {
+        seed: SeedOptions {
+            mode: SeedMode::Scenario,
+            ..SeedOptions::default()
+        },
+        ..DbConfig::in_memory()
+    };
+    let conn = init_db(&config).expect("scenario should seed").connection;
+    let count = |sql: &str| -> i64 {
+        conn.query_row(sql, [], |row| row.get(0))
+            .expect("count should run")
+    };
+
+    let recent =
+        count("SELECT COUNT(*) FROM users WHERE created_at >= datetime('now', '-180 days')");
+    let early = count("SELECT COUNT(*) FROM users WHERE created_at < datetime('now', '-550 days')");
+    assert!(
+        recent > early * 2,
+        "signups grow: {early} early, {recent} recent"
+    );
+    assert_eq!(
+        count(
+            "SELECT COUNT(*) FROM posts JOIN users ON users.id = posts.author_id \
+             WHERE posts.created_at < users.created_at"
+        ),
+        0,
+        "no post predates its author"
+    );
+    assert_eq!(
+        count(
+            "SELECT COUNT(*) FROM sales JOIN users ON users.id = sales.user_id \
+             WHERE sold_at < users.created_at"
+        ),
+        0,
+        "no sale predates its buyer"
+    );
+
+    let per_day = |months: &str| -> f64 {
+        conn.query_row(
+            &format!(
+                "SELECT CAST(COUNT(*) AS REAL) / COUNT(DISTINCT date(sold_at)) FROM sales \
+                 WHERE strftime('%m', sold_at) IN ({months})"
+            ),
+            [],
+            |row| row.get(0),
+        )
+        .expect("sales should be seeded")
+    };
+    let holidays = per_day("'11', '12'");
+    let summer = per_day("'06', '07', '08'");
+    assert!(
+        holidays > summer * 1.5,
+        "{holidays} a day at the holidays, {summer} in summer"
+    );
+}
+
+#[cfg(feature = "seed")]
+#[test]
+fn seed_options_size_and_pick_tables() {
+    let config = DbConfig {
+        seed: SeedOptions {
+            users: 12,
+            posts: 5,
+            tables: Some(vec!["comments".to_string()]),
+            ..SeedOptions::default()
+        },
+        ..DbConfig::in_memory()
+    };
+    let conn = init_db(&config).expect("options should seed").connection;
+    let count = |table: &str| -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+            row.get(0)
+        })
+        .expect("count should run")
+    };
+    assert_eq!(count("users"), 12);
+    assert_eq!(count("posts"), 5);
+    assert_eq!(count("comments"), 15);
+    assert_eq!(count("products"), 0, "products are not needed by comments");
+    assert!(count("post_tags") >= 5);
+
+    let unknown = DbConfig {
+        seed: SeedOptions {
+            tables: Some(vec!["orders".to_string()]),
+            ..SeedOptions::default()
+        },
+        ..DbConfig::in_memory()
+    };
+    assert!(init_db(&unknown).is_err());
+}
+
+#[cfg(feature = "seed")]
+#[test]
+fn seed_specs_define_their_own_tables() {
+    let spec = SeedSpec::from_toml(
+        r#"
+        [[tables]]
+        name = "customers"
+        rows = 20
+        columns = [
+          { name = "name", generator = "name" },
+          { name = "email", generator = "email" },
+          { name = "age", generator = "int-range", min = 18, max = 30 },
+          { name = "plan", generator = "choice", choices = ["free", "pro"] },
+        ]
+
+        [[tables]]
+        name = "orders"
+        rows = 50
+        columns = [
+          { name = "customer_id", generator = "reference", table = "customers" },
+          { name = "total", type = "NUMERIC", generator = "float-range", min = 1, max = 9 },
+          { name = "placed_at", generator = "timestamp", days = 7 },
+        ]
+        "#,
+    )
+    .expect("spec should parse");
+    let mut conn = rusqlite::Connection::open_in_memory().expect("database should open");
+    seed_spec(&mut conn, &spec).expect("spec should seed");
+
+    let count = |sql: &str| -> i64 {
+        conn.query_row(sql, [], |row| row.get(0))
+            .expect("query should run")
+    };
+    assert_eq!(count("SELECT COUNT(*) FROM customers"), 20);
+    assert_eq!(count("SELECT COUNT(DISTINCT email) FROM customers"), 20);
+    assert_eq!(
+        count("SELECT COUNT(*) FROM customers WHERE age NOT BETWEEN 18 AND 30 OR plan NOT IN ('free', 'pro')"),
+        0
+    );
+    assert_eq!(count("SELECT COUNT(*) FROM orders"), 50);
+    assert_eq!(
+        count("SELECT COUNT(*) FROM orders WHERE customer_id NOT IN (SELECT id FROM customers)"),
+        0
+    );
+    assert_eq!(
+        count("SELECT COUNT(*) FROM orders WHERE placed_at < datetime('now', '-8 days')"),
+        0
+    );
+
+    let json = SeedSpec::from_json(
+        r#"{"tables": [{"name": "orders", "rows": 1, "columns": [
+            {"name": "customer_id", "generator": "reference", "table": "customers"}
+        ]}]}"#,
+    );
+    assert!(json.is_err(), "a reference needs an earlier table");
+}
+
+#[test]
+fn insert_returns_new_row_id() {
+    let db = users_database();
+    let config =
+        parse_mutation("ins-users-name-[Ada-Lovelace]-role-admin").expect("insert should parse");
+    let built =
+        build_insert(db.dialect(), &config, &QueryPolicy::default()).expect("insert should build");
+
+    assert_eq!(
+        built.sql,
+        "INSERT INTO \"users\" (\"name\", \"role\") VALUES (?, ?)"
+    );
+    let execution = db
+        .execute(&built.sql, &built.params)
+        .expect("insert should run");
+    assert_eq!(execution.rows_affected, 1);
+    assert_eq!(execution.last_insert_id, Some(4));
+}
+
+#[test]
+fn update_changes_only_filtered_rows() {
+    let db = users_database();
+    let config =
+        parse_mutation("upd-users-role-[super-admin]-where-name-Ada").expect("update should parse");
+    let built =
+        build_update(db.dialect(), &config, &QueryPolicy::default()).expect("update should build");
+
+    assert_eq!(
+        built.sql,
+        "UPDATE \"users\" SET \"role\" = ? WHERE \"name\" = ?"
+    );
+    let execution = db
+        .execute(&built.sql, &built.params)
+        .expect("update should run");
+    assert_eq!(execution.rows_affected, 1);
+}
+
+#[test]
+fn update_without_where_is_refused() {
+    let config = parse_mutation("upd-users-role-admin").expect("update should parse");
+
+    let error = build_update(&Sqlite, &config, &QueryPolicy::default())
+        .expect_err("update without where should not build");
+
+    assert!(matches!(error, QueryBuilderError::MissingWhere("update")));
+}
+
+#[test]
+fn delete_removes_filtered_rows() {
+    let db = users_database();
+    let config = parse_mutation("del-users-where-role-admin").expect("delete should parse");
+    let built =
+        build_delete(db.dialect(), &config, &QueryPolicy::default()).expect("delete should build");
+
+    assert_eq!(built.sql, "DELETE FROM \"users\" WHERE \"role\" = ?");
+    let execution = db
+        .execute(&built.sql, &built.params)
+        .expect("delete should run");
+    assert_eq!(execution.rows_affected, 2);
+}
+
+#[test]
+fn schema_version_changes_with_ddl() {
+    let db = users_database();
+    let before = db.schema_version().expect("version should read");
+
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute_batch("CREATE TABLE widgets (id INTEGER PRIMARY KEY)")
+        .expect("ddl should run");
+
+    let after = db.schema_version().expect("version should read");
+    assert!(before.is_some());
+    assert_ne!(before, after);
+}
+
+#[test]
+fn mock_datasource_answers_built_queries() {
+    let db = MockDatasource::from_json(
+        r#"{"users": [
+            {"id": 1, "name": "Ada", "role": "admin"},
+            {"id": 2, "name": "Alan", "role": "user"},
+            {"id": 3, "name": "Grace", "role": "admin"}
+        ]}"#,
+    )
+    .expect("mock data should parse");
+    let schema = db.load_schema().expect("schema should load");
+    assert!(schema
+        .table("users")
+        .is_some_and(|table| table.has_column("role")));
+
+    for (class_name, expected) in [
+        (
+            "db-users-name-where-role-admin-orderby-name-desc",
+            vec!["Grace", "Ada"],
+        ),
+        ("db-users-name-where-id-after-1-limit-1", vec!["Alan"]),
+        (
+            "db-users-name-where-name-contains-a",
+            vec!["Ada", "Alan", "Grace"],
+        ),
+    ] {
+        let config = parse_class_names(class_name).expect("class name should parse");
+        let built = build_query_for(db.dialect(), &config, &QueryPolicy::default())
+            .expect("query should build");
+        let result = db
+            .query(&built.sql, &built.params, &QueryPolicy::default())
+            .expect("query should run");
+        let names: Vec<_> = result.rows.iter().map(|row| row["name"].clone()).collect();
+        assert_eq!(names, expected, "{class_name}");
+    }
+
+    assert!(db.execute("DELETE FROM users", &[]).is_err());
+}
+
+#[test]
+fn engine_runs_class_names_through_the_prelude() {
+    use tailwindsql::prelude::{Category, Engine, Error, RenderAs};
+
+    let engine = Engine::new(users_database()).expect("schema should load");
+    let result = engine
+        .query("db-users-name-where-role-admin-orderby-name-asc")
+        .expect("query should run");
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.rows[0]["name"], json!("Ada"));
+
+    let html = engine
+        .render("db-users-name-where-id-3", RenderAs::Ul)
+        .expect("render should succeed");
+    assert!(html.contains("Grace"));
+
+    let error = engine
+        .query("db-users-salary")
+        .expect_err("unknown column should fail");
+    assert!(matches!(error, Error::Invalid(_)));
+    assert_eq!(error.category(), Category::User);
+    assert!(!error.is_retryable());
+}
+
+#[test]
+fn engine_queries_stop_at_their_context_deadline() {
+    use std::time::Duration;
+
+    use tailwindsql::prelude::{DbError, Deadline, Engine, Error};
+
+    let engine = Engine::new(users_database()).expect("schema should load");
+    let context = engine
+        .context()
+        .with_principal("reports")
+        .with_limits(QueryPolicy::default().with_max_rows(1));
+    let result = engine
+        .query_in(&context, "db-users-name")
+        .expect("query should run");
+    assert_eq!(result.truncated_at, Some(1));
+
+    let expired = context.with_deadline(Deadline::after(Duration::ZERO));
+    let error = engine
+        .query_in(&expired, "db-users-name")
+        .expect_err("an expired context should not run");
+    assert!(matches!(error, Error::Db(DbError::Timeout(_))));
+
+    // The context's deadline cuts a read short of the longer query timeout.
+    let db = SqliteDatabase::open_in_memory()
+        .expect("in-memory database should open")
+        .with_query_timeout(Duration::from_secs(60))
+        .expect("watchdog should start");
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute_batch(
+            "CREATE VIEW endless AS
+             WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n) SELECT x FROM n",
+        )
+        .expect("fixture should load");
+    let engine = Engine::new(db).expect("schema should load");
+    let budget = Duration::from_millis(200);
+    let error = engine
+        .query_in(
+            &engine.context().with_timeout(budget),
+            "db-endless-x-orderby-x-desc-limit-1",
+        )
+        .expect_err("the query should be cancelled");
+    assert!(matches!(error, Error::Db(DbError::Timeout(limit)) if limit == budget));
+}
+
+#[test]
+fn in_subquery_binds_into_the_outer_query() {
+    let db = users_database();
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute_batch(
+            "CREATE TABLE posts (id INTEGER PRIMARY KEY, author_id INTEGER, title TEXT);
+             INSERT INTO posts (author_id, title) VALUES (1, 'Notes'), (2, 'Turing'), (3, 'COBOL');",
+        )
+        .expect("fixture should load");
+    let config = parse_class_names(
+        "db-posts-title-where-author_id-in-(db-users-id-where-role-admin)-orderby-title-asc",
+    )
+    .expect("class name should parse");
+    let built = build_query_for(
+        db.dialect(),
+        &config,
+        &QueryPolicy::default().with_default_limit(10),
+    )
+    .expect("query should build");
+
+    assert_eq!(
+        built.sql,
+        "SELECT \"title\" FROM \"posts\" WHERE \"author_id\" IN (SELECT \"id\" FROM \
+         \"users\" WHERE \"role\" = ?) ORDER BY \"title\" ASC LIMIT ?"
+    );
+    let result = db
+        .query(&built.sql, &built.params, &QueryPolicy::default())
+        .expect("query should run");
+    let titles: Vec<_> = result.rows.iter().map(|row| row["title"].clone()).collect();
+    assert_eq!(titles, [json!("COBOL"), json!("Notes")]);
+
+    let wide = parse_class_names("db-posts-title-where-author_id-in-(db-users-id-name)")
+        .expect("class name should parse");
+    assert!(matches!(
+        build_query_for(db.dialect(), &wide, &QueryPolicy::default()),
+        Err(QueryBuilderError::SubqueryColumns { .. })
+    ));
+}
+
+#[test]
+fn where_values_bind_with_the_type_of_their_column() {
+    let db = SqliteDatabase::open_in_memory().expect("in-memory database should open");
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute_batch(
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, code TEXT, price REAL, is_active INTEGER) STRICT;
+             INSERT INTO items (code, price, is_active) VALUES ('42', 10.0, 1), ('007', 2.5, 0);",
+        )
+        .expect("fixture should load");
+    let schema = db.load_schema().expect("schema should load");
+    let build = |class_name: &str, schema: Option<&tailwindsql::schema::SchemaCatalog>| {
+        let config = parse_class_names(class_name).expect("class name should parse");
+        let config = schema.map_or(config.clone(), |schema| schema.type_where_values(&config));
+        build_query_for(db.dialect(), &config, &QueryPolicy::default()).expect("query should build")
+    };
+
+    // Without a schema, the value alone decides.
+    let inferred = build(
+        "db-items-id-where-id-1-where-code-007-where-price-2.5-where-is_active-true",
+        None,
+    );
+    assert_eq!(
+        inferred.params,
+        [
+            Value::Integer(1),
+            Value::Text("007".to_string()),
+            Value::Real(2.5),
+            Value::Integer(1),
+        ]
+    );
+
+    // With one, the column's declared type does.
+    for (class_name, expected) in [
+        ("db-items-id-where-code-42", 1),
+        ("db-items-id-where-price-10", 1),
+        ("db-items-id-where-is_active-false", 1),
+        ("db-items-id-where-id-int:2", 1),
+    ] {
+        let built = build(class_name, Some(&schema));
+        let result = db
+            .query(&built.sql, &built.params, &QueryPolicy::default())
+            .expect("query should run");
+        assert_eq!(
+            result.rows.len(),
+            expected,
+            "{class_name}: {:?}",
+            built.params
+        );
+    }
+    assert_eq!(
+        build("db-items-id-where-code-42", Some(&schema)).params,
+        [Value::Text("42".to_string())]
+    );
+    assert_eq!(
+        build("db-items-id-where-price-10", Some(&schema)).params,
+        [Value::Real(10.0)]
+    );
+}
+
+#[test]
+fn date_keywords_only_compare_dates_against_now() {
+    let db = SqliteDatabase::open_in_memory().expect("in-memory database should open");
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute_batch(
+            "CREATE TABLE events (id INTEGER PRIMARY KEY, name TEXT, happened_at DATETIME);
+             INSERT INTO events (name, happened_at) VALUES
+                 ('today', datetime('now')), ('last', datetime('now', '-30 days'));",
+        )
+        .expect("fixture should load");
+    let schema = db.load_schema().expect("schema should load");
+    let run = |class_name: &str| {
+        let config = parse_class_names(class_name).expect("class name should parse");
+        let built = build_query_for(
+            db.dialect(),
+            &schema.type_where_values(&config),
+            &QueryPolicy::default(),
+        )
+        .expect("query should build");
+        let result = db
+            .query(&built.sql, &built.params, &QueryPolicy::default())
+            .expect("query should run");
+        let ids: Vec<_> = result.rows.iter().map(|row| row["id"].clone()).collect();
+        (built.params, ids)
+    };
+
+    assert_eq!(run("db-events-id-where-happened_at-today").1, [json!(1)]);
+    assert_eq!(
+        run("db-events-id-where-happened_at-after-last-7-days").1,
+        [json!(1)]
+    );
+    // Text columns compare the keyword itself.
+    let (params, ids) = run("db-events-id-where-name-today");
+    assert_eq!(params, [Value::Text("today".to_string())]);
+    assert_eq!(ids, [json!(1)]);
+
+    // `last` only reads on as `<n>-<unit>`, so the limit is kept.
+    let (params, ids) = run("db-events-id-where-name-last-limit-5");
+    assert_eq!(params, [Value::Text("last".to_string()), Value::Integer(5)]);
+    assert_eq!(ids, [json!(2)]);
+    let config =
+        parse_class_names("db-events-id-where-name-last-7-fortnights").expect("should parse");
+    assert_eq!(config.where_clauses[0].value, "last");
+}
+
+#[test]
+fn errors_are_classified_once() {
+    use tailwindsql::prelude::{Category, DbError, Error};
+
+    let busy = Error::from(DbError::Sqlite(rusqlite::Error::SqliteFailure(
+        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+        None,
+    )));
+    assert!(busy.is_retryable());
+    assert_eq!(busy.category(), Category::System);
+    assert_eq!(busy.status().as_u16(), 503);
+
+    let budget = Error::from(DbError::MemoryBudget { budget: 1 });
+    assert!(!budget.is_retryable());
+    assert_eq!(budget.category(), Category::User);
+    assert_eq!(budget.status().as_u16(), 413);
+
+    let lock = Error::from(DbError::Lock);
+    assert_eq!(lock.category(), Category::System);
+    assert_eq!(lock.status().as_u16(), 500);
+}
+
+#[test]
+fn window_functions_rank_rows_for_leaderboards() {
+    let db = users_database();
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute_batch(
+            "CREATE TABLE scores (id INTEGER PRIMARY KEY, player TEXT, league TEXT, likes INTEGER);
+             INSERT INTO scores (player, league, likes) VALUES
+                 ('Ada', 'north', 30), ('Alan', 'north', 30), ('Grace', 'north', 10),
+                 ('Edsger', 'south', 20), ('Barbara', 'south', 40);",
+        )
+        .expect("fixture should load");
+    let config = parse_class_names(
+        "db-scores-player-rank-over-likes-desc-dense_rank-over-likes-desc-partition-league-orderby-id-asc",
+    )
+    .expect("class name should parse");
+    assert_eq!(
+        config.to_string(),
+        "db-scores-player-rank-over-likes-desc-dense_rank-over-likes-desc-partition-league-orderby-id-asc"
+    );
+    let built = build_query_for(db.dialect(), &config, &QueryPolicy::default())
+        .expect("query should build");
+
+    assert_eq!(
+        built.sql,
+        "SELECT \"player\", RANK() OVER (ORDER BY \"likes\" DESC) AS \"rank\", \
+         DENSE_RANK() OVER (PARTITION BY \"league\" ORDER BY \"likes\" DESC) AS \
+         \"dense_rank\" FROM \"scores\" ORDER BY \"id\" ASC"
+    );
+    let result = db
+        .query(&built.sql, &built.params, &QueryPolicy::default())
+        .expect("query should run");
+    assert_eq!(result.columns, ["player", "rank", "dense_rank"]);
+    let ranks: Vec<_> = result
+        .rows
+        .iter()
+        .map(|row| (row["rank"].clone(), row["dense_rank"].clone()))
+        .collect();
+    assert_eq!(
+        ranks,
+        [
+            (json!(2), json!(1)),
+            (json!(2), json!(1)),
+            (json!(5), json!(2)),
+            (json!(4), json!(2)),
+            (json!(1), json!(1)),
+        ]
+    );
+
+    // Without `over`, the function name is an ordinary column.
+    let plain = parse_class_names("db-scores-rank").expect("class name should parse");
+    assert_eq!(plain.columns, ["rank"]);
+    assert!(plain.expressions.is_empty());
+}
+
+#[test]
+fn computed_columns_only_accept_arithmetic() {
+    let db = users_database();
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute_batch(
+            "CREATE TABLE products (id INTEGER PRIMARY KEY, name TEXT, price REAL, stock INTEGER);
+             INSERT INTO products (name, price, stock) VALUES ('Lamp', 2.5, 4), ('Desk', 10, 3);",
+        )
+        .expect("fixture should load");
+    let config = parse_class_names(
+        "db-products-name-expr-[price*stock]-as-inventory_value-expr-[stock-(id-1)*2]-as-left",
+    )
+    .expect("class name should parse");
+    assert_eq!(
+        config.to_string(),
+        "db-products-name-expr-[price*stock]-as-inventory_value-expr-[stock-(id-1)*2]-as-left"
+    );
+    let built = build_query_for(db.dialect(), &config, &QueryPolicy::default())
+        .expect("query should build");
+
+    assert_eq!(
+        built.sql,
+        "SELECT \"name\", \"price\" * \"stock\" AS \"inventory_value\", \"stock\" - \
+         ((\"id\" - 1) * 2) AS \"left\" FROM \"products\""
+    );
+    let result = db
+        .query(&built.sql, &built.params, &QueryPolicy::default())
+        .expect("query should run");
+    assert_eq!(result.columns, ["name", "inventory_value", "left"]);
+    assert_eq!(result.rows[1]["inventory_value"], json!(30.0));
+    assert_eq!(result.rows[1]["left"], json!(1));
+
+    for unsafe_expr in [
+        "db-products-expr-[price;drop]-as-x",
+        "db-products-expr-[price*stock]",
+        "db-products-expr-[price)*(stock]-as-x",
+        "db-products-expr-[price*stock]-as-x;y",
+    ] {
+        let error = tailwindsql::parser::parse_class_name_strict(unsafe_expr, |_, _| true)
+            .expect_err("expression should be rejected");
+        assert!(
+            matches!(error, tailwindsql::parser::ParseError::InvalidToken { .. }),
+            "{unsafe_expr}: {error}"
+        );
+    }
+}
+
+#[test]
+fn case_maps_values_to_labels() {
+    let db = users_database();
+    let config = parse_class_names("db-users-name-case-role-admin-Administrator-end-id")
+        .expect("class name should parse");
+    assert_eq!(
+        config.to_string(),
+        "db-users-name-id-case-role-admin-Administrator-end"
+    );
+    let built = build_query_for(db.dialect(), &config, &QueryPolicy::default())
+        .expect("query should build");
+
+    assert_eq!(
+        built.sql,
+        "SELECT \"name\", \"id\", CASE \"role\" WHEN ? THEN ? ELSE CAST(\"role\" AS \
+         TEXT) END AS \"role\" FROM \"users\""
+    );
+    let result = db
+        .query(&built.sql, &built.params, &QueryPolicy::default())
+        .expect("query should run");
+    let roles: Vec<_> = result.rows.iter().map(|row| row["role"].clone()).collect();
+    assert_eq!(
+        roles,
+        [
+            json!("Administrator"),
+            json!("user"),
+            json!("Administrator")
+        ]
+    );
+
+    // A value without a label is dropped rather than guessed at.
+    let dangling = parse_class_names("db-users-name-case-role-admin-Administrator-user")
+        .expect("class name should parse");
+    assert_eq!(
+        dangling.to_string(),
+        "db-users-name-case-role-admin-Administrator-end"
+    );
+
+    // Without listed columns the mapping takes the place of its column in `*`.
+    let implicit = parse_class_names("db-users-case-role-admin-Administrator")
+        .expect("class name should parse");
+    assert!(matches!(
+        build_query_for(db.dialect(), &implicit, &QueryPolicy::default()),
+        Err(QueryBuilderError::CaseWithWildcard(column)) if column == "role"
+    ));
+    let schema = db.load_schema().expect("schema should load");
+    let built = build_query_for(
+        db.dialect(),
+        &schema.expand_wildcards(&implicit),
+        &QueryPolicy::default(),
+    )
+    .expect("query should build");
+    let result = db
+        .query(&built.sql, &built.params, &QueryPolicy::default())
+        .expect("query should run");
+    assert_eq!(result.columns.iter().filter(|c| *c == "role").count(), 1);
+    assert!(result.columns.iter().any(|c| c == "name"));
+    assert_eq!(result.rows[0]["role"], json!("Administrator"));
+
+    // A listed column that is also mapped is selected once, mapped.
+    let listed = parse_class_names("db-users-name-role-case-role-admin-Administrator")
+        .expect("class name should parse");
+    let built = build_query_for(db.dialect(), &listed, &QueryPolicy::default())
+        .expect("query should build");
+    assert_eq!(
+        built.sql,
+        "SELECT \"name\", CASE \"role\" WHEN ? THEN ? ELSE CAST(\"role\" AS TEXT) END \
+         AS \"role\" FROM \"users\""
+    );
+}
+
+#[test]
+fn json_fields_extract_from_text_columns() {
+    let db = SqliteDatabase::open_in_memory().expect("in-memory database should open");
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute_batch(
+            r#"CREATE TABLE products (id INTEGER PRIMARY KEY, title TEXT, metadata TEXT);
+             INSERT INTO products (title, metadata) VALUES
+               ('Mug', '{"color": "red", "sizes": ["S", "M"]}'),
+               ('Lamp', '{"color": "blue"}');"#,
+        )
+        .expect("fixture should load");
+    let config = parse_class_names("db-products-title-json-metadata.color-json-metadata.sizes.1")
+        .expect("class name should parse");
+    assert_eq!(
+        config.to_string(),
+        "db-products-title-json-metadata.color-json-metadata.sizes.1"
+    );
+    let built = build_query_for(db.dialect(), &config, &QueryPolicy::default())
+        .expect("query should build");
+
+    assert_eq!(
+        built.sql,
+        "SELECT \"title\", json_extract(\"metadata\", '$.color') AS \"metadata.color\", \
+         json_extract(\"metadata\", '$.sizes[1]') AS \"metadata.sizes.1\" FROM \
+         \"products\""
+    );
+    let result = db
+        .query(&built.sql, &built.params, &QueryPolicy::default())
+        .expect("query should run");
+    assert_eq!(result.rows[0]["metadata.color"], json!("red"));
+    assert_eq!(result.rows[0]["metadata.sizes.1"], json!("M"));
+    assert_eq!(result.rows[1]["metadata.sizes.1"], json!(null));
+
+    // Keys are identifiers or indexes, so a path cannot carry SQL.
+    let quoted =
+        parse_class_names("db-products-title-json-metadata.a'b").expect("class name should parse");
+    assert!(quoted.expressions.is_empty());
+}
+
+#[test]
+fn materialization_replaces_the_cache_table_with_current_rows() {
+    let db = users_database();
+    let config = parse_class_names("db-users-name-where-role-admin-orderby-name-desc")
+        .expect("class name should parse");
+    let limits = QueryPolicy {
+        default_limit: Some(1),
+        ..QueryPolicy::default()
+    };
+    let script = build_materialization(db.dialect(), "materialized_admins", &config, &limits)
+        .expect("materialization should build");
+    assert_eq!(
+        script,
+        "DROP TABLE IF EXISTS \"materialized_admins\"; CREATE TABLE \
+         \"materialized_admins\" AS SELECT \"name\" FROM \"users\" WHERE \"role\" = \
+         'admin' ORDER BY \"name\" DESC"
+    );
+
+    let names = |db: &SqliteDatabase| {
+        let result = db
+            .query(
+                "SELECT name FROM materialized_admins",
+                &[],
+                &QueryPolicy::default(),
+            )
+            .expect("cache table should exist");
+        result
+            .rows
+            .iter()
+            .map(|row| row["name"].clone())
+            .collect::<Vec<_>>()
+    };
+    let run = |db: &SqliteDatabase| {
+        db.lock()
+            .expect("lock should not be poisoned")
+            .execute_batch(&script)
+            .expect("materialization should run");
+    };
+    run(&db);
+    assert_eq!(names(&db), [json!("Grace"), json!("Ada")]);
+
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute("UPDATE users SET role = 'user' WHERE name = 'Grace'", [])
+        .expect("update should run");
+    run(&db);
+    assert_eq!(names(&db), [json!("Ada")]);
+}
+
+#[test]
+fn keyword_and_mixed_case_columns_are_quoted() {
+    let db = SqliteDatabase::open_in_memory().expect("in-memory database should open");
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute_batch(
+            r#"CREATE TABLE steps ("order" INTEGER, "Label" TEXT);
+             INSERT INTO steps VALUES (2, 'Ship'), (1, 'Pack');"#,
+        )
+        .expect("fixture should load");
+    let config =
+        parse_class_names("db-steps-Label-where-order-1").expect("class name should parse");
+    let built = build_query_for(db.dialect(), &config, &QueryPolicy::default())
+        .expect("query should build");
+
+    assert_eq!(
+        built.sql,
+        "SELECT \"Label\" FROM \"steps\" WHERE \"order\" = ?"
+    );
+    let result = db
+        .query(&built.sql, &built.params, &QueryPolicy::default())
+        .expect("query should run");
+    assert_eq!(result.rows.len(), 1);
+    assert_eq!(result.rows[0]["Label"], json!("Pack"));
+}
+
+#[test]
+fn subject_export_and_erasure_follow_foreign_keys() {
+    let db = SqliteDatabase::open_in_memory().expect("in-memory database should open");
+    let conn = db.lock().expect("lock should not be poisoned");
+    conn.execute_batch(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email TEXT NOT NULL UNIQUE);
+         CREATE TABLE posts (id INTEGER PRIMARY KEY, author_id INTEGER REFERENCES users(id), title TEXT);
+         CREATE TABLE comments (id INTEGER PRIMARY KEY, post_id INTEGER REFERENCES posts(id), body TEXT);
+         INSERT INTO users VALUES (1, 'Ada', 'ada@example.com'), (2, 'Alan', 'alan@example.com');
+         INSERT INTO posts VALUES (10, 1, 'Engines'), (20, 2, 'Machines');
+         INSERT INTO comments VALUES (100, 10, 'On Ada''s post'), (200, 20, 'On Alan''s post');",
+    )
+    .expect("fixture should load");
+    let mut schema = tailwindsql::schema::SchemaCatalog::load(&conn).expect("schema should load");
+    let ada = rusqlite::types::Value::Integer(1);
+
+    let exported = export_subject(&conn, &schema, "users", &ada).expect("export should run");
+    let ids = |table: &str| -> Vec<i64> {
+        exported.tables[table]
+            .iter()
+            .map(|row| row["id"].as_i64().expect("id should be an integer"))
+            .collect()
+    };
+    assert_eq!(ids("users"), [1]);
+    assert_eq!(ids("posts"), [10]);
+    assert_eq!(ids("comments"), [100]);
+    assert!(export_subject(&conn, &schema, "users", &rusqlite::types::Value::Integer(9)).is_err());
+
+    // Anonymizing needs classified columns, and leaves nothing behind without them.
+    assert!(erase_subject(&conn, &schema, "users", &ada, Erasure::Anonymize).is_err());
+    schema.mark_privacy("users", "email", Privacy::Pii);
+    let report = erase_subject(&conn, &schema, "users", &ada, Erasure::Anonymize)
+        .expect("anonymizing should run");
+    assert_eq!(report.tables.get("users"), Some(&1));
+    let email: String = conn
+        .query_row("SELECT email FROM users WHERE id = 1", [], |row| row.get(0))
+        .expect("user should remain");
+    assert_eq!(email, "erased-1");
+
+    let report =
+        erase_subject(&conn, &schema, "users", &ada, Erasure::Delete).expect("delete should run");
+    assert_eq!(report.tables.values().sum::<u64>(), 3);
+    let remaining: i64 = conn
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM users) + (SELECT COUNT(*) FROM posts) \
+             + (SELECT COUNT(*) FROM comments)",
+            [],
+            |row| row.get(0),
+        )
+        .expect("count should run");
+    assert_eq!(remaining, 3);
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "server")]
+
+use std::collections::BTreeMap;
+
+use tailwindsql::server::config::{self, ConfigError};
+
+#[test]
+fn config_keys_become_the_variables_they_name() {
+    let settings = config::parse(
+        r#"
+host = "127.0.0.1"
+port = 8080
+database_path = "data/demo.db"
+chaos_busy_rate = 0.25
+allowed_tables = ["users", "posts"]
+allow_writes = true
+strict_parsing = false
+"#,
+    )
+    .unwrap();
+
+    let expected: BTreeMap<String, String> = [
+        ("HOST", "127.0.0.1"),
+        ("PORT", "8080"),
+        ("DATABASE_PATH", "data/demo.db"),
+        ("CHAOS_BUSY_RATE", "0.25"),
+        ("ALLOWED_TABLES", "users,posts"),
+        ("ALLOW_WRITES", "1"),
+        ("STRICT_PARSING", "0"),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value.to_string()))
+    .collect();
+    assert_eq!(settings, expected);
+}
+
+#[test]
+fn config_refuses_unknown_keys_and_nested_values() {
+    assert!(matches!(
+        config::parse("prot = 8080"),
+        Err(ConfigError::UnknownKey(key)) if key == "prot"
+    ));
+    assert!(matches!(
+        config::parse("PORT = 8080"),
+        Err(ConfigError::UnknownKey(_))
+    ));
+    assert!(matches!(
+        config::parse("allowed_tables = [[\"users\"]]"),
+        Err(ConfigError::Nested(key)) if key == "allowed_tables"
+    ));
+    assert!(config::parse("[server]\nport = 8080").is_err());
+}
+
+#[test]
+fn environment_variables_win_over_the_file() {
+    let dir = std::env::temp_dir().join(format!("tailwindsql-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("tailwindsql.toml");
+    std::fs::write(
+        &path,
+        "class_snippets = \"from-file\"\nclass_aliases = \"top=limit\"\n",
+    )
+    .unwrap();
+
+    std::env::set_var("CLASS_SNIPPETS", "from-env");
+    std::env::remove_var("CLASS_ALIASES");
+    let file = config::ConfigFile::read(&path).unwrap();
+    assert_eq!(file.apply(), 1);
+    assert_eq!(std::env::var("CLASS_SNIPPETS").unwrap(), "from-env");
+    assert_eq!(std::env::var("CLASS_ALIASES").unwrap(), "top=limit");
+
+    assert!(matches!(
+        config::ConfigFile::read(&dir.join("missing.toml")),
+        Err(ConfigError::Read { .. })
+    ));
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,117 @@
+#![cfg(feature = "duckdb")]
+
+use std::path::Path;
+
+use rusqlite::types::Value;
+use serde_json::json;
+use tailwindsql::db::duckdb::DuckDatabase;
+use tailwindsql::db::Backend;
+use tailwindsql::parser::parse_class_names;
+use tailwindsql::query_builder::{build_query_for, QueryPolicy};
+
+fn orders_database() -> DuckDatabase {
+    let db = DuckDatabase::open(Path::new(":memory:")).expect("in-memory database should open");
+    db.execute(
+        "CREATE TABLE orders (
+           id INTEGER, customer VARCHAR, total DECIMAL(8, 2), paid BOOLEAN,
+           placed DATE, shipped TIMESTAMP, units HUGEINT, note BLOB
+         )",
+        &[],
+    )
+    .expect("table should create");
+    db
+}
+
+#[test]
+fn class_names_query_duckdb_with_postgres_sql() {
+    let db = orders_database();
+    let insert = "INSERT INTO orders VALUES ($1, $2, $3, $4, $5, $6, $7, $8)";
+    for (id, customer, total) in [(1, "Ada", 12.5), (2, "Alan", 99.99), (3, "Ada", 40.0)] {
+        let inserted = db
+            .execute(
+                insert,
+                &[
+                    Value::Integer(id),
+                    Value::Text(customer.to_string()),
+                    Value::Real(total),
+                    Value::Integer(1),
+                    Value::Text("2024-02-29".to_string()),
+                    Value::Text("2024-02-29 13:45:00".to_string()),
+                    Value::Integer(id * 1_000),
+                    Value::Blob(vec![0xca, 0xfe]),
+                ],
+            )
+            .expect("row should insert");
+        assert_eq!(inserted.rows_affected, 1);
+        assert_eq!(inserted.last_insert_id, None);
+    }
+
+    let schema = db.load_schema().expect("schema should load");
+    let orders = schema.table("orders").expect("orders should be listed");
+    let columns: Vec<_> = orders.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(
+        columns,
+        ["id", "customer", "total", "paid", "placed", "shipped", "units", "note"]
+    );
+
+    let config = parse_class_names(
+        "db-orders-id-total-paid-placed-shipped-units-note-where-customer-Ada-orderby-id-desc-limit-1",
+    )
+    .expect("class name should parse");
+    let built = build_query_for(db.dialect(), &config, &QueryPolicy::default())
+        .expect("query should build");
+    assert!(built.sql.contains("\"customer\" = $1") && built.sql.ends_with("LIMIT $2"));
+    let result = db
+        .query(&built.sql, &built.params, &QueryPolicy::default())
+        .expect("query should run");
+    assert_eq!(
+        result.columns,
+        columns
+            .iter()
+            .filter(|c| **c != "customer")
+            .copied()
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        serde_json::to_value(&result.rows).expect("rows should serialize"),
+        json!([{
+            "id": 3,
+            "total": 40.0,
+            "paid": true,
+            "placed": "2024-02-29",
+            "shipped": "2024-02-29 13:45:00",
+            "units": 3000,
+            "note": "cafe",
+        }])
+    );
+}
+
+#[test]
+fn registered_files_query_like_tables() {
+    let dir = std::env::temp_dir().join(format!("tailwindsql-duckdb-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("directory should create");
+    let csv = dir.join("it's.csv");
+    std::fs::write(&csv, "city,visits\nOslo,3\nLima,7\n").expect("csv should write");
+
+    let db = orders_database();
+    db.register_file("visits", &csv)
+        .expect("file should register");
+    let config =
+        parse_class_names("db-visits-city-where-visits-after-5").expect("class name should parse");
+    let built = build_query_for(db.dialect(), &config, &QueryPolicy::default())
+        .expect("query should build");
+    let result = db
+        .query(&built.sql, &built.params, &QueryPolicy::default())
+        .expect("query should run");
+    assert_eq!(
+        serde_json::to_value(&result.rows).expect("rows should serialize"),
+        json!([{ "city": "Lima" }])
+    );
+    assert!(db
+        .load_schema()
+        .expect("schema should load")
+        .table("visits")
+        .is_some());
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,120 @@
+#![cfg(feature = "server")]
+
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use tailwindsql::test_support::TestServer;
+
+const ADMIN: &[(&str, &str)] = &[("authorization", "Bearer s3cret")];
+
+// The signing key is read from the environment, so every case runs in this
+// one test rather than racing other tests for it.
+#[tokio::test]
+async fn fragments_need_a_signed_url_or_scope() {
+    std::env::set_var("FRAGMENT_SIGNING_KEY", "embed-key");
+    std::env::set_var("ADMIN_TOKEN", "s3cret");
+    let server = TestServer::start().await.expect("server should start");
+    let fragment = "/api/fragment?className=db-users-name-orderby-id-asc-limit-2&as=ul";
+
+    let unsigned = server.get(fragment).await.expect("request should succeed");
+    assert_eq!(unsigned.status, StatusCode::FORBIDDEN);
+    let admin = server
+        .get_with(fragment, ADMIN)
+        .await
+        .expect("request should succeed");
+    assert_eq!(admin.status, StatusCode::OK);
+
+    let sign = "/api/sign?className=db-users-name-orderby-id-asc-limit-2&as=ul&ttl=5m";
+    let denied = server.get(sign).await.expect("request should succeed");
+    assert_eq!(denied.status, StatusCode::FORBIDDEN);
+    let signed = server
+        .get_with(sign, ADMIN)
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    let url = signed["url"].as_str().expect("url should be a string");
+    assert!(url.contains("&expires=") && url.contains("&sig="));
+
+    let embed = server.get(url).await.expect("request should succeed");
+    assert_eq!(embed.status, StatusCode::OK);
+    assert_eq!(embed.body, admin.body);
+
+    // Any change to the signed parameters breaks the signature.
+    let tampered = url.replace("limit-2", "limit-200");
+    let tampered = server.get(&tampered).await.expect("request should succeed");
+    assert_eq!(tampered.status, StatusCode::FORBIDDEN);
+    let resorted = server
+        .get(&format!("{url}&sort=email"))
+        .await
+        .expect("request should succeed");
+    assert_eq!(resorted.status, StatusCode::FORBIDDEN);
+
+    let expired = server
+        .get_with(&sign.replace("ttl=5m", "ttl=1s"), ADMIN)
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let expired = server
+        .get(expired["url"].as_str().expect("url should be a string"))
+        .await
+        .expect("request should succeed");
+    assert_eq!(expired.status, StatusCode::FORBIDDEN);
+    assert!(expired.body.contains("expired"));
+
+    // Unchanged content is not sent again.
+    let etag = embed
+        .headers
+        .get(header::ETAG)
+        .and_then(|value| value.to_str().ok())
+        .expect("fragments should carry an ETag");
+    let cached = server
+        .get_with(url, &[("if-none-match", etag)])
+        .await
+        .expect("request should succeed");
+    assert_eq!(cached.status, StatusCode::NOT_MODIFIED);
+    assert!(cached.body.is_empty());
+
+    // A scope token lets any class name inside it through.
+    let scope = "/api/scope?tables=users&maxRows=2&as=ul,ol&ttl=5m";
+    let denied = server.get(scope).await.expect("request should succeed");
+    assert_eq!(denied.status, StatusCode::FORBIDDEN);
+    let unknown = server
+        .get_with("/api/scope?tables=nope", ADMIN)
+        .await
+        .expect("request should succeed");
+    assert_eq!(unknown.status, StatusCode::BAD_REQUEST);
+    let issued = server
+        .get_with(scope, ADMIN)
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    let token = issued["token"].as_str().expect("token should be a string");
+    let scoped = |query: &str| format!("/api/fragment?{query}&scope={token}");
+
+    let list = server
+        .get(&scoped("className=db-users-email-orderby-id-asc&as=ol"))
+        .await
+        .expect("request should succeed");
+    assert_eq!(list.status, StatusCode::OK);
+    assert_eq!(list.body.matches("<li").count(), 2);
+    let table = server
+        .get(&scoped("className=db-users-email&as=table"))
+        .await
+        .expect("request should succeed");
+    assert_eq!(table.status, StatusCode::FORBIDDEN);
+    assert!(table.body.contains("render mode not allowed: table"));
+    let posts = server
+        .get(&scoped("className=db-posts-title&as=ul"))
+        .await
+        .expect("request should succeed");
+    assert_eq!(posts.status, StatusCode::FORBIDDEN);
+    let forged = server
+        .get(&scoped("className=db-users-email&as=ul").replace(".", ".0"))
+        .await
+        .expect("request should succeed");
+    assert_eq!(forged.status, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "server")]
+
+use std::sync::Arc;
+
+use serde_json::json;
+use tailwindsql::db::sqlite::SqliteDatabase;
+use tailwindsql::history::{read_history, replay, HistoryEntry, HistoryLog};
+use tailwindsql::query_builder::QueryPolicy;
+
+#[tokio::test]
+async fn replay_reports_changed_row_counts_and_failures() {
+    let path =
+        std::env::temp_dir().join(format!("tailwindsql-history-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let log = HistoryLog::open(&path).expect("history should open");
+    for (class_name, sql, params, rows) in [
+        (
+            "db-users-name-where-role-admin",
+            "SELECT name FROM users WHERE role = ?",
+            json!(["admin"]),
+            2,
+        ),
+        (
+            "db-users-name-where-role-user",
+            "SELECT name FROM users WHERE role = ?",
+            json!(["user"]),
+            5,
+        ),
+        ("db-posts-title", "SELECT title FROM posts", json!([]), 1),
+    ] {
+        let entry = HistoryEntry {
+            class_name: class_name.to_string(),
+            sql: sql.to_string(),
+            params: serde_json::from_value(params).expect("params are an array"),
+            rows,
+            elapsed_ms: 1000.0,
+        };
+        log.record(&entry).expect("entry should be recorded");
+    }
+    let entries = read_history(&path).expect("history should read back");
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(entries.len(), 3);
+
+    let db = SqliteDatabase::open_in_memory().expect("in-memory database should open");
+    db.lock()
+        .expect("lock should not be poisoned")
+        .execute_batch(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, role TEXT);
+             INSERT INTO users (name, role) VALUES ('Ada', 'admin'), ('Alan', 'user'), ('Grace', 'admin');",
+        )
+        .expect("fixture should load");
+    let report = replay(Arc::new(db), entries, 4, QueryPolicy::default())
+        .await
+        .expect("replay should run");
+
+    assert!(!report.is_clean());
+    assert_eq!(report.regressions().count(), 0);
+    let changed: Vec<_> = report
+        .changed_rows()
+        .map(|o| o.entry.class_name.as_str())
+        .collect();
+    assert_eq!(changed, ["db-users-name-where-role-user"]);
+    let failed: Vec<_> = report
+        .failures()
+        .map(|o| o.entry.class_name.as_str())
+        .collect();
+    assert_eq!(failed, ["db-posts-title"]);
+}
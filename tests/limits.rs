@@ -0,0 +1,35 @@
+#![cfg(feature = "server")]
+
+use axum::http::StatusCode;
+use tailwindsql::test_support::TestServer;
+
+// The limits are read from the environment, so they are set once here
+// rather than racing other tests for it.
+#[tokio::test]
+async fn large_results_stop_early_and_report_truncation() {
+    std::env::set_var("MAX_PAYLOAD_BYTES", "200");
+    let server = TestServer::start().await.expect("server should start");
+
+    let response = server
+        .query("db-users-id-name-email-orderby-id-asc-limit-50")
+        .await
+        .expect("request should succeed");
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    let body = response.json().expect("response should be JSON");
+    assert_eq!(body["truncated"], true);
+    let kept = body["results"]
+        .as_array()
+        .expect("results should be an array");
+    assert!(!kept.is_empty() && kept.len() < 50);
+    assert_eq!(body["count"], kept.len());
+    assert_eq!(body["rowCap"], kept.len());
+    assert!(serde_json::to_string(kept).map_or(0, |json| json.len()) <= 202);
+
+    let small = server
+        .query("db-users-id-limit-1")
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    assert!(small.get("truncated").is_none());
+}
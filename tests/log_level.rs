@@ -0,0 +1,66 @@
+#![cfg(feature = "server")]
+
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use tailwindsql::server::logging;
+use tailwindsql::test_support::TestServer;
+
+const ADMIN: &[(&str, &str)] = &[("authorization", "Bearer s3cret")];
+const PATH: &str = "/api/admin/log-level";
+
+// Logging and ADMIN_TOKEN are process-wide, so every case runs in this one
+// test.
+#[tokio::test]
+async fn admins_change_the_log_filter_for_a_while() {
+    std::env::set_var("RUST_LOG", "warn");
+    std::env::set_var("ADMIN_TOKEN", "s3cret");
+    logging::init();
+    let server = TestServer::start().await.expect("server should start");
+
+    let anonymous = server.get(PATH).await.expect("request should succeed");
+    assert_eq!(anonymous.status, StatusCode::FORBIDDEN);
+    let current = server
+        .get_with(PATH, ADMIN)
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    assert_eq!(current["filter"], "warn");
+
+    let invalid = server
+        .request(
+            "PUT",
+            PATH,
+            ADMIN,
+            Some(&json!({ "filter": "tailwindsql=loud" })),
+        )
+        .await
+        .expect("request should succeed");
+    assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+
+    let raised = server
+        .request(
+            "PUT",
+            PATH,
+            ADMIN,
+            Some(&json!({ "filter": "warn,tailwindsql=debug", "for": "1s" })),
+        )
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    // Filters come back in their normalized form, most specific first.
+    assert_eq!(raised["filter"], "tailwindsql=debug,warn");
+    assert_eq!(raised["reverts"], json!({ "to": "warn", "inSeconds": 1 }));
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let restored = server
+        .get_with(PATH, ADMIN)
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    assert_eq!(restored["filter"], "warn");
+}
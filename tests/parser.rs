@@ -0,0 +1,590 @@
+use std::borrow::Cow;
+
+use proptest::prelude::*;
+use tailwindsql::dialect::Sqlite;
+use tailwindsql::parser::tokens::{
+    parse, tokenize, tokenize_with, Clause, GrammarConfig, GrammarVersion, TokenKind,
+};
+use tailwindsql::parser::{
+    config_with_join, grammar_warnings, merge_configs, parse_class_name, parse_class_name_strict,
+    parse_class_name_strict_with, parse_class_name_with, parse_class_names,
+    parse_class_names_strict_with, parse_class_names_with, parse_join_param, OrderDirection,
+    ParseError, QueryConfig,
+};
+use tailwindsql::query_builder::{build_query_for, QueryPolicy};
+
+fn sql(class_names: &str) -> String {
+    let config = parse_class_names(class_names).expect("class names should parse");
+    build_query_for(&Sqlite, &config, &QueryPolicy::default())
+        .expect("query should build")
+        .sql
+}
+
+#[test]
+fn stacked_class_names_keep_every_expression() {
+    assert_eq!(
+        sql("db-posts-title db-posts-rank-over-likes-desc"),
+        "SELECT \"title\", RANK() OVER (ORDER BY \"likes\" DESC) AS \"rank\" FROM \"posts\""
+    );
+    let merged = parse_class_names(
+        "db-products-title-expr-[price*stock]-as-worth db-products-json-metadata.color \
+         db-products-case-featured-1-Yes-0-No-end",
+    )
+    .expect("class names should parse");
+    let aliases: Vec<_> = merged.expressions.iter().map(|e| e.alias()).collect();
+    assert_eq!(aliases, ["worth", "metadata.color", "featured"]);
+}
+
+#[test]
+fn tokens_carry_kinds_and_spans_into_the_input() {
+    let input = "db-users-name-where-age-after-30-orderby-id-desc-limit-5";
+    let tokens = tokenize(input).expect("class name should tokenize");
+    let kinds: Vec<_> = tokens.iter().map(|token| token.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Prefix,
+            TokenKind::Table,
+            TokenKind::Column,
+            TokenKind::Keyword,
+            TokenKind::WhereField,
+            TokenKind::Operator,
+            TokenKind::Value,
+            TokenKind::Keyword,
+            TokenKind::OrderField,
+            TokenKind::Direction,
+            TokenKind::Keyword,
+            TokenKind::Limit,
+        ]
+    );
+    for token in &tokens {
+        assert_eq!(&input[token.start..token.end], token.text);
+    }
+    assert!(tokenize("users-name").is_none());
+    assert!(tokenize("db-").is_none());
+}
+
+#[test]
+fn bracketed_expressions_span_their_segments() {
+    let input = "db-products-expr-[price-cost]-as-margin";
+    let ast = parse(input).expect("class name should parse");
+    let [Clause::Expression { expression, alias }] = ast.clauses.as_slice() else {
+        panic!("expected one expression, got {:?}", ast.clauses);
+    };
+    assert_eq!(expression.text, "[price-cost]");
+    assert_eq!(&input[expression.start..expression.end], "[price-cost]");
+    assert_eq!(alias.text, "margin");
+
+    // Without a closing bracket or an alias the expression is invalid.
+    let unclosed =
+        parse("db-products-expr-[price-cost-as-margin").expect("class name should parse");
+    assert!(unclosed.clauses.is_empty());
+    assert_eq!(unclosed.invalid[0].kind, TokenKind::Invalid);
+    assert!(unclosed.invalid[0].text.starts_with("[price"));
+    let unnamed = parse("db-products-expr-[price*2]").expect("class name should parse");
+    assert!(unnamed.clauses.is_empty() && !unnamed.invalid.is_empty());
+}
+
+#[test]
+fn in_subqueries_nest_with_spans_into_the_outer_input() {
+    let input =
+        "db-posts-title-where-author_id-in-(db-users-id-where-team_id-in-(db-teams-id))-limit-3";
+    let ast = parse(input).expect("class name should parse");
+    let Some(Clause::Where {
+        value,
+        subquery: Some(users),
+        ..
+    }) = ast.clauses.get(1)
+    else {
+        panic!("expected a subquery, got {:?}", ast.clauses);
+    };
+    assert_eq!(value.text, "(db-users-id-where-team_id-in-(db-teams-id))");
+    assert_eq!(&input[users.table.start..users.table.end], "users");
+    let Some(Clause::Where {
+        subquery: Some(teams),
+        ..
+    }) = users.clauses.get(1)
+    else {
+        panic!("expected a nested subquery, got {:?}", users.clauses);
+    };
+    assert_eq!(&input[teams.table.start..teams.table.end], "teams");
+    assert!(matches!(ast.clauses.last(), Some(Clause::Limit(limit)) if limit.text == "3"));
+
+    // `in` is only an operator before a parenthesis.
+    let literal = parse("db-users-name-where-status-in").expect("class name should parse");
+    let Some(Clause::Where {
+        operator, value, ..
+    }) = literal.clauses.get(1)
+    else {
+        panic!("expected a where clause, got {:?}", literal.clauses);
+    };
+    assert!(operator.is_none());
+    assert_eq!(value.text, "in");
+
+    let unbalanced =
+        parse("db-posts-title-where-author_id-in-(db-users-id").expect("class name should parse");
+    assert!(unbalanced.clauses.len() == 1 && !unbalanced.invalid.is_empty());
+}
+
+#[test]
+fn window_and_sort_directions_default_to_ascending() {
+    let config = parse_class_name(
+        "db-scores-player-rank-over-likes-desc-partition-league-row_number-over-id-orderby-player",
+    )
+    .expect("class name should parse");
+    let windows: Vec<_> = config.expressions.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        windows,
+        [
+            "rank-over-likes-desc-partition-league",
+            "row_number-over-id-asc"
+        ]
+    );
+    let order_by = config.order_by.expect("orderby should parse");
+    assert_eq!(order_by.field, "player");
+    assert!(matches!(order_by.direction, OrderDirection::Asc));
+
+    // A window without its field is dropped as invalid.
+    let dangling = parse("db-scores-rank-over").expect("class name should parse");
+    assert!(dangling.clauses.is_empty());
+    assert_eq!(dangling.invalid[0].text, "rank");
+}
+
+#[test]
+fn directions_are_read_in_any_case() {
+    let config = parse_class_name_strict(
+        "db-scores-player-rank-over-likes-DESC-orderby-player-Desc",
+        |_, _| true,
+    )
+    .expect("class name should parse strictly");
+    assert_eq!(config.expressions[0].to_string(), "rank-over-likes-desc");
+    let order_by = config.order_by.expect("orderby should parse");
+    assert!(matches!(order_by.direction, OrderDirection::Desc));
+    assert!(sql("db-users-name-orderby-id-DESC").ends_with("ORDER BY \"id\" DESC"));
+}
+
+#[test]
+fn invalid_tokens_report_where_they_are() {
+    let input = "db-users-name-limit-abc-refresh-soon";
+    let ast = parse(input).expect("class name should parse");
+    let invalid: Vec<_> = ast
+        .invalid
+        .iter()
+        .map(|token| (token.text, token.start))
+        .collect();
+    assert_eq!(invalid, [("abc", 20), ("soon", 32)]);
+    assert_eq!(&input[20..23], "abc");
+
+    // A where field left without a value takes the rest of the class with it.
+    let dangling = parse("db-users-name-where-role").expect("class name should parse");
+    assert_eq!(dangling.invalid.len(), 1);
+    assert_eq!(
+        (dangling.invalid[0].text, dangling.invalid[0].start),
+        ("role", 20)
+    );
+}
+
+#[test]
+fn strict_parsing_rejects_what_the_lenient_parser_guesses_at() {
+    let columns = |table: &str, column: &str| match table {
+        "users" => ["id", "name", "role", "team_id"].contains(&column),
+        "teams" => ["id", "name"].contains(&column),
+        _ => false,
+    };
+    let strict = |class_name: &str| parse_class_name_strict(class_name, columns);
+
+    let config = strict("db-users-name-where-role-admin-orderby-id-desc-limit-5")
+        .expect("known columns should parse");
+    assert_eq!(config.columns, ["name"]);
+
+    // The lenient parser selects a typo as a column.
+    let typo = "db-users-name-ordeby-id";
+    assert_eq!(
+        parse_class_name(typo)
+            .expect("class name should parse")
+            .columns,
+        ["name", "ordeby", "id"]
+    );
+    assert_eq!(
+        strict(typo).unwrap_err(),
+        ParseError::UnknownColumn {
+            table: "users".to_string(),
+            column: "ordeby".to_string(),
+            position: 14,
+        }
+    );
+    assert_eq!(
+        strict("db-users-name-limit-many").unwrap_err(),
+        ParseError::InvalidToken {
+            token: "many".to_string(),
+            position: 20,
+        }
+    );
+    assert!(matches!(
+        strict("db-users-name-where-team_id-in-(db-teams-nickname)"),
+        Err(ParseError::UnknownColumn { table, column, position: 41 })
+            if table == "teams" && column == "nickname"
+    ));
+    assert!(matches!(
+        strict("db-users-name-where-role-$ROLE"),
+        Err(ParseError::UnknownVariable { name, position: 25 }) if name == "ROLE"
+    ));
+    assert!(matches!(
+        strict("users-name"),
+        Err(ParseError::NotAClassName(name)) if name == "users-name"
+    ));
+}
+
+/// Words the grammar reads as something other than a name or value.
+const RESERVED: &[&str] = &[
+    "where",
+    "limit",
+    "offset",
+    "join",
+    "orderby",
+    "search",
+    "refresh",
+    "after",
+    "before",
+    "contains",
+    "in",
+    "last",
+    "over",
+    "expr",
+    "case",
+    "json",
+    "end",
+    "as",
+    "partition",
+    "asc",
+    "desc",
+    "rank",
+    "dense_rank",
+    "row_number",
+    "through",
+];
+
+fn name() -> impl Strategy<Value = String> {
+    "[a-z][a-z_]{0,7}".prop_filter("reserved word", |name| !RESERVED.contains(&name.as_str()))
+}
+
+fn value() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z0-9]{1,6}".prop_filter("reserved word", |value| !RESERVED.contains(&value.as_str())),
+        // Dates and relative dates span dashes, so are bracketed.
+        "[0-9]{4}-[0-9]{2}-[0-9]{2}".prop_map(|date| format!("[{date}]")),
+        (1..30u32).prop_map(|days| format!("last-{days}-days")),
+    ]
+}
+
+fn where_clause() -> impl Strategy<Value = String> {
+    let operator = prop::sample::select(vec!["", "after-", "before-", "contains-"]);
+    (name(), operator, value())
+        .prop_map(|(field, operator, value)| format!("{field}-{operator}{value}"))
+}
+
+fn join() -> impl Strategy<Value = String> {
+    let on = prop::sample::select(vec![
+        "",
+        "id-author_id",
+        "posts.id-post_id",
+        "through-post_tags",
+    ]);
+    let join_type = prop::sample::select(vec!["inner", "left", "right"]);
+    (name(), on, prop::collection::vec(name(), 0..3), join_type).prop_map(
+        |(table, on, columns, join_type)| {
+            format!("join-[{table}:{on}:{}:{join_type}]", columns.join(","))
+        },
+    )
+}
+
+fn window() -> impl Strategy<Value = String> {
+    let function = prop::sample::select(vec!["rank", "dense_rank", "row_number"]);
+    let direction = prop::sample::select(vec!["", "-asc", "-desc"]);
+    (function, name(), direction, prop::option::of(name())).prop_map(
+        |(function, field, direction, partition)| {
+            let partition = partition
+                .map(|p| format!("-partition-{p}"))
+                .unwrap_or_default();
+            format!("{function}-over-{field}{direction}{partition}")
+        },
+    )
+}
+
+prop_compose! {
+    fn class_name()(
+        table in name(),
+        columns in prop::collection::vec(name(), 0..3),
+        windows in prop::collection::vec(window(), 0..2),
+        joins in prop::collection::vec(join(), 0..2),
+        wheres in prop::collection::vec(where_clause(), 0..3),
+        search in prop::option::of(value()),
+        order_by in prop::option::of((name(), prop::sample::select(vec!["asc", "desc"]))),
+        limit in prop::option::of(1..1000i64),
+        offset in prop::option::of(0..1000i64),
+        refresh in prop::option::of(prop::sample::select(vec!["30s", "5m", "2h", "90"])),
+    ) -> String {
+        let mut class_name = format!("db-{table}");
+        for part in columns.iter().chain(&windows).chain(&joins) {
+            class_name.push_str(&format!("-{part}"));
+        }
+        if !wheres.is_empty() {
+            class_name.push_str(&format!("-where-{}", wheres.join("-")));
+        }
+        if let Some(term) = search {
+            class_name.push_str(&format!("-search-{term}"));
+        }
+        if let Some((field, direction)) = order_by {
+            class_name.push_str(&format!("-orderby-{field}-{direction}"));
+        }
+        if let Some(limit) = limit {
+            class_name.push_str(&format!("-limit-{limit}"));
+        }
+        if let Some(offset) = offset {
+            class_name.push_str(&format!("-offset-{offset}"));
+        }
+        if let Some(refresh) = refresh {
+            class_name.push_str(&format!("-refresh-{refresh}"));
+        }
+        class_name
+    }
+}
+
+proptest! {
+    #[test]
+    fn class_names_round_trip_through_display(class_name in class_name()) {
+        let parsed = parse_class_name(&class_name).expect("generated class names parse");
+        let written = parsed.to_class_name();
+        let reparsed = parse_class_name(&written).expect("written class names parse");
+        prop_assert_eq!(format!("{reparsed:?}"), format!("{parsed:?}"));
+        prop_assert_eq!(reparsed.to_class_name(), written);
+    }
+
+    #[test]
+    fn join_params_round_trip_through_display(
+        table in name(),
+        alias in prop::option::of(name()),
+        on in prop::sample::select(vec!["", "id-author_id", "posts.id-post_id", "through-post_tags"]),
+        columns in prop::collection::vec(name(), 0..3),
+        join_type in prop::sample::select(vec!["inner", "left", "right"]),
+    ) {
+        let alias = alias.map(|alias| format!(" as {alias}")).unwrap_or_default();
+        let param = format!("{table}{alias}:{on}:{}:{join_type}", columns.join(","));
+        let parsed = parse_join_param(&param).expect("generated join params parse");
+        let written = parsed.to_join_param();
+        prop_assert_eq!(&written, &param);
+        let reparsed = parse_join_param(&written).expect("written join params parse");
+        prop_assert_eq!(format!("{reparsed:?}"), format!("{parsed:?}"));
+    }
+}
+
+#[test]
+fn dashed_values_joins_and_offsets_round_trip() {
+    let config = parse_class_names(
+        "db-posts-title-join-[users:author_id-id:name:inner]\
+         -where-created_at-after-[2024-01-15]-status-[in]-search-[e-mail]-limit-10-offset-20",
+    )
+    .expect("class names should parse");
+    assert_eq!(config.where_clauses[0].value, "2024-01-15");
+    assert_eq!(config.where_clauses[1].value, "in");
+    assert_eq!(
+        config.joins[0].to_join_param(),
+        "users:author_id-id:name:inner"
+    );
+    assert_eq!(config.offset, Some(20));
+
+    let written = config.to_class_name();
+    assert_eq!(
+        written,
+        "db-posts-title-join-[users:author_id-id:name:inner]\
+         -where-created_at-after-[2024-01-15]-status-[in]-search-[e-mail]-limit-10-offset-20"
+    );
+    let reparsed = parse_class_name(&written).expect("written class names parse");
+    assert_eq!(format!("{reparsed:?}"), format!("{config:?}"));
+
+    // Programmatic joins and pages are written out too.
+    let relative = parse_class_name("db-posts-title-where-created_at-after-last-7-days")
+        .expect("class name should parse");
+    let joined = config_with_join(
+        relative,
+        parse_join_param("tags:through-post_tags:name:left").expect("join should parse"),
+    );
+    let paged = QueryConfig {
+        offset: Some(40),
+        ..joined
+    };
+    assert_eq!(
+        paged.to_class_name(),
+        "db-posts-title-join-[tags:through-post_tags:name:left]\
+         -where-created_at-after-[last-7-days]-offset-40"
+    );
+}
+
+#[test]
+fn older_grammars_keep_their_readings_with_warnings() {
+    assert_eq!(GrammarVersion::parse("v1"), Some(GrammarVersion::V1));
+    assert_eq!(GrammarVersion::parse(" 2 "), Some(GrammarVersion::V2));
+    assert_eq!(GrammarVersion::parse("3"), None);
+    assert_eq!(GrammarVersion::default(), GrammarVersion::CURRENT);
+
+    let class_name = "db-posts-title-search-rust-where-likes-after-10";
+    let v1 = GrammarConfig::default().with_version(GrammarVersion::V1);
+    let old = parse_class_name_with(class_name, &v1).expect("class name should parse");
+    assert_eq!(old.columns, ["title", "search", "rust"]);
+    assert_eq!(old.where_clauses[0].value, "after");
+    let current = parse_class_name(class_name).expect("class name should parse");
+    assert_eq!(
+        current.search.map(|search| search.term).as_deref(),
+        Some("rust")
+    );
+    assert_eq!(current.where_clauses[0].value, "10");
+
+    assert_eq!(
+        grammar_warnings(class_name, &v1),
+        [
+            "grammar v1 is deprecated; migrate class names to v2".to_string(),
+            format!(
+                "{class_name} reads as db-posts-title-search-rust-where-likes-[after] \
+                 under grammar v1 but as db-posts-title-where-likes-after-10-search-rust under v2"
+            ),
+        ]
+    );
+    // A class name both grammars read alike needs no note under the current one.
+    assert!(grammar_warnings("db-users-name-limit-5", &GrammarConfig::default()).is_empty());
+}
+
+#[test]
+fn stacked_class_names_merge_like_utilities() {
+    let config = parse_class_names(
+        "text-sm db-users-name-limit-10 db-users-name-email-where-role-admin \
+         db-posts-title db-users-where-active-1-orderby-name-desc-limit-5",
+    )
+    .expect("class names should parse");
+    assert_eq!(
+        config.to_string(),
+        "db-users-name-email-where-role-admin-active-1-orderby-name-desc-limit-5"
+    );
+    assert!(parse_class_names("text-sm font-bold").is_none());
+
+    let strict = parse_class_names_strict_with(
+        "db-users-name db-users-nmae",
+        &GrammarConfig::default(),
+        |_, column| column == "name",
+    );
+    assert!(matches!(
+        strict,
+        Err(ParseError::UnknownColumn { column, .. }) if column == "nmae"
+    ));
+
+    // A paged config keeps its offset when another class is folded in.
+    let mut paged = parse_class_name("db-posts-title-limit-10").expect("class name should parse");
+    paged.offset = Some(20);
+    let other = parse_class_name("db-posts-id").expect("class name should parse");
+    assert_eq!(merge_configs(paged, other).offset, Some(20));
+}
+
+#[test]
+fn aliases_stand_for_built_in_words() {
+    let grammar = GrammarConfig::default()
+        .with_alias("sort", "orderby")
+        .with_alias("top", "limit")
+        .with_alias("is", "eq")
+        .with_alias("since", "after")
+        .with_alias("nope", "select");
+    let input = "db-users-name-where-role-is-admin-age-since-30-sort-id-desc-top-5-nope";
+    let config = parse_class_name_with(input, &grammar).expect("class name should parse");
+    assert_eq!(
+        config.to_string(),
+        "db-users-name-nope-where-role-admin-age-after-30-orderby-id-desc-limit-5"
+    );
+
+    let tokens = tokenize_with(input, &grammar).expect("class name should tokenize");
+    let aliased: Vec<_> = tokens
+        .iter()
+        .filter_map(|token| token.alias_of.map(|word| (token.text, word)))
+        .collect();
+    assert_eq!(
+        aliased,
+        [
+            ("is", "eq"),
+            ("since", "after"),
+            ("sort", "orderby"),
+            ("top", "limit")
+        ]
+    );
+    // Without the registry the aliases are plain columns and values.
+    let plain = parse_class_name("db-users-name-sort-id").expect("class name should parse");
+    assert_eq!(plain.columns, ["name", "sort", "id"]);
+}
+
+#[test]
+fn snippets_expand_before_tokenizing() {
+    let grammar = GrammarConfig::default()
+        .with_snippet("@active", "-where-status-active-")
+        .with_snippet(
+            "active-recent",
+            "where-status-active-orderby-created_at-desc",
+        );
+    assert_eq!(
+        grammar.expand_snippets("db-users-name-@active-limit-50"),
+        "db-users-name-where-status-active-limit-50"
+    );
+    // The longest name that ends at a segment boundary wins.
+    assert_eq!(
+        grammar.expand_snippets("db-users-name-@active-recent"),
+        "db-users-name-where-status-active-orderby-created_at-desc"
+    );
+    // Unknown names and names inside a segment are left alone.
+    assert_eq!(
+        grammar.expand_snippets("db-users-name-@actively"),
+        "db-users-name-@actively"
+    );
+    assert!(matches!(
+        GrammarConfig::default().expand_snippets("db-users-@active"),
+        Cow::Borrowed(_)
+    ));
+
+    let config = parse_class_names_with("db-users-name-@active db-users-id-limit-5", &grammar)
+        .expect("class names should parse");
+    assert_eq!(
+        config.to_string(),
+        "db-users-name-id-where-status-active-limit-5"
+    );
+    // Strict positions refer to the expanded class name.
+    let strict =
+        parse_class_name_strict_with("db-users-@active", &grammar, |_, column| column != "status");
+    assert!(matches!(
+        strict,
+        Err(ParseError::UnknownColumn { column, position: 15, .. }) if column == "status"
+    ));
+}
+
+#[test]
+fn variables_substitute_only_registered_names() {
+    let grammar = GrammarConfig::default().with_variable("$TENANT", "42");
+    assert_eq!(grammar.resolve_variable("$TENANT"), Ok(Some("42")));
+    assert_eq!(grammar.resolve_variable("42"), Ok(None));
+    assert_eq!(grammar.resolve_variable("$HOME"), Err("HOME".to_string()));
+
+    let config = parse_class_name_with(
+        "db-posts-title-where-tenant_id-$TENANT-author_id-in-(db-users-id-where-tenant_id-$TENANT)",
+        &grammar,
+    )
+    .expect("class name should parse");
+    assert_eq!(config.where_clauses[0].value, "42");
+    let subquery = config.where_clauses[1]
+        .subquery
+        .as_ref()
+        .expect("subquery should parse");
+    assert_eq!(subquery.where_clauses[0].value, "42");
+
+    // Unregistered names are never read from the environment: leniently they
+    // stay literal, strictly they are errors.
+    let unknown = "db-posts-title-where-owner-$HOME";
+    let lenient = parse_class_name_with(unknown, &grammar).expect("class name should parse");
+    assert_eq!(lenient.where_clauses[0].value, "$HOME");
+    assert!(matches!(
+        parse_class_name_strict_with(unknown, &grammar, |_, _| true),
+        Err(ParseError::UnknownVariable { name, position: 27 }) if name == "HOME"
+    ));
+}
@@ -0,0 +1,222 @@
+use rusqlite::Connection;
+use tailwindsql::parser::{config_with_join, parse_class_names, parse_join_params, parse_mutation};
+use tailwindsql::policy::{AccessPolicy, PolicyError};
+use tailwindsql::schema::SchemaCatalog;
+
+fn config_with_joins(class_name: &str, joins: &str) -> tailwindsql::parser::QueryConfig {
+    let config = parse_class_names(class_name).expect("class name should parse");
+    parse_join_params(joins)
+        .into_iter()
+        .fold(config, config_with_join)
+}
+
+#[test]
+fn join_cannot_reach_table_outside_allow_list() {
+    let policy = AccessPolicy::default().allow_tables(["posts"]);
+    let config = config_with_joins("db-posts-title", "users:author_id-id:email");
+
+    assert_eq!(
+        policy.check(&config),
+        Err(PolicyError::TableDenied("users".to_string()))
+    );
+}
+
+#[test]
+fn join_cannot_reach_denied_table() {
+    let policy = AccessPolicy::default().deny_table("users");
+    let config = config_with_joins("db-posts-title", "users:author_id-id:name");
+
+    assert_eq!(
+        policy.check(&config),
+        Err(PolicyError::TableDenied("users".to_string()))
+    );
+}
+
+#[test]
+fn aliased_join_is_checked_against_real_table() {
+    let policy = AccessPolicy::default().deny_table("users");
+    let config = config_with_joins("db-posts-title", "users as author:author_id-id:name");
+
+    assert!(matches!(
+        policy.check(&config),
+        Err(PolicyError::TableDenied(table)) if table == "users"
+    ));
+}
+
+#[test]
+fn join_cannot_select_hidden_column() {
+    let policy = AccessPolicy::default().deny_column("users", "email");
+    let config = config_with_joins("db-posts-title", "users:author_id-id:name,email");
+
+    assert_eq!(
+        policy.check(&config),
+        Err(PolicyError::ColumnDenied {
+            table: "users".to_string(),
+            column: "email".to_string(),
+        })
+    );
+}
+
+#[test]
+fn join_wildcard_is_rejected_when_table_has_hidden_columns() {
+    let policy = AccessPolicy::default().deny_column("users", "email");
+    let config = config_with_joins("db-posts-title", "users:author_id-id");
+
+    assert!(matches!(
+        policy.check(&config),
+        Err(PolicyError::ColumnDenied { column, .. }) if column == "*"
+    ));
+}
+
+#[test]
+fn multi_hop_join_parent_column_is_checked() {
+    let policy = AccessPolicy::default().deny_column("users", "email");
+    let config = config_with_joins(
+        "db-posts-title",
+        "users as author:author_id-id:name,posts:author.email-title:likes",
+    );
+
+    assert!(matches!(
+        policy.check(&config),
+        Err(PolicyError::ColumnDenied { table, column }) if table == "users" && column == "email"
+    ));
+}
+
+#[test]
+fn allowed_joins_pass() {
+    let policy = AccessPolicy::default()
+        .allow_tables(["posts", "users"])
+        .deny_column("users", "email");
+    let config = config_with_joins("db-posts-title", "users:author_id-id:name");
+
+    assert_eq!(policy.check(&config), Ok(()));
+}
+
+#[test]
+fn qualified_column_is_checked_against_its_table() {
+    let policy = AccessPolicy::default().deny_column("users", "email");
+    let config = config_with_joins(
+        "db-posts-title-author.email",
+        "users as author:author_id-id:name",
+    );
+
+    assert_eq!(
+        policy.check(&config),
+        Err(PolicyError::ColumnDenied {
+            table: "users".to_string(),
+            column: "email".to_string(),
+        })
+    );
+}
+
+#[test]
+fn mutations_need_writes_enabled() {
+    let config = parse_mutation("ins-users-name-[Ada]-role-admin").expect("insert should parse");
+
+    assert_eq!(
+        AccessPolicy::default().check_mutation(&config),
+        Err(PolicyError::WritesDisabled)
+    );
+    assert_eq!(
+        AccessPolicy::default()
+            .allow_writes(true)
+            .deny_column("users", "role")
+            .check_mutation(&config),
+        Err(PolicyError::ColumnDenied {
+            table: "users".to_string(),
+            column: "role".to_string(),
+        })
+    );
+}
+
+#[test]
+fn deletes_need_destructive_operations_enabled() {
+    let config = parse_mutation("del-posts-where-id-42").expect("delete should parse");
+
+    assert_eq!(
+        AccessPolicy::default()
+            .allow_writes(true)
+            .check_mutation(&config),
+        Err(PolicyError::DestructiveDisabled)
+    );
+    assert_eq!(
+        AccessPolicy::default()
+            .allow_writes(true)
+            .allow_destructive(true)
+            .check_mutation(&config),
+        Ok(())
+    );
+}
+
+#[test]
+fn subquery_cannot_filter_on_hidden_column() {
+    let policy = AccessPolicy::default().deny_column("users", "password_hash");
+    let config =
+        parse_class_names("db-posts-title-where-author_id-in-(db-users-id-where-password_hash-x)")
+            .expect("class name should parse");
+
+    assert!(matches!(
+        policy.check(&config),
+        Err(PolicyError::ColumnDenied { column, .. }) if column == "password_hash"
+    ));
+}
+
+#[test]
+fn window_function_cannot_rank_by_hidden_column() {
+    let policy = AccessPolicy::default().deny_column("users", "salary");
+    let config =
+        parse_class_names("db-users-name-rank-over-salary-desc").expect("class name should parse");
+
+    assert!(matches!(
+        policy.check(&config),
+        Err(PolicyError::ColumnDenied { column, .. }) if column == "salary"
+    ));
+}
+
+/// `notes` indexed for full-text search over both columns, `drafts` not.
+fn search_schema() -> SchemaCatalog {
+    let conn = Connection::open_in_memory().expect("in-memory database should open");
+    conn.execute_batch(
+        "CREATE TABLE notes (id INTEGER PRIMARY KEY, title TEXT, secret TEXT);
+         CREATE VIRTUAL TABLE notes_fts USING fts5(title, secret, content='notes', content_rowid='id');
+         CREATE TABLE drafts (id INTEGER PRIMARY KEY, title TEXT, secret TEXT, words INTEGER);",
+    )
+    .expect("fixture should load");
+    SchemaCatalog::load(&conn).expect("schema should load")
+}
+
+#[test]
+fn search_skips_hidden_columns() {
+    let schema = search_schema();
+    let policy = AccessPolicy::default().deny_column("drafts", "secret");
+    let config = parse_class_names("db-drafts-id-search-oracle").expect("class name should parse");
+
+    let resolved = schema.resolve_search_visible(&config, &|table, column| {
+        policy.is_column_allowed(table, column)
+    });
+    let search = resolved.search.expect("search should be kept");
+    assert_eq!(search.columns, ["title"]);
+    assert_eq!(search.fts_table, None);
+}
+
+#[test]
+fn search_does_not_use_an_index_over_hidden_columns() {
+    let schema = search_schema();
+    let config = parse_class_names("db-notes-id-search-oracle").expect("class name should parse");
+    let open = AccessPolicy::default();
+    let visible = |policy: &AccessPolicy| {
+        schema
+            .resolve_search_visible(&config, &|table, column| {
+                policy.is_column_allowed(table, column)
+            })
+            .search
+            .expect("search should be kept")
+    };
+
+    assert_eq!(visible(&open).fts_table.as_deref(), Some("notes_fts"));
+
+    // A match through the index would reveal what `secret` holds.
+    let search = visible(&AccessPolicy::default().deny_column("notes", "secret"));
+    assert_eq!(search.fts_table, None);
+    assert_eq!(search.columns, ["title"]);
+}
@@ -0,0 +1,149 @@
+#![cfg(feature = "postgres")]
+
+use bytes::BytesMut;
+use rusqlite::types::Value;
+use serde_json::json;
+use tailwindsql::db::postgres::{PgParam, PgValue};
+use tailwindsql::dialect::{Postgres, SqlDialect};
+use tailwindsql::parser::parse_class_names;
+use tailwindsql::query_builder::{build_query_for, ParamStyle, QueryPolicy};
+use tokio_postgres::types::{FromSql, IsNull, ToSql, Type};
+
+/// The wire bytes `value` is sent as for a placeholder of type `ty`.
+fn encode(value: Value, ty: &Type) -> Result<Option<Vec<u8>>, String> {
+    let mut out = BytesMut::new();
+    match PgParam(&value).to_sql(ty, &mut out) {
+        Ok(IsNull::Yes) => Ok(None),
+        Ok(IsNull::No) => Ok(Some(out.to_vec())),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+fn decode(ty: &Type, raw: &[u8]) -> serde_json::Value {
+    PgValue::from_sql(ty, raw).expect("cell should decode").0
+}
+
+#[test]
+fn placeholders_are_numbered_across_the_whole_query() {
+    let config = parse_class_names(
+        "db-posts-title-where-author_id-in-(db-users-id-where-role-admin)-likes-after-10-limit-5",
+    )
+    .expect("class name should parse");
+    let policy = QueryPolicy {
+        param_style: ParamStyle::Named,
+        ..QueryPolicy::default()
+    };
+    let built = build_query_for(&Postgres, &config, &policy).expect("query should build");
+    assert_eq!(
+        built.sql,
+        "SELECT \"title\" FROM \"posts\" WHERE \"author_id\" IN \
+         (SELECT \"id\" FROM \"users\" WHERE \"role\" = $1) AND \"likes\" > $2 LIMIT $3"
+    );
+    assert_eq!(
+        built.params,
+        [
+            Value::Text("admin".to_string()),
+            Value::Integer(10),
+            Value::Integer(5)
+        ]
+    );
+}
+
+#[test]
+fn the_dialect_quotes_and_spells_postgres_sql() {
+    assert_eq!(
+        Postgres.quote_identifier("say \"hi\""),
+        "\"say \"\"hi\"\"\""
+    );
+    assert_eq!(Postgres.placeholder(3, "role", ParamStyle::Named), "$3");
+    assert_eq!(
+        Postgres.json_extract("\"metadata\"", &["sizes", "1"]),
+        "jsonb_extract_path_text(\"metadata\"::jsonb, 'sizes', '1')"
+    );
+    let mut bound = Vec::new();
+    let expr = Postgres.relative_datetime(
+        &["start of day".to_string(), "-7 days".to_string()],
+        &mut |value| {
+            bound.push(value);
+            format!("${}", bound.len())
+        },
+    );
+    assert_eq!(expr, "(date_trunc('day', now()) + $1::text::interval)");
+    assert_eq!(bound, [Value::Text("-7 days".to_string())]);
+    assert!(Postgres
+        .full_text_match("\"posts\"", "\"posts_fts\"", "$1")
+        .is_none());
+}
+
+#[test]
+fn bound_values_convert_to_the_inferred_type() {
+    assert_eq!(encode(Value::Null, &Type::INT8), Ok(None));
+    assert_eq!(
+        encode(Value::Text(" 42 ".to_string()), &Type::INT4),
+        Ok(Some(42i32.to_be_bytes().to_vec()))
+    );
+    assert_eq!(
+        encode(Value::Integer(7), &Type::INT8),
+        Ok(Some(7i64.to_be_bytes().to_vec()))
+    );
+    assert!(encode(Value::Integer(70_000), &Type::INT2).is_err());
+    assert!(encode(Value::Text("many".to_string()), &Type::INT8).is_err());
+    assert_eq!(
+        encode(Value::Integer(2), &Type::FLOAT8),
+        Ok(Some(2f64.to_be_bytes().to_vec()))
+    );
+    assert_eq!(
+        encode(Value::Text("t".to_string()), &Type::BOOL),
+        Ok(Some(vec![1]))
+    );
+    assert_eq!(encode(Value::Integer(0), &Type::BOOL), Ok(Some(vec![0])));
+    assert!(encode(Value::Text("yes".to_string()), &Type::BOOL).is_err());
+    assert_eq!(
+        encode(Value::Integer(12), &Type::TEXT),
+        Ok(Some(b"12".to_vec()))
+    );
+
+    // Dates and timestamps count from 2000-01-01.
+    assert_eq!(
+        encode(Value::Text("2000-01-02".to_string()), &Type::DATE),
+        Ok(Some(1i32.to_be_bytes().to_vec()))
+    );
+    assert_eq!(
+        encode(
+            Value::Text("2000-01-01 00:00:01".to_string()),
+            &Type::TIMESTAMP
+        ),
+        Ok(Some(1_000_000i64.to_be_bytes().to_vec()))
+    );
+    assert!(encode(Value::Text("soon".to_string()), &Type::TIMESTAMP).is_err());
+}
+
+#[test]
+fn result_cells_decode_to_json() {
+    assert_eq!(decode(&Type::INT8, &(-3i64).to_be_bytes()), json!(-3));
+    assert_eq!(decode(&Type::INT2, &5i16.to_be_bytes()), json!(5));
+    assert_eq!(decode(&Type::FLOAT8, &1.5f64.to_be_bytes()), json!(1.5));
+    assert_eq!(decode(&Type::BOOL, &[1]), json!(true));
+    assert_eq!(decode(&Type::TEXT, b"Ada"), json!("Ada"));
+    assert_eq!(decode(&Type::JSON, br#"{"a":[1]}"#), json!({"a": [1]}));
+    assert_eq!(
+        decode(&Type::DATE, &(-1i32).to_be_bytes()),
+        json!("1999-12-31")
+    );
+    assert_eq!(
+        decode(&Type::TIMESTAMP, &86_400_000_000i64.to_be_bytes()),
+        json!("2000-01-02 00:00:00")
+    );
+    let uuid: Vec<u8> = (0..16).collect();
+    assert_eq!(
+        decode(&Type::UUID, &uuid),
+        json!("00010203-0405-0607-0809-0a0b0c0d0e0f")
+    );
+    assert_eq!(decode(&Type::POINT, &[0; 16]), json!(null));
+    assert_eq!(
+        PgValue::from_sql_null(&Type::INT8)
+            .expect("null should decode")
+            .0,
+        json!(null)
+    );
+}
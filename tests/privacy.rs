@@ -0,0 +1,141 @@
+#![cfg(feature = "server")]
+
+use axum::http::StatusCode;
+use rusqlite::Connection;
+use tailwindsql::db::{self, sqlite::SqliteDatabase};
+use tailwindsql::policy::AccessPolicy;
+use tailwindsql::test_support::TestServer;
+
+const ADMIN: &[(&str, &str)] = &[("authorization", "Bearer s3cret")];
+
+// The settings are read from the environment, so every case runs in this one
+// test rather than racing other tests for it.
+#[tokio::test]
+async fn classified_columns_are_redacted_unless_the_caller_is_admin() {
+    std::env::set_var("PII_COLUMNS", "users.email");
+    std::env::set_var("INTERNAL_COLUMNS", "users.role");
+    std::env::set_var("ADMIN_TOKEN", "s3cret");
+    std::env::set_var("PII_REDACTION", "drop");
+    let server = TestServer::start().await.expect("server should start");
+    let query = "/api/query?className=db-users-name-email-role-orderby-id-asc-limit-2";
+
+    let admin = server
+        .get_with(query, ADMIN)
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    let email = admin["results"][0]["email"]
+        .as_str()
+        .expect("admin sees emails")
+        .to_string();
+    assert!(admin["results"][0]["role"].is_string());
+
+    let api = server
+        .get(query)
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    assert!(api["results"][0].get("email").is_none());
+    assert!(api["results"][0]["role"].is_string());
+    let wrong_token = server
+        .get_with(query, &[("authorization", "Bearer guess")])
+        .await
+        .expect("request should succeed");
+    assert!(!wrong_token.body.contains(&email));
+
+    let embed = server
+        .get("/api/fragment?className=db-users-name-email-role-orderby-id-asc-limit-2&as=table")
+        .await
+        .expect("request should succeed");
+    assert_eq!(embed.status, StatusCode::OK);
+    assert!(!embed.body.contains(&email));
+    assert!(!embed.body.contains("role"));
+
+    // Filtering on a hidden column would reveal it one guess at a time.
+    let probe = server
+        .query("db-users-name-where-email-ada@example.com")
+        .await
+        .expect("request should succeed");
+    assert_eq!(probe.status, StatusCode::FORBIDDEN);
+
+    let schema = server
+        .get("/api/schema")
+        .await
+        .expect("request should succeed");
+    assert!(!schema.body.contains(&email));
+
+    // Subject exports are for admins only, and include every referencing row.
+    // Seeded authors are random, so the subject is whoever wrote a post.
+    let author = server
+        .get("/api/query?className=db-posts-author_id-limit-1")
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON")["results"][0]["author_id"]
+        .clone();
+    let subject = format!("/api/subjects/users/{author}");
+    let denied = server.get(&subject).await.expect("request should succeed");
+    assert_eq!(denied.status, StatusCode::FORBIDDEN);
+    let export = server
+        .get_with(&subject, ADMIN)
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    assert_eq!(export["tables"]["users"][0]["id"], author);
+    assert!(export["tables"]["users"][0]["email"].is_string());
+    assert!(export["tables"]["posts"].is_array());
+
+    // A materialized cache copies the columns unredacted, so it cannot be
+    // read directly, even once the schema has been reloaded with it.
+    let mut conn = Connection::open_in_memory().expect("database should open");
+    db::seed_database(&mut conn).expect("seed should load");
+    conn.execute_batch("CREATE TABLE materialized_people AS SELECT name, email FROM users")
+        .expect("cache table should build");
+    let cached = TestServer::serve(SqliteDatabase::new(conn), AccessPolicy::default())
+        .await
+        .expect("server should start");
+    let direct = cached
+        .query("db-materialized_people-name-email")
+        .await
+        .expect("request should succeed");
+    assert_eq!(direct.status, StatusCode::BAD_REQUEST);
+    assert!(!direct.body.contains(&email));
+    let schema = cached
+        .get("/api/schema")
+        .await
+        .expect("request should succeed");
+    assert!(!schema.body.contains("materialized_people"));
+
+    std::env::set_var("PII_REDACTION", "hash");
+    let hashing = TestServer::start().await.expect("server should start");
+    let hashed = |body: &serde_json::Value| -> Vec<String> {
+        body["results"]
+            .as_array()
+            .expect("results should be an array")
+            .iter()
+            .map(|row| row["email"].as_str().expect("email is hashed").to_string())
+            .collect()
+    };
+    let first = hashed(
+        &hashing
+            .get(query)
+            .await
+            .expect("request should succeed")
+            .json()
+            .expect("response should be JSON"),
+    );
+    let second = hashed(
+        &hashing
+            .get(query)
+            .await
+            .expect("request should succeed")
+            .json()
+            .expect("response should be JSON"),
+    );
+    assert_eq!(first, second);
+    assert_ne!(first[0], first[1]);
+    assert!(first.iter().all(|hash| hash.len() == 32 && hash != &email));
+}
@@ -0,0 +1,138 @@
+#![cfg(feature = "server")]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, StatusCode};
+use tailwindsql::policy::AccessPolicy;
+use tailwindsql::test_support::TestServer;
+
+// The upstream is read from the environment, so every case runs in this one
+// test rather than racing other tests for it.
+#[tokio::test]
+async fn the_proxy_coalesces_and_caches_upstream_responses() {
+    let upstream = TestServer::start_with(AccessPolicy::default().allow_writes(true))
+        .await
+        .expect("upstream should start");
+    std::env::set_var("PROXY_UPSTREAM", upstream.url(""));
+    let proxy = Arc::new(TestServer::start().await.expect("proxy should start"));
+    let query = "/api/query?className=db-users-name-orderby-id-asc-limit-2";
+
+    let direct = upstream.get(query).await.expect("request should succeed");
+    let requests: Vec<_> = (0..8)
+        .map(|_| {
+            let proxy = proxy.clone();
+            tokio::spawn(async move { proxy.get(query).await })
+        })
+        .collect();
+    for request in requests {
+        let response = request
+            .await
+            .expect("task should finish")
+            .expect("request should succeed");
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body, direct.body);
+    }
+    let metrics = proxy
+        .get("/api/metrics")
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    let responses = &metrics["proxy"];
+    assert_eq!(responses["upstream"], upstream.addr().to_string());
+    assert_eq!(responses["misses"], 1);
+    assert_eq!(
+        responses["hits"].as_u64().unwrap_or_default()
+            + responses["coalesced"].as_u64().unwrap_or_default(),
+        7
+    );
+
+    // Cached fragments are revalidated at the edge.
+    let fragment = proxy
+        .fragment("db-users-name-orderby-id-asc-limit-2", "ul")
+        .await
+        .expect("request should succeed");
+    let etag = fragment
+        .headers
+        .get(header::ETAG)
+        .and_then(|value| value.to_str().ok())
+        .expect("fragments should carry an ETag");
+    let cached = proxy
+        .get_with(
+            "/api/fragment?className=db-users-name-orderby-id-asc-limit-2&as=ul",
+            &[("if-none-match", etag)],
+        )
+        .await
+        .expect("request should succeed");
+    assert_eq!(cached.status, StatusCode::NOT_MODIFIED);
+
+    // Fragments are cached per language.
+    let table =
+        "/api/fragment?className=db-products-title-price-orderby-id-asc-limit-2&as=table&page=1";
+    let english = proxy.get(table).await.expect("request should succeed");
+    assert!(english.body.contains("Page 1 of"));
+    let german = proxy
+        .get_with(table, &[("accept-language", "de")])
+        .await
+        .expect("request should succeed");
+    assert!(german.body.contains("Seite 1 von"));
+
+    // Live updates stream through as they arrive, and are not kept.
+    let misses = |proxy: Arc<TestServer>| async move {
+        proxy
+            .get("/api/metrics")
+            .await
+            .expect("request should succeed")
+            .json()
+            .expect("response should be JSON")["proxy"]["misses"]
+            .clone()
+    };
+    let before = misses(proxy.clone()).await;
+    let update = tokio::time::timeout(Duration::from_secs(5), async {
+        proxy
+            .events("/api/live?className=db-users-name-orderby-id-asc-limit-2&as=ul&refresh=1")
+            .await
+            .expect("stream should open")
+            .next()
+            .await
+    })
+    .await
+    .expect("an update should arrive while the stream is open")
+    .expect("stream should be readable")
+    .expect("an update should arrive");
+    assert!(update.contains("<li"), "{update}");
+    assert_eq!(misses(proxy.clone()).await, before);
+
+    // Writes pass through and empty the cache.
+    let renamed = proxy
+        .post(
+            "/api/mutate?className=upd-users-name-[Proxied]-where-id-1",
+            None,
+        )
+        .await
+        .expect("request should succeed");
+    assert_eq!(renamed.status, StatusCode::OK, "{}", renamed.body);
+    let fresh = proxy.get(query).await.expect("request should succeed");
+    assert!(fresh.body.contains("Proxied"));
+
+    // Failures are passed on, and an unreachable upstream is a bad gateway.
+    let invalid = proxy
+        .query("db-nope-name")
+        .await
+        .expect("request should succeed");
+    assert_eq!(
+        invalid.status,
+        upstream
+            .query("db-nope-name")
+            .await
+            .expect("request should succeed")
+            .status
+    );
+    drop(upstream);
+    let unreachable = proxy
+        .get("/api/query?className=db-users-name-limit-1")
+        .await
+        .expect("request should succeed");
+    assert_eq!(unreachable.status, StatusCode::BAD_GATEWAY);
+}
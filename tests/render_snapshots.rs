@@ -0,0 +1,84 @@
+#![cfg(feature = "server")]
+
+use tailwindsql::render::tailwind::{classes_in, fragment_stylesheet, stylesheet};
+use tailwindsql::render::{render_error_with, render_results_with, RenderOptions, Theme};
+use tailwindsql::test_support::render_cases;
+
+#[test]
+fn every_render_mode_matches_its_snapshot() {
+    for case in render_cases() {
+        insta::assert_snapshot!(case.name(), case.snapshot());
+    }
+}
+
+#[test]
+fn the_fragment_stylesheet_covers_every_rendered_class() {
+    let css = fragment_stylesheet();
+    for case in render_cases() {
+        for class in classes_in(&case.render()) {
+            if class == "tailwindsql-fragment" {
+                continue;
+            }
+            let own = stylesheet([class.as_str()]);
+            let rule = own
+                .lines()
+                .find(|line| line.trim_start().starts_with('.'))
+                .unwrap_or_else(|| panic!("no CSS for `{class}` in {}", case.name()));
+            assert!(css.contains(rule), "`{class}` missing from the stylesheet");
+        }
+    }
+    assert!(stylesheet(["not-a-utility", "text-fuchsia-950"]).is_empty());
+    let (_, small) = css
+        .split_once("@media (min-width: 640px) {")
+        .expect("sm: utilities should sit in a media query");
+    assert!(small.contains(".sm\\:px-3 { padding-left: 0.75rem; padding-right: 0.75rem }"));
+}
+
+#[test]
+fn a_theme_replaces_every_palette_class() {
+    let default = Theme::default();
+    let palette: Vec<&str> = [
+        &default.surface,
+        &default.raised,
+        &default.hover,
+        &default.border,
+        &default.text,
+        &default.input_text,
+        &default.label,
+        &default.muted,
+        &default.accent,
+        &default.accent_hover,
+        &default.code,
+        &default.error,
+    ]
+    .into_iter()
+    .flat_map(|classes| classes.split_whitespace())
+    .collect();
+    let options = RenderOptions {
+        theme: Theme::semantic(),
+        pin_first_column: true,
+        ..RenderOptions::default()
+    };
+
+    let mut html = render_error_with("failed", &options.theme);
+    for case in render_cases() {
+        html.push_str(&render_results_with(
+            &case.rows,
+            &case.columns,
+            case.render_as,
+            &options,
+        ));
+    }
+    let classes = classes_in(&html);
+    for class in palette {
+        assert!(!classes.contains(class), "`{class}` survived the theme");
+    }
+    for class in [
+        "tailwindsql-border",
+        "tailwindsql-accent",
+        "tailwindsql-surface",
+        "tailwindsql-error",
+    ] {
+        assert!(classes.contains(class), "`{class}` missing");
+    }
+}
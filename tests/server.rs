@@ -0,0 +1,924 @@
+#![cfg(feature = "server")]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use rusqlite::Connection;
+use serde_json::json;
+use tailwindsql::db::sqlite::SqliteDatabase;
+use tailwindsql::db::{self, Backend, DbError, Worker};
+use tailwindsql::policy::AccessPolicy;
+use tailwindsql::query_builder::QueryPolicy;
+use tailwindsql::server::pages::{ExplorerPage, IndexPage, PageError, PageRenderer};
+use tailwindsql::test_support::TestServer;
+
+#[tokio::test]
+async fn query_api_returns_seeded_rows() {
+    let server = TestServer::start().await.expect("server should start");
+
+    let response = server
+        .query("db-users-name-role-limit-3")
+        .await
+        .expect("request should succeed");
+
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json().expect("response should be JSON");
+    assert_eq!(
+        body["query"],
+        "SELECT \"name\", \"role\", \"id\" AS \"__id\" FROM \"users\" LIMIT ?"
+    );
+    assert_eq!(body["count"], 3);
+}
+
+#[tokio::test]
+async fn fragment_renders_table() {
+    let server = TestServer::start().await.expect("server should start");
+
+    let response = server
+        .fragment("db-products-title-price-limit-2", "table")
+        .await
+        .expect("request should succeed");
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.body.contains("<table"));
+}
+
+#[tokio::test]
+async fn paged_fragment_tables_link_neighbouring_pages() {
+    let server = TestServer::start().await.expect("server should start");
+
+    let response = server
+        .get("/api/fragment?className=db-products-title-price-limit-2&as=table&page=2")
+        .await
+        .expect("request should succeed");
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.body.contains(r#"rel="prev""#));
+    assert!(response.body.contains("page=3"));
+    assert!(response.body.contains("Page 2 of"));
+}
+
+#[tokio::test]
+async fn fragment_headers_round_trip_the_sort() {
+    let server = TestServer::start().await.expect("server should start");
+
+    let response = server
+        .get("/api/fragment?className=db-users-name-email-orderby-id-asc-limit-3&as=table&sort=email&dir=DESC")
+        .await
+        .expect("request should succeed");
+
+    assert_eq!(response.status, StatusCode::OK);
+    let body = &response.body;
+    assert!(body.contains(r#"aria-sort="descending">email"#));
+    assert!(body.contains("sort=email&amp;dir=asc"));
+    assert!(body.contains("sort=name&amp;dir=asc"));
+}
+
+#[tokio::test]
+async fn fragments_speak_the_viewers_language() {
+    let server = TestServer::start().await.expect("server should start");
+    let price = server
+        .get("/api/query?className=db-products-price-where-id-1")
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON")["results"][0]["price"]
+        .to_string();
+    let url =
+        "/api/fragment?className=db-products-title-price-orderby-id-asc-limit-2&as=table&page=1";
+
+    let english = server.get(url).await.expect("request should succeed");
+    assert!(english.body.contains("Page 1 of"));
+    assert!(english.body.contains(&format!(">{price}<")));
+
+    let german = server
+        .get_with(url, &[("accept-language", "fr;q=0.4, de-CH, en;q=0.8")])
+        .await
+        .expect("request should succeed");
+    assert_eq!(german.status, StatusCode::OK);
+    assert_eq!(german.headers["vary"], "accept-language");
+    assert!(german.body.contains("Seite 1 von"));
+    assert!(german.body.contains("Weiter \u{2192}"));
+    let comma = price.replace('.', ",");
+    assert!(german.body.contains(&format!(">{comma}<")));
+    assert_ne!(german.headers["etag"], english.headers["etag"]);
+
+    let empty = server
+        .get_with(
+            "/api/fragment?className=db-products-title-price-where-id-0&as=table",
+            &[("accept-language", "es")],
+        )
+        .await
+        .expect("request should succeed");
+    assert!(empty.body.contains("Sin resultados"));
+}
+
+#[tokio::test]
+async fn schema_of_one_table_refuses_unknown_names() {
+    let server = TestServer::start().await.expect("server should start");
+
+    let users = server
+        .get("/api/schema/users?limit=3")
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    assert_eq!(users["name"], "users");
+    assert_eq!(users["data"].as_array().map(Vec::len), Some(3));
+    assert!(users["rowCount"].as_i64() > Some(3));
+
+    let injected = server
+        .get("/api/schema/users%22%20--")
+        .await
+        .expect("request should succeed");
+    assert_eq!(injected.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn vocabulary_describes_the_filters_columns_take() {
+    let server = TestServer::start_with(AccessPolicy::default().deny_column("products", "stock"))
+        .await
+        .expect("server should start");
+    let vocabulary = server
+        .get("/api/vocabulary/products")
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    let column = |name: &str| {
+        vocabulary["columns"]
+            .as_array()
+            .and_then(|columns| columns.iter().find(|column| column["name"] == name))
+            .cloned()
+    };
+    let operators = |name: &str| -> Vec<String> {
+        column(name).expect("column should be described")["operators"]
+            .as_array()
+            .expect("operators should be a list")
+            .iter()
+            .map(|operator| operator["name"].as_str().unwrap_or_default().to_string())
+            .collect()
+    };
+
+    let price = column("price").expect("price should be described");
+    assert_eq!(price["format"], "number");
+    assert_eq!(operators("price"), ["eq", "gt", "lt", "in"]);
+    assert_eq!(price["operators"][1]["syntax"], "where-price-after-{value}");
+    assert_eq!(column("created_at").expect("dates")["format"], "date");
+    assert_eq!(operators("created_at"), ["eq", "after", "before"]);
+    assert_eq!(operators("title"), ["eq", "contains", "in"]);
+    assert!(
+        column("stock").is_none(),
+        "denied columns cannot be filtered"
+    );
+
+    let unknown = server
+        .get("/api/vocabulary/orders")
+        .await
+        .expect("request should succeed");
+    assert_eq!(unknown.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn mutate_needs_writes_enabled() {
+    let body = json!({"table": "users", "values": {"name": "Ada", "email": "ada@example.com", "role": "admin"}});
+
+    let read_only = TestServer::start().await.expect("server should start");
+    let response = read_only
+        .post("/api/mutate", Some(&body))
+        .await
+        .expect("request should succeed");
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    let writable = TestServer::start_with(AccessPolicy::default().allow_writes(true))
+        .await
+        .expect("server should start");
+    let response = writable
+        .post("/api/mutate", Some(&body))
+        .await
+        .expect("request should succeed");
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.json().expect("response should be JSON")["rowsAffected"],
+        1
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn unix_sockets_replace_stale_sockets_only() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let path = std::env::temp_dir().join(format!("tailwindsql-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    // A regular file at the socket path is refused and kept.
+    std::fs::write(&path, "not a socket").expect("file should write");
+    let refused = TestServer::serve_unix(&path).await;
+    assert!(refused.is_err_and(|error| error.to_string().contains("is not a socket")));
+    assert_eq!(
+        std::fs::read_to_string(&path).expect("file should be kept"),
+        "not a socket"
+    );
+    std::fs::remove_file(&path).expect("file should be removed");
+
+    // A socket left by an earlier run is replaced.
+    drop(std::os::unix::net::UnixListener::bind(&path).expect("socket should bind"));
+    let task = TestServer::serve_unix(&path)
+        .await
+        .expect("stale socket should be replaced");
+    let mut stream = tokio::net::UnixStream::connect(&path)
+        .await
+        .expect("socket should accept");
+    stream
+        .write_all(
+            b"GET /api/query?className=db-users-name-limit-1 HTTP/1.1\r\n\
+              Host: localhost\r\nConnection: close\r\n\r\n",
+        )
+        .await
+        .expect("request should send");
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .await
+        .expect("response should read");
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.contains("\"count\":1"));
+
+    task.abort();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn streamed_rows_serialize_like_collected_rows() {
+    let conn = Connection::open_in_memory().expect("database should open");
+    conn.execute_batch(
+        "CREATE TABLE samples (
+             id INTEGER PRIMARY KEY, flag BOOLEAN, n INTEGER, x REAL, label TEXT, data BLOB
+         );
+         INSERT INTO samples (flag, n, x, label, data) VALUES
+             (1, 0, 0.1, 'plain', x'00ff'),
+             (0, -9223372036854775808, 1e300, 'ünïcode \"quoted\"\n', x''),
+             (2, 9223372036854775807, -0.0, CAST(x'ff6869' AS TEXT), NULL),
+             (NULL, NULL, NULL, NULL, NULL);",
+    )
+    .expect("fixture should load");
+    let server = TestServer::serve(SqliteDatabase::new(conn), AccessPolicy::default())
+        .await
+        .expect("server should start");
+
+    // Cached results take the collect-then-convert path.
+    for class_name in [
+        "db-samples",
+        "db-samples-flag-label-data-orderby-id-desc-limit-2",
+        "db-samples-n-x-where-flag-1",
+    ] {
+        let body = |class_name: String| {
+            let server = &server;
+            async move {
+                let response = server
+                    .query(&class_name)
+                    .await
+                    .expect("request should succeed");
+                assert_eq!(response.status, StatusCode::OK, "{class_name}");
+                response.json().expect("response should be JSON")
+            }
+        };
+        let streamed = body(class_name.to_string()).await;
+        let collected = body(format!("cache:{class_name}")).await;
+        assert_eq!(streamed, collected, "{class_name}");
+    }
+}
+
+#[tokio::test]
+async fn mutate_returning_renders_changed_rows() {
+    let server = TestServer::start_with(AccessPolicy::default().allow_writes(true))
+        .await
+        .expect("server should start");
+
+    let response = server
+        .post(
+            "/api/mutate?className=upd-users-role-[guest]-where-id-1&as=table",
+            None,
+        )
+        .await
+        .expect("request should succeed");
+
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json().expect("response should be JSON");
+    assert!(body["query"]
+        .as_str()
+        .is_some_and(|sql| sql.ends_with(" RETURNING *")));
+    assert_eq!(body["rowsAffected"], 1);
+    assert_eq!(body["rows"][0]["id"], 1);
+    assert_eq!(body["rows"][0]["role"], "guest");
+    assert!(body["html"]
+        .as_str()
+        .is_some_and(|html| html.contains("<table") && html.contains("guest")));
+}
+
+#[tokio::test]
+async fn page_param_adds_offset_and_pagination_metadata() {
+    let server = TestServer::start().await.expect("server should start");
+
+    let response = server
+        .get("/api/query?className=db-users-name-where-role-admin-limit-3&page=2")
+        .await
+        .expect("request should succeed");
+
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json().expect("response should be JSON");
+    assert_eq!(
+        body["query"],
+        "SELECT \"name\", \"id\" AS \"__id\" FROM \"users\" WHERE \"role\" = ? LIMIT ? OFFSET ?"
+    );
+    assert_eq!(body["params"], json!(["admin", 3, 3]));
+    assert_eq!(body["page"], 2);
+    let total = body["total"].as_u64().expect("total should be a count");
+    assert!(total > 3);
+    assert_eq!(body["pageCount"], total.div_ceil(3));
+}
+
+#[tokio::test]
+async fn explain_param_returns_the_query_plan() {
+    let server = TestServer::start().await.expect("server should start");
+
+    let response = server
+        .get("/api/query?className=db-users-name-where-id-3&explain=1")
+        .await
+        .expect("request should succeed");
+
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json().expect("response should be JSON");
+    assert_eq!(
+        body["query"],
+        "EXPLAIN QUERY PLAN SELECT \"name\", \"id\" AS \"__id\" FROM \"users\" WHERE \"id\" = ? LIMIT ?"
+    );
+    assert!(body.get("results").is_none());
+    let detail = body["plan"][0]["detail"]
+        .as_str()
+        .expect("plan should have a detail");
+    assert!(detail.contains("PRIMARY KEY"), "{detail}");
+}
+
+#[tokio::test]
+async fn request_ids_are_echoed_and_reported_in_errors() {
+    let server = TestServer::start().await.expect("server should start");
+
+    let response = server
+        .get_with(
+            "/api/query?className=db-users-name-limit-1",
+            &[("x-request-id", "client-trace-42")],
+        )
+        .await
+        .expect("request should succeed");
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers["x-request-id"], "client-trace-42");
+
+    let response = server
+        .get("/api/query?className=db-nosuchtable-name")
+        .await
+        .expect("request should succeed");
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    let id = response.headers["x-request-id"]
+        .to_str()
+        .expect("request id should be ASCII");
+    assert_eq!(id.len(), 32);
+    let body = response.json().expect("response should be JSON");
+    assert_eq!(body["requestId"], id);
+}
+
+#[tokio::test]
+async fn posts_join_tags_through_the_junction_table() {
+    let server = TestServer::start().await.expect("server should start");
+
+    let response = server
+        .get("/api/query?className=db-posts-title-where-id-1&join=post_tags:id-post_id:tag_id:inner,tags:post_tags.tag_id-id:name:inner")
+        .await
+        .expect("request should succeed");
+
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json().expect("response should be JSON");
+    let rows = body["results"].as_array().expect("results should be rows");
+    assert!(!rows.is_empty() && rows.len() <= 2, "{rows:?}");
+    for row in rows {
+        let title = row["title"].as_str().expect("title should be text");
+        let tag = row["name"].as_str().expect("tag should have a name");
+        // Posts are tagged with the terms in their title.
+        let term = tag.replace('-', " ");
+        assert!(title.to_lowercase().contains(&term), "{title} / {tag}");
+    }
+}
+
+#[tokio::test]
+async fn through_joins_follow_the_junction_foreign_keys() {
+    let server = TestServer::start().await.expect("server should start");
+    let explicit = server
+        .get("/api/query?className=db-posts-title-where-id-1&join=post_tags:id-post_id:tag_id:inner,tags:post_tags.tag_id-id:name:inner")
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+
+    let response = server
+        .get(
+            "/api/query?className=db-posts-title-where-id-1&join=tags:through-post_tags:name:inner",
+        )
+        .await
+        .expect("request should succeed");
+
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json().expect("response should be JSON");
+    assert_eq!(
+        body["query"],
+        "SELECT \"posts\".\"title\", \"tags\".\"name\", \
+         COALESCE(CAST(\"posts\".\"id\" AS TEXT), '') || '/' || \
+         COALESCE(CAST(\"tags\".\"id\" AS TEXT), '') AS \"__id\" FROM \"posts\" INNER JOIN \
+         \"post_tags\" ON \"posts\".\"id\" = \"post_tags\".\"post_id\" INNER JOIN \
+         \"tags\" ON \"post_tags\".\"tag_id\" = \"tags\".\"id\" WHERE \"posts\".\"id\" \
+         = ? LIMIT ?"
+    );
+    let names = |body: &serde_json::Value| {
+        body["results"]
+            .as_array()
+            .expect("results should be rows")
+            .iter()
+            .map(|row| row["name"].clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(&body), names(&explicit));
+
+    let unrelated = server
+        .get("/api/query?className=db-users-name&join=tags:through-post_tags:name")
+        .await
+        .expect("request should succeed");
+    assert_eq!(unrelated.status, StatusCode::BAD_REQUEST);
+    let body = unrelated.json().expect("response should be JSON");
+    assert_eq!(
+        body["error"],
+        "post_tags has no foreign keys linking users to tags"
+    );
+}
+
+#[tokio::test]
+async fn joins_without_columns_follow_foreign_keys() {
+    let server = TestServer::start().await.expect("server should start");
+
+    let response = server
+        .get("/api/query?className=db-users-name-where-id-1&join=posts::title:inner")
+        .await
+        .expect("request should succeed");
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json().expect("response should be JSON");
+    assert_eq!(
+        body["query"],
+        "SELECT \"users\".\"name\", \"posts\".\"title\", \
+         COALESCE(CAST(\"users\".\"id\" AS TEXT), '') || '/' || \
+         COALESCE(CAST(\"posts\".\"id\" AS TEXT), '') AS \"__id\" FROM \"users\" INNER JOIN \
+         \"posts\" ON \"users\".\"id\" = \"posts\".\"author_id\" WHERE \"users\".\"id\" \
+         = ? LIMIT ?"
+    );
+
+    // Keys on the parent work the other way round, and later hops name
+    // their parent.
+    let response = server
+        .get("/api/query?className=db-comments-body-limit-1&join=posts:,users:posts.:name")
+        .await
+        .expect("request should succeed");
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json().expect("response should be JSON");
+    assert!(body["query"].as_str().is_some_and(|sql| sql
+        .contains("\"comments\".\"post_id\" = \"posts\".\"id\"")
+        && sql.contains("\"posts\".\"author_id\" = \"users\".\"id\"")));
+
+    let unrelated = server
+        .get("/api/query?className=db-products-title&join=tags")
+        .await
+        .expect("request should succeed");
+    assert_eq!(unrelated.status, StatusCode::BAD_REQUEST);
+    let body = unrelated.json().expect("response should be JSON");
+    assert_eq!(
+        body["error"],
+        "no foreign key links products and tags; name the join columns"
+    );
+}
+
+#[tokio::test]
+async fn join_keys_found_by_the_schema_are_checked_against_the_policy() {
+    let server = TestServer::start_with(
+        AccessPolicy::default()
+            .deny_column("posts", "author_id")
+            .deny_column("post_tags", "tag_id"),
+    )
+    .await
+    .expect("server should start");
+
+    for path in [
+        "/api/query?className=db-users-name&join=posts::title",
+        "/api/query?className=db-posts-title&join=tags:through-post_tags:name",
+    ] {
+        let response = server.get(path).await.expect("request should succeed");
+        assert_eq!(response.status, StatusCode::FORBIDDEN, "{path}");
+    }
+}
+
+#[tokio::test]
+async fn saved_queries_become_queryable_views() {
+    let server = TestServer::start_with(AccessPolicy::default().allow_writes(true))
+        .await
+        .expect("server should start");
+
+    let response = server
+        .post(
+            "/api/views?name=admins&className=db-users-id-name-where-role-admin",
+            None,
+        )
+        .await
+        .expect("request should succeed");
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.json().expect("response should be JSON")["query"],
+        "CREATE VIEW \"admins\" AS SELECT \"id\", \"name\" FROM \"users\" WHERE \"role\" = 'admin'"
+    );
+
+    let results = |class_name: &'static str| {
+        let server = &server;
+        async move {
+            let response = server
+                .query(class_name)
+                .await
+                .expect("request should succeed");
+            assert_eq!(response.status, StatusCode::OK, "{class_name}");
+            response.json().expect("response should be JSON")["results"].clone()
+        }
+    };
+    let admins = results("db-admins-id-name").await;
+    assert!(admins.as_array().is_some_and(|rows| !rows.is_empty()));
+    // A view has no key of its own, so its rows carry no `__id`.
+    let mut users = results("db-users-id-name-where-role-admin").await;
+    for row in users.as_array_mut().into_iter().flatten() {
+        row.as_object_mut().map(|row| row.remove("__id"));
+    }
+    assert_eq!(admins, users);
+
+    for path in [
+        "/api/views?name=admins&className=db-users-name",
+        "/api/views?name=bad;name&className=db-users-name",
+        "/api/views?name=sqlite_view&className=db-users-name",
+    ] {
+        let response = server
+            .post(path, None)
+            .await
+            .expect("request should succeed");
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{path}");
+    }
+}
+
+#[tokio::test]
+async fn opted_in_results_are_cached_until_a_write() {
+    let server = TestServer::start_with(AccessPolicy::default().allow_writes(true))
+        .await
+        .expect("server should start");
+    let role = |class_name: &'static str| {
+        let server = &server;
+        async move {
+            let response = server
+                .query(class_name)
+                .await
+                .expect("request should succeed");
+            assert_eq!(response.status, StatusCode::OK, "{class_name}");
+            response.json().expect("response should be JSON")["results"][0]["role"].clone()
+        }
+    };
+    let cached = "cache:db-users-role-where-id-1";
+    let before = role(cached).await;
+    assert_eq!(role(cached).await, before);
+
+    let metrics = server
+        .get("/api/metrics")
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    assert_eq!(metrics["resultCache"]["hits"], 1);
+    assert_eq!(metrics["resultCache"]["misses"], 1);
+
+    // A write empties the cache, so the next read sees it.
+    let response = server
+        .post(
+            "/api/mutate?className=upd-users-role-[guest]-where-id-1",
+            None,
+        )
+        .await
+        .expect("request should succeed");
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(role(cached).await, "guest");
+    assert_eq!(role("db-users-role-where-id-1").await, "guest");
+}
+
+#[tokio::test]
+async fn the_connection_worker_runs_jobs_in_order_and_sheds_a_full_queue() {
+    let conn = Connection::open_in_memory().expect("database should open");
+    let worker = Arc::new(Worker::spawn(conn, 1).expect("worker should start"));
+    worker
+        .run(|db| db.execute("CREATE TABLE notes (body TEXT)", &[]))
+        .await
+        .expect("job should run")
+        .expect("table should be created");
+
+    // Hold the connection, then fill the one-job queue behind it.
+    let (release, held) = std::sync::mpsc::channel::<()>();
+    let (started, holding_started) = tokio::sync::oneshot::channel();
+    let holding = tokio::spawn({
+        let worker = worker.clone();
+        async move {
+            worker
+                .run(move |_| {
+                    let _ = started.send(());
+                    held.recv()
+                })
+                .await
+        }
+    });
+    holding_started.await.expect("holding job should start");
+    let queued = tokio::spawn({
+        let worker = worker.clone();
+        async move {
+            worker
+                .run(|db| db.execute("INSERT INTO notes VALUES ('queued')", &[]))
+                .await
+        }
+    });
+    while worker.metrics().queue_depth == 0 {
+        tokio::task::yield_now().await;
+    }
+    let shed = worker.run(|_| ()).await;
+    assert!(matches!(shed, Err(DbError::QueueFull(1))), "{shed:?}");
+
+    release.send(()).expect("holding job should be waiting");
+    holding
+        .await
+        .expect("task should finish")
+        .expect("job should run")
+        .ok();
+    queued
+        .await
+        .expect("task should finish")
+        .expect("job should run")
+        .expect("insert should succeed");
+    let notes = worker
+        .run(|db| db.query("SELECT body FROM notes", &[], &QueryPolicy::default()))
+        .await
+        .expect("job should run")
+        .expect("query should succeed");
+    assert_eq!(notes.rows.len(), 1);
+
+    // A panicking job fails on its own; the connection keeps working.
+    let panicked = worker.run(|_| panic!("job failed")).await;
+    assert!(matches!(panicked, Err(DbError::Join)));
+    assert_eq!(worker.run(|_| 1).await.expect("job should run"), 1);
+}
+
+struct PlainPages;
+
+impl PageRenderer for PlainPages {
+    fn index(&self, page: &IndexPage) -> Result<String, PageError> {
+        let titles: Vec<&str> = page
+            .examples
+            .iter()
+            .map(|card| card.title.as_str())
+            .collect();
+        Ok(format!(
+            "<h1>{}</h1><p>{}</p>",
+            page.hero_value,
+            titles.join(", ")
+        ))
+    }
+
+    fn explorer(&self, page: &ExplorerPage) -> Result<String, PageError> {
+        let tables: Vec<String> = page
+            .tables
+            .iter()
+            .map(|table| format!("{}({})", table.name, table.columns.len()))
+            .collect();
+        Ok(tables.join(" "))
+    }
+}
+
+#[tokio::test]
+async fn pages_render_through_a_custom_renderer() {
+    let server = TestServer::start_with_pages(PlainPages)
+        .await
+        .expect("server should start");
+    let name = server
+        .query("db-users-name-where-id-1")
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON")["results"][0]["name"]
+        .as_str()
+        .expect("the first user should have a name")
+        .to_string();
+
+    let index = server.get("/").await.expect("request should succeed");
+    assert_eq!(index.status, StatusCode::OK);
+    assert!(
+        index.body.starts_with(&format!("<h1>{name}</h1><p>")),
+        "{}",
+        index.body
+    );
+    assert!(!index.body.contains("<html"));
+
+    let explorer = server
+        .get("/explorer")
+        .await
+        .expect("request should succeed");
+    assert_eq!(explorer.status, StatusCode::OK);
+    assert!(
+        explorer
+            .body
+            .split(' ')
+            .any(|table| table.starts_with("users(")),
+        "{}",
+        explorer.body
+    );
+}
+
+#[tokio::test]
+async fn index_template_runs_its_example_queries() {
+    let server = TestServer::start().await.expect("server should start");
+    let products = server
+        .get("/api/fragment?className=db-products-title-limit-5&as=ul")
+        .await
+        .expect("request should succeed");
+    assert_eq!(products.status, StatusCode::OK);
+    assert!(products.body.contains("<ul"), "{}", products.body);
+
+    let index = server.get("/").await.expect("request should succeed");
+    assert_eq!(index.status, StatusCode::OK);
+    assert!(index.body.contains(&products.body), "{}", index.body);
+    // The join examples render through the same `db` helper.
+    assert!(index.body.matches("<table").count() >= 3, "{}", index.body);
+}
+
+#[tokio::test]
+async fn queries_past_the_timeout_are_cancelled() {
+    let endless = "CREATE VIEW endless AS
+        WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n) SELECT x FROM n";
+    let timeout = Duration::from_millis(200);
+
+    let conn = Connection::open_in_memory().expect("database should open");
+    conn.execute_batch(endless).expect("fixture should load");
+    let db = SqliteDatabase::new(conn)
+        .with_query_timeout(timeout)
+        .expect("watchdog should start");
+    let sql = "SELECT max(x) FROM endless";
+    let error = db
+        .query(sql, &[], &QueryPolicy::default())
+        .expect_err("the query should be cancelled");
+    assert!(matches!(error, DbError::Timeout(limit) if limit == timeout));
+    // The interrupt does not outlive the query.
+    let result = db
+        .query("SELECT 1 AS one", &[], &QueryPolicy::default())
+        .expect("the next query should run");
+    assert_eq!(result.columns, ["one"]);
+
+    let mut conn = Connection::open_in_memory().expect("database should open");
+    db::seed_database(&mut conn).expect("seed should load");
+    conn.execute_batch(endless).expect("fixture should load");
+    let db = SqliteDatabase::new(conn)
+        .with_query_timeout(timeout)
+        .expect("watchdog should start");
+    let server = TestServer::serve(db, AccessPolicy::default())
+        .await
+        .expect("server should start");
+    let response = server
+        .query("db-endless-x-orderby-x-desc-limit-1")
+        .await
+        .expect("request should succeed");
+    assert_eq!(response.status, StatusCode::REQUEST_TIMEOUT);
+    let body = response.json().expect("response should be JSON");
+    assert!(body["error"]
+        .as_str()
+        .is_some_and(|error| error.contains("cancelled")));
+    let response = server
+        .query("db-users-name-limit-1")
+        .await
+        .expect("request should succeed");
+    assert_eq!(response.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn rows_carry_their_primary_key_as_id() {
+    let conn = Connection::open_in_memory().expect("database should open");
+    conn.execute_batch(
+        "CREATE TABLE notes (body TEXT);
+         INSERT INTO notes (body) VALUES ('first'), ('second');
+         CREATE TABLE pairs (a INTEGER, b TEXT, PRIMARY KEY (a, b));
+         INSERT INTO pairs VALUES (1, 'x');",
+    )
+    .expect("fixture should load");
+    let server = TestServer::serve(SqliteDatabase::new(conn), AccessPolicy::default())
+        .await
+        .expect("server should start");
+
+    // Without a declared key, the implicit rowid stands in.
+    let body = server
+        .query("db-notes-body-orderby-body-desc")
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    assert_eq!(body["results"][0]["__id"], 2);
+    let body = server
+        .query("db-pairs")
+        .await
+        .expect("request should succeed")
+        .json()
+        .expect("response should be JSON");
+    assert_eq!(body["results"][0]["__id"], "1/x");
+
+    let fragment = server
+        .fragment("db-pairs-a-b", "table")
+        .await
+        .expect("request should succeed");
+    assert!(fragment.body.contains(r#"data-id="1/x""#));
+    assert!(!fragment.body.contains("__id"));
+}
+
+#[tokio::test]
+async fn live_viewers_of_one_fragment_share_a_subscription() {
+    let server = TestServer::start().await.expect("server should start");
+    let path = "/api/live?className=db-users-name-limit-2&as=ul&refresh=1";
+
+    let mut first = server.events(path).await.expect("stream should open");
+    let mut second = server.events(path).await.expect("stream should open");
+    let update = first
+        .next()
+        .await
+        .expect("stream should be readable")
+        .expect("an update should arrive");
+    assert!(update.contains("<li"), "{update}");
+    let shared = second
+        .next()
+        .await
+        .expect("stream should be readable")
+        .expect("an update should arrive");
+    assert_eq!(shared, update);
+
+    let metrics = server
+        .get("/api/metrics")
+        .await
+        .expect("request should succeed");
+    let live = &metrics.json().expect("response should be JSON")["live"];
+    assert_eq!(live["subscriptions"], 1);
+    assert_eq!(live["subscribers"], 2);
+    assert_eq!(live["dropPolicy"], "oldest");
+
+    drop(first);
+    drop(second);
+    // The server notices the streams closing on its own time.
+    let mut subscriptions = None;
+    for _ in 0..50 {
+        let metrics = server
+            .get("/api/metrics")
+            .await
+            .expect("request should succeed");
+        subscriptions =
+            Some(metrics.json().expect("response should be JSON")["live"]["subscriptions"].clone());
+        if subscriptions == Some(json!(0)) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(subscriptions, Some(json!(0)));
+}
+
+#[tokio::test]
+async fn advise_suggests_indexes_for_full_scans() {
+    let server = TestServer::start().await.expect("server should start");
+
+    let response = server
+        .get("/api/advise?className=db-users-name-where-role-admin-orderby-name-asc")
+        .await
+        .expect("request should succeed");
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json().expect("response should be JSON");
+    assert_eq!(body["fullScans"], json!(["users"]));
+    assert_eq!(body["tempSort"], true);
+    assert_eq!(body["suggestions"][0]["columns"], json!(["role", "name"]));
+    assert_eq!(
+        body["suggestions"][0]["sql"],
+        "CREATE INDEX \"idx_users_role_name\" ON \"users\" (\"role\", \"name\")"
+    );
+
+    // Primary key lookups and indexed joins need nothing.
+    let response = server
+        .get("/api/advise?className=db-users-name-where-id-1&join=posts::title")
+        .await
+        .expect("request should succeed");
+    let body = response.json().expect("response should be JSON");
+    assert_eq!(body["fullScans"], json!([]));
+    assert_eq!(body["suggestions"], json!([]));
+}